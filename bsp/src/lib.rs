@@ -4,6 +4,7 @@ mod parser;
 mod tracer;
 mod types;
 mod utils;
+mod visibility;
 mod writer;
 
 pub use parser::parse_bsp;
@@ -13,6 +14,7 @@ pub use types::*;
 
 pub use glam::Vec3;
 pub use tracer::*;
+pub use visibility::*;
 
 #[cfg(test)]
 mod test {
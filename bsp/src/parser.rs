@@ -283,6 +283,38 @@ fn parse_models(i: &[u8]) -> IResult<Vec<Model>> {
     all_consuming(many0(parse_model))(i)
 }
 
+/// Index of the entity using each of the `model_count` brush models, worldspawn for model 0.
+///
+/// The first entity wins if more than one uses a model.
+fn model_entities(entities: &[Entity], model_count: usize) -> Vec<Option<usize>> {
+    let mut res = vec![None; model_count];
+
+    entities
+        .iter()
+        .enumerate()
+        .rev()
+        .for_each(|(entity_idx, entity)| {
+            let model_index = if entity
+                .get("classname")
+                .is_some_and(|classname| classname == "worldspawn")
+            {
+                Some(0)
+            } else {
+                entity
+                    .get("model")
+                    .and_then(|model| model.strip_prefix('*')?.parse::<usize>().ok())
+                    .filter(|&model_index| model_index != 0)
+            };
+
+            if let Some(model_entity) = model_index.and_then(|model_index| res.get_mut(model_index))
+            {
+                *model_entity = Some(entity_idx);
+            }
+        });
+
+    res
+}
+
 type FUCKOFF<'a> = nom::Err<nom::error::Error<&'a [u8]>>;
 
 pub fn parse_bsp(i: &[u8]) -> Result<Bsp, BspError> {
@@ -325,6 +357,7 @@ pub fn parse_bsp(i: &[u8]) -> Result<Bsp, BspError> {
     let (_, surf_edges) =
         parse_surf_edges(lump_section(LUMP_SURFEDGES)).map_err(|_| BspError::ParseSurfEdges)?;
     let (_, models) = parse_models(lump_section(LUMP_MODELS)).map_err(|_| BspError::ParseModels)?;
+    let model_entities = model_entities(&entities, models.len());
 
     Ok(Bsp {
         entities,
@@ -342,5 +375,6 @@ pub fn parse_bsp(i: &[u8]) -> Result<Bsp, BspError> {
         edges,
         surf_edges,
         models,
+        model_entities,
     })
 }
@@ -0,0 +1,175 @@
+//! Tracing against brush entities where they are in the map.
+use glam::Vec3;
+
+use crate::{Bsp, Entity, HullType, LeafContent, TraceResult};
+
+/// Brush entities that players collide with and the spawnflag that makes them not solid, from the game code.
+const SOLID_BRUSH_ENTITIES: &[(&str, Option<u32>)] = &[
+    ("func_wall", None),
+    // starts off
+    ("func_wall_toggle", Some(1)),
+    // passable
+    ("func_door", Some(8)),
+    ("func_door_rotating", Some(8)),
+    ("func_pendulum", Some(32)),
+    ("func_rotating", Some(64)),
+    ("func_rot_button", Some(1)),
+    ("func_conveyor", Some(2)),
+    ("func_breakable", None),
+    ("func_pushable", None),
+    ("func_button", None),
+    ("func_train", None),
+    ("func_tracktrain", None),
+    ("func_trackchange", None),
+    ("func_trackautochange", None),
+    ("func_plat", None),
+    ("func_platrot", None),
+    ("func_guntarget", None),
+    ("func_healthcharger", None),
+    ("func_recharge", None),
+    ("func_tank", None),
+    ("func_vehicle", None),
+    ("momentary_door", None),
+    ("momentary_rot_button", None),
+];
+
+/// Whether players collide with `entity` when it spawns.
+pub fn is_solid_brush_entity(entity: &Entity) -> bool {
+    let Some(classname) = entity.get("classname") else {
+        return false;
+    };
+
+    let spawnflags = entity
+        .get("spawnflags")
+        .and_then(|spawnflags| spawnflags.parse::<u32>().ok())
+        .unwrap_or(0);
+
+    SOLID_BRUSH_ENTITIES
+        .iter()
+        .find(|(solid_classname, _)| solid_classname == classname)
+        .is_some_and(|(_, not_solid_flag)| not_solid_flag.is_none_or(|flag| spawnflags & flag == 0))
+}
+
+/// Contents of the space inside a brush entity, from its `skin` key like `func_water`.
+///
+/// Solid brush entities are traced instead, so they never have any.
+pub fn brush_entity_contents(entity: &Entity) -> Option<LeafContent> {
+    if is_solid_brush_entity(entity) {
+        return None;
+    }
+
+    let contents = entity.get("skin")?.parse::<i32>().ok()?;
+
+    LeafContent::try_from(contents)
+        .ok()
+        .filter(|contents| !matches!(contents, LeafContent::ContentsEmpty))
+}
+
+fn entity_origin(entity: &Entity) -> Vec3 {
+    entity
+        .get("origin")
+        .and_then(|origin| {
+            let mut values = origin
+                .split_whitespace()
+                .map(|value| value.parse::<f32>().ok());
+
+            Some(Vec3::new(values.next()??, values.next()??, values.next()??))
+        })
+        .unwrap_or(Vec3::ZERO)
+}
+
+/// (Model Index, Origin) of a brush entity.
+fn entity_model(entity: &Entity) -> Option<(usize, Vec3)> {
+    let model_index = entity
+        .get("model")?
+        .strip_prefix('*')?
+        .parse::<usize>()
+        .ok()?;
+
+    Some((model_index, entity_origin(entity)))
+}
+
+/// Takes whichever trace stops first, same as `SV_ClipToLinks`.
+fn closest_trace(closest: TraceResult, tr: TraceResult) -> TraceResult {
+    let start_solid = closest.start_solid || tr.start_solid;
+
+    if tr.all_solid || tr.start_solid || tr.fraction < closest.fraction {
+        TraceResult { start_solid, ..tr }
+    } else {
+        TraceResult {
+            start_solid,
+            ..closest
+        }
+    }
+}
+
+impl Bsp {
+    /// (Model Index, Origin) of every solid brush entity, see [`is_solid_brush_entity`].
+    pub fn solid_brush_entities(&self) -> Vec<(usize, Vec3)> {
+        self.brush_entities(is_solid_brush_entity)
+    }
+
+    /// (Model Index, Origin) of every brush entity that `filter` accepts.
+    pub fn brush_entities(&self, filter: impl Fn(&Entity) -> bool) -> Vec<(usize, Vec3)> {
+        self.entities
+            .iter()
+            .filter(|entity| filter(entity))
+            .filter_map(entity_model)
+            .collect()
+    }
+
+    /// (Model Index, Origin, Contents) of every brush entity with contents, see [`brush_entity_contents`].
+    pub fn contents_brush_entities(&self) -> Vec<(usize, Vec3, LeafContent)> {
+        self.entities
+            .iter()
+            .filter_map(|entity| {
+                let contents = brush_entity_contents(entity)?;
+                let (model_index, origin) = entity_model(entity)?;
+
+                Some((model_index, origin, contents))
+            })
+            .collect()
+    }
+
+    /// Traces against the world and every solid brush entity.
+    ///
+    /// Moving entities like doors and trains are traced where they spawn.
+    pub fn trace_world_and_entities(&self, hull_type: HullType, p1: Vec3, p2: Vec3) -> TraceResult {
+        self.trace_world_and_models(&self.solid_brush_entities(), hull_type, p1, p2)
+    }
+
+    /// Same as [`Bsp::trace_world_and_entities`] but with (Model Index, Origin) from [`Bsp::solid_brush_entities`].
+    ///
+    /// Use this when tracing a lot so entities aren't looked up every time.
+    pub fn trace_world_and_models(
+        &self,
+        models: &[(usize, Vec3)],
+        hull_type: HullType,
+        p1: Vec3,
+        p2: Vec3,
+    ) -> TraceResult {
+        let (hull_mins, hull_maxs) = hull_type.size();
+
+        // box around the whole trace, a bit bigger for the epsilon
+        let trace_mins = p1.min(p2) + hull_mins - 1.;
+        let trace_maxs = p1.max(p2) + hull_maxs + 1.;
+
+        models
+            .iter()
+            .filter(|(model_index, origin)| {
+                self.models.get(*model_index).is_some_and(|model| {
+                    (model.mins + *origin).cmple(trace_maxs).all()
+                        && (model.maxs + *origin).cmpge(trace_mins).all()
+                })
+            })
+            .fold(
+                self.trace_line(hull_type, p1, p2),
+                |closest, &(model_index, origin)| {
+                    closest_trace(
+                        closest,
+                        self.trace_line_model(model_index, origin, hull_type, p1, p2),
+                    )
+                },
+            )
+    }
+}
@@ -1,9 +1,12 @@
-use glam::FloatExt;
+use glam::{FloatExt, Vec3};
 
-use crate::{Bsp, LeafContent, Plane};
+use crate::{Bsp, LeafContent, Plane, PlaneType};
 
+mod entities;
+mod pick;
 mod types;
 
+pub use entities::{brush_entity_contents, is_solid_brush_entity};
 pub use types::{HullType, TraceResult};
 
 const DIST_EPSILON: f32 = 1. / 32.;
@@ -19,25 +22,167 @@ fn plane_diff(p: glam::Vec3, plane: &Plane) -> f32 {
     }
 }
 
+/// Traces `hull_type` against a box from `mins` to `maxs`.
+///
+/// Same as the game does for entities without a brush model. Like brush traces, only going into the box is a hit.
+pub fn trace_line_box(
+    mins: Vec3,
+    maxs: Vec3,
+    hull_type: HullType,
+    p1: Vec3,
+    p2: Vec3,
+) -> TraceResult {
+    let (hull_mins, hull_maxs) = hull_type.size();
+
+    // tracing the hull against a box is the same as tracing a point against a bigger box
+    let mins = mins - hull_maxs;
+    let maxs = maxs - hull_mins;
+
+    let planes = [
+        (Vec3::X, maxs.x, PlaneType::X),
+        (Vec3::NEG_X, -mins.x, PlaneType::X),
+        (Vec3::Y, maxs.y, PlaneType::Y),
+        (Vec3::NEG_Y, -mins.y, PlaneType::Y),
+        (Vec3::Z, maxs.z, PlaneType::Z),
+        (Vec3::NEG_Z, -mins.z, PlaneType::Z),
+    ]
+    .map(|(normal, distance, type_)| Plane {
+        normal,
+        distance,
+        type_,
+    });
+
+    let mut tr = TraceResult::default();
+    let inside = |p: Vec3| p.cmpgt(mins).all() && p.cmplt(maxs).all();
+
+    tr.start_solid = inside(p1);
+    tr.all_solid = tr.start_solid && inside(p2);
+    tr.end_pos = p2;
+
+    let mut enter_frac = -1f32;
+    let mut exit_frac = 1f32;
+    let mut enter_plane = None;
+
+    for plane in planes {
+        let d1 = plane.normal.dot(p1) - plane.distance;
+        let d2 = plane.normal.dot(p2) - plane.distance;
+
+        // never inside this side
+        if d1 > 0. && d2 >= d1 {
+            return tr;
+        }
+
+        if d1 <= 0. && d2 <= 0. {
+            continue;
+        }
+
+        if d1 > d2 {
+            // going in
+            let frac = (d1 - DIST_EPSILON) / (d1 - d2);
+
+            if frac > enter_frac {
+                enter_frac = frac;
+                enter_plane = Some(plane);
+            }
+        } else {
+            // going out
+            let frac = (d1 + DIST_EPSILON) / (d1 - d2);
+
+            exit_frac = exit_frac.min(frac);
+        }
+    }
+
+    if let Some(plane) = enter_plane
+        && enter_frac < exit_frac
+    {
+        let frac = enter_frac.max(0.);
+
+        tr.fraction = frac;
+        tr.end_pos = p1.lerp(p2, frac);
+        tr.plane = plane;
+    }
+
+    tr
+}
+
 impl Bsp {
     pub fn trace_line(&self, hull_type: HullType, p1: glam::Vec3, p2: glam::Vec3) -> TraceResult {
+        self.trace_line_model(0, Vec3::ZERO, hull_type, p1, p2)
+    }
+
+    /// Traces a box from `mins` to `maxs` around the points against the world.
+    ///
+    /// Same as the game, the box is traced as the hull from [`HullType::from_size`],
+    /// moved so that its mins line up with the box. Points don't have an offset.
+    pub fn trace_line_size(&self, mins: Vec3, maxs: Vec3, p1: Vec3, p2: Vec3) -> TraceResult {
+        let hull_type = HullType::from_size(mins, maxs);
+
+        let offset = match hull_type {
+            HullType::Point => Vec3::ZERO,
+            HullType::Stand | HullType::Monster | HullType::Duck => hull_type.size().0 - mins,
+        };
+
+        self.trace_line_model(0, offset, hull_type, p1, p2)
+    }
+
+    /// Traces against brush model `model_index` moved by `origin_offset`.
+    ///
+    /// Model 0 is the world. Brush entities are built where they are in the map unless they have an origin brush,
+    /// so `origin_offset` is their `origin` key.
+    pub fn trace_line_model(
+        &self,
+        model_index: usize,
+        origin_offset: Vec3,
+        hull_type: HullType,
+        p1: glam::Vec3,
+        p2: glam::Vec3,
+    ) -> TraceResult {
         let mut tr = TraceResult::default();
-        let head_node = self.models[0].head_nodes[hull_type as i32 as usize];
+
+        let Some(model) = self.models.get(model_index) else {
+            tr.all_solid = false;
+            tr.end_pos = p2;
+
+            return tr;
+        };
+
+        let head_node = model.head_nodes[hull_type as i32 as usize];
+        let (p1, p2) = (p1 - origin_offset, p2 - origin_offset);
 
         match hull_type {
-            HullType::Point => self.trace_line_point(head_node, p1, p2, 0., 1., &mut tr),
+            HullType::Point => self.trace_line_point(head_node, head_node, p1, p2, 0., 1., &mut tr),
             HullType::Stand | HullType::Monster | HullType::Duck => {
-                self.trace_line_hull(hull_type, head_node, p1, p2, 0., 1., &mut tr)
+                self.trace_line_hull(head_node, head_node, p1, p2, 0., 1., &mut tr)
             }
         };
 
-        return tr;
+        if tr.fraction == 1. {
+            tr.end_pos = p2;
+        }
+
+        tr.contents = match hull_type {
+            HullType::Point => self.trace_point(head_node, tr.end_pos),
+            HullType::Stand | HullType::Monster | HullType::Duck => {
+                self.trace_point_hull(head_node, tr.end_pos)
+            }
+        };
+
+        if tr.fraction < 1. {
+            tr.model_index = Some(model_index);
+        }
+
+        // back to where the model is
+        tr.end_pos += origin_offset;
+        tr.plane.distance += tr.plane.normal.dot(origin_offset);
+
+        tr
     }
 
     // ONLY FOR HULL 1 to 3
+    #[allow(clippy::too_many_arguments)]
     fn trace_line_hull(
         &self,
-        hull_type: HullType,
+        head_node: i32,
         num: i32,
         p1: glam::Vec3,
         p2: glam::Vec3,
@@ -50,6 +195,12 @@ impl Bsp {
 
             if !matches!(leaf_content, LeafContent::ContentsSolid) {
                 trace.all_solid = false;
+
+                if matches!(leaf_content, LeafContent::ContentsEmpty) {
+                    trace.in_open = true;
+                } else {
+                    trace.in_water = true;
+                }
             } else {
                 trace.start_solid = true;
             }
@@ -72,7 +223,7 @@ impl Bsp {
         // positive side
         if d1 >= 0. && d2 >= 0. {
             return self.trace_line_hull(
-                hull_type,
+                head_node,
                 node.children[0] as i32,
                 p1,
                 p2,
@@ -85,7 +236,7 @@ impl Bsp {
         // negative side
         if d1 < 0. && d2 < 0. {
             return self.trace_line_hull(
-                hull_type,
+                head_node,
                 node.children[1] as i32,
                 p1,
                 p2,
@@ -111,7 +262,7 @@ impl Bsp {
 
         // back to front
         if !self.trace_line_hull(
-            hull_type,
+            head_node,
             node.children[side] as i32,
             p1,
             mid,
@@ -128,7 +279,7 @@ impl Bsp {
         ) {
             // if not solid then keep tracing with the latter half
             return self.trace_line_hull(
-                hull_type,
+                head_node,
                 node.children[side ^ 1] as i32,
                 mid,
                 p2,
@@ -149,8 +300,6 @@ impl Bsp {
         }
 
         // moving the fraction value out of solid
-        let head_node = self.models[0].head_nodes[hull_type as i32 as usize];
-
         while matches!(
             self.trace_point_hull(head_node, mid),
             LeafContent::ContentsSolid
@@ -175,8 +324,10 @@ impl Bsp {
     }
 
     /// ONLY FOR HULL 0
+    #[allow(clippy::too_many_arguments)]
     fn trace_line_point(
         &self,
+        head_node: i32,
         num: i32,
         p1: glam::Vec3,
         p2: glam::Vec3,
@@ -189,6 +340,12 @@ impl Bsp {
 
             if !matches!(leaf_content, LeafContent::ContentsSolid) {
                 trace.all_solid = false;
+
+                if matches!(leaf_content, LeafContent::ContentsEmpty) {
+                    trace.in_open = true;
+                } else {
+                    trace.in_water = true;
+                }
             } else {
                 trace.start_solid = true;
             }
@@ -210,12 +367,28 @@ impl Bsp {
 
         // positive side
         if d1 >= 0. && d2 >= 0. {
-            return self.trace_line_point(node.children[0] as i32, p1, p2, p1f, p2f, trace);
+            return self.trace_line_point(
+                head_node,
+                node.children[0] as i32,
+                p1,
+                p2,
+                p1f,
+                p2f,
+                trace,
+            );
         }
 
         // negative side
         if d1 < 0. && d2 < 0. {
-            return self.trace_line_point(node.children[1] as i32, p1, p2, p1f, p2f, trace);
+            return self.trace_line_point(
+                head_node,
+                node.children[1] as i32,
+                p1,
+                p2,
+                p1f,
+                p2f,
+                trace,
+            );
         }
 
         // until the segment intersects a node/plane
@@ -233,7 +406,15 @@ impl Bsp {
         let mut mid = p1.lerp(p2, frac);
 
         // back to front
-        if !self.trace_line_point(node.children[side] as i32, p1, mid, p1f, midf, trace) {
+        if !self.trace_line_point(
+            head_node,
+            node.children[side] as i32,
+            p1,
+            mid,
+            p1f,
+            midf,
+            trace,
+        ) {
             return false;
         }
 
@@ -243,6 +424,7 @@ impl Bsp {
         ) {
             // if not solid then keep tracing with the latter half
             return self.trace_line_point(
+                head_node,
                 node.children[side ^ 1] as i32,
                 mid,
                 p2,
@@ -263,7 +445,7 @@ impl Bsp {
         }
 
         // moving the fraction value out of solid
-        while matches!(self.trace_point(0, mid), LeafContent::ContentsSolid) {
+        while matches!(self.trace_point(head_node, mid), LeafContent::ContentsSolid) {
             frac -= 0.1;
 
             if frac < 0. {
@@ -323,4 +505,178 @@ impl Bsp {
 }
 
 #[cfg(test)]
-mod test {}
+mod test {
+    use super::*;
+
+    fn c1a3d() -> Bsp {
+        Bsp::from_bytes(include_bytes!("../tests/c1a3d.bsp")).unwrap()
+    }
+
+    #[test]
+    fn func_wall_blocks_trace() {
+        let bsp = c1a3d();
+
+        // func_wall *15 is a thin wall from x 1528 to 1560
+        let p1 = Vec3::new(1488., -434., 744.);
+        let p2 = Vec3::new(1600., -434., 744.);
+
+        let world = bsp.trace_line(HullType::Point, p1, p2);
+        let wall = bsp.trace_line_model(15, Vec3::ZERO, HullType::Point, p1, p2);
+        let all = bsp.trace_world_and_entities(HullType::Point, p1, p2);
+
+        assert!(wall.fraction < world.fraction);
+        assert_eq!(all.fraction, wall.fraction);
+        assert!((all.end_pos.x - (1528. - DIST_EPSILON)).abs() < 0.01);
+        assert_eq!(all.plane.normal, Vec3::NEG_X);
+
+        // the player hull stops 16 units earlier
+        let all = bsp.trace_world_and_entities(HullType::Stand, p1, p2);
+
+        assert!((all.end_pos.x - (1512. - DIST_EPSILON)).abs() < 0.01);
+        assert_eq!(all.plane.distance, -1512.);
+    }
+
+    #[test]
+    fn model_origin_offset() {
+        let bsp = c1a3d();
+
+        // func_rotating *64 has an origin brush
+        let origin = Vec3::new(-44., -647., 912.);
+        let p1 = origin - Vec3::X * 118.;
+        let p2 = origin + Vec3::X * 118.;
+
+        let tr = bsp.trace_line_model(64, origin, HullType::Stand, p1, p2);
+
+        assert!(tr.fraction < 1.);
+        // plane is where the model is
+        assert!((tr.plane.normal.dot(tr.end_pos) - tr.plane.distance).abs() < 0.1);
+
+        // the model is built around the map origin
+        let tr = bsp.trace_line_model(64, Vec3::ZERO, HullType::Stand, p1, p2);
+
+        assert_eq!(tr.fraction, 1.);
+        assert_eq!(tr.end_pos, p2);
+
+        // no model
+        let tr = bsp.trace_line_model(9999, Vec3::ZERO, HullType::Stand, p1, p2);
+
+        assert_eq!(tr.fraction, 1.);
+        assert!(!tr.all_solid);
+    }
+
+    #[test]
+    fn solid_brush_entities() {
+        let bsp = c1a3d();
+        let entities = bsp.solid_brush_entities();
+        let has_model = |model_index: usize| entities.iter().any(|(idx, _)| *idx == model_index);
+
+        // func_wall and func_door
+        assert!(has_model(15));
+        assert!(has_model(3));
+        // passable func_door, not solid func_rotating and trigger_multiple
+        assert!(!has_model(16));
+        assert!(!has_model(1));
+        assert!(!has_model(4));
+
+        assert!(entities.contains(&(64, Vec3::new(-44., -647., 912.))));
+    }
+
+    #[test]
+    fn brush_entity_contents_from_skin() {
+        let entity = |pairs: &[(&str, &str)]| -> crate::Entity {
+            pairs
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect()
+        };
+
+        let water = entity(&[("classname", "func_water"), ("skin", "-3")]);
+        let illusionary = entity(&[("classname", "func_illusionary"), ("skin", "-1")]);
+        let wall = entity(&[("classname", "func_wall"), ("skin", "-3")]);
+
+        assert!(matches!(
+            brush_entity_contents(&water),
+            Some(LeafContent::ContentsWater)
+        ));
+        assert!(brush_entity_contents(&illusionary).is_none());
+        assert!(brush_entity_contents(&wall).is_none());
+    }
+
+    #[test]
+    fn size_trace() {
+        let bsp = Bsp::from_bytes(include_bytes!("../tests/datacore.bsp")).unwrap();
+
+        // spawn area floor is at z 780
+        let p1 = Vec3::new(-3690., 1576., 900.);
+        let p2 = Vec3::new(-3690., 1576., 700.);
+
+        let stand = bsp.trace_line(HullType::Stand, p1, p2);
+        let (mins, maxs) = HullType::Stand.size();
+
+        assert_eq!(
+            bsp.trace_line_size(mins, maxs, p1, p2).end_pos,
+            stand.end_pos
+        );
+
+        // origin at the feet
+        let tr = bsp.trace_line_size(Vec3::new(-16., -16., 0.), Vec3::new(16., 16., 72.), p1, p2);
+
+        assert!((tr.end_pos.z - (stand.end_pos.z - 36.)).abs() < 0.01);
+        assert!((tr.end_pos.z - (780. + DIST_EPSILON)).abs() < 0.01);
+
+        // small boxes are points
+        let tr = bsp.trace_line_size(Vec3::splat(-2.), Vec3::splat(2.), p1, p2);
+
+        assert_eq!(tr.end_pos, bsp.trace_line(HullType::Point, p1, p2).end_pos);
+
+        assert!(matches!(
+            HullType::from_size(Vec3::new(-16., -16., -18.), Vec3::new(16., 16., 18.)),
+            HullType::Duck
+        ));
+        assert!(matches!(
+            HullType::from_size(Vec3::splat(-24.), Vec3::splat(24.)),
+            HullType::Monster
+        ));
+    }
+
+    #[test]
+    fn box_trace() {
+        let (mins, maxs) = (Vec3::splat(-10.), Vec3::splat(10.));
+
+        let tr = trace_line_box(
+            mins,
+            maxs,
+            HullType::Stand,
+            Vec3::new(-100., 0., 0.),
+            Vec3::new(100., 0., 0.),
+        );
+
+        assert!((tr.end_pos.x - (-26. - DIST_EPSILON)).abs() < 0.01);
+        assert_eq!(tr.plane.normal, Vec3::NEG_X);
+        assert!(!tr.start_solid);
+
+        // going past
+        let tr = trace_line_box(
+            mins,
+            maxs,
+            HullType::Point,
+            Vec3::new(-100., 20., 0.),
+            Vec3::new(100., 20., 0.),
+        );
+
+        assert_eq!(tr.fraction, 1.);
+
+        // starting inside only goes out
+        let tr = trace_line_box(
+            mins,
+            maxs,
+            HullType::Point,
+            Vec3::ZERO,
+            Vec3::new(100., 0., 0.),
+        );
+
+        assert_eq!(tr.fraction, 1.);
+        assert!(tr.start_solid);
+        assert!(!tr.all_solid);
+    }
+}
@@ -0,0 +1,259 @@
+//! Finding the face and entity a trace hits.
+use glam::Vec3;
+
+use crate::{Bsp, HullType, TraceResult};
+
+use super::plane_diff;
+
+// how far a point can be off the edges of a face and still be on it
+const ON_EDGE_EPSILON: f32 = 0.1;
+
+impl Bsp {
+    /// Vertices of face `face_idx` in winding order.
+    ///
+    /// `None` if the face or any of its edges is out of range.
+    pub fn face_vertices(&self, face_idx: usize) -> Option<Vec<Vec3>> {
+        let face = self.faces.get(face_idx)?;
+        let first_edge = usize::try_from(face.first_edge).ok()?;
+
+        self.surf_edges
+            .get(first_edge..first_edge + face.edge_count as usize)?
+            .iter()
+            .map(|&surf_edge| {
+                let [v1, v2] = self.edges.get(surf_edge.unsigned_abs() as usize)?;
+                let vertex = if surf_edge >= 0 { v1 } else { v2 };
+
+                self.vertices.get(*vertex as usize).copied()
+            })
+            .collect()
+    }
+
+    /// `None` if the face, its texinfo or its texture is out of range.
+    pub(super) fn face_texture_name(&self, face_idx: usize) -> Option<String> {
+        let face = self.faces.get(face_idx)?;
+        let texinfo = self.texinfo.get(face.texinfo as usize)?;
+
+        self.textures
+            .get(texinfo.texture_index as usize)
+            .map(|texture| texture.texture_name.get_string_standard())
+    }
+
+    /// Whether `point` on the plane of the face is inside of its edges.
+    fn face_contains(&self, face_idx: usize, point: Vec3) -> bool {
+        let Some(vertices) = self.face_vertices(face_idx) else {
+            return false;
+        };
+
+        let Some(plane) = self.planes.get(self.faces[face_idx].plane as usize) else {
+            return false;
+        };
+
+        let normal = plane.normal;
+
+        // winding doesn't matter as long as every edge has the point on the same side
+        let sides = vertices
+            .iter()
+            .zip(vertices.iter().cycle().skip(1))
+            .map(|(from, to)| {
+                let edge = (*to - *from).normalize_or_zero();
+
+                edge.cross(point - *from).dot(normal)
+            });
+
+        let (mut inside_left, mut inside_right) = (true, true);
+
+        sides.for_each(|side| {
+            inside_left &= side >= -ON_EDGE_EPSILON;
+            inside_right &= side <= ON_EDGE_EPSILON;
+        });
+
+        !vertices.is_empty() && (inside_left || inside_right)
+    }
+
+    /// First face the line from `start` to `end` goes through, same as `SurfaceAtPoint`.
+    ///
+    /// Checks the face polygon instead of the texture extents.
+    pub(super) fn surface_at_point(&self, num: i32, start: Vec3, end: Vec3) -> Option<usize> {
+        if num < 0 {
+            return None;
+        }
+
+        let node = self.nodes.get(num as usize)?;
+        let plane = self.planes.get(node.plane as usize)?;
+
+        let front = plane_diff(start, plane);
+        let back = plane_diff(end, plane);
+        let side = (front < 0.) as usize;
+
+        if (back < 0.) as usize == side {
+            return self.surface_at_point(node.children[side] as i32, start, end);
+        }
+
+        let mid = start.lerp(end, front / (front - back));
+
+        // front to back
+        if let Some(face) = self.surface_at_point(node.children[side] as i32, start, mid) {
+            return Some(face);
+        }
+
+        let first_face = node.first_face as usize;
+
+        if let Some(face) = (first_face..first_face + node.face_count as usize)
+            .find(|&face_idx| self.face_contains(face_idx, mid))
+        {
+            return Some(face);
+        }
+
+        self.surface_at_point(node.children[side ^ 1] as i32, mid, end)
+    }
+
+    /// (Model Index, Origin) of every brush entity that can be seen.
+    ///
+    /// Triggers are skipped because they are invisible in game.
+    pub fn pickable_entities(&self) -> Vec<(usize, Vec3)> {
+        self.brush_entities(|entity| {
+            !entity
+                .get("classname")
+                .is_some_and(|classname| classname.starts_with("trigger_"))
+        })
+    }
+
+    /// Point trace from `origin` along `direction` against the world and every brush entity that can be seen.
+    ///
+    /// For turning a ray from the screen into the face and entity under it.
+    pub fn pick(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> TraceResult {
+        self.pick_models(&self.pickable_entities(), origin, direction, max_distance)
+    }
+
+    /// Same as [`Bsp::pick`] but with (Model Index, Origin) from [`Bsp::pickable_entities`].
+    ///
+    /// Use this when picking every frame so entities aren't looked up every time.
+    pub fn pick_models(
+        &self,
+        models: &[(usize, Vec3)],
+        origin: Vec3,
+        direction: Vec3,
+        max_distance: f32,
+    ) -> TraceResult {
+        let direction = direction.normalize_or_zero();
+        let end = origin + direction * max_distance;
+
+        let mut tr = self.trace_world_and_models(models, HullType::Point, origin, end);
+
+        let Some(model_index) = tr.model_index else {
+            return tr;
+        };
+
+        let Some(model) = self.models.get(model_index) else {
+            return tr;
+        };
+
+        // faces of the model are where it is built
+        let model_origin = models
+            .iter()
+            .find(|(other_index, _)| *other_index == model_index)
+            .map(|(_, model_origin)| *model_origin)
+            .unwrap_or(Vec3::ZERO);
+        let back = tr.end_pos - model_origin - direction;
+
+        tr.entity_index = self.model_entities.get(model_index).copied().flatten();
+        tr.face = self.surface_at_point(model.head_nodes[0], back, end - model_origin);
+        tr.texture_name = tr.face.and_then(|face| self.face_texture_name(face));
+
+        tr
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pick_world_floor() {
+        let bsp = Bsp::from_bytes(include_bytes!("../tests/normal.bsp")).unwrap();
+
+        let tr = bsp.pick(Vec3::new(-40., 0., -60.), Vec3::NEG_Z, 8192.);
+
+        assert!(tr.fraction < 1.);
+        assert!(matches!(tr.contents, crate::LeafContent::ContentsEmpty));
+        assert_eq!(tr.model_index, Some(0));
+        assert_eq!(tr.entity_index, Some(0));
+        assert_eq!(tr.texture_name.as_deref(), Some("DEV_GRAY_10_128"));
+
+        // the floor is under the point
+        let face = tr.face.unwrap();
+        let vertices = bsp.face_vertices(face).unwrap();
+
+        assert_eq!(vertices.len(), bsp.faces[face].edge_count as usize);
+        assert!(vertices.iter().all(|vertex| vertex.z == -104.));
+
+        assert!(bsp.face_vertices(bsp.faces.len()).is_none());
+
+        // edges past the end of the lump
+        let mut broken = Bsp::from_bytes(include_bytes!("../tests/normal.bsp")).unwrap();
+        broken.faces[face].first_edge = broken.surf_edges.len() as i32 - 1;
+
+        assert!(broken.face_vertices(face).is_none());
+        assert!(
+            broken
+                .pick(Vec3::new(-40., 0., -60.), Vec3::NEG_Z, 8192.)
+                .face
+                .is_none()
+        );
+
+        // texinfo and plane past the end of their lumps
+        let mut broken = Bsp::from_bytes(include_bytes!("../tests/normal.bsp")).unwrap();
+        broken.faces[face].texinfo = broken.texinfo.len() as u16;
+        broken.faces[face].plane = broken.planes.len() as u16;
+
+        assert!(broken.face_texture_name(face).is_none());
+        assert!(!broken.face_contains(face, vertices[0]));
+
+        // only picks find the face and entity
+        [HullType::Point, HullType::Stand]
+            .into_iter()
+            .for_each(|hull_type| {
+                let tr = bsp.trace_line(
+                    hull_type,
+                    Vec3::new(-40., 0., -60.),
+                    Vec3::new(-40., 0., -200.),
+                );
+
+                assert_eq!(tr.model_index, Some(0));
+                assert!(tr.face.is_none());
+                assert!(tr.entity_index.is_none());
+            });
+    }
+
+    #[test]
+    fn pick_func_wall() {
+        let bsp = Bsp::from_bytes(include_bytes!("../tests/c1a3d.bsp")).unwrap();
+
+        let tr = bsp.pick(Vec3::new(1488., -434., 744.), Vec3::X, 8192.);
+        let model = &bsp.models[15];
+        let model_faces = model.first_face as usize..(model.first_face + model.face_count) as usize;
+
+        assert_eq!(tr.model_index, Some(15));
+        assert_eq!(
+            bsp.entities[tr.entity_index.unwrap()].get("model"),
+            Some(&"*15".to_string())
+        );
+        assert!(model_faces.contains(&tr.face.unwrap()));
+
+        // every pick that hits something finds the face it hits
+        (0..200).for_each(|idx| {
+            let angle = idx as f32 * 0.37;
+            let direction = Vec3::new(angle.cos(), angle.sin(), (angle * 0.3).sin());
+            let tr = bsp.pick(Vec3::new(1488., -434., 744.), direction, 8192.);
+
+            if tr.fraction >= 1. {
+                return;
+            }
+
+            let face = tr.face.expect("no face");
+            let plane = &bsp.planes[bsp.faces[face].plane as usize];
+
+            assert!((plane.normal.dot(tr.end_pos) - plane.distance).abs() < 0.1);
+        });
+    }
+}
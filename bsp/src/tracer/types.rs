@@ -1,4 +1,4 @@
-use crate::Plane;
+use crate::{LeafContent, Plane};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[repr(C)]
@@ -9,6 +9,39 @@ pub enum HullType {
     Duck = 3,
 }
 
+impl HullType {
+    /// Mins and maxs of the box this hull is made for, same as the game.
+    pub fn size(&self) -> (glam::Vec3, glam::Vec3) {
+        let half = match self {
+            HullType::Point => glam::Vec3::ZERO,
+            HullType::Stand => glam::Vec3::new(16., 16., 36.),
+            HullType::Monster => glam::Vec3::splat(32.),
+            HullType::Duck => glam::Vec3::new(16., 16., 18.),
+        };
+
+        (-half, half)
+    }
+
+    /// Hull that the game picks for a box from `mins` to `maxs`.
+    ///
+    /// Maps only have these four, so any other box is traced as the closest one.
+    pub fn from_size(mins: glam::Vec3, maxs: glam::Vec3) -> Self {
+        let size = maxs - mins;
+
+        if size.x <= 8. {
+            HullType::Point
+        } else if size.x <= 36. {
+            if size.z <= 36. {
+                HullType::Duck
+            } else {
+                HullType::Stand
+            }
+        } else {
+            HullType::Monster
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct TraceResult {
     pub all_solid: bool,
@@ -18,6 +51,18 @@ pub struct TraceResult {
     pub fraction: f32,
     pub end_pos: glam::Vec3,
     pub plane: Plane,
+    /// Contents of the leaf where the trace ends, like water when hitting the floor of a pool.
+    pub contents: LeafContent,
+    /// Face that is hit. Only [`crate::Bsp::pick`] finds it.
+    pub face: Option<usize>,
+    /// Texture of the face that is hit. Only [`crate::Bsp::pick`] finds it.
+    pub texture_name: Option<String>,
+    /// Brush model that is hit, 0 is the world.
+    pub model_index: Option<usize>,
+    /// Index in [`crate::Bsp::entities`] of the entity using the model that is hit.
+    ///
+    /// Only [`crate::Bsp::pick`] finds it.
+    pub entity_index: Option<usize>,
 }
 
 impl Default for TraceResult {
@@ -30,6 +75,11 @@ impl Default for TraceResult {
             fraction: 1.,
             end_pos: glam::Vec3::ZERO,
             plane: Default::default(),
+            contents: LeafContent::ContentsEmpty,
+            face: None,
+            texture_name: None,
+            model_index: None,
+            entity_index: None,
         }
     }
 }
@@ -131,7 +131,7 @@ pub struct ClipNode {
     pub children: [i16; 2],
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(C)]
 pub enum LeafContent {
     ContentsEmpty = -1,
@@ -224,4 +224,6 @@ pub struct Bsp {
     pub edges: Vec<Edge>,
     pub surf_edges: Vec<SurfEdge>,
     pub models: Vec<Model>,
+    /// Index in [`Bsp::entities`] of the entity using each brush model, worldspawn for model 0.
+    pub model_entities: Vec<Option<usize>>,
 }
@@ -0,0 +1,375 @@
+//! Potentially visible set (PVS) from the visibility lump.
+use glam::Vec3;
+
+use crate::{Bsp, MarkSurface};
+
+/// Fixed size set of indices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitSet {
+    words: Vec<u64>,
+    capacity: usize,
+}
+
+impl BitSet {
+    /// Empty set that can hold indices up to `capacity`, exclusive.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            words: vec![0; capacity.div_ceil(64)],
+            capacity,
+        }
+    }
+
+    /// Set with every index up to `capacity`.
+    pub fn full(capacity: usize) -> Self {
+        let mut res = Self::new(capacity);
+
+        (0..capacity).for_each(|idx| res.insert(idx));
+
+        res
+    }
+
+    /// Number of indices the set can hold, see [`BitSet::count`] for the ones in it.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|word| *word == 0)
+    }
+
+    /// Number of indices in the set.
+    pub fn count(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Does nothing if `idx` is out of range.
+    pub fn insert(&mut self, idx: usize) {
+        if idx >= self.capacity {
+            return;
+        }
+
+        self.words[idx / 64] |= 1 << (idx % 64);
+    }
+
+    pub fn remove(&mut self, idx: usize) {
+        if idx >= self.capacity {
+            return;
+        }
+
+        self.words[idx / 64] &= !(1 << (idx % 64));
+    }
+
+    pub fn contains(&self, idx: usize) -> bool {
+        idx < self.capacity && self.words[idx / 64] & (1 << (idx % 64)) != 0
+    }
+
+    /// Adds every index of `other`.
+    pub fn union_with(&mut self, other: &BitSet) {
+        self.words
+            .iter_mut()
+            .zip(other.words.iter())
+            .for_each(|(word, other)| *word |= other);
+    }
+
+    /// Indices in the set, in order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(word_idx, word)| {
+            let mut word = *word;
+
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+
+                Some(word_idx * 64 + bit)
+            })
+        })
+    }
+}
+
+/// Decompresses one row of the visibility lump.
+///
+/// Bytes are copied as is, except a zero byte is followed by how many zero bytes it stands for.
+/// Bit `i` of the row is leaf `i + 1` because leaf 0 is the solid leaf, so the set is shifted to be indexed by leaf.
+/// Anything past `vis_leaf_count` is left out. Data ending early leaves the rest not visible.
+pub fn decompress_pvs(compressed: &[u8], vis_leaf_count: usize) -> BitSet {
+    let row_length = vis_leaf_count.div_ceil(8);
+    let mut res = BitSet::new(vis_leaf_count + 1);
+
+    let mut input = compressed.iter();
+    let mut byte_idx = 0;
+
+    while byte_idx < row_length {
+        let Some(&byte) = input.next() else {
+            break;
+        };
+
+        if byte == 0 {
+            let Some(&zero_count) = input.next() else {
+                break;
+            };
+
+            byte_idx += zero_count as usize;
+            continue;
+        }
+
+        (0..8)
+            .filter(|bit| byte & (1 << bit) != 0)
+            .for_each(|bit| res.insert(byte_idx * 8 + bit + 1));
+
+        byte_idx += 1;
+    }
+
+    res
+}
+
+impl Bsp {
+    /// Number of leaves in the world that have visibility data.
+    ///
+    /// Leaves after these belong to brush entities.
+    pub fn vis_leaf_count(&self) -> usize {
+        self.models
+            .first()
+            .map(|model| model.vis_leaves_count.max(0) as usize)
+            .unwrap_or(0)
+            .min(self.leaves.len().saturating_sub(1))
+    }
+
+    /// Index of the world leaf containing `point`.
+    ///
+    /// Leaf 0 is the solid leaf outside of the world.
+    ///
+    /// Points on a plane go behind it like `Mod_PointInLeaf` in the engine,
+    /// unlike [`Bsp::trace_point`] which puts them in front.
+    pub fn leaf_at(&self, point: Vec3) -> usize {
+        let mut num = self
+            .models
+            .first()
+            .map(|model| model.head_nodes[0])
+            .unwrap_or(0);
+
+        while num >= 0 {
+            let Some(node) = self.nodes.get(num as usize) else {
+                return 0;
+            };
+
+            let plane = &self.planes[node.plane as usize];
+            let distance = point.dot(plane.normal) - plane.distance;
+
+            num = if distance > 0. {
+                node.children[0] as i32
+            } else {
+                node.children[1] as i32
+            };
+        }
+
+        !num as usize
+    }
+
+    /// Leaves that can be seen from `leaf`, indexed by leaf.
+    ///
+    /// Everything is visible from the solid leaf, from a leaf without visibility data,
+    /// or when the map is not vis'd at all. Same as the game.
+    pub fn visible_leaves(&self, leaf: usize) -> BitSet {
+        let vis_leaf_count = self.vis_leaf_count();
+        let all_visible = || {
+            let mut res = BitSet::full(vis_leaf_count + 1);
+            res.remove(0);
+            res
+        };
+
+        if leaf == 0 || self.visibility.is_empty() {
+            return all_visible();
+        }
+
+        let Some(vis_offset) = self
+            .leaves
+            .get(leaf)
+            .and_then(|leaf| usize::try_from(leaf.vis_offset).ok())
+        else {
+            return all_visible();
+        };
+
+        let Some(compressed) = self.visibility.get(vis_offset..) else {
+            return all_visible();
+        };
+
+        decompress_pvs(compressed, vis_leaf_count)
+    }
+
+    /// Indices of the faces in `leaf`.
+    pub fn leaf_faces(&self, leaf: usize) -> &[MarkSurface] {
+        let Some(leaf) = self.leaves.get(leaf) else {
+            return &[];
+        };
+
+        let start = leaf.first_mark_surface as usize;
+        let end = start + leaf.mark_surface_count as usize;
+
+        self.mark_surfaces.get(start..end).unwrap_or_default()
+    }
+
+    /// World faces in every leaf visible from `leaf`, indexed by face.
+    ///
+    /// Faces of brush entities are not in any leaf, they are only culled by their bounding box.
+    pub fn visible_faces(&self, leaf: usize) -> BitSet {
+        let mut res = BitSet::new(self.faces.len());
+
+        self.visible_leaves(leaf)
+            .iter()
+            .flat_map(|leaf| self.leaf_faces(leaf))
+            .for_each(|&face| res.insert(face as usize));
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::LeafContent;
+
+    #[test]
+    fn decompress() {
+        // leaves 1 to 8, then 16 zero leaves, then leaf 25
+        let pvs = decompress_pvs(&[0xff, 0x00, 0x02, 0x01], 32);
+
+        assert_eq!(pvs.capacity(), 33);
+        assert_eq!(pvs.iter().collect::<Vec<_>>(), [1, 2, 3, 4, 5, 6, 7, 8, 25]);
+
+        // leaf count cuts the last byte
+        let pvs = decompress_pvs(&[0x00, 0x01, 0xff], 12);
+
+        assert_eq!(pvs.iter().collect::<Vec<_>>(), [9, 10, 11, 12]);
+
+        // data ending early
+        let pvs = decompress_pvs(&[0x01, 0x00], 64);
+
+        assert_eq!(pvs.iter().collect::<Vec<_>>(), [1]);
+    }
+
+    #[test]
+    fn bit_set() {
+        let mut set = BitSet::new(130);
+
+        [0, 63, 64, 129, 130]
+            .into_iter()
+            .for_each(|idx| set.insert(idx));
+
+        assert_eq!(set.iter().collect::<Vec<_>>(), [0, 63, 64, 129]);
+        assert_eq!(set.count(), 4);
+        assert!(!set.contains(130));
+
+        set.remove(63);
+
+        let mut other = BitSet::new(130);
+        other.insert(1);
+        set.union_with(&other);
+
+        assert_eq!(set.iter().collect::<Vec<_>>(), [0, 1, 64, 129]);
+        assert_eq!(BitSet::full(70).count(), 70);
+    }
+
+    #[test]
+    fn leaf_at_normal() {
+        let bsp = Bsp::from_bytes(include_bytes!("tests/normal.bsp")).unwrap();
+
+        // standing in the room
+        let leaf = bsp.leaf_at(Vec3::new(-40., 0., -67.96875));
+
+        assert_ne!(leaf, 0);
+        assert!(matches!(
+            bsp.leaves[leaf].contents,
+            LeafContent::ContentsEmpty
+        ));
+
+        // way outside of the map
+        assert_eq!(bsp.leaf_at(Vec3::new(9999., 9999., 9999.)), 0);
+
+        // one room sees everything
+        let visible = bsp.visible_leaves(leaf);
+
+        assert!(visible.contains(leaf));
+        assert!(!visible.contains(0));
+
+        let faces = bsp.visible_faces(leaf);
+
+        bsp.leaf_faces(leaf)
+            .iter()
+            .for_each(|&face| assert!(faces.contains(face as usize)));
+    }
+
+    #[test]
+    fn visible_leaves_c1a3d() {
+        let bsp = Bsp::from_bytes(include_bytes!("tests/c1a3d.bsp")).unwrap();
+        let vis_leaf_count = bsp.vis_leaf_count();
+
+        assert_eq!(vis_leaf_count, 514);
+
+        // everything from outside
+        assert_eq!(bsp.visible_leaves(0).count(), vis_leaf_count);
+
+        let mut culled_leaves = 0;
+        let mut visible_pairs = 0;
+        let mut one_way_pairs = 0;
+
+        (1..=vis_leaf_count).for_each(|leaf| {
+            let visible = bsp.visible_leaves(leaf);
+
+            assert_eq!(visible.capacity(), vis_leaf_count + 1);
+            assert!(!visible.contains(0));
+
+            if !matches!(bsp.leaves[leaf].contents, LeafContent::ContentsSolid) {
+                assert!(visible.contains(leaf), "leaf {leaf} cannot see itself");
+            }
+
+            if visible.count() < vis_leaf_count {
+                culled_leaves += 1;
+            }
+
+            // leaves it sees should see it back, vis isn't exactly symmetric though
+            visible.iter().for_each(|other| {
+                visible_pairs += 1;
+
+                if !bsp.visible_leaves(other).contains(leaf) {
+                    one_way_pairs += 1;
+                }
+            });
+        });
+
+        // a real map culls something
+        assert!(culled_leaves > vis_leaf_count / 2);
+        // reading the rows wrong would be nowhere near symmetric
+        assert!(
+            one_way_pairs * 100 < visible_pairs,
+            "{one_way_pairs}/{visible_pairs}"
+        );
+    }
+
+    #[test]
+    fn leaf_at_matches_trace_point() {
+        let bsp = Bsp::from_bytes(include_bytes!("tests/datacore.bsp")).unwrap();
+        let (mins, maxs) = (bsp.models[0].mins, bsp.models[0].maxs);
+
+        // a grid over the map, off the bounds because points on a plane go different ways
+        (0..10)
+            .flat_map(|x| (0..10).flat_map(move |y| (0..10).map(move |z| (x, y, z))))
+            .for_each(|(x, y, z)| {
+                let point =
+                    mins + (maxs - mins) * (Vec3::new(x as f32, y as f32, z as f32) + 0.5) / 10.;
+                let leaf = bsp.leaf_at(point);
+
+                assert_eq!(
+                    bsp.leaves[leaf].contents as i32,
+                    bsp.trace_point(0, point) as i32
+                );
+                assert!(leaf <= bsp.vis_leaf_count());
+            });
+    }
+}
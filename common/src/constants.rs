@@ -3,6 +3,7 @@ pub const UNKNOWN_GAME_MOD: &str = "unknown";
 pub const API_SCOPE_VERSION: &str = "v1";
 pub const GET_MAPS_ENDPOINT: &str = "maps";
 pub const GET_REPLAYS_ENDPOINT: &str = "replays";
+pub const GET_REPLAY_METADATA_ENDPOINT: &str = "replay-metadata";
 pub const REQUEST_COMMON_RESOURCE_ENDPOINT: &str = "common-resource";
 
 // for CheckHostConfiguration
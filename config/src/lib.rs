@@ -4,7 +4,6 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use ghost::GhostBlobType;
 use serde::{Deserialize, Serialize};
 
 /// For native
@@ -22,7 +21,14 @@ pub struct KDRApiServerConfig {
     pub replay_folders: Vec<PathBuf>,
     pub replay_formats: Vec<String>,
     pub replay_folders_search_recursively: bool,
-    pub replay_unknown_format_override: Option<GhostBlobType>,
+    /// Parser name to use when the replay content and file name don't match any parser.
+    pub replay_unknown_format_override: Option<String>,
+    /// Names of the ghost parsers to use. Empty means every registered parser.
+    #[serde(default)]
+    pub replay_parsers: Vec<String>,
+    /// Parses replays on the server and sends them as kdr ghost instead of the original file.
+    #[serde(default)]
+    pub replay_send_as_kdr: bool,
 
     pub port: u16,
     pub use_resmake_zip: bool,
@@ -82,7 +88,9 @@ mod test {
             replay_folders: vec!["/path/to/foldre1".into(), "/path/to/pardre2".into()],
             replay_formats: vec!["dem".to_string(), "dat".to_string()],
             replay_folders_search_recursively: false,
-            replay_unknown_format_override: Some(ghost::GhostBlobType::SRHLKZ),
+            replay_unknown_format_override: Some("hlkz".to_string()),
+            replay_parsers: vec!["demo".to_string(), "hlkz".to_string()],
+            replay_send_as_kdr: true,
             port: 3001,
             use_resmake_zip: false,
             secret: "abcd".into(),
@@ -0,0 +1,376 @@
+//! Movement data derived from ghost frames, such as speed, jumps and strafes.
+//!
+//! Only origin, viewangles, frametime and buttons are used so every format works.
+//! There is no map here so ground is guessed from vertical speed. Walking on slopes counts as being in the air.
+use common::angle_diff;
+use glam::Vec3;
+
+use crate::{DEFAULT_GHOST_FRAMETIME, GhostFrame, GhostInfo, IN_DUCK, IN_MOVELEFT, IN_MOVERIGHT};
+
+// vertical speed below this is not moving vertically
+const GROUND_SPEED_EPSILON: f32 = 0.1;
+// yaw change below this is not turning
+const YAW_EPSILON: f32 = 0.001;
+// view offset is 12 when ducking, 17 in cs and 28 in hl when standing
+const DUCK_VIEWOFFSET_MAX: f32 = 13.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrafeDirection {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveEvent {
+    /// Leaves the ground going up.
+    Jump,
+    Land,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FrameStats {
+    /// Time when the frame starts.
+    pub time: f32,
+    /// From the previous frame to this frame.
+    pub velocity: Vec3,
+    pub horizontal_speed: f32,
+    pub vertical_speed: f32,
+    pub on_ground: bool,
+    /// From buttons, or from view offset if there is no buttons.
+    pub ducking: bool,
+    /// Yaw change from the previous frame in degrees. Positive is turning left.
+    pub yaw_delta: f32,
+    /// From buttons, or from turning direction in the air if there is no buttons.
+    pub strafe: Option<StrafeDirection>,
+    pub event: Option<MoveEvent>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RunStats {
+    pub duration: f32,
+    /// Horizontal distance travelled.
+    pub distance: f32,
+    /// Horizontal.
+    pub max_speed: f32,
+    /// Horizontal.
+    pub average_speed: f32,
+    pub jumps: usize,
+    /// Strafes in the air, counted whenever the strafe direction changes.
+    pub strafes: usize,
+    /// Share of turning air frames where the strafe key is on the same side as turning, from 0 to 1.
+    ///
+    /// `None` when the ghost has no buttons or never turns in the air.
+    pub sync: Option<f32>,
+    pub air_time: f32,
+    pub duck_time: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RunAnalysis {
+    pub stats: RunStats,
+    /// Same length as the ghost frames.
+    pub frames: Vec<FrameStats>,
+}
+
+impl FrameStats {
+    fn turning(&self) -> Option<StrafeDirection> {
+        if self.yaw_delta > YAW_EPSILON {
+            Some(StrafeDirection::Left)
+        } else if self.yaw_delta < -YAW_EPSILON {
+            Some(StrafeDirection::Right)
+        } else {
+            None
+        }
+    }
+}
+
+impl GhostInfo {
+    /// See [`analyze_frames`].
+    pub fn analyze(&self) -> RunAnalysis {
+        analyze_frames(self.frames())
+    }
+}
+
+/// Computes per frame movement data and a summary of the run.
+///
+/// Frames without frametime use [`DEFAULT_GHOST_FRAMETIME`].
+pub fn analyze_frames(frames: &[GhostFrame]) -> RunAnalysis {
+    let frametime = |frame: &GhostFrame| frame.frametime.unwrap_or(DEFAULT_GHOST_FRAMETIME);
+
+    // velocity first because ground needs the next frame
+    let mut time = 0f32;
+    let mut velocity = Vec3::ZERO;
+
+    let mut frame_stats: Vec<FrameStats> = frames
+        .iter()
+        .enumerate()
+        .map(|(idx, frame)| {
+            let mut yaw_delta = 0.;
+
+            if idx != 0 {
+                let prev = &frames[idx - 1];
+                // same as playback, previous frame lasts for its frametime
+                let dt = frametime(prev);
+
+                time += dt;
+
+                // duplicated frames keep the last velocity
+                if dt > 0. {
+                    velocity = (frame.origin - prev.origin) / dt;
+                }
+
+                yaw_delta = angle_diff(prev.viewangles[1], frame.viewangles[1]);
+            }
+
+            let ducking = match frame.buttons {
+                Some(buttons) => buttons & IN_DUCK != 0,
+                None => frame.viewoffset_z > 0. && frame.viewoffset_z <= DUCK_VIEWOFFSET_MAX,
+            };
+
+            FrameStats {
+                time,
+                velocity,
+                horizontal_speed: velocity.truncate().length(),
+                vertical_speed: velocity.z,
+                ducking,
+                yaw_delta,
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    let is_flat = |idx: usize| frame_stats[idx].vertical_speed.abs() < GROUND_SPEED_EPSILON;
+
+    // apex of a jump is only flat for one frame
+    let on_ground: Vec<bool> = (0..frame_stats.len())
+        .map(|idx| {
+            is_flat(idx)
+                && ((idx > 0 && is_flat(idx - 1))
+                    || (idx + 1 < frame_stats.len() && is_flat(idx + 1)))
+        })
+        .collect();
+
+    let mut stats = RunStats::default();
+
+    frames
+        .iter()
+        .zip(frame_stats.iter_mut())
+        .enumerate()
+        .for_each(|(idx, (frame, frame_stat))| {
+            frame_stat.on_ground = on_ground[idx];
+
+            if idx != 0 {
+                frame_stat.event = match (on_ground[idx - 1], on_ground[idx]) {
+                    (true, false) if frame_stat.vertical_speed > 0. => Some(MoveEvent::Jump),
+                    (false, true) => Some(MoveEvent::Land),
+                    _ => None,
+                };
+            }
+
+            let turning = frame_stat.turning();
+
+            frame_stat.strafe = match frame.buttons {
+                Some(buttons) => match (buttons & IN_MOVELEFT != 0, buttons & IN_MOVERIGHT != 0) {
+                    (true, false) => Some(StrafeDirection::Left),
+                    (false, true) => Some(StrafeDirection::Right),
+                    _ => None,
+                },
+                None if !frame_stat.on_ground => turning,
+                None => None,
+            };
+
+            // summary
+            let dt = frametime(frame);
+
+            stats.duration += dt;
+            stats.max_speed = stats.max_speed.max(frame_stat.horizontal_speed);
+
+            if frame_stat.event == Some(MoveEvent::Jump) {
+                stats.jumps += 1;
+            }
+
+            if frame_stat.ducking {
+                stats.duck_time += dt;
+            }
+
+            if !frame_stat.on_ground {
+                stats.air_time += dt;
+            }
+        });
+
+    (stats.strafes, stats.sync) = strafe_sync(
+        frames
+            .iter()
+            .zip(frame_stats.iter())
+            .map(|(frame, frame_stat)| (frame, frame_stat, !frame_stat.on_ground)),
+    );
+
+    stats.distance = frames
+        .windows(2)
+        .map(|pair| (pair[1].origin - pair[0].origin).truncate().length())
+        .sum();
+
+    if stats.duration > 0. {
+        stats.average_speed = stats.distance / stats.duration;
+    }
+
+    RunAnalysis {
+        stats,
+        frames: frame_stats,
+    }
+}
+
+/// Strafe count and sync of frames that are in the air, same as in [`RunStats`].
+///
+/// Frames are (Frame, Stats, In Air). Ground frames still keep track of the strafe key,
+/// so holding it from the ground is not a new strafe.
+pub(crate) fn strafe_sync<'a>(
+    frames: impl Iterator<Item = (&'a GhostFrame, &'a FrameStats, bool)>,
+) -> (usize, Option<f32>) {
+    let mut strafes = 0;
+    let mut synced_frames = 0usize;
+    let mut turning_frames = 0usize;
+    let mut prev_strafe = None;
+
+    frames.for_each(|(frame, frame_stat, in_air)| {
+        if !in_air {
+            prev_strafe = frame_stat.strafe;
+            return;
+        }
+
+        if frame_stat.strafe.is_some() && frame_stat.strafe != prev_strafe {
+            strafes += 1;
+        }
+
+        let turning = frame_stat.turning();
+
+        if frame.buttons.is_some() && turning.is_some() {
+            turning_frames += 1;
+
+            if frame_stat.strafe == turning {
+                synced_frames += 1;
+            }
+        }
+
+        prev_strafe = frame_stat.strafe;
+    });
+
+    let sync = (turning_frames > 0).then(|| synced_frames as f32 / turning_frames as f32);
+
+    (strafes, sync)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{IN_FORWARD, get_ghost::sample_frame};
+
+    const FRAMETIME: f32 = 0.01;
+
+    /// Runs forward at 250 ups, jumps at frame 10 and strafes left then right in the air.
+    fn jump_frames(with_buttons: bool) -> Vec<GhostFrame> {
+        let mut frames = vec![];
+        let mut origin = Vec3::ZERO;
+        let mut yaw = 0f32;
+
+        (0..10).for_each(|_| {
+            origin.x += 250. * FRAMETIME;
+            frames.push(sample_frame(
+                origin,
+                yaw,
+                Some(FRAMETIME),
+                with_buttons.then_some(IN_FORWARD),
+            ));
+        });
+
+        // jump of 268 ups with 800 gravity lands after 67 frames
+        let mut vz = 268f32;
+
+        (0..67).for_each(|idx| {
+            origin.x += 250. * FRAMETIME;
+            origin.z += vz * FRAMETIME;
+            vz -= 800. * FRAMETIME;
+
+            let (buttons, turn) = if idx < 33 {
+                (IN_MOVELEFT | IN_DUCK, 1.)
+            } else {
+                (IN_MOVERIGHT | IN_DUCK, -1.)
+            };
+
+            yaw += turn;
+            frames.push(sample_frame(
+                origin,
+                yaw,
+                Some(FRAMETIME),
+                with_buttons.then_some(buttons),
+            ));
+        });
+
+        origin.z = 0.;
+
+        (0..10).for_each(|_| {
+            origin.x += 250. * FRAMETIME;
+            frames.push(sample_frame(
+                origin,
+                yaw,
+                Some(FRAMETIME),
+                with_buttons.then_some(0),
+            ));
+        });
+
+        frames
+    }
+
+    #[test]
+    fn jump_and_strafes() {
+        let analysis = analyze_frames(&jump_frames(true));
+        let stats = &analysis.stats;
+
+        assert_eq!(analysis.frames.len(), 87);
+        assert_eq!(stats.jumps, 1);
+        assert_eq!(stats.strafes, 2);
+        assert_eq!(stats.sync, Some(1.));
+        assert!((stats.max_speed - 250.).abs() < 0.01);
+        assert!((stats.duration - 0.87).abs() < 0.001);
+        assert!((stats.duck_time - 0.67).abs() < 0.001);
+
+        assert!(analysis.frames[5].on_ground);
+        assert!(!analysis.frames[40].on_ground);
+        assert_eq!(analysis.frames[10].event, Some(MoveEvent::Jump));
+        assert!(
+            analysis
+                .frames
+                .iter()
+                .any(|frame| frame.event == Some(MoveEvent::Land))
+        );
+        assert!(analysis.frames[10].vertical_speed > 260.);
+        assert_eq!(analysis.frames[20].strafe, Some(StrafeDirection::Left));
+        assert!(analysis.frames[20].ducking);
+        assert!(analysis.frames[20].yaw_delta > 0.);
+    }
+
+    #[test]
+    fn strafe_held_from_ground() {
+        let mut frames = jump_frames(true);
+
+        frames[..10]
+            .iter_mut()
+            .for_each(|frame| frame.buttons = Some(IN_FORWARD | IN_MOVELEFT));
+
+        // only changing to right is a new strafe
+        assert_eq!(analyze_frames(&frames).stats.strafes, 1);
+    }
+
+    #[test]
+    fn no_buttons() {
+        let analysis = analyze_frames(&jump_frames(false));
+        let stats = &analysis.stats;
+
+        assert_eq!(stats.jumps, 1);
+        // strafes from turning
+        assert_eq!(stats.strafes, 2);
+        assert_eq!(stats.sync, None);
+        assert_eq!(stats.duck_time, 0.);
+        assert_eq!(analysis.frames[70].strafe, Some(StrafeDirection::Right));
+    }
+}
@@ -0,0 +1,604 @@
+//! Heuristics for suspicious input and timing, like what admins look for in demo checkers.
+//!
+//! Nothing here proves cheating. Findings only point at frames worth watching.
+use common::angle_diff;
+
+use crate::{
+    GhostInfo, IN_JUMP, RunAnalysis,
+    analysis::{FrameStats, strafe_sync},
+};
+
+// msec is a byte in usercmd
+const MAX_FRAMETIME: f32 = 0.255;
+// frametime compared against the median of the chunks of this many frames before and after its own
+const FRAMETIME_WINDOW: usize = 100;
+// frametime off by more than this ratio from the median
+const FRAMETIME_MAX_RATIO: f32 = 2.;
+// this many anomalies in a row is more than lag
+const FRAMETIME_STREAK: usize = 3;
+// median frametime of the next window differs by more than this ratio
+const FPS_CHANGE_MIN_RATIO: f32 = 1.1;
+// view turning more than this in one frame
+const ANGLE_SNAP_DEGREES: f32 = 45.;
+// snapping back within this many frames is an aimbot or a strafe hack
+const ANGLE_SNAP_BACK_FRAMES: usize = 3;
+// teleports can set viewangles
+const TELEPORT_DISTANCE: f32 = 64.;
+// sync of an air phase to count as perfect
+const PERFECT_SYNC: f32 = 0.98;
+// air phases need this many strafes to count for sync
+const PERFECT_SYNC_MIN_STRAFES: usize = 3;
+const PERFECT_SYNC_STREAK: usize = 5;
+// vertical speed going up this much in one frame is a jump
+const JUMP_IMPULSE: f32 = 150.;
+// jump pressed for the first time this many frames before takeoff at most
+const SCRIPTED_JUMP_MAX_FRAMES: usize = 1;
+// and not pressed at all in this many frames before that
+const SCRIPTED_JUMP_LOOKBACK: usize = 10;
+const SCRIPTED_JUMP_STREAK: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Can happen in a legit run.
+    Info,
+    Warning,
+    Suspicious,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindingKind {
+    /// Frametime is invalid or far from the frames around it, as with speedhack.
+    Frametime,
+    /// View turns too much in one frame.
+    AngleSnap,
+    /// Air strafes with perfect sync for many jumps in a row.
+    PerfectSync,
+    /// Bhops where jump is only ever pressed on the ground frame.
+    ScriptedJump,
+    FpsChange,
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    /// Frame where it starts.
+    pub frame: usize,
+    pub kind: FindingKind,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl GhostInfo {
+    /// See [`check_ghost`].
+    pub fn check(&self, analysis: &RunAnalysis) -> Vec<Finding> {
+        check_ghost(self, analysis)
+    }
+}
+
+/// Scans the ghost for suspicious input and timing, sorted by frame.
+///
+/// `analysis` is from [`GhostInfo::analyze`]. Checks that need frametime or buttons are skipped without them.
+pub fn check_ghost(ghost: &GhostInfo, analysis: &RunAnalysis) -> Vec<Finding> {
+    let mut findings = vec![];
+
+    if let Some(frametimes) = frametimes(ghost) {
+        let medians = chunk_medians(&frametimes);
+
+        check_frametime(ghost, &frametimes, &medians, &mut findings);
+        check_fps_change(&frametimes, &medians, &mut findings);
+    }
+
+    check_angle_snap(ghost, &mut findings);
+    check_perfect_sync(ghost, analysis, &mut findings);
+    check_scripted_jump(ghost, analysis, &mut findings);
+
+    findings.sort_by_key(|finding| finding.frame);
+
+    findings
+}
+
+fn frametimes(ghost: &GhostInfo) -> Option<Vec<f32>> {
+    ghost.frames().iter().map(|frame| frame.frametime).collect()
+}
+
+/// Median of every [`FRAMETIME_WINDOW`] frames, the last chunk can be shorter.
+fn chunk_medians(frametimes: &[f32]) -> Vec<f32> {
+    let mut chunk_buffer = Vec::with_capacity(FRAMETIME_WINDOW);
+
+    frametimes
+        .chunks(FRAMETIME_WINDOW)
+        .map(|chunk| {
+            chunk_buffer.clear();
+            chunk_buffer.extend_from_slice(chunk);
+
+            let middle = chunk_buffer.len() / 2;
+
+            *chunk_buffer
+                .select_nth_unstable_by(middle, f32::total_cmp)
+                .1
+        })
+        .collect()
+}
+
+fn check_frametime(
+    ghost: &GhostInfo,
+    frametimes: &[f32],
+    medians: &[f32],
+    findings: &mut Vec<Finding>,
+) {
+    let is_anomaly = |idx: usize| {
+        let frametime = frametimes[idx];
+
+        // demo time starts over in a new entry after level change
+        if frametime == 0.
+            && ghost
+                .segments
+                .iter()
+                .any(|segment| segment.start_frame == idx)
+        {
+            return false;
+        }
+
+        if frametime <= 0. || frametime > MAX_FRAMETIME {
+            return true;
+        }
+
+        let is_off = |median: f32| {
+            frametime > median * FRAMETIME_MAX_RATIO || frametime < median / FRAMETIME_MAX_RATIO
+        };
+
+        // fps changes only differ from one side
+        let chunk = idx / FRAMETIME_WINDOW;
+
+        [chunk.checked_sub(1), Some(chunk + 1)]
+            .into_iter()
+            .filter_map(|chunk| medians.get(chunk?).copied())
+            .map(is_off)
+            .reduce(|a, b| a && b)
+            .unwrap_or(false)
+    };
+
+    let anomalies: Vec<bool> = (0..frametimes.len()).map(is_anomaly).collect();
+    let mut idx = 0;
+
+    while idx < anomalies.len() {
+        if !anomalies[idx] {
+            idx += 1;
+            continue;
+        }
+
+        let streak = anomalies[idx..]
+            .iter()
+            .take_while(|&&anomaly| anomaly)
+            .count();
+
+        let severity = if streak >= FRAMETIME_STREAK {
+            Severity::Warning
+        } else {
+            Severity::Info
+        };
+
+        findings.push(Finding {
+            frame: idx,
+            kind: FindingKind::Frametime,
+            severity,
+            message: format!(
+                "{} frame(s) with unusual frametime, first is {:.01} msec",
+                streak,
+                frametimes[idx] * 1000.
+            ),
+        });
+
+        idx += streak;
+    }
+}
+
+fn check_fps_change(frametimes: &[f32], medians: &[f32], findings: &mut Vec<Finding>) {
+    // last one can be too short to tell
+    let window_medians = &medians[..frametimes.len() / FRAMETIME_WINDOW];
+
+    window_medians
+        .windows(2)
+        .enumerate()
+        .filter(|(_, pair)| pair[0] > 0. && pair[1] > 0.)
+        .filter(|(_, pair)| pair[0].max(pair[1]) / pair[0].min(pair[1]) > FPS_CHANGE_MIN_RATIO)
+        .for_each(|(idx, pair)| {
+            findings.push(Finding {
+                frame: (idx + 1) * FRAMETIME_WINDOW,
+                kind: FindingKind::FpsChange,
+                severity: Severity::Warning,
+                message: format!(
+                    "FPS changes from {:.0} to {:.0}",
+                    1. / pair[0],
+                    1. / pair[1]
+                ),
+            });
+        });
+}
+
+fn check_angle_snap(ghost: &GhostInfo, findings: &mut Vec<Finding>) {
+    let frames = ghost.frames();
+
+    // pitch and yaw
+    let view_delta = |idx: usize| {
+        let (from, to) = (&frames[idx - 1], &frames[idx]);
+
+        (
+            angle_diff(from.viewangles[0], to.viewangles[0]),
+            angle_diff(from.viewangles[1], to.viewangles[1]),
+        )
+    };
+
+    let is_snap = |(pitch, yaw): (f32, f32)| pitch.hypot(yaw) > ANGLE_SNAP_DEGREES;
+
+    // snapping back is part of the previous finding
+    let mut skip_until = 0;
+
+    for idx in 1..frames.len() {
+        let delta = view_delta(idx);
+
+        if idx <= skip_until || !is_snap(delta) {
+            continue;
+        }
+
+        if frames[idx].origin.distance(frames[idx - 1].origin) > TELEPORT_DISTANCE {
+            continue;
+        }
+
+        // going back the same way soon after
+        let snap_back = (idx + 1..frames.len())
+            .take(ANGLE_SNAP_BACK_FRAMES)
+            .find(|&back_idx| {
+                let back = view_delta(back_idx);
+
+                is_snap(back) && back.1.signum() != delta.1.signum()
+            });
+
+        let snaps_back = snap_back.is_some();
+
+        if let Some(back_idx) = snap_back {
+            skip_until = back_idx;
+        }
+
+        let severity = if snaps_back {
+            Severity::Suspicious
+        } else {
+            Severity::Warning
+        };
+
+        findings.push(Finding {
+            frame: idx,
+            kind: FindingKind::AngleSnap,
+            severity,
+            message: format!(
+                "View turns {:.01} degrees in one frame{}",
+                delta.0.hypot(delta.1),
+                if snaps_back { " and back" } else { "" }
+            ),
+        });
+    }
+}
+
+/// Frame ranges in the air, from the analysis ground guess.
+fn air_phases(frame_stats: &[FrameStats]) -> Vec<std::ops::Range<usize>> {
+    let mut phases = vec![];
+    let mut start = None;
+
+    frame_stats
+        .iter()
+        .enumerate()
+        .for_each(|(idx, frame_stat)| match (frame_stat.on_ground, start) {
+            (false, None) => start = Some(idx),
+            (true, Some(air_start)) => {
+                phases.push(air_start..idx);
+                start = None;
+            }
+            _ => (),
+        });
+
+    phases
+}
+
+fn check_perfect_sync(ghost: &GhostInfo, analysis: &RunAnalysis, findings: &mut Vec<Finding>) {
+    if ghost.frames().iter().any(|frame| frame.buttons.is_none()) {
+        return;
+    }
+
+    let mut streak: Vec<usize> = vec![];
+
+    let mut push_streak = |streak: &mut Vec<usize>| {
+        if streak.len() >= PERFECT_SYNC_STREAK {
+            findings.push(Finding {
+                frame: streak[0],
+                kind: FindingKind::PerfectSync,
+                severity: Severity::Suspicious,
+                message: format!("{} jumps in a row with perfect sync", streak.len()),
+            });
+        }
+
+        streak.clear();
+    };
+
+    air_phases(&analysis.frames).into_iter().for_each(|phase| {
+        let (strafes, sync) = strafe_sync(
+            ghost.frames()[phase.clone()]
+                .iter()
+                .zip(analysis.frames[phase.clone()].iter())
+                .map(|(frame, frame_stat)| (frame, frame_stat, true)),
+        );
+
+        // not strafing is not breaking the streak
+        if strafes < PERFECT_SYNC_MIN_STRAFES {
+            return;
+        }
+
+        if sync.is_some_and(|sync| sync >= PERFECT_SYNC) {
+            streak.push(phase.start);
+        } else {
+            push_streak(&mut streak);
+        }
+    });
+
+    push_streak(&mut streak);
+}
+
+fn check_scripted_jump(ghost: &GhostInfo, analysis: &RunAnalysis, findings: &mut Vec<Finding>) {
+    let frames = ghost.frames();
+    let frame_stats = &analysis.frames;
+
+    if frames.iter().any(|frame| frame.buttons.is_none()) {
+        return;
+    }
+
+    let is_jumping = |idx: usize| {
+        frames[idx]
+            .buttons
+            .is_some_and(|buttons| buttons & IN_JUMP != 0)
+    };
+
+    let mut streak: Vec<usize> = vec![];
+
+    let mut push_streak = |streak: &mut Vec<usize>| {
+        if streak.len() >= SCRIPTED_JUMP_STREAK {
+            findings.push(Finding {
+                frame: streak[0],
+                kind: FindingKind::ScriptedJump,
+                severity: Severity::Suspicious,
+                message: format!(
+                    "{} bhops in a row with jump pressed only on the ground frame",
+                    streak.len()
+                ),
+            });
+        }
+
+        streak.clear();
+    };
+
+    // ground guess of the analysis needs 2 flat frames so perfect bhops are found from the jump impulse
+    for idx in 2..frames.len() {
+        let impulse = frame_stats[idx].vertical_speed - frame_stats[idx - 1].vertical_speed;
+
+        if impulse < JUMP_IMPULSE {
+            continue;
+        }
+
+        // still falling right before the jump, so there is no ground frame in between
+        let is_bhop = frame_stats[idx - 1].vertical_speed < 0.;
+
+        // first frame of this press
+        let press_start = (0..idx)
+            .rev()
+            .find(|&i| !is_jumping(i))
+            .map(|i| i + 1)
+            .unwrap_or(0);
+
+        // holding or scrolling jump presses it before landing
+        let pressed_before =
+            (press_start.saturating_sub(SCRIPTED_JUMP_LOOKBACK)..press_start).any(is_jumping);
+
+        if is_bhop && idx - press_start <= SCRIPTED_JUMP_MAX_FRAMES && !pressed_before {
+            streak.push(idx);
+        } else {
+            push_streak(&mut streak);
+        }
+    }
+
+    push_streak(&mut streak);
+}
+
+#[cfg(test)]
+mod test {
+    use glam::Vec3;
+
+    use super::*;
+    use crate::{
+        GhostFrame, GhostSegment,
+        get_ghost::{demo::demo_frametime, sample_frame, sample_ghost},
+    };
+
+    const FRAMETIME: f32 = 0.01;
+
+    fn check(frames: Vec<GhostFrame>) -> Vec<Finding> {
+        let ghost = sample_ghost(frames);
+
+        ghost.check(&ghost.analyze())
+    }
+
+    fn kinds(findings: &[Finding]) -> Vec<FindingKind> {
+        findings.iter().map(|finding| finding.kind).collect()
+    }
+
+    #[test]
+    fn clean_walk() {
+        let frames = (0..500)
+            .map(|idx| {
+                sample_frame(
+                    Vec3::new(idx as f32 * 2.5, 0., 0.),
+                    0.,
+                    Some(FRAMETIME),
+                    Some(0),
+                )
+            })
+            .collect();
+
+        assert!(check(frames).is_empty());
+    }
+
+    #[test]
+    fn timing() {
+        let frames = (0..500)
+            .map(|idx| {
+                let frametime = match idx {
+                    // speedhack
+                    50..55 => FRAMETIME * 4.,
+                    // 100 fps to 250 fps
+                    300.. => 0.004,
+                    _ => FRAMETIME,
+                };
+
+                sample_frame(
+                    Vec3::new(idx as f32 * 2.5, 0., 0.),
+                    0.,
+                    Some(frametime),
+                    Some(0),
+                )
+            })
+            .collect();
+
+        let findings = check(frames);
+
+        assert_eq!(
+            kinds(&findings),
+            vec![FindingKind::Frametime, FindingKind::FpsChange]
+        );
+        assert_eq!(findings[0].frame, 50);
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert_eq!(findings[1].frame, 300);
+    }
+
+    #[test]
+    fn angle_snap_back() {
+        let frames = (0..200)
+            .map(|idx| {
+                let yaw = if idx == 100 { 90. } else { 0. };
+
+                sample_frame(
+                    Vec3::new(idx as f32 * 2.5, 0., 0.),
+                    yaw,
+                    Some(FRAMETIME),
+                    Some(0),
+                )
+            })
+            .collect();
+
+        let findings = check(frames);
+
+        assert_eq!(kinds(&findings), vec![FindingKind::AngleSnap]);
+        assert_eq!(findings[0].frame, 100);
+        assert_eq!(findings[0].severity, Severity::Suspicious);
+    }
+
+    #[test]
+    fn scripted_bhops() {
+        let mut frames = vec![];
+        let mut origin = Vec3::ZERO;
+
+        (0..20).for_each(|_| {
+            origin.x += 2.5;
+            frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0)));
+        });
+
+        (0..12).for_each(|_| {
+            // jump only on the landing frame
+            frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(IN_JUMP)));
+
+            let mut vz = 268f32;
+
+            (0..67).for_each(|_| {
+                origin.x += 2.5;
+                origin.z += vz * FRAMETIME;
+                vz -= 800. * FRAMETIME;
+                frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0)));
+            });
+
+            origin.z = 0.;
+        });
+
+        (0..20).for_each(|_| {
+            origin.x += 2.5;
+            frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0)));
+        });
+
+        let findings = check(frames);
+
+        assert!(kinds(&findings).contains(&FindingKind::ScriptedJump));
+    }
+
+    #[test]
+    fn demo_frames() {
+        // (time, origin, buttons) as in netmsg frames, time is accumulative and jitters around 100 fps
+        let mut demo_frames = vec![];
+        let mut origin = Vec3::ZERO;
+        let mut time = 0.5f32;
+
+        let mut push = |origin: Vec3, buttons: u32| {
+            let idx = demo_frames.len();
+
+            // time starts over in the entry after the level change
+            time = if idx == 400 {
+                0.
+            } else {
+                time + if idx % 2 == 0 { 0.0099 } else { 0.0101 }
+            };
+
+            demo_frames.push((time, origin, buttons));
+        };
+
+        (0..20).for_each(|_| {
+            origin.x += 2.5;
+            push(origin, 0);
+        });
+
+        (0..12).for_each(|_| {
+            push(origin, IN_JUMP);
+
+            let mut vz = 268f32;
+
+            (0..67).for_each(|_| {
+                origin.x += 2.5;
+                origin.z += vz * FRAMETIME;
+                vz -= 800. * FRAMETIME;
+                push(origin, 0);
+            });
+
+            origin.z = 0.;
+        });
+
+        let frames: Vec<GhostFrame> = demo_frames
+            .iter()
+            .scan(0., |last_time, &(time, origin, buttons)| {
+                let frametime = demo_frametime(time, *last_time, 10);
+                *last_time = time;
+
+                Some(sample_frame(origin, 0., Some(frametime), Some(buttons)))
+            })
+            .collect();
+
+        let mut ghost = sample_ghost(frames);
+
+        ghost.segments.push(GhostSegment {
+            map_name: "next".to_string(),
+            start_frame: 400,
+        });
+
+        let findings = ghost.check(&ghost.analyze());
+
+        assert_eq!(kinds(&findings), vec![FindingKind::ScriptedJump]);
+
+        // older ghosts have no frametime where the time starts over
+        ghost.edit_frames(|frames| frames[400].frametime = Some(0.));
+
+        let findings = ghost.check(&ghost.analyze());
+
+        assert_eq!(kinds(&findings), vec![FindingKind::ScriptedJump]);
+    }
+}
@@ -0,0 +1,427 @@
+//! Time ahead or behind between two ghosts of the same map, compared at the same spot.
+//!
+//! Only origin and time are used, so every ghost format works as long as it has frametime.
+use glam::Vec3;
+
+use crate::{GhostError, GhostInfo};
+
+// frames further than this from the other path have no delta
+const MAX_MATCH_DISTANCE: f32 = 256.;
+// matching this much earlier than the last match is the path crossing itself
+const MAX_BACKTRACK_TIME: f32 = 1.;
+// same but for a later pass over the same spot
+const MAX_SKIP_AHEAD_TIME: f32 = 1.;
+
+/// Origins of a path in a k-d tree so the nearest one is found without going through all of them.
+struct KdTree<'a> {
+    points: &'a [Vec3],
+    // every range of nodes is split by its middle node on one axis,
+    // with the nodes before it on the lower side and the nodes after it on the upper side
+    nodes: Vec<usize>,
+}
+
+impl<'a> KdTree<'a> {
+    fn new(points: &'a [Vec3]) -> Self {
+        let mut nodes: Vec<usize> = (0..points.len()).collect();
+
+        Self::build(points, &mut nodes, 0);
+
+        Self { points, nodes }
+    }
+
+    fn build(points: &[Vec3], nodes: &mut [usize], axis: usize) {
+        if nodes.len() <= 1 {
+            return;
+        }
+
+        let middle = nodes.len() / 2;
+
+        nodes.select_nth_unstable_by(middle, |&a, &b| points[a][axis].total_cmp(&points[b][axis]));
+
+        let (lower, upper) = nodes.split_at_mut(middle);
+
+        Self::build(points, lower, (axis + 1) % 3);
+        Self::build(points, &mut upper[1..], (axis + 1) % 3);
+    }
+
+    /// Nearest point to `point` within `radius` that passes `filter`.
+    ///
+    /// Points at the same distance are told apart by the smaller `tie_break`.
+    fn nearest(
+        &self,
+        point: Vec3,
+        radius: f32,
+        filter: impl Fn(usize) -> bool,
+        tie_break: impl Fn(usize) -> f32,
+    ) -> Option<usize> {
+        let mut nearest = Nearest {
+            point,
+            filter,
+            tie_break,
+            max_distance: radius * radius,
+            best: None,
+        };
+
+        self.search(&self.nodes, 0, &mut nearest);
+
+        nearest.best.map(|(idx, _, _)| idx)
+    }
+
+    fn search<F, T>(&self, nodes: &[usize], axis: usize, nearest: &mut Nearest<F, T>)
+    where
+        F: Fn(usize) -> bool,
+        T: Fn(usize) -> f32,
+    {
+        if nodes.is_empty() {
+            return;
+        }
+
+        let middle = nodes.len() / 2;
+        let idx = nodes[middle];
+        let split = self.points[idx];
+
+        nearest.visit(idx, split.distance_squared(nearest.point));
+
+        // every node on the far side is at least this far away on the split axis
+        let plane_distance = nearest.point[axis] - split[axis];
+        let (near, far) = if plane_distance < 0. {
+            (&nodes[..middle], &nodes[middle + 1..])
+        } else {
+            (&nodes[middle + 1..], &nodes[..middle])
+        };
+
+        self.search(near, (axis + 1) % 3, nearest);
+
+        // same distance could still win the tie break
+        if plane_distance * plane_distance <= nearest.bound() {
+            self.search(far, (axis + 1) % 3, nearest);
+        }
+    }
+}
+
+/// Search state of [`KdTree::nearest`].
+struct Nearest<F, T> {
+    point: Vec3,
+    filter: F,
+    tie_break: T,
+    // squared
+    max_distance: f32,
+    // index, squared distance and tie break
+    best: Option<(usize, f32, f32)>,
+}
+
+impl<F, T> Nearest<F, T>
+where
+    F: Fn(usize) -> bool,
+    T: Fn(usize) -> f32,
+{
+    fn bound(&self) -> f32 {
+        self.best
+            .map(|(_, distance, _)| distance)
+            .unwrap_or(self.max_distance)
+    }
+
+    fn visit(&mut self, idx: usize, distance: f32) {
+        if distance > self.bound() || !(self.filter)(idx) {
+            return;
+        }
+
+        let tie_break = (self.tie_break)(idx);
+        let is_better = self.best.is_none_or(|(_, best_distance, best_tie_break)| {
+            distance
+                .total_cmp(&best_distance)
+                .then(tie_break.total_cmp(&best_tie_break))
+                .is_lt()
+        });
+
+        if is_better {
+            self.best = Some((idx, distance, tie_break));
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RouteComparison {
+    /// Start time of every frame of the compared ghost.
+    pub times: Vec<f32>,
+    /// Time of the compared ghost minus the time of the other ghost at the same spot, for every frame.
+    ///
+    /// Positive is behind. `None` when the other ghost never went near.
+    pub deltas: Vec<Option<f32>>,
+}
+
+impl RouteComparison {
+    /// Delta of the frame at `time`.
+    pub fn delta_at(&self, time: f32) -> Option<f32> {
+        let idx = self
+            .times
+            .partition_point(|frame_time| *frame_time <= time)
+            .checked_sub(1)?;
+
+        self.deltas.get(idx).copied().flatten()
+    }
+}
+
+impl GhostInfo {
+    /// See [`compare_routes`].
+    pub fn compare_route(&self, other: &GhostInfo) -> Result<RouteComparison, GhostError> {
+        compare_routes(self, other)
+    }
+}
+
+fn frame_times(ghost: &GhostInfo) -> Result<Vec<f32>, GhostError> {
+    (0..ghost.frames().len())
+        .map(|idx| {
+            ghost
+                .get_frame_time(idx, None)
+                .ok_or(GhostError::NoFrametime)
+        })
+        .collect()
+}
+
+/// Closest point to `point` on the line from `from` to `to`, as a fraction of the line.
+fn project_on_line(point: Vec3, from: Vec3, to: Vec3) -> f32 {
+    let line = to - from;
+    let length_squared = line.length_squared();
+
+    if length_squared == 0. {
+        return 0.;
+    }
+
+    ((point - from).dot(line) / length_squared).clamp(0., 1.)
+}
+
+/// Compares `ghost` against `other` at every frame of `ghost`.
+///
+/// Each frame is matched to the nearest spot on the path of `other`, between its frames.
+/// Both ghosts should be on the same map.
+pub fn compare_routes(ghost: &GhostInfo, other: &GhostInfo) -> Result<RouteComparison, GhostError> {
+    let times = frame_times(ghost)?;
+    let other_times = frame_times(other)?;
+
+    let other_points: Vec<Vec3> = other.frames().iter().map(|frame| frame.origin).collect();
+    let tree = KdTree::new(&other_points);
+
+    // own time and time on the other path of the previous match
+    let mut last_match: Option<(f32, f32)> = None;
+
+    let deltas = ghost
+        .frames()
+        .iter()
+        .zip(times.iter())
+        .map(|(frame, &time)| {
+            let point = frame.origin;
+
+            // where the other ghost should be if both keep the same pace,
+            // so going over the same spot twice picks the right one
+            let expected_time = last_match
+                .map(|(last_time, last_other_time)| last_other_time + time - last_time)
+                .unwrap_or(0.);
+
+            // nearest frame that isn't too far back or ahead
+            let nearest = tree.nearest(
+                point,
+                MAX_MATCH_DISTANCE,
+                |idx| {
+                    last_match.is_none_or(|(_, last_other_time)| {
+                        other_times[idx] >= last_other_time - MAX_BACKTRACK_TIME
+                            && other_times[idx] <= expected_time + MAX_SKIP_AHEAD_TIME
+                    })
+                },
+                |idx| (other_times[idx] - expected_time).abs(),
+            )?;
+
+            // the spot is somewhere on the lines to the frames next to it
+            let spot_time = [nearest.checked_sub(1), Some(nearest + 1)]
+                .into_iter()
+                .flatten()
+                .filter(|&idx| idx < other_points.len())
+                .map(|idx| {
+                    let (from, to) = (nearest.min(idx), nearest.max(idx));
+                    let t = project_on_line(point, other_points[from], other_points[to]);
+                    let spot = other_points[from].lerp(other_points[to], t);
+                    let spot_time = other_times[from] + (other_times[to] - other_times[from]) * t;
+
+                    (spot.distance_squared(point), spot_time)
+                })
+                .min_by(|a, b| a.0.total_cmp(&b.0))
+                .map(|(_, spot_time)| spot_time)
+                .unwrap_or(other_times[nearest]);
+
+            last_match = Some((time, spot_time));
+
+            Some(time - spot_time)
+        })
+        .collect();
+
+    Ok(RouteComparison { times, deltas })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::get_ghost::{sample_frame, sample_ghost};
+
+    const FRAMETIME: f32 = 0.01;
+
+    fn ghost(origins: impl Iterator<Item = Vec3>) -> GhostInfo {
+        sample_ghost(
+            origins
+                .map(|origin| sample_frame(origin, 0., Some(FRAMETIME), None))
+                .collect(),
+        )
+    }
+
+    /// Goes along x at `speed`.
+    fn straight(speed: f32, frame_count: usize) -> GhostInfo {
+        ghost((0..frame_count).map(|idx| Vec3::X * speed * idx as f32 * FRAMETIME))
+    }
+
+    #[test]
+    fn kd_tree_nearest() {
+        // scattered with a few points on top of each other
+        let points: Vec<Vec3> = (0..500)
+            .map(|idx| {
+                Vec3::new(
+                    (idx * 37 % 101) as f32,
+                    (idx * 53 % 89) as f32,
+                    (idx * 11 % 7) as f32,
+                ) * 4.
+            })
+            .collect();
+        let tree = KdTree::new(&points);
+
+        let filter = |idx: usize| idx % 3 != 0;
+        let tie_break = |idx: usize| idx as f32;
+
+        (0..200).for_each(|query| {
+            let point = Vec3::new(
+                (query * 29 % 113) as f32,
+                (query * 17 % 97) as f32,
+                (query % 9) as f32,
+            ) * 3.7;
+
+            let expected = (0..points.len())
+                .filter(|&idx| filter(idx))
+                .map(|idx| (idx, points[idx].distance_squared(point)))
+                .filter(|(_, distance)| *distance <= 40. * 40.)
+                .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)))
+                .map(|(idx, _)| idx);
+
+            assert_eq!(tree.nearest(point, 40., filter, tie_break), expected);
+        });
+
+        assert_eq!(
+            tree.nearest(Vec3::splat(10000.), 40., filter, tie_break),
+            None
+        );
+    }
+
+    #[test]
+    fn faster_ghost_is_ahead() {
+        let slow = straight(100., 200);
+        let fast = straight(200., 100);
+
+        let comparison = slow.compare_route(&fast).unwrap();
+
+        assert_eq!(comparison.deltas.len(), 200);
+
+        // slow is at 100t when fast is there at t/2
+        (0..200).step_by(20).for_each(|idx| {
+            let time = idx as f32 * FRAMETIME;
+            let delta = comparison.deltas[idx].unwrap();
+
+            assert!((delta - time / 2.).abs() < 0.001, "{idx}: {delta}");
+        });
+
+        assert!((comparison.delta_at(1.005).unwrap() - 0.5).abs() < 0.001);
+
+        // and the other way around
+        let comparison = fast.compare_route(&slow).unwrap();
+
+        assert!((comparison.deltas[50].unwrap() + 0.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn far_away_has_no_delta() {
+        let path = straight(100., 100);
+        let other = ghost((0..100).map(|idx| Vec3::new(idx as f32, 1000., 0.)));
+
+        let comparison = path.compare_route(&other).unwrap();
+
+        assert!(comparison.deltas.iter().all(Option::is_none));
+        assert!(comparison.delta_at(0.5).is_none());
+    }
+
+    #[test]
+    fn crossing_keeps_going_forward() {
+        // goes out and comes back on the same line
+        let there_and_back = |speed: f32| {
+            let half = (200. / speed / FRAMETIME) as usize;
+
+            ghost(
+                (0..half)
+                    .chain((0..half).rev())
+                    .map(move |idx| Vec3::X * speed * idx as f32 * FRAMETIME),
+            )
+        };
+
+        let path = there_and_back(100.);
+        let other = there_and_back(100.);
+
+        let comparison = path.compare_route(&other).unwrap();
+
+        // coming back matches the way back instead of the way out
+        comparison.deltas.iter().for_each(|delta| {
+            assert!(delta.unwrap().abs() < 0.02, "{delta:?}");
+        });
+    }
+
+    #[test]
+    fn later_pass_nearby() {
+        // one unit every frame
+        let line = |from: Vec3, to: Vec3| {
+            let steps = from.distance(to) as usize;
+
+            (0..steps).map(move |idx| from.lerp(to, idx as f32 / steps as f32))
+        };
+
+        // goes along x, loops around and goes along x again `second_y` to the side
+        let looped = |first_y: f32, second_y: f32| {
+            ghost(
+                line(Vec3::new(-200., first_y, 0.), Vec3::new(200., first_y, 0.))
+                    .chain(line(
+                        Vec3::new(200., first_y, 0.),
+                        Vec3::new(200., 300., 0.),
+                    ))
+                    .chain(line(Vec3::new(200., 300., 0.), Vec3::new(0., 300., 0.)))
+                    .chain(line(Vec3::new(0., 300., 0.), Vec3::new(0., second_y, 0.)))
+                    .chain(line(
+                        Vec3::new(0., second_y, 0.),
+                        Vec3::new(200., second_y, 0.),
+                    )),
+            )
+        };
+
+        // first pass is closer to the second pass of the other one
+        let path = looped(6., 8.);
+        let other = looped(0., 8.);
+
+        let comparison = path.compare_route(&other).unwrap();
+
+        comparison.deltas.iter().for_each(|delta| {
+            assert!(delta.unwrap().abs() < 0.2, "{delta:?}");
+        });
+    }
+
+    #[test]
+    fn no_frametime() {
+        let mut path = straight(100., 10);
+        path.edit_frames(|frames| frames.iter_mut().for_each(|frame| frame.frametime = None));
+
+        assert!(matches!(
+            path.compare_route(&straight(100., 10)),
+            Err(GhostError::NoFrametime)
+        ));
+    }
+}
@@ -27,6 +27,41 @@ pub enum GhostError {
         source: eyre::Report,
     },
 
+    // Frame index in demo errors is counted through every directory entry, starting from the loading entry.
+    #[error("Invalid string in {field} at demo frame {frame}")]
+    DemoInvalidString { frame: usize, field: &'static str },
+
+    #[error("Invalid `{name}` user message at demo frame {frame}: {reason}")]
+    DemoInvalidUserMessage {
+        frame: usize,
+        name: &'static str,
+        reason: &'static str,
+    },
+
+    #[error("Unknown player index {player_index} at demo frame {frame}")]
+    DemoUnknownPlayer { frame: usize, player_index: u8 },
+
+    #[error("Unknown weapon id {weapon_id} at demo frame {frame}")]
+    DemoUnknownWeapon { frame: usize, weapon_id: u8 },
+
+    #[error("Sound without resource index at demo frame {frame}")]
+    DemoMissingSoundIndex { frame: usize },
+
+    #[error("No ghost parser is registered as `{name}`")]
+    UnknownParser { name: String },
+
+    #[error("Ghost has no frametime")]
+    NoFrametime,
+
+    #[error("Ghosts are {distance:.02} units apart where they are joined")]
+    Discontinuity { distance: f32 },
+
+    #[error("Failed to write ghost data: {source}")]
+    GhostWrite {
+        #[source]
+        source: eyre::Report,
+    },
+
     #[error("IO error: {source}")]
     IOError {
         #[source]
@@ -1,4 +1,7 @@
-use std::{collections::HashMap, str::from_utf8};
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::from_utf8,
+};
 
 use dem::{
     bit::BitSliceCast,
@@ -10,8 +13,56 @@ use nom::{
 };
 
 use super::*;
+use crate::GhostError;
+
+/// Collects errors so that lenient parsing can go on after a bad message.
+struct DemoErrorReport {
+    mode: GhostParseMode,
+    // first error in strict mode
+    error: Option<GhostError>,
+    warnings: Vec<GhostError>,
+}
+
+impl DemoErrorReport {
+    fn new(mode: GhostParseMode) -> Self {
+        Self {
+            mode,
+            error: None,
+            warnings: vec![],
+        }
+    }
+
+    /// Returns the value, otherwise records the error and returns `None` so that the message is skipped.
+    fn check<T>(&mut self, res: Result<T, GhostError>) -> Option<T> {
+        let err = match res {
+            Ok(value) => return Some(value),
+            Err(err) => err,
+        };
+
+        match self.mode {
+            GhostParseMode::Strict => {
+                self.error.get_or_insert(err);
+            }
+            GhostParseMode::Lenient => self.warnings.push(err),
+        }
+
+        None
+    }
 
-pub fn demo_ghost_parse(filename: &str, demo: &Demo) -> eyre::Result<GhostInfo> {
+    /// Returns the warnings, or the first error in strict mode.
+    fn finish(self) -> Result<Vec<GhostError>, GhostError> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.warnings),
+        }
+    }
+}
+
+pub fn demo_ghost_parse(
+    filename: &str,
+    demo: &Demo,
+    mode: GhostParseMode,
+) -> Result<GhostInfo, GhostError> {
     // Because player origin/viewangles and animation are on different frame, we have to sync it.
     // Order goes: players info > animation > player info > ...
     // TODO parses everything within netmsg
@@ -33,74 +84,59 @@ pub fn demo_ghost_parse(filename: &str, demo: &Demo) -> eyre::Result<GhostInfo>
     // player names
     let mut player_names: HashMap<u8, String> = HashMap::new();
 
-    // key is the resource index
-    let mut resource_lookup: HashMap<u32, String> = HashMap::new();
-
-    // weapon/viewmodel related stuffs
-    let mut weapon_list: HashMap<u8, String> = HashMap::new();
+    let mut resources = DemoResources::default();
     let mut weapon_sequence = None;
 
-    // can only build resource lookup from entry 0
-    demo.directory.entries[0]
-        .frames
-        .iter()
-        .for_each(|frame| match &frame.frame_data {
-            FrameData::NetworkMessage(a) => {
-                let netmessage = &a.as_ref().1;
+    let mut report = DemoErrorReport::new(mode);
 
-                let MessageData::Parsed(ref messages) = netmessage.messages else {
-                    return;
-                };
-
-                messages.iter().for_each(|message| match &message {
-                    NetMessage::EngineMessage(engine_message) => {
-                        let a = engine_message.as_ref();
-
-                        match a {
-                            EngineMessage::SvcResourceList(resource_list) => {
-                                resource_list.resources.iter().for_each(|resource| {
-                                    // do not insert if somethign is already there, dont ask me why it is this way,
-                                    let idx = resource.index.to_u32();
-
-                                    if !resource_lookup.contains_key(&idx) {
-                                        resource_lookup.insert(idx, resource.name.get_string());
-                                    }
-                                });
-                            }
-                            _ => (),
-                        }
-                    }
-                    NetMessage::UserMessage(user_message) => {
-                        let name = user_message.name.to_str().unwrap();
-
-                        // this is only in entry 0
-                        if name == "WeaponList" {
-                            let null_pos = user_message
-                                .data
-                                .iter()
-                                .position(|&x| x == 0)
-                                .expect("invalid WeaponList user message");
-
-                            let weapon_name = from_utf8(&user_message.data[..null_pos])
-                                .unwrap()
-                                .strip_prefix("weapon_")
-                                .expect("weapon does not have `weapon_` prefix");
-                            let weapon_id = user_message.data[user_message.data.len() - 2];
-
-                            weapon_list.insert(weapon_id, weapon_name.to_string());
-                        }
-                    }
-                });
-            }
-            _ => (),
+    // first entry is for loading, it has resources but no player
+    demo.directory
+        .entries
+        .iter()
+        .take(1)
+        .flat_map(|entry| entry.frames.iter())
+        .enumerate()
+        .for_each(|(frame_idx, frame)| {
+            demo_frame_messages(&frame.frame_data)
+                .into_iter()
+                .flatten()
+                .for_each(|message| {
+                    report.check(resources.update(frame_idx, message));
+                })
         });
 
+    let loading_frame_count = demo
+        .directory
+        .entries
+        .first()
+        .map_or(0, |entry| entry.frames.len());
+
+    // the demo header only knows the first map
+    let mut segments = vec![GhostSegment {
+        map_name: demo
+            .header
+            .map_name
+            .to_str()
+            .map_err(|_| GhostError::DemoInvalidString {
+                frame: 0,
+                field: "map name",
+            })?
+            .to_string(),
+        start_frame: 0,
+    }];
+    let mut ghost_frame_count = 0usize;
+
     // now ghost frames here
-    let ghost_frames = demo.directory.entries[1]
-        .frames
+    // every entry after the first one is playback, a new one starts after level change
+    let ghost_frames = demo
+        .directory
+        .entries
         .iter()
+        .skip(1)
+        .flat_map(|entry| entry.frames.iter())
         .enumerate()
-        .filter_map(|(_frame_idx, frame)| match &frame.frame_data {
+        .map(|(frame_idx, frame)| (frame_idx + loading_frame_count, frame))
+        .filter_map(|(frame_idx, frame)| match &frame.frame_data {
             // FrameData::ClientData(client) => {
             //     Some(GhostFrame {
             //         origin: client.origin.into(),
@@ -137,8 +173,15 @@ pub fn demo_ghost_parse(filename: &str, demo: &Demo) -> eyre::Result<GhostInfo>
                 None
             }
             FrameData::Sound(sound) => {
+                let file_name = report.check(sound.sample.to_str().map_err(|_| {
+                    GhostError::DemoInvalidString {
+                        frame: frame_idx,
+                        field: "sound sample",
+                    }
+                }))?;
+
                 let sound_frame = GhostFrameSound {
-                    file_name: sound.sample.to_str().unwrap().to_owned(),
+                    file_name: file_name.to_owned(),
                     channel: sound.channel,
                     volume: sound.volume,
                     origin: None,
@@ -159,37 +202,62 @@ pub fn demo_ghost_parse(filename: &str, demo: &Demo) -> eyre::Result<GhostInfo>
                 origin = from_fn(|i| sim_org[i]);
                 viewoffset_z = view_height[2];
 
+                // command that the client sends for this frame
+                let usercmd = &netmessage.info.usercmd;
+
                 let mut entity_text = vec![];
                 let mut say_text = vec![];
                 let mut weapon_change = None;
 
                 messages.iter().for_each(|message| {
+                    report.check(resources.update(frame_idx, message));
+
                     match message {
                         // get player names
                         NetMessage::UserMessage(user_message) => {
-                            let message_name = user_message.name.to_str().unwrap();
+                            // bad name is already reported when updating resources
+                            let Ok(message_name) = user_message.name.to_str() else {
+                                return;
+                            };
 
                             if message_name == "SayText" {
-                                // need to sub 1 because this idx is higher than 1 in our name list
-                                let player_idx = user_message.data[0] - 1;
-                                let player_name = player_names.get(&player_idx).unwrap();
-
-                                // println!("{:?}", user_message.data);
-
-                                let (_, saytext) =
-                                    processing_saytext2(&user_message.data[1..], player_name)
-                                        .unwrap();
+                                let Some(saytext) = report.check(demo_saytext(
+                                    frame_idx,
+                                    &user_message.data[..],
+                                    &player_names,
+                                )) else {
+                                    return;
+                                };
 
                                 say_text.push(saytext);
                             }
 
                             if message_name == "CurWeapon" {
-                                let weapon_state = user_message.data[0];
-                                let weapon_id = user_message.data[1];
+                                let Some(&[weapon_state, weapon_id]) =
+                                    report.check(user_message.data.get(..2).ok_or(
+                                        GhostError::DemoInvalidUserMessage {
+                                            frame: frame_idx,
+                                            name: "CurWeapon",
+                                            reason: "message is too short",
+                                        },
+                                    ))
+                                else {
+                                    return;
+                                };
 
                                 if weapon_state != 0 {
-                                    weapon_change =
-                                        weapon_list.get(&weapon_id).unwrap().to_string().into()
+                                    let Some(weapon_name) =
+                                        report.check(resources.weapon_list.get(&weapon_id).ok_or(
+                                            GhostError::DemoUnknownWeapon {
+                                                frame: frame_idx,
+                                                weapon_id,
+                                            },
+                                        ))
+                                    else {
+                                        return;
+                                    };
+
+                                    weapon_change = weapon_name.to_string().into()
                                 }
                             }
                         }
@@ -215,8 +283,19 @@ pub fn demo_ghost_parse(filename: &str, demo: &Demo) -> eyre::Result<GhostInfo>
                                         x as f32 / 8192.
                                     };
 
+                                    let Some(text) =
+                                        report.check(text_entity.message.to_str().map_err(|_| {
+                                            GhostError::DemoInvalidString {
+                                                frame: frame_idx,
+                                                field: "text message",
+                                            }
+                                        }))
+                                    else {
+                                        return;
+                                    };
+
                                     let frame_text = GhostFrameEntityText {
-                                        text: text_entity.message.to_str().unwrap().to_string(),
+                                        text: text.to_string(),
                                         // need to normalize position
                                         location: [
                                             normalize_pos(text_entity.x),
@@ -237,14 +316,21 @@ pub fn demo_ghost_parse(filename: &str, demo: &Demo) -> eyre::Result<GhostInfo>
                             }
                             // sounds
                             EngineMessage::SvcSound(sound) => {
-                                let sound_index = sound
-                                    .sound_index_short
-                                    .as_ref()
-                                    .or(sound.sound_index_long.as_ref())
-                                    .map(|i| i.to_u32())
-                                    .expect("sound does not have a resource index");
+                                let Some(sound_index) = report.check(
+                                    sound
+                                        .sound_index_short
+                                        .as_ref()
+                                        .or(sound.sound_index_long.as_ref())
+                                        .map(|i| i.to_u32())
+                                        .ok_or(GhostError::DemoMissingSoundIndex {
+                                            frame: frame_idx,
+                                        }),
+                                ) else {
+                                    return;
+                                };
 
-                                let Some(sound_name) = resource_lookup.get(&sound_index) else {
+                                let Some(sound_name) = resources.resource_lookup.get(&sound_index)
+                                else {
                                     println!("no sound found");
                                     return;
                                 };
@@ -268,11 +354,9 @@ pub fn demo_ghost_parse(filename: &str, demo: &Demo) -> eyre::Result<GhostInfo>
                                     }
                                 }
 
-                                let sound_name_length = sound_name.len();
-
                                 let sound_frame = GhostFrameSound {
                                     // excluding null terminator
-                                    file_name: sound_name[..sound_name_length - 1].to_owned(),
+                                    file_name: sound_name.trim_end_matches('\0').to_owned(),
                                     channel: sound.channel.to_i32(),
                                     volume,
                                     origin,
@@ -287,21 +371,19 @@ pub fn demo_ghost_parse(filename: &str, demo: &Demo) -> eyre::Result<GhostInfo>
                                 let user_index = user_info.index;
 
                                 // "\\bottomcolor\\6\\cl_dlmax\\512\\cl_lc\\1\\cl_lw\\1\\cl_updaterate\\102\\topcolor\\30\\rate\\100000\\name\\hono dille\\*sid\\76561198152358431\\model\\sas"
-                                let info_str = user_info.user_info.to_str().unwrap();
-
-                                let key = "\\name\\";
-                                if let Some(name_start) = info_str.find("\\name\\") {
-                                    let name_really_start = name_start + key.len();
-                                    let name_length = info_str[name_really_start..]
-                                        .find("\\")
-                                        .unwrap_or(info_str.len() - name_really_start);
-
-                                    player_names.insert(
-                                        user_index,
-                                        info_str
-                                            [name_really_start..(name_really_start + name_length)]
-                                            .to_string(),
-                                    );
+                                let Some(info_str) =
+                                    report.check(user_info.user_info.to_str().map_err(|_| {
+                                        GhostError::DemoInvalidString {
+                                            frame: frame_idx,
+                                            field: "user info",
+                                        }
+                                    }))
+                                else {
+                                    return;
+                                };
+
+                                if let Some(name) = user_info_value(info_str, "name") {
+                                    player_names.insert(user_index, name.to_string());
                                 }
                             }
                             // animations
@@ -315,36 +397,48 @@ pub fn demo_ghost_parse(filename: &str, demo: &Demo) -> eyre::Result<GhostInfo>
                                     return;
                                 };
 
-                                if let Some(sequence_bytes) = delta.get("sequence\0") {
+                                if let Some(sequence_bytes) =
+                                    delta.get("sequence\0").filter(|bytes| bytes.len() >= 4)
+                                {
                                     let sequence_bytes: [u8; 4] = from_fn(|i| sequence_bytes[i]);
                                     sequence = Some(i32::from_le_bytes(sequence_bytes));
                                 }
 
-                                if let Some(anim_frame_bytes) = delta.get("frame\0") {
+                                if let Some(anim_frame_bytes) =
+                                    delta.get("frame\0").filter(|bytes| bytes.len() >= 4)
+                                {
                                     let anim_frame_bytes: [u8; 4] =
                                         from_fn(|i: usize| anim_frame_bytes[i]);
                                     anim_frame = Some(f32::from_le_bytes(anim_frame_bytes));
                                 }
 
-                                if let Some(animtime_bytes) = delta.get("animtime\0") {
+                                if let Some(animtime_bytes) =
+                                    delta.get("animtime\0").filter(|bytes| bytes.len() >= 4)
+                                {
                                     let animtime_bytes: [u8; 4] = from_fn(|i| animtime_bytes[i]);
                                     animtime = Some(f32::from_le_bytes(animtime_bytes));
                                 }
 
-                                if let Some(gaitsequence_bytes) = delta.get("gaitsequence\0") {
+                                if let Some(gaitsequence_bytes) =
+                                    delta.get("gaitsequence\0").filter(|bytes| bytes.len() >= 4)
+                                {
                                     let gaitsequence_bytes: [u8; 4] =
                                         from_fn(|i| gaitsequence_bytes[i]);
                                     gaitsequence = Some(i32::from_le_bytes(gaitsequence_bytes));
                                 }
 
-                                if let Some(blending0) = delta.get("blending[0]\0") {
+                                if let Some(blending0) =
+                                    delta.get("blending[0]\0").and_then(|x| x.first())
+                                {
                                     // blending is just [u8; 1]
-                                    blending[0] = blending0[0];
+                                    blending[0] = *blending0;
                                 }
 
-                                if let Some(blending1) = delta.get("blending[1]\0") {
+                                if let Some(blending1) =
+                                    delta.get("blending[1]\0").and_then(|x| x.first())
+                                {
                                     // blending is just [u8; 1]
-                                    blending[1] = blending1[0];
+                                    blending[1] = *blending1;
                                 }
                             }
                             _ => (),
@@ -370,42 +464,554 @@ pub fn demo_ghost_parse(filename: &str, demo: &Demo) -> eyre::Result<GhostInfo>
                 weapon_sequence = None;
                 sound_vec.clear();
 
-                Some(GhostFrame {
+                // server info in playback means the level is changed
+                if let Some(map_name) = resources.map_name.take() {
+                    demo_level_change(&mut segments, map_name, ghost_frame_count);
+                }
+
+                ghost_frame_count += 1;
+
+                let ghost_frame = GhostFrame {
                     origin: Vec3::from_array(origin),
                     viewangles: Vec3::from_array(viewangles),
                     viewoffset_z,
                     frametime: Some(frame.time), /* time here is accummulative, will fix
                                                   * after */
-                    buttons: None,
+                    buttons: Some(usercmd.buttons as u32),
                     fov,
                     extras: frame_extra.into(),
-                })
+                };
+
+                Some((ghost_frame, usercmd.msec))
             }
             _ => None,
         })
-        .scan(0., |acc, mut frame: GhostFrame| {
+        .scan(0., |acc, (mut frame, msec): (GhostFrame, u8)| {
             // Cummulative time is 1 2 3 4, so do subtraction to get the correct frametime
             // TODO i think iam stupid, there is no neeed to calculate frame time like this
             // can just derive from demo right away
             // matter of fact, rewrite this horseshit
             let cum_time = frame.frametime.unwrap();
 
-            frame.frametime = Some(cum_time - *acc);
+            frame.frametime = Some(demo_frametime(cum_time, *acc, msec));
             *acc = cum_time;
 
             Some(frame)
         })
         .collect::<Vec<GhostFrame>>();
 
-    let map_name = demo.header.map_name.to_str()?.to_string();
-    let game_mod = demo.header.game_directory.to_str()?.to_string();
-
-    Ok(GhostInfo {
-        ghost_name: filename.to_owned(),
-        map_name,
+    let game_mod = demo
+        .header
+        .game_directory
+        .to_str()
+        .map_err(|_| GhostError::DemoInvalidString {
+            frame: 0,
+            field: "game directory",
+        })?
+        .to_string();
+
+    let mut ghost = GhostInfo::new(
+        filename.to_owned(),
+        segments[0].map_name.to_owned(),
         game_mod,
-        frames: ghost_frames,
-    })
+        ghost_frames,
+    );
+
+    ghost.segments = segments;
+    ghost.warnings = report.finish()?;
+
+    // the recording client
+    let local_user_info = resources.user_info.get(&resources.local_player_index);
+    let local_user_info_value = |key| {
+        local_user_info
+            .and_then(|info_str| user_info_value(info_str, key))
+            .map(|value| value.to_string())
+    };
+
+    ghost.metadata = GhostMetadata {
+        player_name: local_user_info_value("name"),
+        steam_id: local_user_info_value("*sid"),
+        ..GhostMetadata::from_source(GhostBlobType::Demo)
+    };
+
+    Ok(ghost)
+}
+
+/// Player entity from delta packet entities.
+#[derive(Default)]
+struct DemoPlayer {
+    name: Option<String>,
+    steam_id: Option<String>,
+    origin: [f32; 3],
+    // model angles, pitch is a third of view pitch and inverted
+    angles: [f32; 3],
+    sequence: Option<i32>,
+    anim_frame: Option<f32>,
+    animtime: Option<f32>,
+    gaitsequence: Option<i32>,
+    blending: [u8; 2],
+    // player entity is in the packet entities, it is sent at least once and not removed since
+    seen: bool,
+    // player entity was removed after it has frames
+    gap: bool,
+    // next frame starts a new segment
+    level_changed: bool,
+    // time of the last frame, to derive frametime
+    last_time: f32,
+    frames: Vec<GhostFrame>,
+    segments: Vec<GhostSegment>,
+}
+
+impl DemoPlayer {
+    fn update<'a>(&mut self, get: impl Fn(&str) -> Option<&'a [u8]>) {
+        let get_f32 = |key: &str| {
+            get(key)
+                .filter(|bytes| bytes.len() >= 4)
+                .map(|bytes| f32::from_le_bytes(from_fn(|i| bytes[i])))
+        };
+        let get_i32 = |key: &str| {
+            get(key)
+                .filter(|bytes| bytes.len() >= 4)
+                .map(|bytes| i32::from_le_bytes(from_fn(|i| bytes[i])))
+        };
+
+        ["origin[0]\0", "origin[1]\0", "origin[2]\0"]
+            .into_iter()
+            .enumerate()
+            .for_each(|(i, key)| {
+                if let Some(x) = get_f32(key) {
+                    self.origin[i] = x;
+                    self.seen = true;
+                }
+            });
+
+        ["angles[0]\0", "angles[1]\0", "angles[2]\0"]
+            .into_iter()
+            .enumerate()
+            .for_each(|(i, key)| {
+                if let Some(x) = get_f32(key) {
+                    self.angles[i] = x;
+                }
+            });
+
+        self.sequence = get_i32("sequence\0").or(self.sequence);
+        self.anim_frame = get_f32("frame\0").or(self.anim_frame);
+        self.animtime = get_f32("animtime\0").or(self.animtime);
+        self.gaitsequence = get_i32("gaitsequence\0").or(self.gaitsequence);
+
+        if let Some(blending0) = get("blending[0]\0").and_then(|x| x.first()) {
+            self.blending[0] = *blending0;
+        }
+
+        if let Some(blending1) = get("blending[1]\0").and_then(|x| x.first()) {
+            self.blending[1] = *blending1;
+        }
+    }
+
+    fn remove(&mut self) {
+        self.seen = false;
+        self.gap = !self.frames.is_empty();
+    }
+
+    fn level_change(&mut self) {
+        // entities are sent again on the new map
+        self.seen = false;
+        self.level_changed = true;
+    }
+
+    fn push_frame(&mut self, map_name: &str, time: f32) {
+        // first frame of the player on the current map
+        if self.segments.is_empty() || self.level_changed {
+            // no holding over a level change
+            self.gap = false;
+            self.level_changed = false;
+            self.segments.push(GhostSegment {
+                map_name: map_name.to_string(),
+                start_frame: self.frames.len(),
+            });
+        }
+
+        // hold the last frame while the player is gone so it does not slide to where it comes back
+        if self.gap {
+            if let Some(last) = self.frames.last() {
+                let hold = GhostFrame {
+                    frametime: Some((time - self.last_time).max(0.)),
+                    extras: None,
+                    ..last.clone()
+                };
+
+                self.frames.push(hold);
+                self.last_time = time;
+            }
+
+            self.gap = false;
+        }
+
+        // sequence is only sent when it changes
+        let anim = GhostFrameAnim {
+            sequence: self.sequence.take(),
+            frame: self.anim_frame.take(),
+            animtime: self.animtime.take(),
+            gaitsequence: self.gaitsequence.take(),
+            blending: self.blending,
+        };
+
+        self.frames.push(GhostFrame {
+            origin: Vec3::from_array(self.origin),
+            viewangles: Vec3::new(-self.angles[0] * 3., self.angles[1], self.angles[2]),
+            viewoffset_z: 0.,
+            // time can start over in a new entry
+            frametime: Some((time - self.last_time).max(0.)),
+            buttons: None,
+            fov: None,
+            extras: Some(GhostFrameExtra {
+                sound: vec![],
+                entity_text: vec![],
+                anim: Some(anim),
+                say_text: vec![],
+                weapon_change: None,
+                weapon_sequence: None,
+            }),
+        });
+
+        self.last_time = time;
+    }
+}
+
+/// Returns one ghost for every player in the demo except the recording client.
+///
+/// This is for spectator and HLTV demos where other players are only in delta packet entities.
+///
+/// Ghosts are on the same clock as [`demo_ghost_parse`]. The first frametime of a ghost
+/// is the time before the player shows up.
+pub fn demo_multi_ghost_parse(filename: &str, demo: &Demo) -> Result<Vec<GhostInfo>, GhostError> {
+    let game_mod = demo
+        .header
+        .game_directory
+        .to_str()
+        .map_err(|_| GhostError::DemoInvalidString {
+            frame: 0,
+            field: "game directory",
+        })?
+        .to_string();
+
+    let mut resources = DemoResources::default();
+    let mut current_map_name = demo
+        .header
+        .map_name
+        .to_str()
+        .map_err(|_| GhostError::DemoInvalidString {
+            frame: 0,
+            field: "map name",
+        })?
+        .to_string();
+
+    // key is entity index, which is player index + 1
+    let mut players: BTreeMap<u16, DemoPlayer> = BTreeMap::new();
+
+    demo.directory
+        .entries
+        .iter()
+        .enumerate()
+        .flat_map(|(entry_idx, entry)| entry.frames.iter().map(move |frame| (entry_idx, frame)))
+        .enumerate()
+        .for_each(|(frame_idx, (entry_idx, frame))| {
+            let Some(messages) = demo_frame_messages(&frame.frame_data) else {
+                return;
+            };
+
+            messages.iter().for_each(|message| {
+                // bad messages are reported by demo_ghost_parse
+                let _ = resources.update(frame_idx, message);
+
+                let NetMessage::EngineMessage(engine_message) = message else {
+                    return;
+                };
+
+                match engine_message.as_ref() {
+                    EngineMessage::SvcUpdateUserInfo(user_info) => {
+                        let Ok(info_str) = user_info.user_info.to_str() else {
+                            return;
+                        };
+
+                        let player = players.entry(user_info.index as u16 + 1).or_default();
+
+                        if let Some(name) = user_info_value(info_str, "name") {
+                            player.name = name.to_string().into();
+                        }
+
+                        if let Some(steam_id) = user_info_value(info_str, "*sid") {
+                            player.steam_id = steam_id.to_string().into();
+                        }
+                    }
+                    EngineMessage::SvcDeltaPacketEntities(delta_packet_entities) => {
+                        delta_packet_entities
+                            .entity_states
+                            .iter()
+                            .for_each(|entity_state| {
+                                let entity_index = entity_state.entity_index;
+
+                                // only player entities
+                                if entity_index == 0 || entity_index > resources.max_players as u16
+                                {
+                                    return;
+                                }
+
+                                let player = players.entry(entity_index).or_default();
+
+                                if entity_state.remove_entity {
+                                    player.remove();
+                                    return;
+                                }
+
+                                let Some(delta) = entity_state.delta.as_ref() else {
+                                    return;
+                                };
+
+                                player.update(|key| delta.get(key).map(|x| &x[..]));
+                            });
+                    }
+                    _ => (),
+                }
+            });
+
+            // loading entry has no player
+            if entry_idx == 0 {
+                resources.map_name = None;
+                return;
+            }
+
+            // level change
+            if let Some(map_name) = resources.map_name.take() {
+                if map_name != current_map_name {
+                    players.values_mut().for_each(DemoPlayer::level_change);
+
+                    current_map_name = map_name;
+                }
+            }
+
+            let local_entity_index = resources.local_player_index as u16 + 1;
+
+            players
+                .iter_mut()
+                .filter(|(entity_index, player)| {
+                    **entity_index != local_entity_index && player.seen
+                })
+                .for_each(|(_, player)| player.push_frame(&current_map_name, frame.time));
+        });
+
+    let ghosts = players
+        .into_iter()
+        .filter(|(_, player)| !player.frames.is_empty())
+        .map(|(entity_index, player)| {
+            let ghost_name = player
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("{} (player {})", filename, entity_index));
+
+            let mut ghost = GhostInfo::new(
+                ghost_name,
+                player.segments[0].map_name.to_owned(),
+                game_mod.to_owned(),
+                player.frames,
+            );
+
+            ghost.segments = player.segments;
+            ghost.metadata = GhostMetadata {
+                player_name: player.name,
+                steam_id: player.steam_id,
+                ..GhostMetadata::from_source(GhostBlobType::Demo)
+            };
+
+            ghost
+        })
+        .collect();
+
+    Ok(ghosts)
+}
+
+/// Resources that later messages refer to by index.
+#[derive(Default)]
+struct DemoResources {
+    // key is the resource index
+    resource_lookup: HashMap<u32, String>,
+    // weapon/viewmodel related stuffs
+    weapon_list: HashMap<u8, String>,
+    // map from the latest server info
+    map_name: Option<String>,
+    // player slots, from server info
+    max_players: u8,
+    // the recording client, 0 based
+    local_player_index: u8,
+    // key is the player index, 0 based
+    user_info: HashMap<u8, String>,
+}
+
+impl DemoResources {
+    fn update(&mut self, frame: usize, message: &NetMessage) -> Result<(), GhostError> {
+        match message {
+            NetMessage::EngineMessage(engine_message) => match engine_message.as_ref() {
+                EngineMessage::SvcResourceList(resource_list) => {
+                    resource_list.resources.iter().for_each(|resource| {
+                        // do not insert if somethign is already there, dont ask me why it is this way,
+                        let idx = resource.index.to_u32();
+
+                        if !self.resource_lookup.contains_key(&idx) {
+                            self.resource_lookup.insert(idx, resource.name.get_string());
+                        }
+                    });
+                }
+                // bad string is reported by demo_ghost_parse
+                EngineMessage::SvcUpdateUserInfo(user_info) => {
+                    if let Ok(info_str) = user_info.user_info.to_str() {
+                        self.user_info.insert(user_info.index, info_str.to_string());
+                    }
+                }
+                // new level, resource indices start over
+                EngineMessage::SvcServerInfo(server_info) => {
+                    self.resource_lookup.clear();
+                    self.max_players = server_info.max_players;
+                    self.local_player_index = server_info.player_index;
+
+                    // "maps/de_dust2.bsp"
+                    self.map_name =
+                        server_info
+                            .map_file_name
+                            .to_str()
+                            .ok()
+                            .and_then(|map_file_name| {
+                                Path::new(map_file_name.trim_end_matches('\0'))
+                                    .file_stem()?
+                                    .to_str()
+                                    .map(|map_name| map_name.to_string())
+                            });
+                }
+                _ => (),
+            },
+            NetMessage::UserMessage(user_message) => {
+                let name =
+                    user_message
+                        .name
+                        .to_str()
+                        .map_err(|_| GhostError::DemoInvalidString {
+                            frame,
+                            field: "user message name",
+                        })?;
+
+                // this is only in entry 0
+                if name == "WeaponList" {
+                    let invalid = |reason| GhostError::DemoInvalidUserMessage {
+                        frame,
+                        name: "WeaponList",
+                        reason,
+                    };
+
+                    let null_pos = user_message
+                        .data
+                        .iter()
+                        .position(|&x| x == 0)
+                        .ok_or_else(|| invalid("weapon name is not null terminated"))?;
+
+                    let weapon_name = from_utf8(&user_message.data[..null_pos])
+                        .map_err(|_| invalid("weapon name is not UTF-8"))?
+                        .strip_prefix("weapon_")
+                        .ok_or_else(|| invalid("weapon does not have `weapon_` prefix"))?;
+                    // second to last byte
+                    let weapon_id = *user_message
+                        .data
+                        .iter()
+                        .rev()
+                        .nth(1)
+                        .ok_or_else(|| invalid("message is too short"))?;
+
+                    self.weapon_list.insert(weapon_id, weapon_name.to_string());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Frametime from the accumulative time of the demo frame.
+///
+/// Time starts over in a new entry, then the frame is as long as its command.
+pub(crate) fn demo_frametime(time: f32, last_time: f32, msec: u8) -> f32 {
+    if time < last_time {
+        return msec as f32 / 1000.;
+    }
+
+    time - last_time
+}
+
+/// Starts a new segment if the map is different from the current one.
+fn demo_level_change(segments: &mut Vec<GhostSegment>, map_name: String, start_frame: usize) {
+    if segments
+        .last()
+        .is_none_or(|segment| segment.map_name != map_name)
+    {
+        segments.push(GhostSegment {
+            map_name,
+            start_frame,
+        });
+    }
+}
+
+/// Gets the value of a key in user info string.
+fn user_info_value<'a>(info_str: &'a str, key: &str) -> Option<&'a str> {
+    let key = format!("\\{}\\", key);
+
+    let value_start = info_str.find(&key)? + key.len();
+    let value_length = info_str[value_start..]
+        .find("\\")
+        .unwrap_or(info_str.len() - value_start);
+
+    Some(&info_str[value_start..(value_start + value_length)])
+}
+
+fn demo_frame_messages(frame_data: &FrameData) -> Option<&Vec<NetMessage>> {
+    let FrameData::NetworkMessage(box_type) = frame_data else {
+        return None;
+    };
+
+    let MessageData::Parsed(ref messages) = box_type.as_ref().1.messages else {
+        return None;
+    };
+
+    Some(messages)
+}
+
+/// SayText is the player index followed by the text.
+fn demo_saytext(
+    frame: usize,
+    data: &[u8],
+    player_names: &HashMap<u8, String>,
+) -> Result<GhostFrameSayText, GhostError> {
+    let invalid = |reason| GhostError::DemoInvalidUserMessage {
+        frame,
+        name: "SayText",
+        reason,
+    };
+
+    let (&player_idx, text) = data
+        .split_first()
+        .ok_or_else(|| invalid("message is empty"))?;
+
+    // need to sub 1 because this idx is higher than 1 in our name list
+    // 0 is the server, which has no name
+    let player_name = match player_idx.checked_sub(1) {
+        Some(name_idx) => player_names.get(&name_idx).map(String::as_str).ok_or(
+            GhostError::DemoUnknownPlayer {
+                frame,
+                player_index: player_idx,
+            },
+        )?,
+        None => "",
+    };
+
+    processing_saytext2(text, player_name)
+        .map(|(_, saytext)| saytext)
+        .map_err(|_| invalid("cannot parse text"))
 }
 
 fn processing_saytext2<'a>(
@@ -475,3 +1081,182 @@ fn processing_saytext2<'a>(
         },
     ))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn player_delta(origin: [f32; 3]) -> HashMap<&'static str, Vec<u8>> {
+        ["origin[0]\0", "origin[1]\0", "origin[2]\0"]
+            .into_iter()
+            .zip(origin)
+            .map(|(key, x)| (key, x.to_le_bytes().to_vec()))
+            .collect()
+    }
+
+    fn update_player(player: &mut DemoPlayer, origin: [f32; 3]) {
+        let delta = player_delta(origin);
+
+        player.update(|key| delta.get(key).map(|x| &x[..]));
+    }
+
+    #[test]
+    fn error_report_strict() {
+        let mut report = DemoErrorReport::new(GhostParseMode::Strict);
+
+        assert_eq!(report.check::<u8>(Ok(1)), Some(1));
+        assert!(
+            report
+                .check::<u8>(Err(GhostError::DemoMissingSoundIndex { frame: 3 }))
+                .is_none()
+        );
+        report.check::<u8>(Err(GhostError::DemoMissingSoundIndex { frame: 5 }));
+
+        // first error
+        assert!(matches!(
+            report.finish(),
+            Err(GhostError::DemoMissingSoundIndex { frame: 3 })
+        ));
+    }
+
+    #[test]
+    fn error_report_lenient() {
+        let mut report = DemoErrorReport::new(GhostParseMode::Lenient);
+
+        report.check::<u8>(Err(GhostError::DemoMissingSoundIndex { frame: 3 }));
+        report.check::<u8>(Err(GhostError::DemoUnknownWeapon {
+            frame: 5,
+            weapon_id: 30,
+        }));
+
+        let warnings = report.finish().unwrap();
+
+        assert_eq!(warnings.len(), 2);
+        assert!(matches!(
+            warnings[1],
+            GhostError::DemoUnknownWeapon {
+                frame: 5,
+                weapon_id: 30
+            }
+        ));
+    }
+
+    #[test]
+    fn level_change_segments() {
+        let mut segments = vec![GhostSegment {
+            map_name: "kz_a".to_string(),
+            start_frame: 0,
+        }];
+
+        // server info of the same map
+        demo_level_change(&mut segments, "kz_a".to_string(), 10);
+        demo_level_change(&mut segments, "kz_b".to_string(), 20);
+        demo_level_change(&mut segments, "kz_a".to_string(), 30);
+
+        let segments: Vec<_> = segments
+            .iter()
+            .map(|segment| (segment.map_name.as_str(), segment.start_frame))
+            .collect();
+
+        assert_eq!(segments, [("kz_a", 0), ("kz_b", 20), ("kz_a", 30)]);
+    }
+
+    #[test]
+    fn saytext_player_name() {
+        let names = HashMap::from([(1u8, "player".to_string())]);
+
+        let saytext = demo_saytext(0, b"\x02\x02#Cstrike_Chat_Allhello\n", &names).unwrap();
+        assert_eq!(saytext.text, [(2, "player: hello".to_string())]);
+
+        assert!(matches!(
+            demo_saytext(7, b"\x05\x02hello", &names),
+            Err(GhostError::DemoUnknownPlayer {
+                frame: 7,
+                player_index: 5
+            })
+        ));
+
+        assert!(matches!(
+            demo_saytext(7, b"", &names),
+            Err(GhostError::DemoInvalidUserMessage { frame: 7, .. })
+        ));
+    }
+
+    #[test]
+    fn user_info() {
+        let info_str = "\\bottomcolor\\6\\name\\hono dille\\*sid\\76561198152358431";
+
+        assert_eq!(user_info_value(info_str, "name"), Some("hono dille"));
+        assert_eq!(user_info_value(info_str, "*sid"), Some("76561198152358431"));
+        assert_eq!(user_info_value(info_str, "model"), None);
+    }
+
+    #[test]
+    fn player_frames() {
+        let mut player = DemoPlayer::default();
+
+        update_player(&mut player, [1., 2., 3.]);
+        assert!(player.seen);
+
+        // shows up 1 second in
+        player.push_frame("kz_a", 1.0);
+        update_player(&mut player, [2., 2., 3.]);
+        player.push_frame("kz_a", 1.01);
+
+        assert_eq!(player.frames.len(), 2);
+        assert_eq!(player.frames[0].frametime, Some(1.0));
+        assert_eq!(player.frames[1].origin, Vec3::new(2., 2., 3.));
+        assert!((player.frames[1].frametime.unwrap() - 0.01).abs() < 1e-5);
+    }
+
+    #[test]
+    fn player_gap() {
+        let mut player = DemoPlayer::default();
+
+        update_player(&mut player, [1., 2., 3.]);
+        player.push_frame("kz_a", 1.0);
+
+        player.remove();
+        assert!(!player.seen);
+
+        update_player(&mut player, [100., 2., 3.]);
+        player.push_frame("kz_a", 3.0);
+
+        // held at the old origin for the time gone, then the new origin
+        assert_eq!(player.frames.len(), 3);
+        assert_eq!(player.frames[1].origin, Vec3::new(1., 2., 3.));
+        assert_eq!(player.frames[1].frametime, Some(2.0));
+        assert_eq!(player.frames[2].origin, Vec3::new(100., 2., 3.));
+        assert_eq!(player.frames[2].frametime, Some(0.));
+        assert_eq!(player.segments.len(), 1);
+    }
+
+    #[test]
+    fn player_level_change() {
+        let mut player = DemoPlayer::default();
+
+        update_player(&mut player, [1., 2., 3.]);
+        player.push_frame("kz_a", 1.0);
+        player.push_frame("kz_a", 1.01);
+
+        player.remove();
+        player.level_change();
+
+        update_player(&mut player, [100., 2., 3.]);
+        // time starts over in a new entry
+        player.push_frame("kz_b", 0.5);
+
+        // no hold frame over a level change
+        assert_eq!(player.frames.len(), 3);
+        assert_eq!(player.frames[2].origin, Vec3::new(100., 2., 3.));
+        assert_eq!(player.frames[2].frametime, Some(0.));
+
+        let segments: Vec<_> = player
+            .segments
+            .iter()
+            .map(|segment| (segment.map_name.as_str(), segment.start_frame))
+            .collect();
+
+        assert_eq!(segments, [("kz_a", 0), ("kz_b", 2)]);
+    }
+}
@@ -0,0 +1,403 @@
+//! Editing ghosts by time.
+//!
+//! Every edit keeps [`GhostInfo::segments`] in order and rebuilds the time index.
+use std::ops::Range;
+
+use super::{GhostFrame, GhostFrameExtra, GhostInfo, GhostSegment, lerp_frames};
+use crate::GhostError;
+
+impl GhostFrameExtra {
+    /// Adds discrete data of `other` after this one.
+    fn merge(&mut self, other: &GhostFrameExtra) {
+        self.sound.extend(other.sound.iter().cloned());
+        self.entity_text.extend(other.entity_text.iter().cloned());
+        self.say_text.extend(other.say_text.iter().cloned());
+
+        // later one wins
+        if other.anim.is_some() {
+            self.anim = other.anim.clone();
+        }
+
+        if other.weapon_change.is_some() {
+            self.weapon_change = other.weapon_change.clone();
+        }
+
+        if other.weapon_sequence.is_some() {
+            self.weapon_sequence = other.weapon_sequence;
+        }
+    }
+}
+
+impl GhostInfo {
+    fn require_frametime(&self) -> Result<(), GhostError> {
+        if self.has_frametime(None) {
+            Ok(())
+        } else {
+            Err(GhostError::NoFrametime)
+        }
+    }
+
+    /// First frame starting at or after `time`.
+    fn frame_index_after(&self, time: f32) -> usize {
+        self.sync_time_index();
+
+        self.time_index.borrow().start_times[..self.frames.len()]
+            .partition_point(|&start| start < time)
+    }
+
+    /// Keeps only the frames starting in the time range.
+    pub fn trim_time(&mut self, range: Range<f32>) -> Result<(), GhostError> {
+        self.require_frametime()?;
+
+        let frames = self.frame_index_after(range.start)..self.frame_index_after(range.end);
+
+        self.trim(frames);
+
+        Ok(())
+    }
+
+    /// Removes the frames starting in the time range and joins what is left.
+    ///
+    /// The map after the cut is kept if the cut goes over a map change.
+    pub fn cut_time(&mut self, range: Range<f32>) -> Result<(), GhostError> {
+        self.require_frametime()?;
+
+        let start = self.frame_index_after(range.start);
+        let end = self.frame_index_after(range.end).max(start);
+        let removed = end - start;
+        // segment of the first frame after the cut
+        let segment_after = self.get_segment_index(end);
+
+        self.frames.drain(start..end);
+
+        // a cut to the end has no frames left for the segment after it
+        // but an empty ghost still keeps its map
+        let frame_count = self.frames.len().max(1);
+
+        self.segments = std::mem::take(&mut self.segments)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(idx, segment)| {
+                if segment.start_frame < start {
+                    Some(segment)
+                } else if segment.start_frame < end {
+                    (idx == segment_after).then_some(GhostSegment {
+                        start_frame: start,
+                        ..segment
+                    })
+                } else {
+                    Some(GhostSegment {
+                        start_frame: segment.start_frame - removed,
+                        ..segment
+                    })
+                }
+            })
+            .filter(|segment| segment.start_frame < frame_count)
+            .collect();
+
+        if let Some(segment) = self.segments.first() {
+            self.map_name = segment.map_name.clone();
+        }
+
+        self.rebuild_time_index();
+
+        Ok(())
+    }
+
+    /// Appends frames of `other` after this ghost.
+    ///
+    /// Fails if the last origin and the first origin of `other` are more than `max_distance` apart.
+    /// Metadata of this ghost is kept but the official time is removed because it is no longer the same run.
+    pub fn concat(&mut self, other: GhostInfo, max_distance: f32) -> Result<(), GhostError> {
+        if let (Some(last), Some(first)) = (self.frames.last(), other.frames.first()) {
+            let distance = last.origin.distance(first.origin);
+
+            if distance > max_distance {
+                return Err(GhostError::Discontinuity { distance });
+            }
+        }
+
+        let offset = self.frames.len();
+
+        other.segments.into_iter().for_each(|segment| {
+            // same map continues
+            if self
+                .segments
+                .last()
+                .is_some_and(|last| last.map_name == segment.map_name)
+            {
+                return;
+            }
+
+            self.segments.push(GhostSegment {
+                start_frame: segment.start_frame + offset,
+                ..segment
+            });
+        });
+
+        self.frames.extend(other.frames);
+        self.warnings.extend(other.warnings);
+        self.metadata.official_time = None;
+
+        self.rebuild_time_index();
+
+        Ok(())
+    }
+
+    /// Shifts the ghost in time.
+    ///
+    /// Positive offset holds the first frame for that long, same as how other players show up late.
+    /// Negative offset removes frames from the start.
+    pub fn offset_time(&mut self, offset: f32) -> Result<(), GhostError> {
+        self.require_frametime()?;
+
+        if offset < 0. {
+            let length = self.frame_start_time(self.frames.len(), None);
+
+            return self.trim_time(-offset..length + 1.);
+        }
+
+        let Some(first) = self.frames.first() else {
+            return Ok(());
+        };
+
+        let hold = GhostFrame {
+            frametime: Some(offset),
+            // events are not repeated
+            extras: None,
+            ..first.clone()
+        };
+
+        self.frames.insert(0, hold);
+
+        // first segment still starts at 0
+        self.segments
+            .iter_mut()
+            .skip(1)
+            .for_each(|segment| segment.start_frame += 1);
+
+        self.rebuild_time_index();
+
+        Ok(())
+    }
+
+    /// Resamples the ghost to `rate` frames per second.
+    ///
+    /// Origin and viewangles are interpolated like [`GhostInfo::get_frame`].
+    /// Buttons and events of every original frame are merged into the new frame that it starts in, so nothing is lost when going down.
+    pub fn resample(&mut self, rate: f32) -> Result<(), GhostError> {
+        self.require_frametime()?;
+
+        if self.frames.is_empty() || rate <= 0. {
+            return Ok(());
+        }
+
+        let frametime = 1. / rate;
+        let length = self.frame_start_time(self.frames.len(), None);
+        // float error in the length shouldn't add a frame
+        let count = ((length / frametime - 0.001).ceil() as usize).max(1);
+
+        let new_index = |time: f32| ((time / frametime) as usize).min(count - 1);
+
+        let mut frames: Vec<GhostFrame> = (0..count)
+            .map(|idx| {
+                let time = idx as f32 * frametime;
+                let from = self.search_frame_index(time, None);
+                let to = (from + 1).min(self.frames.len() - 1);
+
+                let from_time = self.frame_start_time(from, None);
+                let to_time = self.frame_start_time(from + 1, None);
+
+                let target = if to_time > from_time {
+                    ((time - from_time) / (to_time - from_time)).clamp(0., 1.)
+                } else {
+                    0.
+                };
+
+                GhostFrame {
+                    frametime: Some(frametime),
+                    // filled with original frames starting in this frame
+                    extras: None,
+                    ..lerp_frames(&self.frames[from], &self.frames[to], target)
+                }
+            })
+            .collect();
+
+        // going up has new frames without original frames, they keep the interpolated buttons
+        let mut has_original = vec![false; count];
+
+        self.frames.iter().enumerate().for_each(|(idx, frame)| {
+            let new_idx = new_index(self.frame_start_time(idx, None));
+            let new_frame = &mut frames[new_idx];
+
+            if !has_original[new_idx] {
+                new_frame.buttons = frame.buttons;
+                has_original[new_idx] = true;
+            } else if let (Some(buttons), Some(other)) = (new_frame.buttons.as_mut(), frame.buttons)
+            {
+                *buttons |= other;
+            }
+
+            if let Some(extra) = &frame.extras {
+                match new_frame.extras.as_mut() {
+                    Some(new_extra) => new_extra.merge(extra),
+                    None => new_frame.extras = Some(extra.clone()),
+                }
+            }
+        });
+
+        // map changes can end up in the same frame, the first one stays
+        let mut segments: Vec<GhostSegment> = self
+            .segments
+            .iter()
+            .map(|segment| GhostSegment {
+                start_frame: new_index(self.frame_start_time(segment.start_frame, None)),
+                ..segment.clone()
+            })
+            .collect();
+
+        segments.dedup_by_key(|segment| segment.start_frame);
+
+        self.frames = frames;
+        self.segments = segments;
+
+        self.rebuild_time_index();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use glam::Vec3;
+
+    use super::*;
+    use crate::{
+        GhostFrameSound,
+        get_ghost::{sample_frames, sample_ghost},
+    };
+
+    fn two_maps() -> GhostInfo {
+        let mut ghost = sample_ghost(sample_frames(100, Some(0.01)));
+
+        ghost.segments.push(GhostSegment {
+            map_name: "second".into(),
+            start_frame: 60,
+        });
+
+        ghost
+    }
+
+    #[test]
+    fn trim_and_cut() {
+        let mut trimmed = two_maps();
+        trimmed.trim_time(0.495..0.795).unwrap();
+
+        assert_eq!(trimmed.frames.len(), 30);
+        assert_eq!(trimmed.segments.len(), 2);
+        assert_eq!(trimmed.segments[1].start_frame, 10);
+        assert!((trimmed.get_ghost_length()(0.) - 0.3).abs() < 0.001);
+
+        let mut cut = two_maps();
+        cut.cut_time(0.495..0.795).unwrap();
+
+        assert_eq!(cut.frames.len(), 70);
+        // second map starts where the cut is
+        assert_eq!(cut.segments[1].start_frame, 50);
+
+        let mut cut = two_maps();
+        cut.cut_time(0.0..0.695).unwrap();
+
+        assert_eq!(cut.segments.len(), 1);
+        assert_eq!(cut.map_name, "second");
+
+        let mut cut = two_maps();
+        cut.cut_time(0.495..1.).unwrap();
+
+        assert_eq!(cut.frames.len(), 50);
+        assert_eq!(cut.segments.len(), 1);
+        assert_eq!(cut.map_name, "test");
+    }
+
+    #[test]
+    fn concat_checks_distance() {
+        let mut first = two_maps();
+        let mut second = sample_ghost(sample_frames(10, Some(0.01)));
+
+        // starts where the first one ends
+        let offset = first.frames[99].origin - second.frames[0].origin;
+
+        second.edit_frames(|frames| frames.iter_mut().for_each(|frame| frame.origin += offset));
+
+        assert!(matches!(
+            first.concat(sample_ghost(sample_frames(10, Some(0.01))), 16.),
+            Err(GhostError::Discontinuity { .. })
+        ));
+
+        first.concat(second, 16.).unwrap();
+
+        assert_eq!(first.frames.len(), 110);
+        // back to the first map
+        assert_eq!(first.segments.len(), 3);
+        assert_eq!(first.segments[2].start_frame, 100);
+    }
+
+    #[test]
+    fn offset() {
+        let mut ghost = two_maps();
+
+        ghost.offset_time(1.).unwrap();
+
+        assert_eq!(ghost.frames.len(), 101);
+        assert_eq!(ghost.segments[1].start_frame, 61);
+        assert_eq!(ghost.get_frame_time(1, None), Some(1.));
+
+        ghost.offset_time(-1.).unwrap();
+
+        assert_eq!(ghost.frames.len(), 100);
+        assert_eq!(ghost.segments[1].start_frame, 60);
+
+        assert!(matches!(
+            sample_ghost(sample_frames(10, None)).offset_time(1.),
+            Err(GhostError::NoFrametime)
+        ));
+    }
+
+    #[test]
+    fn resample_keeps_events() {
+        let mut frames = sample_frames(1000, Some(0.001));
+
+        frames[505].buttons = Some(crate::IN_USE);
+        frames[505].extras = Some(GhostFrameExtra {
+            sound: vec![GhostFrameSound {
+                file_name: "jump.wav".into(),
+                channel: 0,
+                volume: 1.,
+                origin: None,
+            }],
+            entity_text: vec![],
+            anim: None,
+            say_text: vec![],
+            weapon_change: None,
+            weapon_sequence: None,
+        });
+
+        let mut ghost = sample_ghost(frames);
+        ghost.segments.push(GhostSegment {
+            map_name: "second".into(),
+            start_frame: 705,
+        });
+
+        let original = ghost.frames[300].clone();
+
+        ghost.resample(100.).unwrap();
+
+        assert_eq!(ghost.frames.len(), 100);
+        assert_eq!(ghost.segments[1].start_frame, 70);
+        assert!(ghost.frames[50].buttons.unwrap() & crate::IN_USE != 0);
+        assert_eq!(ghost.frames[50].extras.as_ref().unwrap().sound.len(), 1);
+        assert!(ghost.frames[30].origin.distance(original.origin) < 0.01);
+        assert!((ghost.get_ghost_length()(0.) - 1.).abs() < 0.001);
+        assert_eq!(ghost.frames[0].viewangles, Vec3::new(0., -180., 0.));
+    }
+}
@@ -0,0 +1,736 @@
+//! kdr native ghost format.
+//!
+//! Unlike community formats, this keeps everything from [`GhostFrame`] and [`GhostFrameExtra`]
+//! so that a demo can be parsed once on the server and sent to the client as a much smaller file.
+//!
+//! Layout, all little endian:
+//! - magic `KDRG`, `u32` version
+//! - ghost name, map name, game mod as strings
+//! - frame count as varint
+//! - frames
+//! - map segments
+//! - metadata
+//!
+//! Origins and view angles are quantized and stored as the zigzag varint difference from the previous frame.
+//! Other fields are only written when they change from the previous frame, as told by the frame flags.
+//!
+//! Strings and lists are prefixed with their varint length. Options are prefixed with a `u8` of 0 or 1.
+use byte_writer::ByteWriter;
+use eyre::eyre;
+use nom::{
+    IResult as _IResult,
+    bytes::complete::{tag, take},
+    combinator::map_res,
+    number::complete::{le_f32, le_i8, le_u8, le_u32},
+    sequence::tuple,
+};
+
+use super::*;
+
+type IResult<'a, T> = _IResult<&'a [u8], T>;
+
+const KDR_GHOST_MAGIC: &[u8; 4] = b"KDRG";
+/// Bumps whenever the layout changes.
+pub const KDR_GHOST_VERSION: u32 = 1;
+
+// 1/16 unit, finer than what the game sends over the net
+const ORIGIN_SCALE: f32 = 16.;
+// 16 bit angle, same as the game
+const ANGLE_SCALE: f32 = 65536. / 360.;
+
+// tells which fields are different from the previous frame
+const FLAG_FRAMETIME: u8 = 1 << 0;
+const FLAG_VIEWOFFSET: u8 = 1 << 1;
+const FLAG_BUTTONS: u8 = 1 << 2;
+const FLAG_FOV: u8 = 1 << 3;
+const FLAG_EXTRAS: u8 = 1 << 4;
+
+fn quantize(value: f32, scale: f32) -> i32 {
+    (value * scale).round() as i32
+}
+
+fn dequantize(value: i32, scale: f32) -> f32 {
+    value as f32 / scale
+}
+
+/// Values carried over from the previous frame.
+#[derive(Default)]
+struct KdrFrameState {
+    origin: [i32; 3],
+    viewangles: [i32; 3],
+    viewoffset_z: f32,
+    frametime: Option<f32>,
+    buttons: Option<u32>,
+    fov: Option<f32>,
+}
+
+struct KdrWriter {
+    writer: ByteWriter,
+}
+
+impl KdrWriter {
+    fn varint(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+
+            if value == 0 {
+                self.writer.append_u8(byte);
+                break;
+            }
+
+            self.writer.append_u8(byte | 0x80);
+        }
+    }
+
+    fn zigzag(&mut self, value: i32) {
+        self.varint(((value << 1) ^ (value >> 31)) as u32 as u64);
+    }
+
+    fn string(&mut self, s: &str) {
+        self.varint(s.len() as u64);
+        self.writer.append_string(s);
+    }
+
+    fn option<T>(&mut self, value: Option<T>, f: impl FnOnce(&mut Self, T)) {
+        match value {
+            Some(value) => {
+                self.writer.append_u8(1);
+                f(self, value);
+            }
+            None => self.writer.append_u8(0),
+        }
+    }
+
+    fn list<T>(&mut self, values: &[T], mut f: impl FnMut(&mut Self, &T)) {
+        self.varint(values.len() as u64);
+        values.iter().for_each(|value| f(self, value));
+    }
+
+    fn f32s(&mut self, values: &[f32]) {
+        values.iter().for_each(|&x| self.writer.append_f32(x));
+    }
+
+    fn extras(&mut self, extras: &GhostFrameExtra) {
+        self.list(&extras.sound, |w, sound| {
+            w.string(&sound.file_name);
+            w.zigzag(sound.channel);
+            w.writer.append_f32(sound.volume);
+            w.option(sound.origin, |w, origin| w.f32s(&origin));
+        });
+
+        self.list(&extras.entity_text, |w, text| {
+            w.string(&text.text);
+            w.f32s(&text.location);
+            w.f32s(&text.color);
+            w.writer.append_f32(text.life);
+            w.writer.append_i8(text.channel);
+        });
+
+        self.option(extras.anim.as_ref(), |w, anim| {
+            w.option(anim.sequence, Self::zigzag);
+            w.option(anim.frame, |w, x| w.writer.append_f32(x));
+            w.option(anim.animtime, |w, x| w.writer.append_f32(x));
+            w.option(anim.gaitsequence, Self::zigzag);
+            w.writer.append_u8_slice(&anim.blending);
+        });
+
+        self.list(&extras.say_text, |w, say_text| {
+            w.list(&say_text.text, |w, (color, text)| {
+                w.writer.append_u8(*color);
+                w.string(text);
+            });
+        });
+
+        self.option(extras.weapon_change.as_deref(), Self::string);
+        self.option(extras.weapon_sequence, Self::zigzag);
+    }
+
+    fn frame(&mut self, state: &mut KdrFrameState, frame: &GhostFrame) {
+        let origin: [i32; 3] = from_fn(|i| quantize(frame.origin[i], ORIGIN_SCALE));
+        let viewangles: [i32; 3] = from_fn(|i| quantize(frame.viewangles[i], ANGLE_SCALE));
+
+        let mut flags = 0;
+
+        if frame.frametime != state.frametime {
+            flags |= FLAG_FRAMETIME;
+        }
+
+        if frame.viewoffset_z != state.viewoffset_z {
+            flags |= FLAG_VIEWOFFSET;
+        }
+
+        if frame.buttons != state.buttons {
+            flags |= FLAG_BUTTONS;
+        }
+
+        if frame.fov != state.fov {
+            flags |= FLAG_FOV;
+        }
+
+        if frame.extras.is_some() {
+            flags |= FLAG_EXTRAS;
+        }
+
+        self.writer.append_u8(flags);
+
+        (0..3).for_each(|i| self.zigzag(origin[i].wrapping_sub(state.origin[i])));
+        (0..3).for_each(|i| self.zigzag(viewangles[i].wrapping_sub(state.viewangles[i])));
+
+        if flags & FLAG_FRAMETIME != 0 {
+            self.option(frame.frametime, |w, x| w.writer.append_f32(x));
+        }
+
+        if flags & FLAG_VIEWOFFSET != 0 {
+            self.writer.append_f32(frame.viewoffset_z);
+        }
+
+        if flags & FLAG_BUTTONS != 0 {
+            self.option(frame.buttons, |w, x| w.varint(x as u64));
+        }
+
+        if flags & FLAG_FOV != 0 {
+            self.option(frame.fov, |w, x| w.writer.append_f32(x));
+        }
+
+        if let Some(extras) = &frame.extras {
+            self.extras(extras);
+        }
+
+        *state = KdrFrameState {
+            origin,
+            viewangles,
+            viewoffset_z: frame.viewoffset_z,
+            frametime: frame.frametime,
+            buttons: frame.buttons,
+            fov: frame.fov,
+        };
+    }
+}
+
+/// Writes [`GhostInfo`] as kdr ghost.
+///
+/// Origins are rounded to 1/16 unit and view angles are rounded to 16 bit angles. Everything else is kept as is.
+pub fn kdr_ghost_write(ghost: &GhostInfo) -> eyre::Result<Vec<u8>> {
+    let mut w = KdrWriter {
+        writer: ByteWriter::new(),
+    };
+
+    w.writer.append_u8_slice(KDR_GHOST_MAGIC);
+    w.writer.append_u32(KDR_GHOST_VERSION);
+
+    w.string(&ghost.ghost_name);
+    w.string(&ghost.map_name);
+    w.string(&ghost.game_mod);
+
+    w.varint(ghost.frames.len() as u64);
+
+    let mut state = KdrFrameState::default();
+
+    ghost
+        .frames
+        .iter()
+        .for_each(|frame| w.frame(&mut state, frame));
+
+    w.list(&ghost.segments, |w, segment| {
+        w.string(&segment.map_name);
+        w.varint(segment.start_frame as u64);
+    });
+
+    let metadata = &ghost.metadata;
+
+    w.option(metadata.player_name.as_deref(), KdrWriter::string);
+    w.option(metadata.steam_id.as_deref(), KdrWriter::string);
+    w.option(metadata.official_time, |w, x| w.writer.append_f32(x));
+    w.option(metadata.record_date.as_deref(), KdrWriter::string);
+    w.option(
+        metadata.source_format.clone().map(<&str>::from),
+        KdrWriter::string,
+    );
+
+    Ok(w.writer.data)
+}
+
+fn varint(i: &[u8]) -> IResult<u64> {
+    let mut res = 0u64;
+
+    for (idx, &byte) in i.iter().enumerate().take(10) {
+        res |= ((byte & 0x7f) as u64) << (idx * 7);
+
+        if byte & 0x80 == 0 {
+            return Ok((&i[idx + 1..], res));
+        }
+    }
+
+    Err(nom::Err::Error(nom::error::Error::new(
+        i,
+        nom::error::ErrorKind::TooLarge,
+    )))
+}
+
+fn zigzag(i: &[u8]) -> IResult<i32> {
+    let (i, value) = varint(i)?;
+    let value = value as u32;
+
+    Ok((i, ((value >> 1) as i32) ^ -((value & 1) as i32)))
+}
+
+fn string(i: &[u8]) -> IResult<String> {
+    let (i, len) = varint(i)?;
+
+    map_res(take(len as usize), |s: &[u8]| {
+        str::from_utf8(s).map(|s| s.to_string())
+    })(i)
+}
+
+fn option<'a, T>(
+    mut f: impl FnMut(&'a [u8]) -> IResult<'a, T>,
+) -> impl FnMut(&'a [u8]) -> IResult<'a, Option<T>> {
+    move |i| {
+        let (i, present) = le_u8(i)?;
+
+        if present == 0 {
+            return Ok((i, None));
+        }
+
+        let (i, value) = f(i)?;
+
+        Ok((i, Some(value)))
+    }
+}
+
+fn list<'a, T>(
+    mut f: impl FnMut(&'a [u8]) -> IResult<'a, T>,
+) -> impl FnMut(&'a [u8]) -> IResult<'a, Vec<T>> {
+    move |i| {
+        let (mut i, len) = varint(i)?;
+        // don't trust the length for allocation
+        let mut res = Vec::with_capacity((len as usize).min(i.len()));
+
+        for _ in 0..len {
+            let (next, value) = f(i)?;
+            i = next;
+            res.push(value);
+        }
+
+        Ok((i, res))
+    }
+}
+
+fn f32_array<const N: usize>(i: &[u8]) -> IResult<[f32; N]> {
+    let mut i = i;
+    let mut res = [0f32; N];
+
+    for x in res.iter_mut() {
+        (i, *x) = le_f32(i)?;
+    }
+
+    Ok((i, res))
+}
+
+fn kdr_ghost_frame_extra_parse(i: &[u8]) -> IResult<GhostFrameExtra> {
+    let (i, sound) = list(|i| {
+        let (i, file_name) = string(i)?;
+        let (i, channel) = zigzag(i)?;
+        let (i, volume) = le_f32(i)?;
+        let (i, origin) = option(f32_array::<3>)(i)?;
+
+        Ok((
+            i,
+            GhostFrameSound {
+                file_name,
+                channel,
+                volume,
+                origin,
+            },
+        ))
+    })(i)?;
+
+    let (i, entity_text) = list(|i| {
+        let (i, text) = string(i)?;
+        let (i, location) = f32_array::<2>(i)?;
+        let (i, color) = f32_array::<4>(i)?;
+        let (i, life) = le_f32(i)?;
+        let (i, channel) = le_i8(i)?;
+
+        Ok((
+            i,
+            GhostFrameEntityText {
+                text,
+                location,
+                color,
+                life,
+                channel,
+            },
+        ))
+    })(i)?;
+
+    let (i, anim) = option(|i| {
+        let (i, sequence) = option(zigzag)(i)?;
+        let (i, frame) = option(le_f32)(i)?;
+        let (i, animtime) = option(le_f32)(i)?;
+        let (i, gaitsequence) = option(zigzag)(i)?;
+        let (i, blending0) = le_u8(i)?;
+        let (i, blending1) = le_u8(i)?;
+
+        Ok((
+            i,
+            GhostFrameAnim {
+                sequence,
+                frame,
+                animtime,
+                gaitsequence,
+                blending: [blending0, blending1],
+            },
+        ))
+    })(i)?;
+
+    let (i, say_text) = list(|i| {
+        let (i, text) = list(|i| {
+            let (i, color) = le_u8(i)?;
+            let (i, text) = string(i)?;
+
+            Ok((i, (color, text)))
+        })(i)?;
+
+        Ok((i, GhostFrameSayText { text }))
+    })(i)?;
+
+    let (i, weapon_change) = option(string)(i)?;
+    let (i, weapon_sequence) = option(zigzag)(i)?;
+
+    Ok((
+        i,
+        GhostFrameExtra {
+            sound,
+            entity_text,
+            anim,
+            say_text,
+            weapon_change,
+            weapon_sequence,
+        },
+    ))
+}
+
+fn kdr_ghost_frame_parse<'a>(i: &'a [u8], state: &mut KdrFrameState) -> IResult<'a, GhostFrame> {
+    let (mut i, flags) = le_u8(i)?;
+
+    for idx in 0..3 {
+        let (next, delta) = zigzag(i)?;
+        i = next;
+        state.origin[idx] = state.origin[idx].wrapping_add(delta);
+    }
+
+    for idx in 0..3 {
+        let (next, delta) = zigzag(i)?;
+        i = next;
+        state.viewangles[idx] = state.viewangles[idx].wrapping_add(delta);
+    }
+
+    if flags & FLAG_FRAMETIME != 0 {
+        (i, state.frametime) = option(le_f32)(i)?;
+    }
+
+    if flags & FLAG_VIEWOFFSET != 0 {
+        (i, state.viewoffset_z) = le_f32(i)?;
+    }
+
+    if flags & FLAG_BUTTONS != 0 {
+        (i, state.buttons) = option(|i| varint(i).map(|(i, x)| (i, x as u32)))(i)?;
+    }
+
+    if flags & FLAG_FOV != 0 {
+        (i, state.fov) = option(le_f32)(i)?;
+    }
+
+    let extras = if flags & FLAG_EXTRAS != 0 {
+        let (next, extras) = kdr_ghost_frame_extra_parse(i)?;
+        i = next;
+
+        Some(extras)
+    } else {
+        None
+    };
+
+    Ok((
+        i,
+        GhostFrame {
+            origin: Vec3::from_array(from_fn(|idx| dequantize(state.origin[idx], ORIGIN_SCALE))),
+            viewangles: Vec3::from_array(from_fn(|idx| {
+                dequantize(state.viewangles[idx], ANGLE_SCALE)
+            })),
+            viewoffset_z: state.viewoffset_z,
+            frametime: state.frametime,
+            buttons: state.buttons,
+            fov: state.fov,
+            extras,
+        },
+    ))
+}
+
+fn kdr_ghost_header_parse(i: &[u8]) -> IResult<(u32, String, String, String, u64)> {
+    let (i, _) = tag(KDR_GHOST_MAGIC.as_slice())(i)?;
+    let (i, version) = le_u32(i)?;
+
+    // don't read the rest if it is a different version
+    if version != KDR_GHOST_VERSION {
+        return Ok((i, (version, String::new(), String::new(), String::new(), 0)));
+    }
+
+    let (i, ghost_name) = string(i)?;
+    let (i, map_name) = string(i)?;
+    let (i, game_mod) = string(i)?;
+    let (i, frame_count) = varint(i)?;
+
+    Ok((i, (version, ghost_name, map_name, game_mod, frame_count)))
+}
+
+pub fn kdr_ghost_parse(_file_name: &str, file: &[u8]) -> eyre::Result<GhostInfo> {
+    let (mut i, (version, ghost_name, map_name, game_mod, frame_count)) =
+        kdr_ghost_header_parse(file).map_err(|_| eyre!("Cannot parse kdr ghost header"))?;
+
+    if version != KDR_GHOST_VERSION {
+        return Err(eyre!(
+            "Unsupported kdr ghost version {}, expecting {}",
+            version,
+            KDR_GHOST_VERSION
+        ));
+    }
+
+    let mut state = KdrFrameState::default();
+    let mut frames = Vec::with_capacity((frame_count as usize).min(file.len()));
+
+    for frame_idx in 0..frame_count {
+        let (next, frame) = kdr_ghost_frame_parse(i, &mut state)
+            .map_err(|_| eyre!("Cannot parse kdr ghost frame {}", frame_idx))?;
+
+        i = next;
+        frames.push(frame);
+    }
+
+    let (i, segments) = list(|i| {
+        let (i, map_name) = string(i)?;
+        let (i, start_frame) = varint(i)?;
+
+        Ok((
+            i,
+            GhostSegment {
+                map_name,
+                start_frame: start_frame as usize,
+            },
+        ))
+    })(i)
+    .map_err(|_| eyre!("Cannot parse kdr ghost segments"))?;
+
+    let (i, (player_name, steam_id, official_time, record_date, source_format)) = tuple((
+        option(string),
+        option(string),
+        option(le_f32),
+        option(string),
+        option(string),
+    ))(i)
+    .map_err(|_| eyre!("Cannot parse kdr ghost metadata"))?;
+
+    let metadata = GhostMetadata {
+        player_name,
+        steam_id,
+        official_time,
+        record_date,
+        // written by kdr, so it should always be known
+        source_format: source_format
+            .and_then(|source_format| GhostBlobType::try_from(source_format.as_str()).ok())
+            .or(Some(GhostBlobType::Kdr)),
+    };
+
+    if !i.is_empty() {
+        return Err(eyre!("Trailing {} bytes after kdr ghost frames", i.len()));
+    }
+
+    // the rest of the ghost expects sorted segments from frame 0 on the map of the ghost
+    if let Some(first) = segments
+        .first()
+        .filter(|first| first.start_frame != 0 || first.map_name != map_name)
+    {
+        return Err(eyre!(
+            "First kdr ghost segment starts at frame {} on `{}` instead of frame 0 on `{}`",
+            first.start_frame,
+            first.map_name,
+            map_name
+        ));
+    }
+
+    if let Some(segment) = segments.windows(2).find(|pair| {
+        pair[1].start_frame <= pair[0].start_frame || pair[1].start_frame >= frames.len()
+    }) {
+        return Err(eyre!(
+            "kdr ghost segment starts at frame {} out of order or past {} frames",
+            segment[1].start_frame,
+            frames.len()
+        ));
+    }
+
+    let mut ghost = GhostInfo::new(ghost_name, map_name, game_mod, frames);
+
+    ghost.metadata = metadata;
+
+    if !segments.is_empty() {
+        ghost.segments = segments;
+    }
+
+    Ok(ghost)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample_extras(i: usize) -> GhostFrameExtra {
+        GhostFrameExtra {
+            sound: vec![GhostFrameSound {
+                file_name: format!("player/pl_step{}.wav", i % 4 + 1),
+                channel: 4,
+                volume: 0.5,
+                origin: Some([1., -2., 3.5]),
+            }],
+            entity_text: vec![GhostFrameEntityText {
+                text: "Timer started".to_string(),
+                location: [0.5, 0.25],
+                color: [1., 0.5, 0., 1.],
+                life: 0.19,
+                channel: -1,
+            }],
+            anim: Some(GhostFrameAnim {
+                sequence: Some(-3),
+                frame: None,
+                animtime: Some(1.25),
+                gaitsequence: Some(6),
+                blending: [12, 250],
+            }),
+            say_text: vec![GhostFrameSayText {
+                text: vec![(2, "player".to_string()), (0, ": gg ♥".to_string())],
+            }],
+            weapon_change: Some("usp".to_string()),
+            weapon_sequence: None,
+        }
+    }
+
+    #[test]
+    fn write_parse() {
+        let mut frames = sample_frames(500, Some(0.001));
+
+        frames.iter_mut().enumerate().for_each(|(i, frame)| {
+            frame.viewoffset_z = if i % 50 < 25 { 17. } else { 12. };
+            frame.fov = (i > 100).then_some(90.);
+
+            if i % 50 == 3 {
+                frame.frametime = Some(0.0125);
+                frame.extras = Some(sample_extras(i));
+            }
+        });
+
+        let mut ghost = GhostInfo::new(
+            "kz_hj_demo".to_string(),
+            "kz_hj".to_string(),
+            "cstrike".to_string(),
+            frames,
+        );
+
+        ghost.segments.push(GhostSegment {
+            map_name: "kz_lj".to_string(),
+            start_frame: 250,
+        });
+
+        ghost.metadata = GhostMetadata {
+            player_name: Some("player".to_string()),
+            steam_id: Some("STEAM_0:1:2345".to_string()),
+            official_time: Some(12.345),
+            record_date: None,
+            source_format: Some(GhostBlobType::Demo),
+        };
+
+        let bytes = kdr_ghost_write(&ghost).unwrap();
+        let parsed = kdr_ghost_parse("kz_hj_demo.kdr", &bytes).unwrap();
+
+        assert_eq!(parsed.ghost_name, ghost.ghost_name);
+        assert_eq!(parsed.map_name, ghost.map_name);
+        assert_eq!(parsed.game_mod, ghost.game_mod);
+        assert_eq!(parsed.frames.len(), ghost.frames.len());
+        assert_eq!(parsed.segments.len(), 2);
+        assert_eq!(parsed.get_segment(249).unwrap().map_name, "kz_hj");
+        assert_eq!(parsed.get_segment(250).unwrap().map_name, "kz_lj");
+        assert_eq!(parsed.metadata.player_name.as_deref(), Some("player"));
+        assert_eq!(parsed.metadata.steam_id.as_deref(), Some("STEAM_0:1:2345"));
+        assert_eq!(parsed.metadata.official_time, Some(12.345));
+        assert_eq!(parsed.metadata.record_date, None);
+        assert!(matches!(
+            parsed.metadata.source_format,
+            Some(GhostBlobType::Demo)
+        ));
+
+        ghost
+            .frames
+            .iter()
+            .zip(parsed.frames.iter())
+            .for_each(|(a, b)| {
+                assert!(a.origin.abs_diff_eq(b.origin, 0.5 / ORIGIN_SCALE));
+
+                (0..3).for_each(|i| {
+                    assert!(angle_diff(a.viewangles[i], b.viewangles[i]).abs() <= 0.5 / ANGLE_SCALE)
+                });
+
+                assert_eq!(a.viewoffset_z, b.viewoffset_z);
+                assert_eq!(a.frametime, b.frametime);
+                assert_eq!(a.buttons, b.buttons);
+                assert_eq!(a.fov, b.fov);
+                // no PartialEq for extras
+                assert_eq!(format!("{:?}", a.extras), format!("{:?}", b.extras));
+            });
+
+        // raw f32 origin and angles alone would take 24 bytes per frame
+        assert!(bytes.len() < ghost.frames.len() * 24);
+    }
+
+    #[test]
+    fn bad_segments() {
+        let write = |segments: &[(&str, usize)]| {
+            let mut ghost = GhostInfo::new(
+                "test".to_string(),
+                "first".to_string(),
+                "cstrike".to_string(),
+                sample_frames(100, Some(0.01)),
+            );
+
+            ghost.segments = segments
+                .iter()
+                .map(|&(map_name, start_frame)| GhostSegment {
+                    map_name: map_name.to_string(),
+                    start_frame,
+                })
+                .collect();
+
+            kdr_ghost_write(&ghost).unwrap()
+        };
+
+        let parse = |bytes: Vec<u8>| kdr_ghost_parse("test.kdr", &bytes);
+
+        assert!(parse(write(&[("first", 0), ("second", 50)])).is_ok());
+        // not from frame 0
+        assert!(parse(write(&[("first", 10), ("second", 50)])).is_err());
+        // not the map of the ghost
+        assert!(parse(write(&[("second", 0)])).is_err());
+        // unsorted
+        assert!(parse(write(&[("first", 0), ("second", 50), ("third", 20)])).is_err());
+        // past the frames
+        assert!(parse(write(&[("first", 0), ("second", 100)])).is_err());
+    }
+
+    #[test]
+    fn bad_version() {
+        let ghost = GhostInfo::new(String::new(), String::new(), String::new(), vec![]);
+        let mut bytes = kdr_ghost_write(&ghost).unwrap();
+
+        bytes[4] = 255;
+
+        assert!(kdr_ghost_parse("bad.kdr", &bytes).is_err());
+    }
+}
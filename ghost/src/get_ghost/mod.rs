@@ -1,15 +1,36 @@
-use std::{array::from_fn, path::Path};
+use std::{array::from_fn, ops::Range, path::Path};
 
 use common::angle_diff;
 use dem::types::Demo;
 use glam::{FloatExt, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::GhostBlobType;
 
 pub(crate) mod demo;
+mod edit;
+pub(crate) mod kdr;
 pub(crate) mod romanian_jumpers;
+pub(crate) mod schema;
 pub(crate) mod simen;
 pub(crate) mod sourceruns_hlkz;
 pub(crate) mod surf_gateway;
 
+/// Frametime to use when writing a ghost without frametime into a format that needs it.
+pub const DEFAULT_GHOST_FRAMETIME: f32 = 0.010000001;
+
+/// What to do when a part of the replay is broken.
+///
+/// Only demo parsing can skip parts. Other formats always fail on bad data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GhostParseMode {
+    /// Fails on the first bad message.
+    #[default]
+    Strict,
+    /// Skips bad messages and records them in [`GhostInfo::warnings`].
+    Lenient,
+}
+
 #[derive(Debug, Clone)]
 pub struct GhostFrameSound {
     pub file_name: String,
@@ -78,6 +99,52 @@ pub struct GhostFrameExtra {
     pub weapon_sequence: Option<i32>,
 }
 
+// bits of `GhostFrame::buttons`, same as HLSDK in_buttons.h
+pub const IN_ATTACK: u32 = 1 << 0;
+pub const IN_JUMP: u32 = 1 << 1;
+pub const IN_DUCK: u32 = 1 << 2;
+pub const IN_FORWARD: u32 = 1 << 3;
+pub const IN_BACK: u32 = 1 << 4;
+pub const IN_USE: u32 = 1 << 5;
+pub const IN_CANCEL: u32 = 1 << 6;
+pub const IN_LEFT: u32 = 1 << 7;
+pub const IN_RIGHT: u32 = 1 << 8;
+pub const IN_MOVELEFT: u32 = 1 << 9;
+pub const IN_MOVERIGHT: u32 = 1 << 10;
+pub const IN_ATTACK2: u32 = 1 << 11;
+pub const IN_RUN: u32 = 1 << 12;
+pub const IN_RELOAD: u32 = 1 << 13;
+pub const IN_ALT1: u32 = 1 << 14;
+pub const IN_SCORE: u32 = 1 << 15;
+
+const BUTTON_NAMES: &[(u32, &str)] = &[
+    (IN_ATTACK, "attack"),
+    (IN_JUMP, "jump"),
+    (IN_DUCK, "duck"),
+    (IN_FORWARD, "forward"),
+    (IN_BACK, "back"),
+    (IN_USE, "use"),
+    (IN_CANCEL, "cancel"),
+    (IN_LEFT, "left"),
+    (IN_RIGHT, "right"),
+    (IN_MOVELEFT, "moveleft"),
+    (IN_MOVERIGHT, "moveright"),
+    (IN_ATTACK2, "attack2"),
+    (IN_RUN, "speed"),
+    (IN_RELOAD, "reload"),
+    (IN_ALT1, "alt1"),
+    (IN_SCORE, "showscores"),
+];
+
+/// Names of the pressed buttons, same as the console commands without `+`.
+pub fn button_names(buttons: u32) -> Vec<&'static str> {
+    BUTTON_NAMES
+        .iter()
+        .filter(|(bit, _)| buttons & bit != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub struct GhostFrame {
     pub origin: Vec3,
@@ -98,15 +165,187 @@ pub struct GhostFrameAnim {
     pub blending: [u8; 2],
 }
 
+/// Part of a ghost that is played on one map.
+#[derive(Debug, Clone)]
+pub struct GhostSegment {
+    pub map_name: String,
+    /// Index of the first frame on this map.
+    pub start_frame: usize,
+}
+
+/// Information about the run that is not in the frames.
+///
+/// Everything is optional because most formats only have some of it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GhostMetadata {
+    pub player_name: Option<String>,
+    pub steam_id: Option<String>,
+    /// Run time from the record in seconds, which can be different from the ghost length.
+    pub official_time: Option<f32>,
+    /// As written in the replay. Unix timestamps are converted to `YYYY-MM-DD`.
+    pub record_date: Option<String>,
+    /// Format the ghost is parsed from.
+    ///
+    /// kdr ghost keeps the format it is converted from.
+    pub source_format: Option<GhostBlobType>,
+}
+
+impl GhostMetadata {
+    pub(crate) fn from_source(source_format: GhostBlobType) -> Self {
+        Self {
+            source_format: Some(source_format),
+            ..Default::default()
+        }
+    }
+}
+
+/// Converts unix timestamp to `YYYY-MM-DD`.
+pub(crate) fn unix_timestamp_to_date(timestamp: u64) -> String {
+    // days to civil from http://howardhinnant.github.io/date_algorithms.html
+    let days = (timestamp / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
 #[derive(Debug)]
 pub struct GhostInfo {
     pub ghost_name: String,
+    /// Map of the first segment.
     pub map_name: String,
     pub game_mod: String,
-    pub frames: Vec<GhostFrame>,
+    pub metadata: GhostMetadata,
+    /// Read with [`GhostInfo::frames`] and change with [`GhostInfo::edit_frames`] so frame times stay indexed.
+    frames: Vec<GhostFrame>,
+    /// Map changes in the ghost, sorted by start frame.
+    ///
+    /// There is always one segment starting at frame 0.
+    pub segments: Vec<GhostSegment>,
+    /// Problems skipped when parsing with [`GhostParseMode::Lenient`].
+    pub warnings: Vec<crate::GhostError>,
+    time_index: GhostTimeIndex,
+}
+
+/// Cumulative frame times so that looking up a frame from time doesn't need to go through every frame.
+#[derive(Debug, Default)]
+struct GhostTimeIndex {
+    /// Start time of every frame. The last element is the ghost length.
+    ///
+    /// Frames without frametime take no time, same as [`GhostInfo::get_ghost_length`].
+    start_times: Vec<f32>,
+}
+
+impl GhostTimeIndex {
+    fn new(frames: &[GhostFrame]) -> Self {
+        let mut start_times = Vec::with_capacity(frames.len() + 1);
+        let mut time = 0f32;
+
+        start_times.push(time);
+
+        frames.iter().for_each(|frame| {
+            time += frame.frametime.unwrap_or(0.);
+            start_times.push(time);
+        });
+
+        Self { start_times }
+    }
 }
 
 impl GhostInfo {
+    pub fn new(
+        ghost_name: String,
+        map_name: String,
+        game_mod: String,
+        frames: Vec<GhostFrame>,
+    ) -> Self {
+        let time_index = GhostTimeIndex::new(&frames);
+
+        let segments = vec![GhostSegment {
+            map_name: map_name.clone(),
+            start_frame: 0,
+        }];
+
+        Self {
+            ghost_name,
+            map_name,
+            game_mod,
+            metadata: GhostMetadata::default(),
+            frames,
+            segments,
+            warnings: vec![],
+            time_index,
+        }
+    }
+
+    /// Player name if the replay has one, otherwise the ghost name.
+    pub fn display_name(&self) -> &str {
+        self.metadata
+            .player_name
+            .as_deref()
+            .unwrap_or(&self.ghost_name)
+    }
+
+    pub fn frames(&self) -> &[GhostFrame] {
+        &self.frames
+    }
+
+    /// Changes frames with `edit` and rebuilds cumulative frame times after.
+    pub fn edit_frames<T>(&mut self, edit: impl FnOnce(&mut Vec<GhostFrame>) -> T) -> T {
+        let res = edit(&mut self.frames);
+
+        self.rebuild_time_index();
+
+        res
+    }
+
+    /// Rebuilds cumulative frame times. Must be called whenever frames are modified.
+    fn rebuild_time_index(&mut self) {
+        self.time_index = GhostTimeIndex::new(&self.frames);
+    }
+
+    /// Start time of frame `index`, `index` can go up to the frame count.
+    fn frame_start_time(&self, index: usize, frametime: Option<f32>) -> f32 {
+        match frametime {
+            Some(frametime) => frametime * index as f32,
+            None => self.time_index.start_times[index],
+        }
+    }
+
+    /// The last frame that starts at or before `time`, or the first frame.
+    fn search_frame_index(&self, time: f32, frametime: Option<f32>) -> usize {
+        // binary search for the first frame starting after `time`
+        let mut low = 0;
+        let mut high = self.frames.len();
+
+        while low < high {
+            let mid = low + (high - low) / 2;
+
+            if self.frame_start_time(mid, frametime) <= time {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+
+        low.saturating_sub(1)
+    }
+
+    fn has_frametime(&self, frametime: Option<f32>) -> bool {
+        frametime.is_some()
+            || self
+                .frames
+                .first()
+                .is_some_and(|frame| frame.frametime.is_some())
+    }
+
     /// Returns an interpolated [`GhostFrame`] based on current time and the round down frame index.
     ///
     /// Takes an optional argument to force frametime.
@@ -114,35 +353,19 @@ impl GhostInfo {
         let frame0 = self.frames.first()?;
 
         // No frame time, not sure how to accumulate correctly
-        if frame0.frametime.is_none() && frametime.is_none() {
+        if !self.has_frametime(frametime) {
             return None;
         }
 
-        let mut from_time = 0f32;
-        let mut to_time = 0f32;
-        let mut to_index = 0usize;
-
-        for (index, frame) in self.frames.iter().enumerate() {
-            let add_time = if let Some(frametime) = frametime {
-                frametime
-            } else {
-                frame.frametime.unwrap()
-            };
-
-            // only exit when greater means we are having the "to" frame
-            if to_time > time {
-                break;
-            }
-
-            from_time = to_time;
-            to_time += add_time;
-            to_index = index;
-        }
+        let to_index = self.search_frame_index(time, frametime);
 
         if to_index == 0 {
             return Some((0, frame0.clone()));
         }
 
+        let from_time = self.frame_start_time(to_index, frametime);
+        let to_time = self.frame_start_time(to_index + 1, frametime);
+
         // If exceeding the number of available frames then we have nothing.
         // This is to make sure that we know when it ends.
         if to_index == self.frames.len() - 1 && time >= to_time {
@@ -157,71 +380,96 @@ impl GhostInfo {
         // clamp because vec lerp extrapolates as well.
         let target = target.clamp(0., 1.);
 
-        let new_origin = from_frame.origin.lerp(to_frame.origin, target as f32);
-
-        let viewangles_diff: [f32; 3] = from_fn(|i| {
-            angle_diff(
-                // normalize is not what we want as we are in between +/-
-                from_frame.viewangles[i],
-                to_frame.viewangles[i],
-            )
-        });
-        let viewangles_diff = Vec3::from(viewangles_diff);
-        let new_viewangles = from_frame
-            .viewangles
-            // attention, lerp to `from + diff`
-            .lerp(from_frame.viewangles + viewangles_diff, target as f32);
-
-        let new_fov = if from_frame.fov.is_some() && to_frame.fov.is_some() {
-            let from_fov = from_frame.fov.unwrap();
-            let to_fov = to_frame.fov.unwrap();
-            Some(from_fov.lerp(to_fov, target as f32))
-        } else {
-            None
-        };
-
         // Maybe do some interpolation for sequence in the future? Though only demo would have it.
-        Some((
-            // to index is guaranteed to not be 0
-            to_index - 1,
-            GhostFrame {
-                origin: new_origin,
-                viewangles: new_viewangles,
-                viewoffset_z: from_frame.viewoffset_z.lerp(to_frame.viewoffset_z, target),
-                frametime: from_frame.frametime,
-                buttons: from_frame.buttons,
-                fov: new_fov,
-                extras: from_frame.extras.clone(),
-            },
-        ))
+        let frame = lerp_frames(from_frame, to_frame, target);
+
+        // to index is guaranteed to not be 0
+        Some((to_index - 1, frame))
     }
 
     /// Returns the frame index from a given time.
+    ///
+    /// Returns 0 if there is no frametime to go by.
     pub fn get_frame_index(&self, time: f32, frametime: Option<f32>) -> usize {
-        let mut to_time = 0f32;
-        let mut to_index = 0usize;
+        if !self.has_frametime(frametime) {
+            return 0;
+        }
 
-        for (index, frame) in self.frames.iter().enumerate() {
-            let add_time = if let Some(frametime) = frametime {
-                frametime
-            } else {
-                frame.frametime.unwrap()
-            };
+        self.search_frame_index(time, frametime)
+    }
 
-            // only exit when greater means we are having the "to" frame
-            if to_time > time {
-                break;
-            }
+    /// Returns the recorded [`GhostFrame`] at the given time without any interpolation.
+    ///
+    /// Frame `i` spans from [`GhostInfo::get_frame_time`] of `i` until that of `i + 1`.
+    ///
+    /// Takes an optional argument to force frametime.
+    pub fn get_frame_exact(
+        &self,
+        time: f32,
+        frametime: Option<f32>,
+    ) -> Option<(usize, GhostFrame)> {
+        if !self.has_frametime(frametime) {
+            return None;
+        }
 
-            to_time += add_time;
-            to_index = index;
+        // same as interpolated frame, nothing after the end
+        if time >= self.frame_start_time(self.frames.len(), frametime) {
+            return None;
         }
 
-        if to_index == 0 {
-            return 0;
+        let index = self.search_frame_index(time, frametime);
+
+        self.frames.get(index).map(|frame| (index, frame.clone()))
+    }
+
+    /// Returns the time when the frame at `index` starts.
+    ///
+    /// Takes an optional argument to force frametime.
+    pub fn get_frame_time(&self, index: usize, frametime: Option<f32>) -> Option<f32> {
+        if index >= self.frames.len() || !self.has_frametime(frametime) {
+            return None;
+        }
+
+        Some(self.frame_start_time(index, frametime))
+    }
+
+    /// Returns the index of the segment that the frame at `index` belongs to.
+    pub fn get_segment_index(&self, index: usize) -> usize {
+        self.segments
+            .partition_point(|segment| segment.start_frame <= index)
+            .saturating_sub(1)
+    }
+
+    /// Returns the segment that the frame at `index` belongs to.
+    pub fn get_segment(&self, index: usize) -> Option<&GhostSegment> {
+        self.segments.get(self.get_segment_index(index))
+    }
+
+    /// Keeps only the frames in `range` and moves segments along with them.
+    pub fn trim(&mut self, range: Range<usize>) {
+        let end = range.end.min(self.frames.len());
+        let start = range.start.min(end);
+        let first_segment = self.get_segment_index(start);
+
+        self.frames.truncate(end);
+        self.frames.drain(..start);
+
+        self.segments = self
+            .segments
+            .drain(first_segment..)
+            .enumerate()
+            .filter(|(idx, segment)| *idx == 0 || segment.start_frame < end)
+            .map(|(_, segment)| GhostSegment {
+                start_frame: segment.start_frame.saturating_sub(start),
+                ..segment
+            })
+            .collect();
+
+        if let Some(segment) = self.segments.first() {
+            self.map_name = segment.map_name.clone();
         }
 
-        to_index
+        self.rebuild_time_index();
     }
 
     // /// Rotates viewangle and vieworigin around origin z axis (height) by `rotation` value
@@ -235,8 +483,8 @@ impl GhostInfo {
     // Returns a closure that we can re-use
     // This closure can also take in an argument in case the ghost doesn't have frame time.
     pub fn get_ghost_length(&self) -> Box<dyn Fn(f32) -> f32 + '_> {
-        let has_frametime = self.frames[0].frametime.is_some();
-        let maybe_total_length: f32 = self.frames.iter().filter_map(|frame| frame.frametime).sum();
+        let has_frametime = self.has_frametime(None);
+        let maybe_total_length = self.frame_start_time(self.frames.len(), None);
 
         let use_custom_frametime = move |frametime: f32| frametime * self.frames.len() as f32;
         let ignore_custom_frametime = move |_: f32| maybe_total_length;
@@ -263,8 +511,175 @@ impl GhostInfo {
     }
 }
 
+/// Moves origin, viewangles, view offset and fov from `from` toward `to` by `target` from 0 to 1.
+///
+/// Everything else is from `from`.
+fn lerp_frames(from: &GhostFrame, to: &GhostFrame, target: f32) -> GhostFrame {
+    let new_origin = from.origin.lerp(to.origin, target);
+
+    let viewangles_diff: [f32; 3] = from_fn(|i| {
+        angle_diff(
+            // normalize is not what we want as we are in between +/-
+            from.viewangles[i],
+            to.viewangles[i],
+        )
+    });
+    let viewangles_diff = Vec3::from(viewangles_diff);
+    let new_viewangles = from
+        .viewangles
+        // attention, lerp to `from + diff`
+        .lerp(from.viewangles + viewangles_diff, target);
+
+    let new_fov = match (from.fov, to.fov) {
+        (Some(from_fov), Some(to_fov)) => Some(from_fov.lerp(to_fov, target)),
+        _ => None,
+    };
+
+    GhostFrame {
+        origin: new_origin,
+        viewangles: new_viewangles,
+        viewoffset_z: from.viewoffset_z.lerp(to.viewoffset_z, target),
+        fov: new_fov,
+        ..from.clone()
+    }
+}
+
 pub fn file_name_get_stem(file_name: &str) -> Option<String> {
     let p = Path::new(file_name);
 
     p.file_name()?.to_str()?.to_string().into()
 }
+
+#[cfg(test)]
+pub(crate) fn sample_frames(count: usize, frametime: Option<f32>) -> Vec<GhostFrame> {
+    (0..count)
+        .map(|i| {
+            let i = i as f32;
+
+            GhostFrame {
+                viewangles: Vec3::new((i * 0.05).sin() * 89., (i * 2.5) % 360. - 180., 0.),
+                ..sample_frame(
+                    Vec3::new(i * 3.25, -i * 1.5, 64. + (i * 0.1).sin() * 32.),
+                    0.,
+                    frametime,
+                    Some(i as u32 % 4 * 2),
+                )
+            }
+        })
+        .collect()
+}
+
+/// Frame at `origin` looking level at `yaw`.
+#[cfg(test)]
+pub(crate) fn sample_frame(
+    origin: Vec3,
+    yaw: f32,
+    frametime: Option<f32>,
+    buttons: Option<u32>,
+) -> GhostFrame {
+    GhostFrame {
+        origin,
+        viewangles: Vec3::new(0., yaw, 0.),
+        viewoffset_z: 0.,
+        frametime,
+        buttons,
+        fov: None,
+        extras: None,
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn sample_ghost(frames: Vec<GhostFrame>) -> GhostInfo {
+    GhostInfo::new("test".into(), "test".into(), "cstrike".into(), frames)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn uneven_ghost() -> GhostInfo {
+        let frames = sample_frames(200, None)
+            .into_iter()
+            .enumerate()
+            .map(|(i, frame)| GhostFrame {
+                frametime: Some(0.004 + (i % 7) as f32 * 0.003),
+                ..frame
+            })
+            .collect();
+
+        sample_ghost(frames)
+    }
+
+    /// Frame index and start times of the frame and the next one by going through every frame.
+    fn linear_scan(ghost: &GhostInfo, time: f32) -> (usize, f32, f32) {
+        let mut from_time = 0f32;
+        let mut to_time = 0f32;
+        let mut to_index = 0;
+
+        for (index, frame) in ghost.frames.iter().enumerate() {
+            if to_time > time {
+                break;
+            }
+
+            from_time = to_time;
+            to_time += frame.frametime.unwrap();
+            to_index = index;
+        }
+
+        (to_index, from_time, to_time)
+    }
+
+    #[test]
+    fn search_matches_linear_scan() {
+        let ghost = uneven_ghost();
+        let length = ghost.get_ghost_length()(0.);
+
+        let times = (0..=1000)
+            .map(|i| length * 1.1 * i as f32 / 1000.)
+            // every frame boundary exactly
+            .chain((0..=ghost.frames.len()).map(|i| ghost.frame_start_time(i, None)));
+
+        times.for_each(|time| {
+            let (index, from_time, to_time) = linear_scan(&ghost, time);
+
+            assert_eq!(ghost.get_frame_index(time, None), index, "{time}");
+
+            let expected = if index == 0 {
+                Some((0, ghost.frames[0].origin))
+            } else if index == ghost.frames.len() - 1 && time >= to_time {
+                None
+            } else {
+                let target = ((time - from_time) / (to_time - from_time)).clamp(0., 1.);
+
+                Some((
+                    index - 1,
+                    ghost.frames[index - 1]
+                        .origin
+                        .lerp(ghost.frames[index].origin, target),
+                ))
+            };
+
+            let frame = ghost
+                .get_frame(time, None)
+                .map(|(index, frame)| (index, frame.origin));
+
+            assert_eq!(frame, expected, "{time}");
+        });
+    }
+
+    #[test]
+    fn edit_frames_rebuilds_index() {
+        let mut ghost = uneven_ghost();
+        let length = ghost.get_ghost_length()(0.);
+
+        ghost.edit_frames(|frames| frames.extend(sample_frames(10, Some(0.01))));
+
+        assert_eq!(ghost.get_frame_time(200, None), Some(length));
+        assert_eq!(ghost.get_frame_index(length + 0.015, None), 201);
+
+        ghost.edit_frames(|frames| frames.truncate(50));
+
+        assert_eq!(ghost.get_frame_index(length, None), 49);
+        assert!(ghost.get_frame_exact(length, None).is_none());
+    }
+}
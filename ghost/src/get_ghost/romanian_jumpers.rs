@@ -24,22 +24,87 @@ pub fn romanian_jumpers_ghost_parse(filename: &str, file: &str) -> eyre::Result<
     let romanian_jumpers_ghost: RjGhostInfo = serde_json::from_str(&file)?;
 
     // Convert romanian_jumpers_ghost to our normal ghost.
-    Ok(GhostInfo {
-        ghost_name: filename.to_owned(),
-        map_name: "NoMapName".to_string(),
-        game_mod: "cstrike".to_string(),
-        frames: romanian_jumpers_ghost
+    let frames = romanian_jumpers_ghost
+        .frames
+        .iter()
+        .map(|ghost| GhostFrame {
+            frametime: Some(ghost.frametime),
+            origin: Vec3::from_array([ghost.origin[0], -ghost.origin[2], ghost.origin[1]]),
+            viewangles: Vec3::from_array([ghost.viewangles[0], ghost.viewangles[1], 0.]),
+            viewoffset_z: 0.,
+            buttons: ghost.buttons.into(),
+            fov: None,
+            extras: None,
+        })
+        .collect();
+
+    let mut ghost = GhostInfo::new(
+        filename.to_owned(),
+        "NoMapName".to_string(),
+        "cstrike".to_string(),
+        frames,
+    );
+
+    ghost.metadata = GhostMetadata::from_source(GhostBlobType::RomanianJumpers);
+
+    Ok(ghost)
+}
+
+/// Writes [`GhostInfo`] as romanian jumpers JSON ghost.
+///
+/// Ghost without frametime will use [`DEFAULT_GHOST_FRAMETIME`].
+pub fn romanian_jumpers_ghost_write(ghost: &GhostInfo) -> eyre::Result<String> {
+    let mut time = 0f32;
+
+    let frames = ghost
+        .frames
+        .iter()
+        .map(|frame| {
+            let frametime = frame.frametime.unwrap_or(DEFAULT_GHOST_FRAMETIME);
+            time += frametime;
+
+            RjGhostFrame {
+                // reverse of the parser
+                origin: [frame.origin[0], frame.origin[2], -frame.origin[1]],
+                viewangles: [frame.viewangles[0], frame.viewangles[1]],
+                frametime,
+                time,
+                buttons: frame.buttons.unwrap_or(0),
+            }
+        })
+        .collect();
+
+    Ok(serde_json::to_string(&RjGhostInfo { frames })?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn write_parse() {
+        let ghost = GhostInfo::new(
+            "bkz_goldbhop".to_string(),
+            "bkz_goldbhop".to_string(),
+            "cstrike".to_string(),
+            sample_frames(100, Some(0.004)),
+        );
+
+        let file = romanian_jumpers_ghost_write(&ghost).unwrap();
+        let ghost2 = romanian_jumpers_ghost_parse("bkz_goldbhop.rj.json", &file).unwrap();
+
+        assert_eq!(ghost.frames.len(), ghost2.frames.len());
+
+        ghost
             .frames
             .iter()
-            .map(|ghost| GhostFrame {
-                frametime: Some(ghost.frametime),
-                origin: Vec3::from_array([ghost.origin[0], -ghost.origin[2], ghost.origin[1]]),
-                viewangles: Vec3::from_array([ghost.viewangles[0], ghost.viewangles[1], 0.]),
-                viewoffset_z: 0.,
-                buttons: ghost.buttons.into(),
-                fov: None,
-                extras: None,
-            })
-            .collect(),
-    })
+            .zip(ghost2.frames.iter())
+            .for_each(|(a, b)| {
+                assert_eq!(a.origin, b.origin);
+                // romanian jumpers doesn't have roll
+                assert_eq!(a.viewangles.truncate(), b.viewangles.truncate());
+                assert_eq!(a.buttons, b.buttons);
+                assert_eq!(a.frametime, b.frametime);
+            });
+    }
 }
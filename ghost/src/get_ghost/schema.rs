@@ -0,0 +1,404 @@
+//! Ghost format that describes its own layout, for servers that log runs in their own way.
+//!
+//! CSV ghost has the header as `# key: value` lines before the rows. Other `#` lines are comments.
+//!
+//! ```text
+//! # map: kz_longjumps2
+//! # columns: time, origin, angles, buttons
+//! # time_unit: ms
+//! 0, -1024, 512, 36.03125, 0, 90, 0, 0
+//! 10, -1022, 512, 36.03125, 0, 90, 0, 2
+//! ```
+//!
+//! JSON ghost has the same keys in `header` and every frame is an array of numbers.
+//!
+//! ```text
+//! {
+//!     "header": { "map": "kz_longjumps2", "columns": ["frametime", "origin", "angles"] },
+//!     "frames": [[0.01, -1024, 512, 36.03125, 0, 90, 0]]
+//! }
+//! ```
+//!
+//! Header keys:
+//! - `map`: required.
+//! - `columns`: `time` (accumulated) or `frametime`, `origin` (x y z), `angles` (pitch yaw roll), `buttons`, `fov` and `viewoffset` (z).
+//!   Single components are `origin_x`, `origin_y`, `origin_z`, `pitch`, `yaw` and `roll`. `_` skips a column.
+//! - `time_unit`: `s` (default) or `ms`.
+//! - `angle_unit`: `deg` (default) or `rad`.
+//! - `distance_unit`: `units` (default) or `m`. Applies to origin and view offset.
+//! - `game_mod` and `player_name`: optional.
+use eyre::eyre;
+use serde::Deserialize;
+
+use crate::sniff::sniff_text;
+
+use super::*;
+
+// 1 unit is 1 inch
+const UNITS_PER_METER: f32 = 39.37008;
+
+#[derive(Debug, Default, Deserialize)]
+struct SchemaHeader {
+    map: Option<String>,
+    game_mod: Option<String>,
+    player_name: Option<String>,
+    #[serde(default)]
+    columns: Vec<String>,
+    time_unit: Option<String>,
+    angle_unit: Option<String>,
+    distance_unit: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SchemaJsonGhost {
+    header: SchemaHeader,
+    frames: Vec<Vec<f32>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SchemaColumn {
+    Time,
+    Frametime,
+    Origin(usize),
+    Angles(usize),
+    Buttons,
+    Fov,
+    ViewOffset,
+    Skip,
+}
+
+impl SchemaColumn {
+    /// One name can be multiple columns, eg `origin`.
+    fn from_name(name: &str) -> eyre::Result<Vec<Self>> {
+        let columns = match name.trim() {
+            "time" => vec![Self::Time],
+            "frametime" => vec![Self::Frametime],
+            "origin" => (0..3).map(Self::Origin).collect(),
+            "origin_x" => vec![Self::Origin(0)],
+            "origin_y" => vec![Self::Origin(1)],
+            "origin_z" => vec![Self::Origin(2)],
+            "angles" | "viewangles" => (0..3).map(Self::Angles).collect(),
+            "pitch" => vec![Self::Angles(0)],
+            "yaw" => vec![Self::Angles(1)],
+            "roll" => vec![Self::Angles(2)],
+            "buttons" => vec![Self::Buttons],
+            "fov" => vec![Self::Fov],
+            "viewoffset" | "viewoffset_z" => vec![Self::ViewOffset],
+            "_" => vec![Self::Skip],
+            other => return Err(eyre!("Unknown schema ghost column `{}`", other)),
+        };
+
+        Ok(columns)
+    }
+}
+
+/// Header after checking.
+struct Schema {
+    columns: Vec<SchemaColumn>,
+    time_scale: f32,
+    angle_scale: f32,
+    distance_scale: f32,
+}
+
+impl TryFrom<&SchemaHeader> for Schema {
+    type Error = eyre::Report;
+
+    fn try_from(header: &SchemaHeader) -> Result<Self, Self::Error> {
+        let columns = header
+            .columns
+            .iter()
+            .map(|name| SchemaColumn::from_name(name))
+            .collect::<eyre::Result<Vec<_>>>()?
+            .concat();
+
+        if let Some(column) = columns
+            .iter()
+            .enumerate()
+            .find(|&(idx, column)| *column != SchemaColumn::Skip && columns[..idx].contains(column))
+            .map(|(_, column)| column)
+        {
+            return Err(eyre!("Schema ghost column {:?} is declared twice", column));
+        }
+
+        let has = |column: SchemaColumn| columns.contains(&column);
+
+        if !(0..3).all(|i| has(SchemaColumn::Origin(i))) {
+            return Err(eyre!("Schema ghost needs all origin columns"));
+        }
+
+        if has(SchemaColumn::Time) == has(SchemaColumn::Frametime) {
+            return Err(eyre!(
+                "Schema ghost needs either `time` or `frametime` column"
+            ));
+        }
+
+        let time_scale = match header.time_unit.as_deref().unwrap_or("s") {
+            "s" => 1.,
+            "ms" => 0.001,
+            other => return Err(eyre!("Unknown schema ghost time unit `{}`", other)),
+        };
+
+        let angle_scale = match header.angle_unit.as_deref().unwrap_or("deg") {
+            "deg" => 1.,
+            "rad" => 180. / std::f32::consts::PI,
+            other => return Err(eyre!("Unknown schema ghost angle unit `{}`", other)),
+        };
+
+        let distance_scale = match header.distance_unit.as_deref().unwrap_or("units") {
+            "units" => 1.,
+            "m" => UNITS_PER_METER,
+            other => return Err(eyre!("Unknown schema ghost distance unit `{}`", other)),
+        };
+
+        Ok(Self {
+            columns,
+            time_scale,
+            angle_scale,
+            distance_scale,
+        })
+    }
+}
+
+impl Schema {
+    fn frames(&self, rows: &[Vec<f32>]) -> eyre::Result<Vec<GhostFrame>> {
+        // same as hlkz, frame time is derived from the previous frame
+        let mut prev_time = None;
+
+        rows.iter()
+            .enumerate()
+            .map(|(idx, row)| {
+                if row.len() != self.columns.len() {
+                    return Err(eyre!(
+                        "Schema ghost frame {} has {} values, expecting {}",
+                        idx,
+                        row.len(),
+                        self.columns.len()
+                    ));
+                }
+
+                let mut frame = GhostFrame {
+                    origin: Vec3::ZERO,
+                    viewangles: Vec3::ZERO,
+                    viewoffset_z: 0.,
+                    frametime: None,
+                    buttons: None,
+                    fov: None,
+                    extras: None,
+                };
+
+                self.columns
+                    .iter()
+                    .zip(row)
+                    .for_each(|(column, &value)| match column {
+                        SchemaColumn::Time => {
+                            let time = value * self.time_scale;
+
+                            frame.frametime = Some(time - prev_time.unwrap_or(time));
+                            prev_time = Some(time);
+                        }
+                        SchemaColumn::Frametime => frame.frametime = Some(value * self.time_scale),
+                        SchemaColumn::Origin(i) => frame.origin[*i] = value * self.distance_scale,
+                        SchemaColumn::Angles(i) => frame.viewangles[*i] = value * self.angle_scale,
+                        SchemaColumn::Buttons => frame.buttons = Some(value as u32),
+                        SchemaColumn::Fov => frame.fov = Some(value),
+                        SchemaColumn::ViewOffset => {
+                            frame.viewoffset_z = value * self.distance_scale
+                        }
+                        SchemaColumn::Skip => (),
+                    });
+
+                Ok(frame)
+            })
+            .collect()
+    }
+}
+
+fn schema_csv_parse(file: &str) -> eyre::Result<(SchemaHeader, Vec<Vec<f32>>)> {
+    let mut header = SchemaHeader::default();
+    let mut rows = vec![];
+
+    for (line_idx, line) in file.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(comment) = line.strip_prefix('#') {
+            let Some((key, value)) = comment.split_once(':') else {
+                continue;
+            };
+
+            let value = value.trim().to_string();
+
+            match key.trim() {
+                "map" => header.map = Some(value),
+                "game_mod" => header.game_mod = Some(value),
+                "player_name" => header.player_name = Some(value),
+                "columns" => header.columns = value.split(',').map(str::to_string).collect(),
+                "time_unit" => header.time_unit = Some(value),
+                "angle_unit" => header.angle_unit = Some(value),
+                "distance_unit" => header.distance_unit = Some(value),
+                _ => (),
+            }
+
+            continue;
+        }
+
+        let row = line
+            .split(',')
+            .map(|value| value.trim().parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| eyre!("Cannot parse schema ghost line {}", line_idx + 1))?;
+
+        rows.push(row);
+    }
+
+    Ok((header, rows))
+}
+
+/// Parses CSV or JSON schema ghost, see module docs for the layout.
+pub fn schema_ghost_parse(filename: &str, file: &str) -> eyre::Result<GhostInfo> {
+    let (header, rows) = if file.trim_start().starts_with('{') {
+        let ghost: SchemaJsonGhost = serde_json::from_str(file)?;
+
+        (ghost.header, ghost.frames)
+    } else {
+        schema_csv_parse(file)?
+    };
+
+    let schema = Schema::try_from(&header)?;
+    let frames = schema.frames(&rows)?;
+
+    let map_name = header
+        .map
+        .ok_or_else(|| eyre!("Schema ghost has no map name"))?;
+
+    let mut ghost = GhostInfo::new(
+        filename.to_owned(),
+        map_name,
+        header.game_mod.unwrap_or_else(|| "cstrike".to_string()),
+        frames,
+    );
+
+    ghost.metadata = GhostMetadata {
+        player_name: header.player_name,
+        ..GhostMetadata::from_source(GhostBlobType::Schema)
+    };
+
+    Ok(ghost)
+}
+
+/// Same as [`GhostBlobType::sniff`] but for schema ghost.
+pub fn schema_ghost_sniff(data: &[u8]) -> Option<f32> {
+    let text = sniff_text(data)?;
+
+    if text.trim_start().starts_with('{') {
+        let is_schema = ["header", "columns", "frames"]
+            .iter()
+            .all(|key| text.contains(format!("\"{}\"", key).as_str()));
+
+        return is_schema.then_some(0.9);
+    }
+
+    text.lines()
+        .take_while(|line| line.trim_start().starts_with('#'))
+        .any(|line| {
+            line.trim_start()
+                .trim_start_matches('#')
+                .trim_start()
+                .starts_with("columns:")
+        })
+        .then_some(0.9)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 0.001, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn csv_parse() {
+        let file = "\
+# exported from some timer plugin
+# map: kz_longjumps2
+# player_name: khang
+# columns: time, origin, pitch, yaw, _, buttons
+# time_unit: ms
+# angle_unit: rad
+0, -1024, 512, 36, 0, 1.5707964, 123, 0
+10, -1022, 512, 36, 0, 3.1415927, 123, 2
+30, -1018, 513, 37, 0.5, 0, 123, 6
+";
+
+        let ghost = schema_ghost_parse("kz_longjumps2.ghost.csv", file).unwrap();
+
+        assert_eq!(ghost.map_name, "kz_longjumps2");
+        assert_eq!(ghost.display_name(), "khang");
+        assert_eq!(ghost.metadata.source_format, Some(GhostBlobType::Schema));
+        assert_eq!(ghost.frames.len(), 3);
+
+        assert_eq!(ghost.frames[0].frametime, Some(0.));
+        assert_close(ghost.frames[1].frametime.unwrap(), 0.01);
+        assert_close(ghost.frames[2].frametime.unwrap(), 0.02);
+
+        assert_eq!(ghost.frames[2].origin, Vec3::new(-1018., 513., 37.));
+        assert_close(ghost.frames[0].viewangles[1], 90.);
+        assert_close(ghost.frames[1].viewangles[1], 180.);
+        assert_close(ghost.frames[2].viewangles[0], 28.647888);
+        assert_eq!(ghost.frames[2].buttons, Some(6));
+        assert_eq!(ghost.frames[2].fov, None);
+
+        assert!(schema_ghost_sniff(file.as_bytes()).is_some());
+    }
+
+    #[test]
+    fn json_parse() {
+        let file = r#"{
+    "header": {
+        "map": "surf_ski_2",
+        "columns": ["frametime", "origin", "angles", "fov", "viewoffset"],
+        "distance_unit": "m"
+    },
+    "frames": [
+        [0.01, 1, 2, 3, 10, 20, 0, 90, 0.4572],
+        [0.015, 2, 2, 3, 10, 25, 0, 100, 0.4572]
+    ]
+}"#;
+
+        let ghost = schema_ghost_parse("surf_ski_2.ghost.json", file).unwrap();
+
+        assert_eq!(ghost.map_name, "surf_ski_2");
+        assert_eq!(ghost.game_mod, "cstrike");
+        assert_eq!(ghost.frames.len(), 2);
+
+        assert_eq!(ghost.frames[1].frametime, Some(0.015));
+        assert_close(ghost.frames[0].origin[0], UNITS_PER_METER);
+        assert_close(ghost.frames[1].origin[0], UNITS_PER_METER * 2.);
+        assert_close(ghost.frames[0].viewoffset_z, 18.);
+        assert_eq!(ghost.frames[1].viewangles, Vec3::new(10., 25., 0.));
+        assert_eq!(ghost.frames[1].fov, Some(100.));
+        assert_eq!(ghost.frames[1].buttons, None);
+
+        assert!(schema_ghost_sniff(file.as_bytes()).is_some());
+    }
+
+    #[test]
+    fn bad_schema() {
+        // no time
+        assert!(schema_ghost_parse("a", "# map: a\n# columns: origin\n0,0,0").is_err());
+        // missing origin z
+        assert!(
+            schema_ghost_parse("a", "# map: a\n# columns: time, origin_x, origin_y\n0,0,0")
+                .is_err()
+        );
+        // not enough values
+        assert!(schema_ghost_parse("a", "# map: a\n# columns: time, origin\n0,0,0").is_err());
+        // no map
+        assert!(schema_ghost_parse("a", "# columns: time, origin\n0,0,0,0").is_err());
+        assert!(schema_ghost_parse("a", "# map: a\n# columns: time, origin\n0,0,0,0").is_ok());
+    }
+}
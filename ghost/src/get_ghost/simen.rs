@@ -20,7 +20,7 @@ pub fn simen_ghost_parse(filename: &str, file: &str) -> eyre::Result<GhostInfo>
     // clippy is wrong
     #[allow(clippy::let_and_return)]
     let res = match map(
-        preceded(
+        tuple((
             simen_wrbot_header,
             all_consuming(delimited(
                 opt(multispace0),
@@ -32,20 +32,20 @@ pub fn simen_ghost_parse(filename: &str, file: &str) -> eyre::Result<GhostInfo>
                 ),
                 opt(multispace0),
             )),
-        ),
-        |frames| {
+        )),
+        |(metadata, frames)| {
             let map_name = filename
                 .split(".")
                 .next()
                 .expect("simen ghost file should have dot in it")
                 .to_string();
 
-            GhostInfo {
-                ghost_name: filename.to_owned(),
-                map_name,
-                game_mod: "cstrike".to_string(),
-                frames,
-            }
+            let mut ghost =
+                GhostInfo::new(filename.to_owned(), map_name, "cstrike".to_string(), frames);
+
+            ghost.metadata = metadata;
+
+            ghost
         },
     )(&file)
     {
@@ -57,28 +57,50 @@ pub fn simen_ghost_parse(filename: &str, file: &str) -> eyre::Result<GhostInfo>
     res
 }
 
-fn simen_wrbot_header(i: &str) -> IResult<&str, u8> {
+fn simen_wrbot_header(i: &str) -> IResult<&str, GhostMetadata> {
     map(
         tuple((
-            skip_line, // Time
-            skip_line, // Name
-            skip_line, // SteamID
-            skip_line, // Date
-            skip_line, // Location
-            skip_line, // ??
+            header_line, // Time
+            header_line, // Name
+            header_line, // SteamID
+            header_line, // Date
+            skip_line,   // Location
+            skip_line,   // ??
         )),
-        |_| 0u8,
+        |(time, name, steam_id, date, _, _)| {
+            let non_empty = |s: &str| (!s.is_empty()).then(|| s.to_string());
+
+            GhostMetadata {
+                player_name: non_empty(name),
+                steam_id: non_empty(steam_id),
+                official_time: time.parse().ok(),
+                // writer puts 0 when there is no date
+                record_date: non_empty(date).filter(|date| date != "0"),
+                source_format: Some(GhostBlobType::Simen),
+            }
+        },
     )(i)
 }
 
 fn simen_wrbot_line(i: &str) -> IResult<&str, SimenGhostFrame> {
     map(
         tuple((
-            float, float, float, float, float, float, float, float, u32, float, float,
+            float,
+            float,
+            float,
+            float,
+            float,
+            float,
+            float,
+            float,
+            preceded(space0, u32),
+            float,
+            float,
         )),
         |(pitch, yaw, posx, posy, posz, velx, vely, velz, buttons, move1, move2)| SimenGhostFrame {
             frame: GhostFrame {
-                frametime: Some(0.010000001),
+                // simen ghost doesn't have frametime, it is always 100 fps
+                frametime: Some(DEFAULT_GHOST_FRAMETIME),
                 origin: Vec3::from_array([posx, posy, posz]),
                 viewangles: Vec3::from_array([pitch, yaw, 0.]),
                 viewoffset_z: 0.,
@@ -93,6 +115,65 @@ fn simen_wrbot_line(i: &str) -> IResult<&str, SimenGhostFrame> {
     )(i)
 }
 
+/// Writes [`GhostInfo`] as simen wrbot ghost.
+///
+/// Simen ghost is always 100 fps so frametime is not written.
+/// Velocity is derived from the origin and moves are zero.
+pub fn simen_ghost_write(ghost: &GhostInfo) -> eyre::Result<String> {
+    let mut res = String::new();
+
+    let metadata = &ghost.metadata;
+
+    // header: time, name, steamid, date, location, ??
+    res += &format!(
+        "{}\n",
+        metadata
+            .official_time
+            .unwrap_or_else(|| ghost.get_ghost_length()(DEFAULT_GHOST_FRAMETIME))
+    );
+    res += &format!("{}\n", ghost.display_name());
+    res += &format!(
+        "{}\n",
+        metadata.steam_id.as_deref().unwrap_or("STEAM_0:0:0")
+    );
+    res += &format!("{}\n", metadata.record_date.as_deref().unwrap_or("0"));
+    res += &format!("{}\n", ghost.map_name);
+    res += "0\n";
+
+    ghost.frames.iter().enumerate().for_each(|(idx, frame)| {
+        let velocity = idx
+            .checked_sub(1)
+            .map(|prev_idx| {
+                (frame.origin - ghost.frames[prev_idx].origin)
+                    / frame.frametime.unwrap_or(DEFAULT_GHOST_FRAMETIME)
+            })
+            .filter(|velocity| velocity.is_finite())
+            .unwrap_or(Vec3::ZERO);
+
+        res += &format!(
+            "{} {} {} {} {} {} {} {} {} 0 0\n",
+            frame.viewangles[0],
+            frame.viewangles[1],
+            frame.origin[0],
+            frame.origin[1],
+            frame.origin[2],
+            velocity[0],
+            velocity[1],
+            velocity[2],
+            frame.buttons.unwrap_or(0),
+        );
+    });
+
+    Ok(res)
+}
+
+fn header_line(i: &str) -> IResult<&str, &str> {
+    map(
+        tuple((take_till(|c| c == '\n'), take(1usize))),
+        |(line, _): (&str, _)| line.trim(),
+    )(i)
+}
+
 fn skip_line(i: &str) -> IResult<&str, u8> {
     map(tuple((take_till(|c| c == '\n'), take(1usize))), |_| 0u8)(i)
 }
@@ -106,3 +187,43 @@ fn signed_float(i: &str) -> IResult<&str, f32> {
 pub fn float(i: &str) -> IResult<&str, f32> {
     preceded(space0, signed_float)(i)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn write_parse() {
+        let ghost = GhostInfo::new(
+            "c1a0.simen.txt".to_string(),
+            "c1a0".to_string(),
+            "cstrike".to_string(),
+            sample_frames(100, Some(DEFAULT_GHOST_FRAMETIME)),
+        );
+
+        let file = simen_ghost_write(&ghost).unwrap();
+        let ghost2 = simen_ghost_parse("c1a0.simen.txt", &file).unwrap();
+
+        assert_eq!(ghost2.map_name, "c1a0");
+        assert_eq!(
+            ghost2.metadata.player_name.as_deref(),
+            Some("c1a0.simen.txt")
+        );
+        assert_eq!(ghost2.metadata.steam_id.as_deref(), Some("STEAM_0:0:0"));
+        assert_eq!(ghost2.metadata.record_date, None);
+        assert!(ghost2.metadata.official_time.is_some());
+        assert_eq!(ghost.frames.len(), ghost2.frames.len());
+
+        ghost
+            .frames
+            .iter()
+            .zip(ghost2.frames.iter())
+            .for_each(|(a, b)| {
+                assert_eq!(a.origin, b.origin);
+                // simen doesn't have roll
+                assert_eq!(a.viewangles.truncate(), b.viewangles.truncate());
+                assert_eq!(a.buttons, b.buttons);
+                assert_eq!(a.frametime, b.frametime);
+            });
+    }
+}
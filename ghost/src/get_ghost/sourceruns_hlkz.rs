@@ -1,3 +1,4 @@
+use byte_writer::ByteWriter;
 use eyre::eyre;
 use nom::{
     IResult as _IResult,
@@ -46,6 +47,35 @@ fn srhlkz_ghost_frame_parse(i: &[u8]) -> IResult<SRHLKZGhostFrame> {
     )(i)
 }
 
+/// Writes [`GhostInfo`] as SourceRuns HLKZ replay data.
+///
+/// The format stores accumulated time so the first frametime is lost.
+/// Ghost without frametime will use [`DEFAULT_GHOST_FRAMETIME`].
+///
+/// The map name is in the file name, eg `<map name>_0_0_<whatever>.dat`.
+pub fn srhlkz_ghost_write(ghost: &GhostInfo) -> eyre::Result<Vec<u8>> {
+    let mut writer = ByteWriter::new();
+    let mut time = 0f32;
+
+    ghost.frames.iter().enumerate().for_each(|(idx, frame)| {
+        // first frame starts at 0
+        if idx != 0 {
+            time += frame.frametime.unwrap_or(DEFAULT_GHOST_FRAMETIME);
+        }
+
+        writer.append_f32(time);
+        frame
+            .origin
+            .to_array()
+            .into_iter()
+            .chain(frame.viewangles.to_array())
+            .for_each(|x| writer.append_f32(x));
+        writer.append_u16(frame.buttons.unwrap_or(0) as u16);
+    });
+
+    Ok(writer.data)
+}
+
 pub fn srhlkz_ghost_parse(file_name: &str, file: &[u8]) -> eyre::Result<GhostInfo> {
     let (_, frames) = all_consuming(many0(srhlkz_ghost_frame_parse))(file)
         .map_err(|_| eyre!("Cannot parse SourceRuns HLKZ replay data"))?;
@@ -64,27 +94,70 @@ pub fn srhlkz_ghost_parse(file_name: &str, file: &[u8]) -> eyre::Result<GhostInf
     // so we start with this
     let mut prev_time = frames[0].time;
 
-    Ok(GhostInfo {
-        ghost_name: file_name.to_string(),
-        map_name: map_name.to_string(),
-        game_mod: "ag".into(),
-        frames: frames
-            .into_iter()
-            .map(|frame| {
-                let res = GhostFrame {
-                    origin: frame.origin.into(),
-                    viewangles: frame.angles.into(),
-                    viewoffset_z: 0.,
-                    frametime: (frame.time - prev_time).into(),
-                    buttons: (frame.buttons as u32).into(),
-                    fov: None,
-                    extras: None,
-                };
-
-                prev_time = frame.time;
-
-                res
-            })
-            .collect(),
-    })
+    let frames = frames
+        .into_iter()
+        .map(|frame| {
+            let res = GhostFrame {
+                origin: frame.origin.into(),
+                viewangles: frame.angles.into(),
+                viewoffset_z: 0.,
+                frametime: (frame.time - prev_time).into(),
+                buttons: (frame.buttons as u32).into(),
+                fov: None,
+                extras: None,
+            };
+
+            prev_time = frame.time;
+
+            res
+        })
+        .collect();
+
+    let mut ghost = GhostInfo::new(
+        file_name.to_string(),
+        map_name.to_string(),
+        "ag".into(),
+        frames,
+    );
+
+    ghost.metadata = GhostMetadata::from_source(GhostBlobType::SRHLKZ);
+
+    Ok(ghost)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn write_parse() {
+        let mut frames = sample_frames(100, Some(0.001));
+        // first frametime is always 0 when parsed
+        frames[0].frametime = Some(0.);
+
+        let ghost = GhostInfo::new(
+            "hl1_bhop_oc".to_string(),
+            "hl1_bhop_oc".to_string(),
+            "ag".to_string(),
+            frames,
+        );
+
+        let file = srhlkz_ghost_write(&ghost).unwrap();
+        let ghost2 = srhlkz_ghost_parse("hl1_bhop_oc_0_0_1.dat", &file).unwrap();
+
+        assert_eq!(ghost2.map_name, "hl1_bhop_oc");
+        assert_eq!(ghost.frames.len(), ghost2.frames.len());
+
+        ghost
+            .frames
+            .iter()
+            .zip(ghost2.frames.iter())
+            .for_each(|(a, b)| {
+                assert_eq!(a.origin, b.origin);
+                assert_eq!(a.viewangles, b.viewangles);
+                assert_eq!(a.buttons, b.buttons);
+                // accumulated time loses some precision
+                assert!((a.frametime.unwrap() - b.frametime.unwrap()).abs() < 0.0001);
+            });
+    }
 }
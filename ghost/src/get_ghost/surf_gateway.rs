@@ -28,22 +28,116 @@ pub fn surf_gateway_ghost_parse(filename: &str, file: &str) -> eyre::Result<Ghos
     let surf_gateway_ghost: SgGhostInfo = serde_json::from_str(&file)?;
 
     // Convert surf_gateway_ghost to our normal ghost.
-    Ok(GhostInfo {
-        ghost_name: filename.to_owned(),
-        map_name: "NoMapName".to_string(),
-        game_mod: "cstrike".to_string(),
-        frames: surf_gateway_ghost
+    let frames = surf_gateway_ghost
+        .frames
+        .iter()
+        .map(|ghost| GhostFrame {
+            frametime: None,
+            origin: Vec3::from_array(ghost.origin),
+            viewangles: Vec3::from_array(ghost.viewangles),
+            viewoffset_z: 0.,
+            buttons: ghost.buttons.into(),
+            fov: None,
+            extras: None,
+        })
+        .collect();
+
+    let non_empty = |s: String| (!s.is_empty()).then_some(s);
+
+    let mut ghost = GhostInfo::new(
+        filename.to_owned(),
+        non_empty(surf_gateway_ghost.map).unwrap_or_else(|| "NoMapName".to_string()),
+        "cstrike".to_string(),
+        frames,
+    );
+
+    ghost.metadata = GhostMetadata {
+        player_name: non_empty(surf_gateway_ghost.name),
+        steam_id: non_empty(surf_gateway_ghost.authid),
+        official_time: Some(surf_gateway_ghost.time),
+        record_date: (surf_gateway_ghost.timestamp != 0)
+            .then(|| unix_timestamp_to_date(surf_gateway_ghost.timestamp as u64)),
+        source_format: Some(GhostBlobType::SurfGateway),
+    };
+
+    Ok(ghost)
+}
+
+/// Writes [`GhostInfo`] as surf gateway JSON ghost.
+///
+/// Surf gateway frametime is not a time so it is written as milliseconds, like usercmd msec.
+pub fn surf_gateway_ghost_write(ghost: &GhostInfo) -> eyre::Result<String> {
+    let frames = ghost
+        .frames
+        .iter()
+        .map(|frame| SgGhostFrame {
+            origin: frame.origin.to_array(),
+            viewangles: frame.viewangles.to_array(),
+            moves: [0.; 3],
+            buttons: frame.buttons.unwrap_or(0),
+            impulses: 0,
+            frametime: frame
+                .frametime
+                .map(|frametime| (frametime * 1000.).round() as u32)
+                .unwrap_or(0),
+        })
+        .collect();
+
+    let surf_gateway_ghost = SgGhostInfo {
+        map: ghost.map_name.to_owned(),
+        // date string cannot go back to timestamp
+        timestamp: 0,
+        name: ghost.display_name().to_owned(),
+        authid: ghost.metadata.steam_id.clone().unwrap_or_default(),
+        time: ghost
+            .metadata
+            .official_time
+            .unwrap_or_else(|| ghost.get_ghost_length()(0.)),
+        startvel: [0.; 3],
+        frames,
+    };
+
+    Ok(serde_json::to_string(&surf_gateway_ghost)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn write_parse() {
+        let ghost = GhostInfo::new(
+            "surf_ski_2".to_string(),
+            "surf_ski_2".to_string(),
+            "cstrike".to_string(),
+            sample_frames(100, None),
+        );
+
+        let file = surf_gateway_ghost_write(&ghost).unwrap();
+        let ghost2 = surf_gateway_ghost_parse("surf_ski_2.sg.json", &file).unwrap();
+
+        assert_eq!(ghost.frames.len(), ghost2.frames.len());
+        assert_eq!(ghost2.map_name, "surf_ski_2");
+        assert_eq!(ghost2.metadata.player_name.as_deref(), Some("surf_ski_2"));
+        assert_eq!(ghost2.metadata.steam_id, None);
+        assert_eq!(ghost2.metadata.record_date, None);
+
+        ghost
             .frames
             .iter()
-            .map(|ghost| GhostFrame {
-                frametime: None,
-                origin: Vec3::from_array(ghost.origin),
-                viewangles: Vec3::from_array(ghost.viewangles),
-                viewoffset_z: 0.,
-                buttons: ghost.buttons.into(),
-                fov: None,
-                extras: None,
-            })
-            .collect(),
-    })
+            .zip(ghost2.frames.iter())
+            .for_each(|(a, b)| {
+                assert_eq!(a.origin, b.origin);
+                assert_eq!(a.viewangles, b.viewangles);
+                assert_eq!(a.buttons, b.buttons);
+                assert_eq!(b.frametime, None);
+            });
+    }
+
+    #[test]
+    fn timestamp_date() {
+        assert_eq!(unix_timestamp_to_date(0), "1970-01-01");
+        assert_eq!(unix_timestamp_to_date(951782400), "2000-02-29");
+        assert_eq!(unix_timestamp_to_date(1700000000), "2023-11-14");
+    }
 }
@@ -0,0 +1,595 @@
+//! KZ jumpstats from a ghost and its map.
+//!
+//! Ground contact is traced against the map and its solid brush entities with the player hull, same as `PM_CategorizePosition`,
+//! instead of guessing it from the trajectory like [`crate::analysis`].
+use bsp::{Bsp, Entity, HullType, LeafContent};
+use glam::{Vec3, Vec3Swizzles};
+
+use crate::{
+    GhostInfo,
+    analysis::{FrameStats, strafe_sync},
+};
+
+// same as pm_shared.c
+const GROUND_TRACE_DISTANCE: f32 = 2.;
+const GROUND_MIN_NORMAL_Z: f32 = 0.7;
+const GROUND_MAX_UP_SPEED: f32 = 180.;
+
+// half size and bottom of the hulls
+const STAND_HULL_MIN_Z: f32 = -36.;
+const DUCK_HULL_MIN_Z: f32 = -18.;
+const HULL_HALF_WIDTH: f32 = 16.;
+
+// leaving the ground slower than this is falling or ducktap
+const JUMP_MIN_UP_SPEED: f32 = 100.;
+// ground time before a jump that still counts as bhop
+const BHOP_MAX_GROUND_TIME: f32 = 0.05;
+// ducktap only goes up 18 units
+const DUCKTAP_MAX_HEIGHT: f32 = 24.;
+// ducktap steps up by the hull offset in one frame and then falls, jumping keeps going up
+const DUCKTAP_RISE: f32 = DUCK_HULL_MIN_Z - STAND_HULL_MIN_Z;
+const DUCKTAP_RISE_EPSILON: f32 = 4.;
+// ground is found before the feet touch it
+const SAME_HEIGHT_EPSILON: f32 = GROUND_TRACE_DISTANCE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JumpKind {
+    LongJump,
+    Bhop,
+    CountJump,
+    LadderJump,
+}
+
+#[derive(Debug, Clone)]
+pub struct JumpStat {
+    pub kind: JumpKind,
+    /// Last frame on the ground or ladder.
+    pub start_frame: usize,
+    /// First frame back on the ground or ladder.
+    pub end_frame: usize,
+    /// Horizontal distance between takeoff and landing at takeoff height, plus player width.
+    ///
+    /// Ladder jumps don't add the player width.
+    pub distance: f32,
+    /// Horizontal speed at takeoff.
+    pub prestrafe: f32,
+    /// Horizontal speed in the air.
+    pub max_speed: f32,
+    pub strafes: usize,
+    /// Same as [`crate::RunStats::sync`].
+    pub sync: Option<f32>,
+    /// Gap between the takeoff block and the landing block.
+    ///
+    /// `None` when landing on a different height or there is no gap under the jump.
+    pub block: Option<f32>,
+    /// Landing height minus takeoff height, from the feet.
+    pub height_difference: f32,
+    pub air_time: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Contact {
+    Ground,
+    Ladder,
+    Air,
+}
+
+fn to_bsp_vec(v: Vec3) -> bsp::Vec3 {
+    bsp::Vec3::from_array(v.to_array())
+}
+
+fn from_bsp_vec(v: bsp::Vec3) -> Vec3 {
+    Vec3::from_array(v.to_array())
+}
+
+fn hull_min_z(ducking: bool) -> f32 {
+    if ducking {
+        DUCK_HULL_MIN_Z
+    } else {
+        STAND_HULL_MIN_Z
+    }
+}
+
+/// Player bounding box at `origin`.
+pub(crate) fn player_box(origin: Vec3, ducking: bool) -> (Vec3, Vec3) {
+    let min_z = hull_min_z(ducking);
+
+    (
+        origin + Vec3::new(-HULL_HALF_WIDTH, -HULL_HALF_WIDTH, min_z),
+        origin + Vec3::new(HULL_HALF_WIDTH, HULL_HALF_WIDTH, -min_z),
+    )
+}
+
+/// Bounding boxes of brush entities that `filter` accepts.
+pub(crate) fn brush_entity_boxes(bsp: &Bsp, filter: impl Fn(&Entity) -> bool) -> Vec<(Vec3, Vec3)> {
+    bsp.brush_entities(filter)
+        .into_iter()
+        .filter_map(|(model_index, origin)| {
+            let model = bsp.models.get(model_index)?;
+
+            // model bounds are relative to the entity origin
+            Some((
+                from_bsp_vec(model.mins + origin),
+                from_bsp_vec(model.maxs + origin),
+            ))
+        })
+        .collect()
+}
+
+/// Bounding boxes of `func_ladder`.
+fn ladder_boxes(bsp: &Bsp) -> Vec<(Vec3, Vec3)> {
+    brush_entity_boxes(bsp, |entity| {
+        entity
+            .get("classname")
+            .is_some_and(|classname| classname == "func_ladder")
+    })
+}
+
+fn is_on_ground(
+    bsp: &Bsp,
+    solid_entities: &[(usize, bsp::Vec3)],
+    origin: Vec3,
+    frame_stat: &FrameStats,
+) -> bool {
+    if frame_stat.vertical_speed > GROUND_MAX_UP_SPEED {
+        return false;
+    }
+
+    let end = origin - Vec3::Z * GROUND_TRACE_DISTANCE;
+
+    let (hull, other_hull) = if frame_stat.ducking {
+        (HullType::Duck, HullType::Stand)
+    } else {
+        (HullType::Stand, HullType::Duck)
+    };
+
+    // duck state is only a guess without buttons
+    [hull, other_hull]
+        .into_iter()
+        .map(|hull| {
+            bsp.trace_world_and_models(solid_entities, hull, to_bsp_vec(origin), to_bsp_vec(end))
+        })
+        .find(|tr| !tr.start_solid)
+        .is_some_and(|tr| tr.fraction < 1. && tr.plane.normal.z >= GROUND_MIN_NORMAL_Z)
+}
+
+fn is_on_ladder(ladders: &[(Vec3, Vec3)], origin: Vec3, ducking: bool) -> bool {
+    let (player_min, player_max) = player_box(origin, ducking);
+    let (player_min, player_max) = (player_min - 1., player_max + 1.);
+
+    ladders
+        .iter()
+        .any(|(mins, maxs)| player_min.cmple(*maxs).all() && player_max.cmpge(*mins).all())
+}
+
+/// Gap between the blocks under the jump at `height`, measured along the jump.
+fn block_distance(
+    bsp: &Bsp,
+    solid_entities: &[(usize, bsp::Vec3)],
+    takeoff: Vec3,
+    landing: Vec3,
+    height: f32,
+) -> Option<f32> {
+    // just inside the top of the blocks
+    let takeoff = takeoff.xy().extend(height - 1.);
+    let landing = landing.xy().extend(height - 1.);
+    let middle = (takeoff + landing) / 2.;
+
+    if matches!(
+        bsp.trace_point(0, to_bsp_vec(middle)),
+        LeafContent::ContentsSolid
+    ) {
+        return None;
+    }
+
+    let edge = |to: Vec3| {
+        bsp.trace_world_and_models(
+            solid_entities,
+            HullType::Point,
+            to_bsp_vec(middle),
+            to_bsp_vec(to),
+        )
+    };
+
+    let takeoff_edge = edge(takeoff);
+    let landing_edge = edge(landing);
+
+    if takeoff_edge.fraction >= 1. || landing_edge.fraction >= 1. {
+        return None;
+    }
+
+    let block = from_bsp_vec(takeoff_edge.end_pos)
+        .xy()
+        .distance(from_bsp_vec(landing_edge.end_pos).xy());
+
+    // traces stop a bit before the wall
+    Some(block.round())
+}
+
+/// Finds jumps in the ghost and measures them like KZ plugins.
+///
+/// Every frame is traced against `bsp`, so only pass a ghost segment that is on this map.
+pub fn jumpstats(ghost: &GhostInfo, bsp: &Bsp) -> Vec<JumpStat> {
+    let analysis = ghost.analyze();
+    let frames = ghost.frames();
+    let frame_stats = &analysis.frames;
+    let ladders = ladder_boxes(bsp);
+    // func_wall blocks are everywhere on KZ maps
+    let solid_entities = bsp.solid_brush_entities();
+
+    let contacts: Vec<Contact> = frames
+        .iter()
+        .zip(frame_stats.iter())
+        .map(|(frame, frame_stat)| {
+            if is_on_ground(bsp, &solid_entities, frame.origin, frame_stat) {
+                Contact::Ground
+            } else if is_on_ladder(&ladders, frame.origin, frame_stat.ducking) {
+                Contact::Ladder
+            } else {
+                Contact::Air
+            }
+        })
+        .collect();
+
+    let feet_z = |idx: usize| frames[idx].origin.z + hull_min_z(frame_stats[idx].ducking);
+
+    let mut jumps = vec![];
+    // start of the previous air phase and whether it was a ducktap
+    let mut prev_air_was_ducktap = false;
+    let mut air_start = None;
+
+    for idx in 1..frames.len() {
+        if contacts[idx] != Contact::Air {
+            if let Some(start) = air_start.take() {
+                let height_gain = (start..idx)
+                    .map(|i| frames[i].origin.z - frames[start - 1].origin.z)
+                    .fold(0f32, f32::max);
+
+                prev_air_was_ducktap =
+                    height_gain < DUCKTAP_MAX_HEIGHT && frame_stats[start].vertical_speed > 0.;
+            }
+
+            continue;
+        }
+
+        if contacts[idx - 1] == Contact::Air {
+            continue;
+        }
+
+        air_start = Some(idx);
+
+        let takeoff = idx - 1;
+        let is_ladder = contacts[takeoff] == Contact::Ladder;
+
+        if !is_ladder && frame_stats[idx].vertical_speed < JUMP_MIN_UP_SPEED {
+            continue;
+        }
+
+        // ducktap is only counted for the next jump
+        // a jump at a low frame rate can rise as much in its first frame, but it is still going up after that
+        let rise = frames[idx].origin.z - frames[takeoff].origin.z;
+        let next_up_speed = frame_stats
+            .get(idx + 1)
+            .map_or(0., |frame_stat| frame_stat.vertical_speed);
+
+        if !is_ladder
+            && (rise - DUCKTAP_RISE).abs() < DUCKTAP_RISE_EPSILON
+            && next_up_speed < JUMP_MIN_UP_SPEED
+        {
+            continue;
+        }
+
+        // ends in the air
+        let Some(landing) = (idx..frames.len()).find(|&i| contacts[i] != Contact::Air) else {
+            break;
+        };
+
+        let kind = if is_ladder {
+            JumpKind::LadderJump
+        } else {
+            // ground time before this jump
+            let ground_start = (0..takeoff)
+                .rev()
+                .find(|&i| contacts[i] != Contact::Ground)
+                .map(|i| i + 1);
+
+            let ground_time = ground_start
+                .map(|ground_start| frame_stats[takeoff].time - frame_stats[ground_start].time);
+
+            match ground_time {
+                Some(time) if time <= BHOP_MAX_GROUND_TIME && prev_air_was_ducktap => {
+                    JumpKind::CountJump
+                }
+                Some(time) if time <= BHOP_MAX_GROUND_TIME => JumpKind::Bhop,
+                _ => JumpKind::LongJump,
+            }
+        };
+
+        let takeoff_height = feet_z(takeoff);
+        let takeoff_origin = frames[takeoff].origin;
+
+        // where the feet go back down to takeoff height
+        let landing_origin = (idx + 1..=landing)
+            .find(|&i| feet_z(i) <= takeoff_height && feet_z(i - 1) > takeoff_height)
+            .map(|i| {
+                let (from, to) = (feet_z(i - 1), feet_z(i));
+                let t = (from - takeoff_height) / (from - to);
+
+                frames[i - 1].origin.lerp(frames[i].origin, t)
+            })
+            .unwrap_or(frames[landing].origin);
+
+        let distance = takeoff_origin.xy().distance(landing_origin.xy())
+            + if is_ladder { 0. } else { HULL_HALF_WIDTH * 2. };
+
+        let height_difference = feet_z(landing) - takeoff_height;
+
+        let block = if !is_ladder && height_difference.abs() < SAME_HEIGHT_EPSILON {
+            block_distance(
+                bsp,
+                &solid_entities,
+                takeoff_origin,
+                landing_origin,
+                takeoff_height,
+            )
+        } else {
+            None
+        };
+
+        // takeoff frame for the strafe key held from the ground
+        let (strafes, sync) = strafe_sync(
+            frames[takeoff..landing]
+                .iter()
+                .zip(&frame_stats[takeoff..landing])
+                .enumerate()
+                .map(|(i, (frame, frame_stat))| (frame, frame_stat, i != 0)),
+        );
+
+        jumps.push(JumpStat {
+            kind,
+            start_frame: takeoff,
+            end_frame: landing,
+            distance,
+            prestrafe: frame_stats[idx].horizontal_speed,
+            max_speed: frame_stats[idx..landing]
+                .iter()
+                .map(|frame_stat| frame_stat.horizontal_speed)
+                .fold(0., f32::max),
+            strafes,
+            sync,
+            block,
+            height_difference,
+            air_time: frame_stats[landing].time - frame_stats[takeoff].time,
+        });
+    }
+
+    jumps
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        GhostFrame,
+        get_ghost::{sample_frame, sample_ghost},
+    };
+
+    const FRAMETIME: f32 = 0.01;
+    // standing on the floor of the test map
+    const FLOOR_ORIGIN_Z: f32 = -67.96875;
+
+    fn datacore() -> Bsp {
+        Bsp::from_bytes(include_bytes!("../../bsp/src/tests/datacore.bsp")).unwrap()
+    }
+
+    /// Moves with gravity until the origin is back down to `floor_z`, which is not pushed.
+    fn fly(frames: &mut Vec<GhostFrame>, origin: &mut Vec3, velocity: Vec3, floor_z: f32) {
+        fly_at(frames, origin, velocity, floor_z, FRAMETIME);
+    }
+
+    fn fly_at(
+        frames: &mut Vec<GhostFrame>,
+        origin: &mut Vec3,
+        mut velocity: Vec3,
+        floor_z: f32,
+        frametime: f32,
+    ) {
+        loop {
+            *origin += velocity * frametime;
+            velocity.z -= 800. * frametime;
+
+            if origin.z <= floor_z {
+                origin.z = floor_z;
+                return;
+            }
+
+            frames.push(sample_frame(*origin, 0., Some(frametime), Some(0)));
+        }
+    }
+
+    #[test]
+    fn bhop_and_countjump() {
+        let bsp = datacore();
+        // open area near the spawn
+        let floor_z = 816.03125;
+        let jump = Vec3::new(250., 0., 268.);
+
+        let mut frames = vec![];
+        let mut origin = Vec3::new(-3690., 1576., floor_z);
+
+        (0..10).for_each(|_| frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0))));
+
+        fly(&mut frames, &mut origin, jump, floor_z);
+        frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0)));
+        fly(&mut frames, &mut origin, jump, floor_z);
+
+        (0..10).for_each(|_| frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0))));
+
+        // ducktap moves up without jumping
+        origin += Vec3::new(2.5, 0., 18.);
+        frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0)));
+        fly(&mut frames, &mut origin, Vec3::X * 250., floor_z);
+        frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0)));
+        fly(&mut frames, &mut origin, jump, floor_z);
+
+        (0..10).for_each(|_| frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0))));
+
+        let jumps = jumpstats(&sample_ghost(frames), &bsp);
+        let kinds: Vec<JumpKind> = jumps.iter().map(|jump| jump.kind).collect();
+
+        assert_eq!(
+            kinds,
+            [JumpKind::LongJump, JumpKind::Bhop, JumpKind::CountJump]
+        );
+
+        jumps.iter().for_each(|jump| {
+            assert!((jump.prestrafe - 250.).abs() < 0.01);
+            assert!(jump.height_difference.abs() < SAME_HEIGHT_EPSILON);
+            assert!(jump.block.is_none());
+            assert!((jump.distance - 202.).abs() < 2.5);
+        });
+    }
+
+    #[test]
+    fn low_rate_bhop_and_countjump() {
+        // 20 Hz, a jump rises about as much as a ducktap in its first frame
+        const FRAMETIME: f32 = 0.05;
+
+        let bsp = datacore();
+        let floor_z = 816.03125;
+        let jump = Vec3::new(250., 0., 268.);
+
+        let mut frames = vec![];
+        let mut origin = Vec3::new(-3690., 1576., floor_z);
+
+        (0..4).for_each(|_| frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0))));
+
+        fly_at(&mut frames, &mut origin, jump, floor_z, FRAMETIME);
+        frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0)));
+        fly_at(&mut frames, &mut origin, jump, floor_z, FRAMETIME);
+
+        (0..4).for_each(|_| frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0))));
+
+        origin += Vec3::new(12.5, 0., 18.);
+        frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0)));
+        fly_at(&mut frames, &mut origin, Vec3::X * 250., floor_z, FRAMETIME);
+        frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0)));
+        fly_at(&mut frames, &mut origin, jump, floor_z, FRAMETIME);
+
+        (0..4).for_each(|_| frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0))));
+
+        let jumps = jumpstats(&sample_ghost(frames), &bsp);
+        let kinds: Vec<JumpKind> = jumps.iter().map(|jump| jump.kind).collect();
+
+        assert_eq!(
+            kinds,
+            [JumpKind::LongJump, JumpKind::Bhop, JumpKind::CountJump]
+        );
+    }
+
+    #[test]
+    fn ladder_jump() {
+        let bsp = datacore();
+        // floor in front of the ladder at x -1860, wall behind at x -1980
+        let floor_z = 924.03125;
+
+        let mut frames = vec![];
+        let mut origin = Vec3::new(-1876., 3204., 960.);
+
+        // climbing
+        (0..20).for_each(|_| {
+            origin.z += 2.;
+            frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0)));
+        });
+
+        let takeoff_x = origin.x;
+
+        fly(&mut frames, &mut origin, Vec3::new(-80., 0., 270.), floor_z);
+
+        (0..10).for_each(|_| frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0))));
+
+        let jumps = jumpstats(&sample_ghost(frames), &bsp);
+
+        assert_eq!(jumps.len(), 1);
+
+        let jump = &jumps[0];
+
+        assert_eq!(jump.kind, JumpKind::LadderJump);
+        assert!(jump.height_difference < -SAME_HEIGHT_EPSILON);
+        assert!(jump.block.is_none());
+        // no player width and measured before going down to the floor
+        assert!(jump.distance > 0.);
+        assert!(jump.distance < takeoff_x - origin.x);
+    }
+
+    #[test]
+    fn block_jump() {
+        let bsp = datacore();
+        // over a 64 units wide pit from x -2088 to -2024
+        let floor_z = 1068. + 0.03125;
+
+        let mut frames = vec![];
+        let mut origin = Vec3::new(-2120., 2452., floor_z);
+
+        (0..10).for_each(|_| frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0))));
+
+        fly(&mut frames, &mut origin, Vec3::new(250., 0., 268.), floor_z);
+
+        (0..10).for_each(|_| frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0))));
+
+        let jumps = jumpstats(&sample_ghost(frames), &bsp);
+
+        assert_eq!(jumps.len(), 1);
+        assert_eq!(jumps[0].kind, JumpKind::LongJump);
+        assert_eq!(jumps[0].block, Some(64.));
+        assert!(jumps[0].distance > 64. + 32.);
+    }
+
+    #[test]
+    fn small_room_jump() {
+        let bsp = Bsp::from_bytes(include_bytes!("../../bsp/src/tests/normal.bsp")).unwrap();
+
+        let mut frames = vec![];
+        let mut origin = Vec3::new(-40., 0., FLOOR_ORIGIN_Z);
+
+        (0..10).for_each(|_| frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0))));
+
+        // low jump because of the ceiling
+        let mut vz = 200f32;
+
+        while frames.len() < 200 {
+            origin.x += 120. * FRAMETIME;
+            origin.z += vz * FRAMETIME;
+            vz -= 800. * FRAMETIME;
+
+            if origin.z <= FLOOR_ORIGIN_Z {
+                origin.z = FLOOR_ORIGIN_Z;
+                break;
+            }
+
+            frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0)));
+        }
+
+        let landing_x = origin.x;
+
+        (0..10).for_each(|_| frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(0))));
+
+        let ghost = sample_ghost(frames);
+
+        let jumps = jumpstats(&ghost, &bsp);
+
+        assert_eq!(jumps.len(), 1);
+
+        let jump = &jumps[0];
+
+        assert_eq!(jump.kind, JumpKind::LongJump);
+        assert_eq!(jump.start_frame, 9);
+        assert!((jump.prestrafe - 120.).abs() < 0.01);
+        assert!(jump.height_difference.abs() < SAME_HEIGHT_EPSILON);
+        // flat floor
+        assert!(jump.block.is_none());
+        // ground can be found a frame before touching it
+        assert!(jump.distance <= landing_x + 40. + 32. + 0.01);
+        assert!(jump.distance > landing_x + 40. + 32. - 2. * 120. * FRAMETIME);
+    }
+}
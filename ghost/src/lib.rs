@@ -1,17 +1,39 @@
 use std::path::Path;
 
+pub use analysis::{FrameStats, MoveEvent, RunAnalysis, RunStats, StrafeDirection, analyze_frames};
+pub use checker::{Finding, FindingKind, Severity, check_ghost};
+pub use compare::{RouteComparison, compare_routes};
 pub use error::GhostError;
+pub use jumpstats::{JumpKind, JumpStat, jumpstats};
+pub use pmove::{
+    FORWARD_SPEED, MoveVars, PlayerMove, PlayerState, SIDE_SPEED, UP_SPEED, UserCmd, VEC_DUCK_VIEW,
+    VEC_VIEW, simulate_ghost,
+};
+pub use timer::{RunTimer, run_timer};
 
+mod analysis;
+mod checker;
+mod compare;
 mod error;
 mod get_ghost;
+mod jumpstats;
+mod parser;
+mod pmove;
+mod sniff;
+mod timer;
 
 pub use get_ghost::*;
 use get_ghost::{
-    demo::demo_ghost_parse, romanian_jumpers::romanian_jumpers_ghost_parse,
-    simen::simen_ghost_parse, sourceruns_hlkz::srhlkz_ghost_parse,
-    surf_gateway::surf_gateway_ghost_parse,
+    demo::{demo_ghost_parse, demo_multi_ghost_parse},
+    kdr::{kdr_ghost_parse, kdr_ghost_write},
+    romanian_jumpers::{romanian_jumpers_ghost_parse, romanian_jumpers_ghost_write},
+    simen::{simen_ghost_parse, simen_ghost_write},
+    sourceruns_hlkz::{srhlkz_ghost_parse, srhlkz_ghost_write},
+    surf_gateway::{surf_gateway_ghost_parse, surf_gateway_ghost_write},
 };
+pub use parser::{BuiltinGhostParser, GhostParser, GhostParserRegistry, SchemaGhostParser};
 use serde::{Deserialize, Serialize};
+pub use sniff::SNIFF_MIN_CONFIDENCE;
 
 // when data sent over the net, we just need to know the variant and then parse it from the client side
 #[derive(Debug, Serialize, Deserialize)]
@@ -21,17 +43,75 @@ pub enum GhostBlob {
     SurfGateway(String),
     RomanianJumpers(String),
     SRHLKZ(Vec<u8>),
+    /// Already parsed ghost in kdr format. Smaller than demo and keeps everything.
+    Kdr(Vec<u8>),
     Unknown,
 }
 
-// for forcing ghost type regardless of the file format
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl GhostBlob {
+    /// Wraps file content as the given type without checking the content.
+    pub fn from_bytes(
+        blob_type: GhostBlobType,
+        file_name: &str,
+        data: Vec<u8>,
+    ) -> Result<Self, GhostError> {
+        match blob_type {
+            GhostBlobType::Demo => Ok(Self::Demo(data)),
+            GhostBlobType::Simen | GhostBlobType::SurfGateway | GhostBlobType::RomanianJumpers => {
+                let s_data = str::from_utf8(&data).map_err(|op| GhostError::Utf8Error {
+                    source: op.into(),
+                    path: file_name.into(),
+                })?;
+
+                let s_data = s_data.to_string();
+
+                match blob_type {
+                    GhostBlobType::Demo
+                    | GhostBlobType::SRHLKZ
+                    | GhostBlobType::Kdr
+                    | GhostBlobType::Schema => {
+                        unreachable!()
+                    }
+                    GhostBlobType::Simen => Ok(Self::Simen(s_data)),
+                    GhostBlobType::SurfGateway => Ok(Self::SurfGateway(s_data)),
+                    GhostBlobType::RomanianJumpers => Ok(Self::RomanianJumpers(s_data)),
+                }
+            }
+            GhostBlobType::SRHLKZ => Ok(Self::SRHLKZ(data)),
+            GhostBlobType::Kdr => Ok(Self::Kdr(data)),
+            // only registered parsers read it
+            GhostBlobType::Schema => Err(GhostError::UnknownFormat {
+                path: file_name.into(),
+            }),
+        }
+    }
+
+    /// Returns the file content of the blob.
+    pub fn into_bytes(self) -> Option<Vec<u8>> {
+        match self {
+            GhostBlob::Demo(items) | GhostBlob::SRHLKZ(items) | GhostBlob::Kdr(items) => {
+                Some(items)
+            }
+            GhostBlob::Simen(s) | GhostBlob::SurfGateway(s) | GhostBlob::RomanianJumpers(s) => {
+                Some(s.into_bytes())
+            }
+            GhostBlob::Unknown => None,
+        }
+    }
+}
+
+// for forcing ghost type regardless of the file name
+// content sniffing still comes first
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GhostBlobType {
     Demo,
     Simen,
     SurfGateway,
     RomanianJumpers,
     SRHLKZ,
+    Kdr,
+    /// Only a [`GhostMetadata::source_format`]. There is no schema blob, it is sent as kdr ghost.
+    Schema,
 }
 
 const DEMO_CODE: &str = "demo";
@@ -39,21 +119,28 @@ const SIMEN_CODE: &str = "simen";
 const SURF_GATEWAY_CODE: &str = "surf_gateway";
 const ROMANIAN_JUMPERS_CODE: &str = "romanian_jumpers";
 const SOURCERUNS_HLKZ_CODE: &str = "hlkz";
+const KDR_CODE: &str = "kdr";
+const SCHEMA_CODE: &str = "schema";
 
 impl TryFrom<&str> for GhostBlobType {
     type Error = &'static str;
 
+    /// Takes either the code or the variant name, which is how the type is written in config files.
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        if value == SIMEN_CODE {
+        if value == SIMEN_CODE || value == "Simen" {
             return Ok(GhostBlobType::Simen);
-        } else if value == SURF_GATEWAY_CODE {
+        } else if value == SURF_GATEWAY_CODE || value == "SurfGateway" {
             return Ok(GhostBlobType::SurfGateway);
-        } else if value == ROMANIAN_JUMPERS_CODE {
+        } else if value == ROMANIAN_JUMPERS_CODE || value == "RomanianJumpers" {
             return Ok(GhostBlobType::RomanianJumpers);
-        } else if value == DEMO_CODE {
+        } else if value == DEMO_CODE || value == "Demo" {
             return Ok(GhostBlobType::Demo);
-        } else if value == SOURCERUNS_HLKZ_CODE {
+        } else if value == SOURCERUNS_HLKZ_CODE || value == "SRHLKZ" {
             return Ok(GhostBlobType::SRHLKZ);
+        } else if value == KDR_CODE || value == "Kdr" {
+            return Ok(GhostBlobType::Kdr);
+        } else if value == SCHEMA_CODE || value == "Schema" {
+            return Ok(GhostBlobType::Schema);
         } else {
             return Err(format!("unknown blob type `{}`", value).leak());
         }
@@ -68,11 +155,27 @@ impl From<GhostBlobType> for &str {
             GhostBlobType::SurfGateway => SURF_GATEWAY_CODE,
             GhostBlobType::RomanianJumpers => ROMANIAN_JUMPERS_CODE,
             GhostBlobType::SRHLKZ => SOURCERUNS_HLKZ_CODE,
+            GhostBlobType::Kdr => KDR_CODE,
+            GhostBlobType::Schema => SCHEMA_CODE,
         }
     }
 }
 
 impl GhostBlobType {
+    /// File suffix that [`GhostBlobType::try_from_file_name`] recognizes.
+    pub fn file_suffix(&self) -> &'static str {
+        match self {
+            GhostBlobType::Demo => ".dem",
+            GhostBlobType::Simen => ".simen.txt",
+            GhostBlobType::SurfGateway => ".sg.json",
+            GhostBlobType::RomanianJumpers => ".rj.json",
+            GhostBlobType::SRHLKZ => ".dat",
+            GhostBlobType::Kdr => ".kdr",
+            // or `.ghost.json`, which is up to the registered parser
+            GhostBlobType::Schema => ".ghost.csv",
+        }
+    }
+
     pub fn try_from_file_name(s: &str) -> Option<Self> {
         if s.ends_with(".dem") {
             Self::Demo.into()
@@ -84,6 +187,8 @@ impl GhostBlobType {
             Self::RomanianJumpers.into()
         } else if s.ends_with(".dat") {
             Self::SRHLKZ.into()
+        } else if s.ends_with(".kdr") {
+            Self::Kdr.into()
         } else {
             None
         }
@@ -109,45 +214,44 @@ pub fn get_ghost_blob_from_bytes(
     data: Vec<u8>,
     overridden_option: Option<GhostBlobType>,
 ) -> Result<GhostBlob, GhostError> {
-    let Some(blob_type) = overridden_option.or(GhostBlobType::try_from_file_name(file_name)) else {
+    // renamed files should still work, so content goes before file name
+    let sniffed = GhostBlobType::sniff(&data)
+        .filter(|(_, confidence)| *confidence >= SNIFF_MIN_CONFIDENCE)
+        .map(|(blob_type, _)| blob_type);
+
+    // override is only for formats that can't be recognised
+    let Some(blob_type) = sniffed
+        .or_else(|| GhostBlobType::try_from_file_name(file_name))
+        .or(overridden_option)
+    else {
         return Err(GhostError::UnknownFormat {
             path: file_name.into(),
         });
     };
 
-    match blob_type {
-        GhostBlobType::Demo => Ok(GhostBlob::Demo(data)),
-        GhostBlobType::Simen | GhostBlobType::SurfGateway | GhostBlobType::RomanianJumpers => {
-            let s_data = str::from_utf8(&data).map_err(|op| GhostError::Utf8Error {
-                source: op.into(),
-                path: file_name.into(),
-            })?;
-
-            let s_data = s_data.to_string();
-
-            match blob_type {
-                GhostBlobType::Demo | GhostBlobType::SRHLKZ => unreachable!(),
-                GhostBlobType::Simen => Ok(GhostBlob::Simen(s_data)),
-                GhostBlobType::SurfGateway => Ok(GhostBlob::SurfGateway(s_data)),
-                GhostBlobType::RomanianJumpers => Ok(GhostBlob::RomanianJumpers(s_data)),
-            }
-        }
-        GhostBlobType::SRHLKZ => Ok(GhostBlob::SRHLKZ(data)),
-    }
+    GhostBlob::from_bytes(blob_type, file_name, data)
 }
 
-pub fn get_ghost_from_blob(file_name: &str, blob: GhostBlob) -> Result<GhostInfo, GhostError> {
+/// Parses the blob into [`GhostInfo`].
+///
+/// With [`GhostParseMode::Lenient`], bad demo messages are skipped and recorded in [`GhostInfo::warnings`].
+pub fn get_ghost_from_blob(
+    file_name: &str,
+    blob: GhostBlob,
+    mode: GhostParseMode,
+) -> Result<GhostInfo, GhostError> {
     match blob {
         GhostBlob::Demo(demo_bytes) => {
             let demo = dem::open_demo_from_bytes(&demo_bytes)
                 .map_err(|op| GhostError::DemoParse { source: op })?;
 
-            demo_ghost_parse(file_name, &demo)
+            return demo_ghost_parse(file_name, &demo, mode);
         }
         GhostBlob::Simen(s) => simen_ghost_parse(file_name, &s),
         GhostBlob::SurfGateway(s) => surf_gateway_ghost_parse(file_name, &s),
         GhostBlob::RomanianJumpers(s) => romanian_jumpers_ghost_parse(file_name, &s),
         GhostBlob::SRHLKZ(items) => srhlkz_ghost_parse(file_name, &items),
+        GhostBlob::Kdr(items) => kdr_ghost_parse(file_name, &items),
         GhostBlob::Unknown => {
             return Err(GhostError::UnknownFormat {
                 path: file_name.into(),
@@ -157,6 +261,48 @@ pub fn get_ghost_from_blob(file_name: &str, blob: GhostBlob) -> Result<GhostInfo
     .map_err(|op| GhostError::GhostParse { source: op })
 }
 
+/// Same as [`get_ghost_from_blob`] but also returns every other player in the replay as their own ghost.
+///
+/// Only demos can have other players.
+pub fn get_ghost_with_players_from_blob(
+    file_name: &str,
+    blob: GhostBlob,
+    mode: GhostParseMode,
+) -> Result<(GhostInfo, Vec<GhostInfo>), GhostError> {
+    let GhostBlob::Demo(demo_bytes) = blob else {
+        return get_ghost_from_blob(file_name, blob, mode).map(|ghost| (ghost, vec![]));
+    };
+
+    let demo = dem::open_demo_from_bytes(&demo_bytes)
+        .map_err(|op| GhostError::DemoParse { source: op })?;
+
+    let ghost = demo_ghost_parse(file_name, &demo, mode)?;
+    let players = demo_multi_ghost_parse(file_name, &demo)?;
+
+    Ok((ghost, players))
+}
+
+/// Writes [`GhostInfo`] into a blob of the given type.
+///
+/// Writing demo and schema ghost is not supported.
+pub fn write_ghost_to_blob(
+    ghost: &GhostInfo,
+    blob_type: GhostBlobType,
+) -> Result<GhostBlob, GhostError> {
+    match blob_type {
+        GhostBlobType::Demo => err!("Writing demo is not supported"),
+        GhostBlobType::Simen => simen_ghost_write(ghost).map(GhostBlob::Simen),
+        GhostBlobType::SurfGateway => surf_gateway_ghost_write(ghost).map(GhostBlob::SurfGateway),
+        GhostBlobType::RomanianJumpers => {
+            romanian_jumpers_ghost_write(ghost).map(GhostBlob::RomanianJumpers)
+        }
+        GhostBlobType::SRHLKZ => srhlkz_ghost_write(ghost).map(GhostBlob::SRHLKZ),
+        GhostBlobType::Kdr => kdr_ghost_write(ghost).map(GhostBlob::Kdr),
+        GhostBlobType::Schema => err!("Writing schema ghost is not supported"),
+    }
+    .map_err(|op| GhostError::GhostWrite { source: op })
+}
+
 #[macro_export]
 macro_rules! err {
     ($e: ident) => {{
@@ -0,0 +1,322 @@
+//! Replay formats as trait objects so that formats outside this crate can be added.
+//!
+//! Built-in formats are registered as [`BuiltinGhostParser`]. Other formats only exist where they are registered,
+//! so the server should send them to the client as kdr ghost. [`SchemaGhostParser`] is one of them.
+use std::{fmt, sync::Arc};
+
+use crate::{
+    GhostBlob, GhostBlobType, GhostError, GhostInfo, GhostParseMode, SNIFF_MIN_CONFIDENCE,
+    get_ghost::schema::{schema_ghost_parse, schema_ghost_sniff},
+    get_ghost_from_blob,
+};
+
+/// A replay format that can be parsed into [`GhostInfo`].
+pub trait GhostParser: Send + Sync {
+    /// Name to select the parser with, such as in the server config.
+    fn name(&self) -> &str;
+
+    /// Whether the file name looks like this format.
+    fn matches_file_name(&self, file_name: &str) -> bool;
+
+    /// Confidence from 0 to 1 that the content is this format, `None` if it is not.
+    fn sniff(&self, data: &[u8]) -> Option<f32>;
+
+    fn parse(
+        &self,
+        file_name: &str,
+        data: &[u8],
+        mode: GhostParseMode,
+    ) -> Result<GhostInfo, GhostError>;
+
+    /// Built-in type that the client can parse by itself.
+    fn blob_type(&self) -> Option<GhostBlobType> {
+        None
+    }
+}
+
+/// Parser for formats in [`GhostBlobType`].
+pub struct BuiltinGhostParser(pub GhostBlobType);
+
+impl GhostParser for BuiltinGhostParser {
+    fn name(&self) -> &str {
+        self.0.clone().into()
+    }
+
+    fn matches_file_name(&self, file_name: &str) -> bool {
+        GhostBlobType::try_from_file_name(file_name).is_some_and(|blob_type| blob_type == self.0)
+    }
+
+    fn sniff(&self, data: &[u8]) -> Option<f32> {
+        GhostBlobType::sniff(data)
+            .filter(|(blob_type, _)| *blob_type == self.0)
+            .map(|(_, confidence)| confidence)
+    }
+
+    fn parse(
+        &self,
+        file_name: &str,
+        data: &[u8],
+        mode: GhostParseMode,
+    ) -> Result<GhostInfo, GhostError> {
+        let blob = GhostBlob::from_bytes(self.0.clone(), file_name, data.to_vec())?;
+
+        get_ghost_from_blob(file_name, blob, mode)
+    }
+
+    fn blob_type(&self) -> Option<GhostBlobType> {
+        Some(self.0.clone())
+    }
+}
+
+/// Ghost that declares its own columns and units in a header, as `.ghost.csv` or `.ghost.json`.
+///
+/// Layout is described in `get_ghost/schema.rs`.
+pub struct SchemaGhostParser;
+
+impl GhostParser for SchemaGhostParser {
+    fn name(&self) -> &str {
+        "schema"
+    }
+
+    fn matches_file_name(&self, file_name: &str) -> bool {
+        file_name.ends_with(".ghost.csv") || file_name.ends_with(".ghost.json")
+    }
+
+    fn sniff(&self, data: &[u8]) -> Option<f32> {
+        schema_ghost_sniff(data)
+    }
+
+    fn parse(
+        &self,
+        file_name: &str,
+        data: &[u8],
+        _mode: GhostParseMode,
+    ) -> Result<GhostInfo, GhostError> {
+        let file = str::from_utf8(data).map_err(|op| GhostError::Utf8Error {
+            source: op,
+            path: file_name.into(),
+        })?;
+
+        schema_ghost_parse(file_name, file).map_err(|op| GhostError::GhostParse { source: op })
+    }
+}
+
+/// Parsers that can be looked up by name, file name or content.
+#[derive(Clone)]
+pub struct GhostParserRegistry {
+    parsers: Vec<Arc<dyn GhostParser>>,
+}
+
+impl Default for GhostParserRegistry {
+    /// Registry with every built-in format and [`SchemaGhostParser`].
+    fn default() -> Self {
+        let mut registry = Self::new();
+
+        [
+            GhostBlobType::Demo,
+            GhostBlobType::Simen,
+            GhostBlobType::SurfGateway,
+            GhostBlobType::RomanianJumpers,
+            GhostBlobType::SRHLKZ,
+            GhostBlobType::Kdr,
+        ]
+        .into_iter()
+        .for_each(|blob_type| registry.register(BuiltinGhostParser(blob_type)));
+
+        registry.register(SchemaGhostParser);
+
+        registry
+    }
+}
+
+impl fmt::Debug for GhostParserRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+impl GhostParserRegistry {
+    /// Empty registry.
+    pub fn new() -> Self {
+        Self { parsers: vec![] }
+    }
+
+    /// Adds a parser. Parser with the same name is replaced.
+    pub fn register(&mut self, parser: impl GhostParser + 'static) {
+        self.parsers.retain(|other| other.name() != parser.name());
+        self.parsers.push(Arc::new(parser));
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.parsers.iter().map(|parser| parser.name())
+    }
+
+    /// Finds parser by name.
+    ///
+    /// Built-in formats can also be found by their [`GhostBlobType`] variant name, eg `SurfGateway`.
+    pub fn get(&self, name: &str) -> Option<&dyn GhostParser> {
+        self.position(name).map(|idx| self.parsers[idx].as_ref())
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        let code: Option<&str> = GhostBlobType::try_from(name).ok().map(|x| x.into());
+
+        self.parsers
+            .iter()
+            .position(|parser| parser.name() == name || Some(parser.name()) == code)
+    }
+
+    /// New registry with only the given parsers, in the given order.
+    pub fn select(&self, names: &[String]) -> Result<Self, GhostError> {
+        let parsers = names
+            .iter()
+            .map(|name| {
+                self.position(name)
+                    .map(|idx| self.parsers[idx].clone())
+                    .ok_or_else(|| GhostError::UnknownParser { name: name.clone() })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { parsers })
+    }
+
+    /// Finds the parser for a file, same order as [`crate::get_ghost_blob_from_bytes`].
+    ///
+    /// Content goes first, then the file name, then the overridden parser name for formats that can't be recognised.
+    pub fn find(
+        &self,
+        file_name: &str,
+        data: &[u8],
+        overridden_option: Option<&str>,
+    ) -> Option<&dyn GhostParser> {
+        let sniffed = self
+            .parsers
+            .iter()
+            .filter_map(|parser| Some((parser, parser.sniff(data)?)))
+            .filter(|(_, confidence)| *confidence >= SNIFF_MIN_CONFIDENCE)
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(parser, _)| parser.as_ref());
+
+        sniffed
+            .or_else(|| self.find_by_file_name(file_name))
+            .or_else(|| overridden_option.and_then(|name| self.get(name)))
+    }
+
+    pub fn find_by_file_name(&self, file_name: &str) -> Option<&dyn GhostParser> {
+        self.parsers
+            .iter()
+            .find(|parser| parser.matches_file_name(file_name))
+            .map(|parser| parser.as_ref())
+    }
+
+    pub fn parse(
+        &self,
+        file_name: &str,
+        data: &[u8],
+        overridden_option: Option<&str>,
+        mode: GhostParseMode,
+    ) -> Result<GhostInfo, GhostError> {
+        let Some(parser) = self.find(file_name, data, overridden_option) else {
+            return Err(GhostError::UnknownFormat {
+                path: file_name.into(),
+            });
+        };
+
+        parser.parse(file_name, data, mode)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{get_ghost::sample_frames, write_ghost_to_blob};
+
+    /// Same as simen but with a different suffix, like a server plugin would have.
+    struct TimerPluginParser;
+
+    impl GhostParser for TimerPluginParser {
+        fn name(&self) -> &str {
+            "timer_plugin"
+        }
+
+        fn matches_file_name(&self, file_name: &str) -> bool {
+            file_name.ends_with(".timer")
+        }
+
+        fn sniff(&self, _data: &[u8]) -> Option<f32> {
+            None
+        }
+
+        fn parse(
+            &self,
+            file_name: &str,
+            data: &[u8],
+            mode: GhostParseMode,
+        ) -> Result<GhostInfo, GhostError> {
+            BuiltinGhostParser(GhostBlobType::Simen).parse(file_name, data, mode)
+        }
+    }
+
+    fn simen_bytes() -> Vec<u8> {
+        let ghost = GhostInfo::new(
+            "c1a0.simen.txt".to_string(),
+            "c1a0".to_string(),
+            "cstrike".to_string(),
+            sample_frames(20, Some(0.01)),
+        );
+
+        write_ghost_to_blob(&ghost, GhostBlobType::Simen)
+            .unwrap()
+            .into_bytes()
+            .unwrap()
+    }
+
+    #[test]
+    fn register_and_find() {
+        let mut registry = GhostParserRegistry::default();
+        registry.register(TimerPluginParser);
+
+        assert!(registry.get("timer_plugin").is_some());
+        assert_eq!(registry.get("SurfGateway").unwrap().name(), "surf_gateway");
+
+        // content still wins
+        let parser = registry.find("c1a0.timer", &simen_bytes(), None).unwrap();
+        assert_eq!(parser.name(), "simen");
+
+        let parser = registry.find("c1a0.timer", b"garbage", None).unwrap();
+        assert_eq!(parser.name(), "timer_plugin");
+        assert!(parser.blob_type().is_none());
+
+        // override only when nothing else knows the file
+        let parser = registry
+            .find("c1a0.timer", b"garbage", Some("demo"))
+            .unwrap();
+        assert_eq!(parser.name(), "timer_plugin");
+
+        let parser = registry
+            .find("c1a0.what", b"garbage", Some("demo"))
+            .unwrap();
+        assert_eq!(parser.name(), "demo");
+
+        let ghost = registry
+            .parse("c1a0.timer", &simen_bytes(), None, GhostParseMode::Strict)
+            .unwrap();
+        assert_eq!(ghost.frames().len(), 20);
+    }
+
+    #[test]
+    fn select() {
+        let registry = GhostParserRegistry::default()
+            .select(&["demo".to_string(), "Simen".to_string()])
+            .unwrap();
+
+        assert_eq!(registry.names().collect::<Vec<_>>(), ["demo", "simen"]);
+        assert!(registry.find("a.rj.json", b"", None).is_none());
+
+        assert!(
+            GhostParserRegistry::default()
+                .select(&["nope".to_string()])
+                .is_err()
+        );
+    }
+}
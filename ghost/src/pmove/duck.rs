@@ -0,0 +1,129 @@
+//! Ducking and switching between the standing and ducking hulls.
+use bsp::HullType;
+use glam::Vec3;
+
+use crate::IN_DUCK;
+
+use super::{DUCK_HULL_OFFSET, Pmove, VEC_DUCK_VIEW, VEC_VIEW};
+
+// seconds to fully duck on the ground
+const TIME_TO_DUCK: f32 = 0.4;
+// PLAYER_DUCKING_MULTIPLIER
+pub(super) const DUCK_SPEED_MULTIPLIER: f32 = 0.333;
+// how far up to look for space after ducking into something
+const CROUCH_STUCK_MAX_STEPS: usize = 36;
+
+/// Eases the view going down, same as `PM_SplineFraction`.
+fn spline_fraction(value: f32, scale: f32) -> f32 {
+    let value = scale * value;
+    let value_squared = value * value;
+
+    3. * value_squared - 2. * value_squared * value
+}
+
+impl Pmove<'_> {
+    /// Same as `PM_Duck`.
+    ///
+    /// Ducking on the ground takes [`TIME_TO_DUCK`] while the view goes down, ducking in the air is instant.
+    pub(super) fn duck(&mut self) {
+        let buttons = self.cmd.buttons;
+        let pressed = (self.state.old_buttons ^ buttons) & buttons;
+        let holding = buttons & IN_DUCK != 0;
+
+        if holding {
+            self.state.old_buttons |= IN_DUCK;
+        } else {
+            self.state.old_buttons &= !IN_DUCK;
+        }
+
+        if self.state.ducking {
+            self.cmd.forwardmove *= DUCK_SPEED_MULTIPLIER;
+            self.cmd.sidemove *= DUCK_SPEED_MULTIPLIER;
+            self.cmd.upmove *= DUCK_SPEED_MULTIPLIER;
+        }
+
+        if !holding && !self.state.in_duck && !self.state.ducking {
+            return;
+        }
+
+        if !holding {
+            self.unduck();
+
+            return;
+        }
+
+        if pressed & IN_DUCK != 0 && !self.state.ducking {
+            self.state.duck_time = 1000.;
+            self.state.in_duck = true;
+        }
+
+        if !self.state.in_duck {
+            return;
+        }
+
+        let time = (1. - self.state.duck_time / 1000.).max(0.);
+
+        if self.state.duck_time / 1000. <= 1. - TIME_TO_DUCK || !self.state.on_ground {
+            self.state.ducking = true;
+            self.state.in_duck = false;
+            self.state.view_offset_z = VEC_DUCK_VIEW;
+
+            // keeps the feet where they are
+            if self.state.on_ground {
+                self.state.origin.z -= DUCK_HULL_OFFSET;
+
+                self.fix_crouch_stuck(1.);
+                self.categorize_position();
+            }
+        } else {
+            let duck_fraction = spline_fraction(time, 1. / TIME_TO_DUCK);
+
+            self.state.view_offset_z = (VEC_DUCK_VIEW - DUCK_HULL_OFFSET) * duck_fraction
+                + VEC_VIEW * (1. - duck_fraction);
+        }
+    }
+
+    /// Stands up if there is space, same as `PM_UnDuck`.
+    ///
+    /// Letting go of duck on the ground before fully ducking moves the player up, which is ducktap.
+    fn unduck(&mut self) {
+        let mut new_origin = self.state.origin;
+
+        if self.state.on_ground {
+            new_origin.z += DUCK_HULL_OFFSET;
+        }
+
+        // the current hull first, same as the game
+        if !self.fits(self.state.hull(), new_origin) || !self.fits(HullType::Stand, new_origin) {
+            return;
+        }
+
+        self.state.ducking = false;
+        self.state.in_duck = false;
+        self.state.view_offset_z = VEC_VIEW;
+        self.state.duck_time = 0.;
+        self.state.origin = new_origin;
+
+        self.categorize_position();
+    }
+
+    /// Moves the player along z until it fits, same as `PM_FixPlayerCrouchStuck`.
+    fn fix_crouch_stuck(&mut self, direction: f32) {
+        let hull = self.state.hull();
+        let test = self.state.origin;
+
+        if self.fits(hull, test) {
+            return;
+        }
+
+        for _ in 0..CROUCH_STUCK_MAX_STEPS {
+            self.state.origin += Vec3::Z * direction;
+
+            if self.fits(hull, self.state.origin) {
+                return;
+            }
+        }
+
+        self.state.origin = test;
+    }
+}
@@ -0,0 +1,115 @@
+//! Climbing `func_ladder`.
+use bsp::{HullType, LeafContent};
+use glam::Vec3;
+
+use crate::{IN_BACK, IN_FORWARD, IN_JUMP, IN_MOVELEFT, IN_MOVERIGHT};
+
+use super::{
+    Pmove, angle_vectors, duck::DUCK_SPEED_MULTIPLIER, from_bsp_vec, hull_size, to_bsp_vec,
+};
+
+const MAX_CLIMB_SPEED: f32 = 200.;
+const LADDER_JUMP_SPEED: f32 = 270.;
+
+impl Pmove<'_> {
+    /// (Model Index, Origin) of the ladder the player is in, same as `PM_Ladder`.
+    pub(super) fn ladder(&self) -> Option<(usize, bsp::Vec3)> {
+        let hull = self.state.hull();
+        let origin = to_bsp_vec(self.state.origin);
+
+        self.world
+            .ladders
+            .iter()
+            .copied()
+            .find(|(model_index, ladder_origin)| {
+                self.bsp.models.get(*model_index).is_some_and(|model| {
+                    self.bsp
+                        .trace_point_hull(model.head_nodes[hull as usize], origin - *ladder_origin)
+                        != LeafContent::ContentsEmpty
+                })
+            })
+    }
+
+    /// Same as `PM_LadderMove`. Returns false when jumping off the ladder.
+    ///
+    /// Forward and back climb along the ladder depending on where the player looks.
+    pub(super) fn ladder_move(&mut self, (model_index, ladder_origin): (usize, bsp::Vec3)) -> bool {
+        let (mins, _) = hull_size(self.state.hull());
+        let origin = self.state.origin;
+
+        let model = &self.bsp.models[model_index];
+        let center = (model.mins + model.maxs) * 0.5 + ladder_origin;
+
+        let floor = origin + Vec3::Z * (mins.z - 1.);
+        let on_floor = self.point_contents(floor) == LeafContent::ContentsSolid;
+
+        // a point, same as `PM_TraceModel`, because the hull is already in the ladder
+        let tr = self.bsp.trace_line_model(
+            model_index,
+            ladder_origin,
+            HullType::Point,
+            to_bsp_vec(origin),
+            center,
+        );
+
+        // can't see the ladder, keeps going
+        if tr.fraction == 1. {
+            return true;
+        }
+
+        let normal = from_bsp_vec(tr.plane.normal);
+        let buttons = self.cmd.buttons;
+
+        let mut speed = MAX_CLIMB_SPEED.min(self.vars.maxspeed);
+
+        if self.state.ducking {
+            speed *= DUCK_SPEED_MULTIPLIER;
+        }
+
+        let axis = |positive: u32, negative: u32| {
+            let mut res = 0.;
+
+            if buttons & positive != 0 {
+                res += speed;
+            }
+
+            if buttons & negative != 0 {
+                res -= speed;
+            }
+
+            res
+        };
+
+        let forward = axis(IN_FORWARD, IN_BACK);
+        let right = axis(IN_MOVERIGHT, IN_MOVELEFT);
+
+        if buttons & IN_JUMP != 0 {
+            self.state.velocity = normal * LADDER_JUMP_SPEED;
+
+            return false;
+        }
+
+        if forward == 0. && right == 0. {
+            self.state.velocity = Vec3::ZERO;
+
+            return true;
+        }
+
+        let (view_forward, view_right, _) = angle_vectors(self.angles);
+        let velocity = view_forward * forward + view_right * right;
+
+        // into the ladder becomes up the ladder
+        let perp = Vec3::Z.cross(normal).normalize_or_zero();
+        let normal_speed = velocity.dot(normal);
+        let lateral = velocity - normal * normal_speed;
+
+        self.state.velocity = lateral - normal.cross(perp) * normal_speed;
+
+        // backing away from the ladder on the ground gets off it
+        if on_floor && normal_speed > 0. {
+            self.state.velocity += normal * MAX_CLIMB_SPEED;
+        }
+
+        true
+    }
+}
@@ -0,0 +1,777 @@
+//! Player movement, ported from `PM_PlayerMove` in HLSDK pm_shared.c.
+//!
+//! Walking, air strafing, ducking, stepping, ladders and water work the same as the game,
+//! clipping against the clipnode hulls of the map and its solid brush entities.
+//! Water also comes from brush entities with contents like `func_water`.
+//! Sounds, fall damage, punch angles, water currents and getting unstuck are left out.
+//!
+//! There is no randomness or clock, so the same commands on the same map always end up in the same place.
+//! That makes it possible to check it against recorded ghosts without rendering anything.
+use bsp::{Bsp, HullType, LeafContent};
+use glam::Vec3;
+
+use crate::{
+    DEFAULT_GHOST_FRAMETIME, GhostFrame, GhostInfo, IN_BACK, IN_FORWARD, IN_JUMP, IN_MOVELEFT,
+    IN_MOVERIGHT,
+};
+
+mod duck;
+mod ladder;
+mod walk;
+mod water;
+
+/// View height when standing.
+pub const VEC_VIEW: f32 = 28.;
+/// View height when fully ducked.
+pub const VEC_DUCK_VIEW: f32 = 12.;
+// the origin moves by the difference of the bottoms of the hulls when ducking on the ground
+const DUCK_HULL_OFFSET: f32 = 18.;
+
+// same as pm_shared.c
+const GROUND_TRACE_DISTANCE: f32 = 2.;
+const GROUND_MIN_NORMAL_Z: f32 = 0.7;
+const GROUND_MAX_UP_SPEED: f32 = 180.;
+
+// default cl_forwardspeed, cl_sidespeed and cl_upspeed
+pub const FORWARD_SPEED: f32 = 400.;
+pub const SIDE_SPEED: f32 = 400.;
+pub const UP_SPEED: f32 = 320.;
+
+/// Server cvars that change movement. Defaults are the same as Half-Life.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MoveVars {
+    pub gravity: f32,
+    pub stopspeed: f32,
+    pub maxspeed: f32,
+    pub accelerate: f32,
+    pub airaccelerate: f32,
+    pub friction: f32,
+    pub edgefriction: f32,
+    pub stepsize: f32,
+    pub maxvelocity: f32,
+    /// Slows down jumps faster than 1.7 times `maxspeed`, same as `PM_PreventMegaBunnyJumping`.
+    ///
+    /// Servers that allow bhop don't have it.
+    pub bunnyhop_cap: bool,
+}
+
+impl Default for MoveVars {
+    fn default() -> Self {
+        Self {
+            gravity: 800.,
+            stopspeed: 100.,
+            maxspeed: 320.,
+            accelerate: 10.,
+            airaccelerate: 10.,
+            friction: 4.,
+            edgefriction: 2.,
+            stepsize: 18.,
+            maxvelocity: 2000.,
+            bunnyhop_cap: true,
+        }
+    }
+}
+
+/// One frame of input, same as `usercmd_t`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UserCmd {
+    /// In seconds instead of the milliseconds of `usercmd_t`.
+    pub frametime: f32,
+    /// Pitch, yaw and roll in degrees. Pitch goes down.
+    pub viewangles: Vec3,
+    pub forwardmove: f32,
+    pub sidemove: f32,
+    pub upmove: f32,
+    /// Same bits as [`GhostFrame::buttons`].
+    pub buttons: u32,
+}
+
+impl UserCmd {
+    /// Command of a ghost frame, moving with default `cl_forwardspeed` and `cl_sidespeed` for the pressed buttons.
+    ///
+    /// Ghosts only have button bits, so a lower move speed or analog input is not what the player actually sent.
+    pub fn from_ghost_frame(frame: &GhostFrame) -> Self {
+        let buttons = frame.buttons.unwrap_or(0);
+        let axis = |positive: u32, negative: u32, speed: f32| {
+            let mut res = 0.;
+
+            if buttons & positive != 0 {
+                res += speed;
+            }
+
+            if buttons & negative != 0 {
+                res -= speed;
+            }
+
+            res
+        };
+
+        Self {
+            frametime: frame.frametime.unwrap_or(DEFAULT_GHOST_FRAMETIME),
+            viewangles: frame.viewangles,
+            forwardmove: axis(IN_FORWARD, IN_BACK, FORWARD_SPEED),
+            sidemove: axis(IN_MOVERIGHT, IN_MOVELEFT, SIDE_SPEED),
+            upmove: 0.,
+            buttons,
+        }
+    }
+}
+
+/// Everything about the player that carries over to the next command.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayerState {
+    pub origin: Vec3,
+    pub velocity: Vec3,
+    /// Eyes above the origin.
+    pub view_offset_z: f32,
+    /// Fully ducked and using the duck hull.
+    pub ducking: bool,
+    /// Holding duck but not fully ducked yet.
+    pub in_duck: bool,
+    /// Milliseconds left of ducking, counting down from 1000.
+    pub duck_time: f32,
+    pub on_ground: bool,
+    pub on_ladder: bool,
+    /// 0 is out of water, 1 is feet, 2 is waist and 3 is eyes.
+    pub water_level: u8,
+    pub water_type: LeafContent,
+    /// Milliseconds left of jumping out of water.
+    pub water_jump_time: f32,
+    /// Horizontal velocity while jumping out of water.
+    pub water_jump_velocity: Vec3,
+    /// Jump and duck of the last command, so holding them doesn't do it again.
+    pub old_buttons: u32,
+}
+
+impl PlayerState {
+    /// Standing still at `origin`.
+    pub fn new(origin: Vec3) -> Self {
+        Self {
+            origin,
+            velocity: Vec3::ZERO,
+            view_offset_z: VEC_VIEW,
+            ducking: false,
+            in_duck: false,
+            duck_time: 0.,
+            on_ground: false,
+            on_ladder: false,
+            water_level: 0,
+            water_type: LeafContent::ContentsEmpty,
+            water_jump_time: 0.,
+            water_jump_velocity: Vec3::ZERO,
+            old_buttons: 0,
+        }
+    }
+
+    pub fn hull(&self) -> HullType {
+        if self.ducking {
+            HullType::Duck
+        } else {
+            HullType::Stand
+        }
+    }
+
+    /// Where the eyes are.
+    pub fn view_origin(&self) -> Vec3 {
+        self.origin + Vec3::Z * self.view_offset_z
+    }
+}
+
+/// Moves players around one map.
+#[derive(Debug, Clone)]
+pub struct PlayerMove {
+    pub vars: MoveVars,
+    /// (Model Index, Origin) of solid brush entities.
+    solid_entities: Vec<(usize, bsp::Vec3)>,
+    /// (Model Index, Origin) of ladders.
+    ladders: Vec<(usize, bsp::Vec3)>,
+    /// (Model Index, Origin, Contents) of brush entities like `func_water`.
+    contents_entities: Vec<(usize, bsp::Vec3, LeafContent)>,
+}
+
+impl PlayerMove {
+    /// Looks up the brush entities of `bsp` once. Use the same map for [`PlayerMove::player_move`].
+    pub fn new(bsp: &Bsp, vars: MoveVars) -> Self {
+        Self {
+            vars,
+            solid_entities: bsp.solid_brush_entities(),
+            ladders: bsp.brush_entities(|entity| {
+                entity
+                    .get("classname")
+                    .is_some_and(|classname| classname == "func_ladder")
+            }),
+            contents_entities: bsp.contents_brush_entities(),
+        }
+    }
+
+    /// Runs one command, same as `PM_PlayerMove`.
+    pub fn player_move(&self, bsp: &Bsp, state: &mut PlayerState, cmd: &UserCmd) {
+        Pmove {
+            bsp,
+            world: self,
+            vars: &self.vars,
+            state,
+            cmd: *cmd,
+            angles: Vec3::ZERO,
+            forward: Vec3::X,
+            right: Vec3::NEG_Y,
+            flying: false,
+        }
+        .player_move();
+    }
+}
+
+/// Moves from `start` with the command of every frame of `ghost` after the first.
+///
+/// Frame `i` is where the command of frame `i` ends up, so the result lines up with the frames to compare origins.
+///
+/// Commands come from [`UserCmd::from_ghost_frame`] with full [`FORWARD_SPEED`] and [`SIDE_SPEED`] for every pressed button.
+/// A player with lower move speeds, `+speed`, `+klook` or a controller moves differently in game, so the result drifts away from the ghost.
+pub fn simulate_ghost(
+    ghost: &GhostInfo,
+    bsp: &Bsp,
+    vars: MoveVars,
+    start: PlayerState,
+) -> Vec<PlayerState> {
+    let player_move = PlayerMove::new(bsp, vars);
+    let mut state = start;
+
+    std::iter::once(start)
+        .chain(ghost.frames().iter().skip(1).map(|frame| {
+            player_move.player_move(bsp, &mut state, &UserCmd::from_ghost_frame(frame));
+
+            state
+        }))
+        .collect()
+}
+
+fn to_bsp_vec(v: Vec3) -> bsp::Vec3 {
+    bsp::Vec3::from_array(v.to_array())
+}
+
+fn from_bsp_vec(v: bsp::Vec3) -> Vec3 {
+    Vec3::from_array(v.to_array())
+}
+
+/// Mins and maxs of `hull`.
+fn hull_size(hull: HullType) -> (Vec3, Vec3) {
+    let (mins, maxs) = hull.size();
+
+    (from_bsp_vec(mins), from_bsp_vec(maxs))
+}
+
+/// Forward, right and up of pitch, yaw and roll in degrees, same as `AngleVectors`.
+pub(crate) fn angle_vectors(angles: Vec3) -> (Vec3, Vec3, Vec3) {
+    let (sp, cp) = angles.x.to_radians().sin_cos();
+    let (sy, cy) = angles.y.to_radians().sin_cos();
+    let (sr, cr) = angles.z.to_radians().sin_cos();
+
+    let forward = Vec3::new(cp * cy, cp * sy, -sp);
+    let right = Vec3::new(-sr * sp * cy + cr * sy, -sr * sp * sy - cr * cy, -sr * cp);
+    let up = Vec3::new(cr * sp * cy + sr * sy, cr * sp * sy - sr * cy, cr * cp);
+
+    (forward, right, up)
+}
+
+/// The parts of `pmtrace_t` that movement needs.
+struct Trace {
+    all_solid: bool,
+    start_solid: bool,
+    fraction: f32,
+    end_pos: Vec3,
+    normal: Vec3,
+}
+
+/// Everything `pmove` holds while running one command.
+struct Pmove<'a> {
+    bsp: &'a Bsp,
+    world: &'a PlayerMove,
+    vars: &'a MoveVars,
+    state: &'a mut PlayerState,
+    /// Ducking slows it down.
+    cmd: UserCmd,
+    angles: Vec3,
+    forward: Vec3,
+    right: Vec3,
+    /// On a ladder, `MOVETYPE_FLY`.
+    flying: bool,
+}
+
+impl Pmove<'_> {
+    fn player_move(&mut self) {
+        self.check_parameters();
+        self.reduce_timers();
+
+        (self.forward, self.right, _) = angle_vectors(self.angles);
+
+        self.categorize_position();
+
+        let ladder = self.ladder();
+        self.state.on_ladder = ladder.is_some();
+
+        self.duck();
+
+        // jumping off a ladder walks again
+        self.flying = ladder.is_some_and(|ladder| self.ladder_move(ladder));
+
+        let jumping = self.cmd.buttons & IN_JUMP != 0;
+
+        if self.flying {
+            self.check_water();
+
+            if !jumping {
+                self.state.old_buttons &= !IN_JUMP;
+            }
+
+            self.fly_move();
+
+            return;
+        }
+
+        if !self.in_water() {
+            self.add_correct_gravity();
+        }
+
+        if self.state.water_jump_time > 0. {
+            self.water_jump();
+            self.fly_move();
+            self.check_water();
+
+            return;
+        }
+
+        if self.state.water_level >= 2 {
+            if self.state.water_level == 2 {
+                self.check_water_jump();
+            }
+
+            if self.state.velocity.z < 0. && self.state.water_jump_time > 0. {
+                self.state.water_jump_time = 0.;
+            }
+
+            if jumping {
+                self.jump();
+            } else {
+                self.state.old_buttons &= !IN_JUMP;
+            }
+
+            self.water_move();
+            self.categorize_position();
+
+            return;
+        }
+
+        if !jumping {
+            self.state.old_buttons &= !IN_JUMP;
+        } else if ladder.is_none() {
+            self.jump();
+        }
+
+        if self.state.on_ground {
+            self.state.velocity.z = 0.;
+            self.friction();
+        }
+
+        self.check_velocity();
+
+        if self.state.on_ground {
+            self.walk_move();
+        } else {
+            self.air_move();
+        }
+
+        self.categorize_position();
+        self.check_velocity();
+
+        if !self.in_water() {
+            self.fixup_gravity_velocity();
+        }
+
+        if self.state.on_ground {
+            self.state.velocity.z = 0.;
+        }
+    }
+
+    fn frametime(&self) -> f32 {
+        self.cmd.frametime
+    }
+
+    /// Caps the wish speed and takes the view angles, same as `PM_CheckParamters`.
+    fn check_parameters(&mut self) {
+        let speed = Vec3::new(self.cmd.forwardmove, self.cmd.sidemove, self.cmd.upmove).length();
+
+        if speed != 0. && speed > self.vars.maxspeed {
+            let ratio = self.vars.maxspeed / speed;
+
+            self.cmd.forwardmove *= ratio;
+            self.cmd.sidemove *= ratio;
+            self.cmd.upmove *= ratio;
+        }
+
+        self.angles = Vec3::new(self.cmd.viewangles.x, self.cmd.viewangles.y, 0.);
+
+        if self.angles.y > 180. {
+            self.angles.y -= 360.;
+        }
+    }
+
+    fn reduce_timers(&mut self) {
+        if self.state.duck_time > 0. {
+            self.state.duck_time = (self.state.duck_time - self.frametime() * 1000.).max(0.);
+        }
+    }
+
+    /// Same as `PM_PlayerTrace`, against the world and solid brush entities.
+    fn trace(&self, hull: HullType, start: Vec3, end: Vec3) -> Trace {
+        let tr = self.bsp.trace_world_and_models(
+            &self.world.solid_entities,
+            hull,
+            to_bsp_vec(start),
+            to_bsp_vec(end),
+        );
+
+        Trace {
+            all_solid: tr.all_solid,
+            start_solid: tr.start_solid,
+            fraction: tr.fraction,
+            end_pos: from_bsp_vec(tr.end_pos),
+            normal: from_bsp_vec(tr.plane.normal),
+        }
+    }
+
+    fn player_trace(&self, start: Vec3, end: Vec3) -> Trace {
+        self.trace(self.state.hull(), start, end)
+    }
+
+    /// Whether the player fits at `origin` with `hull`, same as `PM_TestPlayerPosition`.
+    fn fits(&self, hull: HullType, origin: Vec3) -> bool {
+        !self.trace(hull, origin, origin).start_solid
+    }
+
+    /// Contents at `point`, same as `PM_PointContents`.
+    ///
+    /// Currents in the world count as water. Outside of solid, brush entities with contents like `func_water` win over the world.
+    fn point_contents(&self, point: Vec3) -> LeafContent {
+        let head_node = self
+            .bsp
+            .models
+            .first()
+            .map(|model| model.head_nodes[0])
+            .unwrap_or(0);
+
+        let contents = match self.bsp.trace_point(head_node, to_bsp_vec(point)) {
+            LeafContent::ContentsCurrent0
+            | LeafContent::ContentsCurrent90
+            | LeafContent::ContentsCurrent180
+            | LeafContent::ContentsCurrent270
+            | LeafContent::ContentsCurrentUp
+            | LeafContent::ContentsCurrentDown => LeafContent::ContentsWater,
+            contents => contents,
+        };
+
+        if contents == LeafContent::ContentsSolid {
+            return contents;
+        }
+
+        self.world
+            .contents_entities
+            .iter()
+            .find(|(model_index, origin, _)| {
+                self.bsp.models.get(*model_index).is_some_and(|model| {
+                    self.bsp
+                        .trace_point(model.head_nodes[0], to_bsp_vec(point) - *origin)
+                        != LeafContent::ContentsEmpty
+                })
+            })
+            .map(|(_, _, contents)| *contents)
+            .unwrap_or(contents)
+    }
+
+    fn in_water(&self) -> bool {
+        self.state.water_level > 1
+    }
+
+    /// Finds the ground and water level, same as `PM_CatagorizePosition`.
+    fn categorize_position(&mut self) {
+        self.check_water();
+
+        if self.state.velocity.z > GROUND_MAX_UP_SPEED {
+            self.state.on_ground = false;
+
+            return;
+        }
+
+        let origin = self.state.origin;
+        let tr = self.player_trace(origin, origin - Vec3::Z * GROUND_TRACE_DISTANCE);
+
+        self.state.on_ground = tr.normal.z >= GROUND_MIN_NORMAL_Z;
+
+        if self.state.on_ground {
+            self.state.water_jump_time = 0.;
+
+            // sticks to the ground
+            if self.state.water_level < 2 && !tr.start_solid && !tr.all_solid {
+                self.state.origin = tr.end_pos;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        IN_DUCK,
+        get_ghost::{sample_frame, sample_ghost},
+    };
+
+    const FRAMETIME: f32 = 0.01;
+    // standing on the floor of the test map
+    const FLOOR_ORIGIN_Z: f32 = -67.96875;
+
+    fn normal() -> Bsp {
+        Bsp::from_bytes(include_bytes!("../../../bsp/src/tests/normal.bsp")).unwrap()
+    }
+
+    fn cmd(yaw: f32, forwardmove: f32, buttons: u32) -> UserCmd {
+        UserCmd {
+            frametime: FRAMETIME,
+            viewangles: Vec3::new(0., yaw, 0.),
+            forwardmove,
+            buttons,
+            ..Default::default()
+        }
+    }
+
+    fn run(bsp: &Bsp, state: &mut PlayerState, cmd: UserCmd, frames: usize) {
+        let player_move = PlayerMove::new(bsp, MoveVars::default());
+
+        (0..frames).for_each(|_| player_move.player_move(bsp, state, &cmd));
+    }
+
+    fn standing(bsp: &Bsp, origin: Vec3) -> PlayerState {
+        let mut state = PlayerState::new(origin);
+
+        run(bsp, &mut state, cmd(0., 0., 0), 100);
+        assert!(state.on_ground);
+
+        state
+    }
+
+    #[test]
+    fn falls_onto_floor() {
+        let bsp = normal();
+        let mut state = PlayerState::new(Vec3::new(-40., 0., -40.));
+
+        run(&bsp, &mut state, cmd(0., 0., 0), 10);
+
+        // gravity is split around the move so the fall is exact
+        let time = 10. * FRAMETIME;
+
+        assert!(!state.on_ground);
+        assert!((state.origin.z - (-40. - 400. * time * time)).abs() < 0.01);
+        assert!((state.velocity.z + 800. * time).abs() < 0.01);
+
+        run(&bsp, &mut state, cmd(0., 0., 0), 100);
+
+        assert!(state.on_ground);
+        assert_eq!(state.origin, Vec3::new(-40., 0., FLOOR_ORIGIN_Z));
+        assert_eq!(state.velocity, Vec3::ZERO);
+    }
+
+    #[test]
+    fn walks_into_wall() {
+        let bsp = normal();
+        let mut state = standing(&bsp, Vec3::new(-40., 0., -40.));
+
+        // forwardmove is capped by maxspeed
+        run(&bsp, &mut state, cmd(0., 400., 0), 10);
+
+        let speed = state.velocity.length();
+
+        assert!(speed > 0. && speed <= 320.);
+        assert_eq!(state.velocity.y, 0.);
+
+        run(&bsp, &mut state, cmd(0., 400., 0), 100);
+
+        // the wall is at x = 56
+        assert!(state.on_ground);
+        assert!((state.origin.x - (56. - 16.)).abs() < 0.1);
+        assert_eq!(state.velocity.x, 0.);
+        assert_eq!(state.origin.z, FLOOR_ORIGIN_Z);
+
+        // friction stops it
+        state.velocity = Vec3::new(-300., 0., 0.);
+        run(&bsp, &mut state, cmd(0., 0., 0), 100);
+
+        assert_eq!(state.velocity, Vec3::ZERO);
+    }
+
+    #[test]
+    fn walks_up_stairs() {
+        let bsp = Bsp::from_bytes(include_bytes!("../../../bsp/src/tests/datacore.bsp")).unwrap();
+        let start = standing(&bsp, Vec3::new(-2660., 2695., 980.));
+
+        // 8 unit steps going to -y
+        let mut state = start;
+        run(&bsp, &mut state, cmd(270., 400., 0), 100);
+
+        assert!(state.on_ground);
+        assert_eq!(state.origin.z, start.origin.z + 80.);
+        assert_eq!(state.velocity.length(), 320.);
+
+        // can't go up without stepping
+        let player_move = PlayerMove::new(
+            &bsp,
+            MoveVars {
+                stepsize: 0.,
+                ..Default::default()
+            },
+        );
+        let mut state = start;
+
+        (0..100).for_each(|_| player_move.player_move(&bsp, &mut state, &cmd(270., 400., 0)));
+
+        assert_eq!(state.origin.z, start.origin.z);
+        assert_eq!(state.velocity, Vec3::ZERO);
+    }
+
+    #[test]
+    fn ducks_and_stands() {
+        let bsp = normal();
+        let mut state = standing(&bsp, Vec3::new(-40., 0., -40.));
+
+        // takes 0.4 seconds
+        run(&bsp, &mut state, cmd(0., 0., IN_DUCK), 20);
+
+        assert!(state.in_duck && !state.ducking);
+        assert!(state.view_offset_z < VEC_VIEW);
+
+        run(&bsp, &mut state, cmd(0., 0., IN_DUCK), 30);
+
+        assert!(state.ducking);
+        assert_eq!(state.hull(), HullType::Duck);
+        assert_eq!(state.view_offset_z, VEC_DUCK_VIEW);
+        assert_eq!(state.origin.z, FLOOR_ORIGIN_Z - DUCK_HULL_OFFSET);
+
+        // slower when ducked
+        run(&bsp, &mut state, cmd(0., 400., IN_DUCK), 20);
+
+        assert!(state.velocity.x > 0. && state.velocity.x <= 320. * 0.333 + 0.01);
+
+        run(&bsp, &mut state, cmd(0., 0., 0), 100);
+
+        assert!(!state.ducking);
+        assert_eq!(state.view_offset_z, VEC_VIEW);
+        assert_eq!(state.origin.z, FLOOR_ORIGIN_Z);
+    }
+
+    #[test]
+    fn jump_once_while_holding() {
+        // the test map has a low ceiling
+        let bsp = Bsp::from_bytes(include_bytes!("../../../bsp/src/tests/datacore.bsp")).unwrap();
+        let mut state = standing(&bsp, Vec3::new(-3576., 1576., 824.));
+        let start_z = state.origin.z;
+        let player_move = PlayerMove::new(&bsp, MoveVars::default());
+
+        let mut max_z = state.origin.z;
+        let mut jumps = 0;
+
+        (0..200).for_each(|_| {
+            let was_on_ground = state.on_ground;
+
+            player_move.player_move(&bsp, &mut state, &cmd(0., 0., IN_JUMP));
+
+            if was_on_ground && !state.on_ground {
+                jumps += 1;
+            }
+
+            max_z = max_z.max(state.origin.z);
+        });
+
+        assert_eq!(jumps, 1);
+        assert!(state.on_ground);
+        assert_eq!(state.origin.z, start_z);
+        // a jump is 45 units high
+        assert!((max_z - start_z - 45.).abs() < 0.5);
+    }
+
+    #[test]
+    fn climbs_ladder() {
+        let bsp = Bsp::from_bytes(include_bytes!("../../../bsp/src/tests/datacore.bsp")).unwrap();
+
+        // in front of the ladder on x = -1860, looking at it
+        let mut state = standing(&bsp, Vec3::new(-1890., 3204., 930.));
+        let start_z = state.origin.z;
+
+        run(&bsp, &mut state, cmd(0., 400., IN_FORWARD), 50);
+
+        assert!(state.on_ladder);
+        assert!(state.origin.z > start_z + 50.);
+        assert!(state.velocity.z > 0. && state.velocity.z <= 200.);
+
+        // jumping pushes away from it
+        run(&bsp, &mut state, cmd(0., 0., IN_JUMP), 1);
+
+        assert!(state.velocity.x < -200.);
+    }
+
+    #[test]
+    fn replays_worked_out_ghost() {
+        let bsp = normal();
+        let start = standing(&bsp, Vec3::new(-40., 0., -40.));
+
+        // path worked out by hand from pm_shared.c, not from the simulation
+        let mut origin = start.origin;
+        let mut speed = 0f32;
+        let mut frames = vec![sample_frame(origin, 0., Some(FRAMETIME), Some(0))];
+
+        // running forward, friction then acceleration
+        (0..15).for_each(|_| {
+            if speed >= 0.1 {
+                speed = (speed - speed.max(100.) * 4. * FRAMETIME).max(0.);
+            }
+
+            speed += (10. * FRAMETIME * 320.).min(320. - speed);
+            origin.x += speed * FRAMETIME;
+
+            frames.push(sample_frame(origin, 0., Some(FRAMETIME), Some(IN_FORWARD)));
+        });
+
+        // jumping, half of the gravity before and after moving and too fast to air accelerate forward
+        let mut velocity_z = 268.32816 - 800. * 0.5 * FRAMETIME;
+
+        (0..5).for_each(|_| {
+            origin += Vec3::new(speed, 0., velocity_z) * FRAMETIME;
+            velocity_z -= 800. * FRAMETIME;
+
+            frames.push(sample_frame(
+                origin,
+                0.,
+                Some(FRAMETIME),
+                Some(IN_FORWARD | IN_JUMP),
+            ));
+        });
+
+        let ghost = sample_ghost(frames);
+
+        let states = simulate_ghost(&ghost, &bsp, MoveVars::default(), start);
+
+        assert_eq!(states.len(), ghost.frames().len());
+
+        states
+            .iter()
+            .zip(ghost.frames().iter())
+            .enumerate()
+            .for_each(|(idx, (state, frame))| {
+                assert!(
+                    state.origin.distance(frame.origin) < 0.05,
+                    "frame {idx}: {} is not {}",
+                    state.origin,
+                    frame.origin
+                );
+            });
+
+        assert!(!states.last().unwrap().on_ground);
+    }
+}
@@ -0,0 +1,397 @@
+//! Walking, air movement and sliding along walls.
+use glam::{Vec3, Vec3Swizzles};
+
+use crate::IN_JUMP;
+
+use super::{GROUND_MIN_NORMAL_Z, Pmove, hull_size};
+
+// velocity this close to 0 is 0
+const STOP_EPSILON: f32 = 0.1;
+const MAX_BUMPS: usize = 4;
+const MAX_CLIP_PLANES: usize = 5;
+// wish speed in the air is capped at this, which is what makes air strafing work
+const AIR_WISH_SPEED_CAP: f32 = 30.;
+// traced down from the feet in front of the player to find edges
+const EDGE_TRACE_AHEAD: f32 = 16.;
+const EDGE_TRACE_DEPTH: f32 = 34.;
+// sqrt(2 * 800 * 45), the same no matter the gravity
+const JUMP_SPEED: f32 = 268.32816;
+const BUNNYJUMP_MAX_SPEED_FACTOR: f32 = 1.7;
+
+/// Removes the part of `velocity` going into the plane, same as `PM_ClipVelocity`.
+fn clip_velocity(velocity: Vec3, normal: Vec3, overbounce: f32) -> Vec3 {
+    let backoff = velocity.dot(normal) * overbounce;
+    let res = velocity - normal * backoff;
+
+    Vec3::select(res.abs().cmplt(Vec3::splat(STOP_EPSILON)), Vec3::ZERO, res)
+}
+
+impl Pmove<'_> {
+    /// Direction and speed from the command on the ground plane, capped by `maxspeed`.
+    fn wish_velocity(&self) -> (Vec3, f32) {
+        let forward = self.forward.with_z(0.).normalize_or_zero();
+        let right = self.right.with_z(0.).normalize_or_zero();
+
+        let wishvel = (forward * self.cmd.forwardmove + right * self.cmd.sidemove).with_z(0.);
+        let wishspeed = wishvel.length();
+
+        (
+            wishvel.normalize_or_zero(),
+            wishspeed.min(self.vars.maxspeed),
+        )
+    }
+
+    /// Same as `PM_Accelerate`.
+    pub(super) fn accelerate(&mut self, wishdir: Vec3, wishspeed: f32, accel: f32) {
+        if self.state.water_jump_time > 0. {
+            return;
+        }
+
+        let addspeed = wishspeed - self.state.velocity.dot(wishdir);
+
+        if addspeed <= 0. {
+            return;
+        }
+
+        let accelspeed = (accel * self.frametime() * wishspeed).min(addspeed);
+
+        self.state.velocity += wishdir * accelspeed;
+    }
+
+    /// Same as `PM_AirAccelerate`.
+    fn air_accelerate(&mut self, wishdir: Vec3, wishspeed: f32, accel: f32) {
+        if self.state.water_jump_time > 0. {
+            return;
+        }
+
+        let addspeed = wishspeed.min(AIR_WISH_SPEED_CAP) - self.state.velocity.dot(wishdir);
+
+        if addspeed <= 0. {
+            return;
+        }
+
+        let accelspeed = (accel * wishspeed * self.frametime()).min(addspeed);
+
+        self.state.velocity += wishdir * accelspeed;
+    }
+
+    /// Slows down on the ground, twice as much near an edge. Same as `PM_Friction`.
+    pub(super) fn friction(&mut self) {
+        if self.state.water_jump_time > 0. {
+            return;
+        }
+
+        let velocity = self.state.velocity;
+        let speed = velocity.length();
+
+        if speed < 0.1 {
+            return;
+        }
+
+        let mut drop = 0.;
+
+        if self.state.on_ground {
+            let (mins, _) = hull_size(self.state.hull());
+            let origin = self.state.origin;
+
+            let start =
+                (origin.xy() + velocity.xy() / speed * EDGE_TRACE_AHEAD).extend(origin.z + mins.z);
+            let stop = start - Vec3::Z * EDGE_TRACE_DEPTH;
+
+            let friction = if self.player_trace(start, stop).fraction == 1. {
+                self.vars.friction * self.vars.edgefriction
+            } else {
+                self.vars.friction
+            };
+
+            let control = speed.max(self.vars.stopspeed);
+
+            drop += control * friction * self.frametime();
+        }
+
+        let newspeed = (speed - drop).max(0.) / speed;
+
+        self.state.velocity *= newspeed;
+    }
+
+    /// Same as `PM_AddCorrectGravity`, half of the gravity before moving.
+    pub(super) fn add_correct_gravity(&mut self) {
+        if self.state.water_jump_time > 0. {
+            return;
+        }
+
+        self.state.velocity.z -= self.vars.gravity * 0.5 * self.frametime();
+
+        self.check_velocity();
+    }
+
+    /// Same as `PM_FixupGravityVelocity`, the other half of the gravity after moving.
+    pub(super) fn fixup_gravity_velocity(&mut self) {
+        if self.state.water_jump_time > 0. {
+            return;
+        }
+
+        self.state.velocity.z -= self.vars.gravity * self.frametime() * 0.5;
+
+        self.check_velocity();
+    }
+
+    /// Caps every axis of the velocity at `maxvelocity`, same as `PM_CheckVelocity`.
+    pub(super) fn check_velocity(&mut self) {
+        let maxvelocity = self.vars.maxvelocity;
+
+        self.state.velocity = Vec3::select(
+            self.state.velocity.is_nan_mask(),
+            Vec3::ZERO,
+            self.state.velocity,
+        )
+        .clamp(Vec3::splat(-maxvelocity), Vec3::splat(maxvelocity));
+    }
+
+    /// Same as `PM_Jump`.
+    pub(super) fn jump(&mut self) {
+        if self.state.water_jump_time > 0. {
+            self.state.water_jump_time =
+                (self.state.water_jump_time - self.frametime() * 1000.).max(0.);
+
+            return;
+        }
+
+        // swimming up
+        if self.state.water_level >= 2 {
+            self.state.on_ground = false;
+            self.state.velocity.z = match self.state.water_type {
+                bsp::LeafContent::ContentsWater => 100.,
+                bsp::LeafContent::ContentsSlime => 80.,
+                _ => 50.,
+            };
+
+            return;
+        }
+
+        // can't jump again until jump is let go
+        if !self.state.on_ground {
+            self.state.old_buttons |= IN_JUMP;
+
+            return;
+        }
+
+        if self.state.old_buttons & IN_JUMP != 0 {
+            return;
+        }
+
+        self.state.on_ground = false;
+
+        if self.vars.bunnyhop_cap {
+            self.prevent_mega_bunny_jumping();
+        }
+
+        self.state.velocity.z = JUMP_SPEED;
+
+        self.fixup_gravity_velocity();
+
+        self.state.old_buttons |= IN_JUMP;
+    }
+
+    /// Same as `PM_PreventMegaBunnyJumping`.
+    fn prevent_mega_bunny_jumping(&mut self) {
+        let max_scaled_speed = BUNNYJUMP_MAX_SPEED_FACTOR * self.vars.maxspeed;
+
+        if max_scaled_speed <= 0. {
+            return;
+        }
+
+        let speed = self.state.velocity.length();
+
+        if speed <= max_scaled_speed {
+            return;
+        }
+
+        self.state.velocity *= max_scaled_speed / speed * 0.65;
+    }
+
+    /// Moves along the ground and up steps, same as `PM_WalkMove`.
+    pub(super) fn walk_move(&mut self) {
+        let (wishdir, wishspeed) = self.wish_velocity();
+
+        self.state.velocity.z = 0.;
+        self.accelerate(wishdir, wishspeed, self.vars.accelerate);
+        self.state.velocity.z = 0.;
+
+        if self.state.velocity.length() < 1. {
+            self.state.velocity = Vec3::ZERO;
+
+            return;
+        }
+
+        let original = self.state.origin;
+        let original_velocity = self.state.velocity;
+
+        let dest = original + (original_velocity * self.frametime()).with_z(0.);
+        let tr = self.player_trace(original, dest);
+
+        if tr.fraction == 1. {
+            self.state.origin = tr.end_pos;
+
+            return;
+        }
+
+        // slide along the ground first
+        self.fly_move();
+
+        let down = self.state.origin;
+        let down_velocity = self.state.velocity;
+
+        // then try stepping up, moving and coming back down
+        self.state.origin = original;
+        self.state.velocity = original_velocity;
+
+        let step = Vec3::Z * self.vars.stepsize;
+        let tr = self.player_trace(original, original + step);
+
+        if !tr.start_solid && !tr.all_solid {
+            self.state.origin = tr.end_pos;
+        }
+
+        self.fly_move();
+
+        let tr = self.player_trace(self.state.origin, self.state.origin - step);
+
+        // stepped onto something too steep
+        let use_down = if tr.normal.z < GROUND_MIN_NORMAL_Z {
+            true
+        } else {
+            if !tr.start_solid && !tr.all_solid {
+                self.state.origin = tr.end_pos;
+            }
+
+            let up = self.state.origin;
+
+            down.xy().distance_squared(original.xy()) > up.xy().distance_squared(original.xy())
+        };
+
+        if use_down {
+            self.state.origin = down;
+            self.state.velocity = down_velocity;
+        } else {
+            self.state.velocity.z = down_velocity.z;
+        }
+    }
+
+    /// Same as `PM_AirMove`.
+    pub(super) fn air_move(&mut self) {
+        let (wishdir, wishspeed) = self.wish_velocity();
+
+        self.air_accelerate(wishdir, wishspeed, self.vars.airaccelerate);
+
+        self.fly_move();
+    }
+
+    /// Moves along the velocity for the frame, sliding along up to 4 surfaces. Same as `PM_FlyMove`.
+    pub(super) fn fly_move(&mut self) {
+        let primal_velocity = self.state.velocity;
+        let mut original_velocity = primal_velocity;
+        let mut planes: Vec<Vec3> = Vec::with_capacity(MAX_CLIP_PLANES);
+        let mut all_fraction = 0.;
+        let mut time_left = self.frametime();
+
+        for _ in 0..MAX_BUMPS {
+            if self.state.velocity == Vec3::ZERO {
+                break;
+            }
+
+            let origin = self.state.origin;
+            let tr = self.player_trace(origin, origin + self.state.velocity * time_left);
+
+            all_fraction += tr.fraction;
+
+            // stuck in something
+            if tr.all_solid {
+                self.state.velocity = Vec3::ZERO;
+
+                return;
+            }
+
+            if tr.fraction > 0. {
+                self.state.origin = tr.end_pos;
+                original_velocity = self.state.velocity;
+                planes.clear();
+            }
+
+            if tr.fraction == 1. {
+                break;
+            }
+
+            time_left -= time_left * tr.fraction;
+
+            if planes.len() >= MAX_CLIP_PLANES {
+                self.state.velocity = Vec3::ZERO;
+
+                break;
+            }
+
+            planes.push(tr.normal);
+
+            // walking players in the air only clip, floors keep the clip for the next planes
+            if !self.flying && !self.state.on_ground {
+                let mut new_velocity = original_velocity;
+
+                planes.iter().for_each(|plane| {
+                    new_velocity = clip_velocity(original_velocity, *plane, 1.);
+
+                    if plane.z > GROUND_MIN_NORMAL_Z {
+                        original_velocity = new_velocity;
+                    }
+                });
+
+                self.state.velocity = new_velocity;
+                original_velocity = new_velocity;
+
+                continue;
+            }
+
+            // a velocity along one plane that doesn't go into the others
+            let mut clipped = None;
+
+            for (idx, plane) in planes.iter().enumerate() {
+                let velocity = clip_velocity(original_velocity, *plane, 1.);
+
+                self.state.velocity = velocity;
+
+                if planes
+                    .iter()
+                    .enumerate()
+                    .all(|(other_idx, other)| other_idx == idx || velocity.dot(*other) >= 0.)
+                {
+                    clipped = Some(velocity);
+
+                    break;
+                }
+            }
+
+            if clipped.is_none() {
+                // along the crease of two planes
+                if planes.len() != 2 {
+                    self.state.velocity = Vec3::ZERO;
+
+                    break;
+                }
+
+                let dir = planes[0].cross(planes[1]);
+
+                self.state.velocity = dir * dir.dot(self.state.velocity);
+            }
+
+            // don't go back and forth in corners
+            if self.state.velocity.dot(primal_velocity) <= 0. {
+                self.state.velocity = Vec3::ZERO;
+
+                break;
+            }
+        }
+
+        if all_fraction == 0. {
+            self.state.velocity = Vec3::ZERO;
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! Swimming and jumping out of water.
+use bsp::{HullType, LeafContent};
+use glam::Vec3;
+
+use crate::IN_JUMP;
+
+use super::{Pmove, hull_size};
+
+// sinking when not pressing anything
+const WATER_SINK_SPEED: f32 = 60.;
+const WATER_WISH_SPEED_SCALE: f32 = 0.8;
+// looks for a ledge this high and this far in front
+const WATER_JUMP_HEIGHT: f32 = 8.;
+const WATER_JUMP_DISTANCE: f32 = 24.;
+const WATER_JUMP_UP_SPEED: f32 = 225.;
+const WATER_JUMP_PUSH_SPEED: f32 = 50.;
+// milliseconds
+const WATER_JUMP_TIME: f32 = 2000.;
+const WATER_JUMP_MAX_TIME: f32 = 10000.;
+// falling faster than this into water doesn't jump back out
+const WATER_JUMP_MAX_FALL_SPEED: f32 = 180.;
+
+/// Water, slime and lava, same as the range of `PM_CheckWater`.
+fn is_water(contents: LeafContent) -> bool {
+    let contents = contents as i32;
+
+    contents <= LeafContent::ContentsWater as i32
+        && contents > LeafContent::ContentsTranslucent as i32
+}
+
+impl Pmove<'_> {
+    /// Finds how deep in water the player is, same as `PM_CheckWater`.
+    ///
+    /// Feet, waist and eyes are checked.
+    pub(super) fn check_water(&mut self) -> bool {
+        let (mins, maxs) = hull_size(self.state.hull());
+        let origin = self.state.origin;
+
+        let mut point = origin + (mins + maxs) * 0.5;
+        point.z = origin.z + mins.z + 1.;
+
+        self.state.water_level = 0;
+        self.state.water_type = LeafContent::ContentsEmpty;
+
+        let contents = self.point_contents(point);
+
+        if is_water(contents) {
+            self.state.water_type = contents;
+            self.state.water_level = 1;
+
+            point.z = origin.z + (mins.z + maxs.z) * 0.5;
+
+            if is_water(self.point_contents(point)) {
+                self.state.water_level = 2;
+
+                point.z = origin.z + self.state.view_offset_z;
+
+                if is_water(self.point_contents(point)) {
+                    self.state.water_level = 3;
+                }
+            }
+        }
+
+        self.in_water()
+    }
+
+    /// Same as `PM_WaterMove`.
+    ///
+    /// Swims where the player looks, with friction and 80% of the speed.
+    pub(super) fn water_move(&mut self) {
+        let mut wishvel = self.forward * self.cmd.forwardmove + self.right * self.cmd.sidemove;
+
+        if self.cmd.forwardmove == 0. && self.cmd.sidemove == 0. && self.cmd.upmove == 0. {
+            wishvel.z -= WATER_SINK_SPEED;
+        } else {
+            wishvel.z += self.cmd.upmove;
+        }
+
+        let wishspeed = wishvel.length().min(self.vars.maxspeed) * WATER_WISH_SPEED_SCALE;
+
+        let speed = self.state.velocity.length();
+        let newspeed = if speed != 0. {
+            let newspeed = (speed - self.frametime() * speed * self.vars.friction).max(0.);
+
+            self.state.velocity *= newspeed / speed;
+
+            newspeed
+        } else {
+            0.
+        };
+
+        if wishspeed < 0.1 {
+            return;
+        }
+
+        let addspeed = wishspeed - newspeed;
+
+        if addspeed > 0. {
+            let accelspeed = (self.vars.accelerate * wishspeed * self.frametime()).min(addspeed);
+
+            self.state.velocity += wishvel.normalize_or_zero() * accelspeed;
+        }
+
+        // like a step, from above down to where it would go
+        let dest = self.state.origin + self.state.velocity * self.frametime();
+        let start = dest + Vec3::Z * (self.vars.stepsize + 1.);
+        let tr = self.player_trace(start, dest);
+
+        if !tr.start_solid && !tr.all_solid {
+            self.state.origin = tr.end_pos;
+
+            return;
+        }
+
+        self.fly_move();
+    }
+
+    /// Starts jumping out of water when facing a ledge, same as `PM_CheckWaterJump`.
+    pub(super) fn check_water_jump(&mut self) {
+        if self.state.water_jump_time > 0. {
+            return;
+        }
+
+        if self.state.velocity.z < -WATER_JUMP_MAX_FALL_SPEED {
+            return;
+        }
+
+        let flat_velocity = self.state.velocity.with_z(0.);
+        let flat_forward = self.forward.with_z(0.).normalize_or_zero();
+
+        // backing into water from steps
+        if flat_velocity.length() != 0. && flat_velocity.normalize().dot(flat_forward) < 0. {
+            return;
+        }
+
+        let (_, maxs) = hull_size(self.state.hull());
+
+        let mut start = self.state.origin + Vec3::Z * WATER_JUMP_HEIGHT;
+        let tr = self.trace(
+            HullType::Point,
+            start,
+            start + flat_forward * WATER_JUMP_DISTANCE,
+        );
+
+        // a wall in front
+        if tr.fraction < 1. && tr.normal.z.abs() < 0.1 {
+            start.z += maxs.z - WATER_JUMP_HEIGHT;
+
+            self.state.water_jump_velocity = tr.normal * -WATER_JUMP_PUSH_SPEED;
+
+            // with nothing above it
+            let tr = self.trace(
+                HullType::Point,
+                start,
+                start + flat_forward * WATER_JUMP_DISTANCE,
+            );
+
+            if tr.fraction == 1. {
+                self.state.water_jump_time = WATER_JUMP_TIME;
+                self.state.velocity.z = WATER_JUMP_UP_SPEED;
+                self.state.old_buttons |= IN_JUMP;
+            }
+        }
+    }
+
+    /// Keeps pushing onto the ledge while jumping out of water, same as `PM_WaterJump`.
+    pub(super) fn water_jump(&mut self) {
+        self.state.water_jump_time = self.state.water_jump_time.min(WATER_JUMP_MAX_TIME);
+
+        if self.state.water_jump_time == 0. {
+            return;
+        }
+
+        self.state.water_jump_time -= self.frametime() * 1000.;
+
+        if self.state.water_jump_time < 0. || self.state.water_level == 0 {
+            self.state.water_jump_time = 0.;
+        }
+
+        self.state.velocity.x = self.state.water_jump_velocity.x;
+        self.state.velocity.y = self.state.water_jump_velocity.y;
+    }
+}
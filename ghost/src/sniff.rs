@@ -0,0 +1,257 @@
+//! Guesses ghost format from file content so that renamed files still work.
+use crate::GhostBlobType;
+
+/// Confidence needed for [`GhostBlobType::sniff`] to be used over the file name.
+pub const SNIFF_MIN_CONFIDENCE: f32 = 0.5;
+
+// text formats are only checked at the start of the file
+const SNIFF_TEXT_LENGTH: usize = 4096;
+
+const DEMO_MAGIC: &[u8] = b"HLDEMO\0";
+const KDR_MAGIC: &[u8] = b"KDRG";
+
+// time, origin, angles, buttons
+const SRHLKZ_FRAME_SIZE: usize = 30;
+// how many frames to check, fewer than this is too short to tell
+const SRHLKZ_SNIFF_FRAMES: usize = 16;
+// longest time between two frames
+const SRHLKZ_MAX_FRAMETIME: f32 = 1.;
+// extended map limit
+const MAX_COORD: f32 = 32768.;
+
+impl GhostBlobType {
+    /// Guesses the format from file content, with a confidence from 0 to 1.
+    ///
+    /// Magic bytes are certain. JSON and simen are checked by their layout at the start of the file.
+    /// SourceRuns HLKZ has no header so it is only a guess from the frame values.
+    pub fn sniff(data: &[u8]) -> Option<(Self, f32)> {
+        if data.starts_with(DEMO_MAGIC) {
+            return Some((Self::Demo, 1.));
+        }
+
+        if data.starts_with(KDR_MAGIC) {
+            return Some((Self::Kdr, 1.));
+        }
+
+        let text = sniff_text(data);
+
+        text.and_then(sniff_json)
+            .or_else(|| text.and_then(sniff_simen))
+            .or_else(|| sniff_srhlkz(data))
+    }
+}
+
+/// Start of the file as text, if it is text.
+pub(crate) fn sniff_text(data: &[u8]) -> Option<&str> {
+    let data = &data[..data.len().min(SNIFF_TEXT_LENGTH)];
+
+    match str::from_utf8(data) {
+        Ok(text) => Some(text),
+        // cut in the middle of a character
+        Err(err) if err.error_len().is_none() => str::from_utf8(&data[..err.valid_up_to()]).ok(),
+        Err(_) => None,
+    }
+}
+
+fn sniff_json(text: &str) -> Option<(GhostBlobType, f32)> {
+    if !text.trim_start().starts_with('{') {
+        return None;
+    }
+
+    let has_keys = |keys: &[&str]| {
+        keys.iter()
+            .all(|key| text.contains(format!("\"{}\"", key).as_str()))
+    };
+
+    // surf gateway puts player info before frames
+    if has_keys(&["authid", "startvel"]) {
+        return Some((GhostBlobType::SurfGateway, 0.9));
+    }
+
+    if has_keys(&["frames", "position", "orientation", "length"]) {
+        return Some((GhostBlobType::RomanianJumpers, 0.9));
+    }
+
+    None
+}
+
+fn sniff_simen(text: &str) -> Option<(GhostBlobType, f32)> {
+    let mut lines: Vec<&str> = text.split('\n').collect();
+
+    // last line can be cut off
+    lines.pop();
+
+    // header: time, name, steamid, date, location, ??
+    let header = lines.get(..6)?;
+    let frames: Vec<&str> = lines[6..]
+        .iter()
+        .copied()
+        .filter(|line| !line.trim().is_empty())
+        .take(4)
+        .collect();
+
+    if frames.is_empty() {
+        return None;
+    }
+
+    // pitch yaw origin velocity buttons moves
+    let is_frame = |line: &str| {
+        let values: Vec<&str> = line.split_whitespace().collect();
+
+        values.len() == 11 && values.iter().all(|value| value.parse::<f32>().is_ok())
+    };
+
+    if !frames.iter().all(|line| is_frame(line)) {
+        return None;
+    }
+
+    let has_time = header[0].trim().parse::<f32>().is_ok();
+
+    Some((GhostBlobType::Simen, if has_time { 0.9 } else { 0.7 }))
+}
+
+fn sniff_srhlkz(data: &[u8]) -> Option<(GhostBlobType, f32)> {
+    if data.len() < SRHLKZ_FRAME_SIZE * SRHLKZ_SNIFF_FRAMES
+        || !data.len().is_multiple_of(SRHLKZ_FRAME_SIZE)
+    {
+        return None;
+    }
+
+    let mut first_time = None;
+    let mut prev_time = None;
+
+    for frame in data
+        .chunks_exact(SRHLKZ_FRAME_SIZE)
+        .take(SRHLKZ_SNIFF_FRAMES)
+    {
+        let value = |idx: usize| f32::from_le_bytes(std::array::from_fn(|i| frame[idx * 4 + i]));
+
+        let time = value(0);
+
+        if !time.is_finite() {
+            return None;
+        }
+
+        // time is accumulated with frametimes in between
+        if let Some(prev_time) = prev_time
+            && !(prev_time..=prev_time + SRHLKZ_MAX_FRAMETIME).contains(&time)
+        {
+            return None;
+        }
+
+        first_time.get_or_insert(time);
+        prev_time = Some(time);
+
+        let is_origin = |x: f32| x.is_finite() && x.abs() <= MAX_COORD;
+        let is_angle = |x: f32| x.is_finite() && x.abs() <= 360.;
+
+        if !(1..4).all(|idx| is_origin(value(idx))) || !(4..7).all(|idx| is_angle(value(idx))) {
+            return None;
+        }
+    }
+
+    // zeroed data has everything in range but time doesn't move
+    if prev_time <= first_time {
+        return None;
+    }
+
+    Some((GhostBlobType::SRHLKZ, 0.6))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{GhostInfo, get_ghost::sample_frames, write_ghost_to_blob};
+
+    fn sniff_written(blob_type: GhostBlobType) -> Option<GhostBlobType> {
+        let ghost = GhostInfo::new(
+            "kz_sniff".to_string(),
+            "kz_sniff".to_string(),
+            "cstrike".to_string(),
+            sample_frames(200, Some(0.01)),
+        );
+
+        let bytes = write_ghost_to_blob(&ghost, blob_type)
+            .unwrap()
+            .into_bytes()
+            .unwrap();
+
+        GhostBlobType::sniff(&bytes)
+            .filter(|(_, confidence)| *confidence >= SNIFF_MIN_CONFIDENCE)
+            .map(|(blob_type, _)| blob_type)
+    }
+
+    #[test]
+    fn sniff_written_formats() {
+        assert!(matches!(
+            sniff_written(GhostBlobType::Simen),
+            Some(GhostBlobType::Simen)
+        ));
+        assert!(matches!(
+            sniff_written(GhostBlobType::SurfGateway),
+            Some(GhostBlobType::SurfGateway)
+        ));
+        assert!(matches!(
+            sniff_written(GhostBlobType::RomanianJumpers),
+            Some(GhostBlobType::RomanianJumpers)
+        ));
+        assert!(matches!(
+            sniff_written(GhostBlobType::SRHLKZ),
+            Some(GhostBlobType::SRHLKZ)
+        ));
+        assert!(matches!(
+            sniff_written(GhostBlobType::Kdr),
+            Some(GhostBlobType::Kdr)
+        ));
+    }
+
+    #[test]
+    fn sniff_demo_magic() {
+        assert!(matches!(
+            GhostBlobType::sniff(b"HLDEMO\0\0\x05\0\0\0"),
+            Some((GhostBlobType::Demo, 1.))
+        ));
+    }
+
+    #[test]
+    fn sniff_unknown() {
+        assert!(GhostBlobType::sniff(b"").is_none());
+        assert!(GhostBlobType::sniff(b"{\"hello\": \"world\"}").is_none());
+        assert!(GhostBlobType::sniff(b"not a ghost").is_none());
+    }
+
+    #[test]
+    fn sniff_binary_is_not_srhlkz() {
+        let zeros = vec![0u8; SRHLKZ_FRAME_SIZE * 100];
+
+        assert!(GhostBlobType::sniff(&zeros).is_none());
+
+        // deterministic noise
+        let mut seed = 0x2545_f491_u32;
+        let noise: Vec<u8> = (0..SRHLKZ_FRAME_SIZE * 100)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+
+                seed as u8
+            })
+            .collect();
+
+        assert!(GhostBlobType::sniff(&noise).is_none());
+
+        // a few frames of a real ghost are too short to tell
+        let ghost = GhostInfo::new(
+            "kz_sniff".to_string(),
+            "kz_sniff".to_string(),
+            "cstrike".to_string(),
+            sample_frames(4, Some(0.01)),
+        );
+        let bytes = write_ghost_to_blob(&ghost, GhostBlobType::SRHLKZ)
+            .unwrap()
+            .into_bytes()
+            .unwrap();
+
+        assert!(GhostBlobType::sniff(&bytes).is_none());
+    }
+}
@@ -0,0 +1,386 @@
+//! Run time from the timer buttons and triggers of KZ maps.
+//!
+//! Buttons and triggers are found from their target or name, so maps with their own timer setup are not timed.
+use bsp::{Bsp, Entity};
+use glam::Vec3;
+
+use crate::{
+    GhostInfo, IN_USE,
+    jumpstats::{brush_entity_boxes, player_box},
+    pmove::angle_vectors,
+};
+
+// used by KZ maps and timer plugins
+const START_NAMES: &[&str] = &[
+    "counter_start",
+    "clockstartbutton",
+    "firsttimerelay",
+    "climb_startbutton",
+    "timer_startbutton",
+];
+const STOP_NAMES: &[&str] = &[
+    "counter_off",
+    "clockstopbutton",
+    "clockstop",
+    "lasttimerelay",
+    "climb_endbutton",
+    "timer_stopbutton",
+];
+
+// fire their target when touched, same as pressing a button
+const TRIGGER_CLASSNAMES: &[&str] = &["trigger_multiple", "trigger_once"];
+
+// same as PLAYER_SEARCH_RADIUS, measured from the eyes to the closest point of the button
+const USE_RANGE: f32 = 64.;
+
+#[derive(Debug, Clone)]
+pub struct RunTimer {
+    /// Frame where the start button is pressed.
+    pub start_frame: usize,
+    /// Frame where the stop button is pressed.
+    pub end_frame: usize,
+    /// From the start of the start frame to the start of the end frame.
+    ///
+    /// `None` when the ghost has no frametime.
+    pub time: Option<f32>,
+}
+
+impl RunTimer {
+    /// Frames to keep with [`GhostInfo::trim`] so the ghost is only the run.
+    pub fn frame_range(&self) -> std::ops::Range<usize> {
+        self.start_frame..self.end_frame + 1
+    }
+}
+
+fn is_named(entity: &Entity, key: &str, names: &[&str]) -> bool {
+    entity
+        .get(key)
+        .is_some_and(|name| names.contains(&name.as_str()))
+}
+
+fn is_timer_button(entity: &Entity, names: &[&str]) -> bool {
+    let is_button = entity
+        .get("classname")
+        .is_some_and(|classname| classname == "func_button");
+
+    is_button && (is_named(entity, "target", names) || is_named(entity, "targetname", names))
+}
+
+fn is_timer_trigger(entity: &Entity, names: &[&str]) -> bool {
+    let is_trigger = entity
+        .get("classname")
+        .is_some_and(|classname| TRIGGER_CLASSNAMES.contains(&classname.as_str()));
+
+    is_trigger && is_named(entity, "target", names)
+}
+
+/// Boxes of everything that starts or stops the timer.
+struct TimerEntities {
+    /// Pressed by touching or using.
+    buttons: Vec<(Vec3, Vec3)>,
+    /// Pressed by touching.
+    triggers: Vec<(Vec3, Vec3)>,
+}
+
+impl TimerEntities {
+    fn new(bsp: &Bsp, names: &[&str]) -> Self {
+        Self {
+            buttons: brush_entity_boxes(bsp, |entity| is_timer_button(entity, names)),
+            triggers: brush_entity_boxes(bsp, |entity| is_timer_trigger(entity, names)),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.buttons.is_empty() && self.triggers.is_empty()
+    }
+}
+
+fn distance_to_box(point: Vec3, (mins, maxs): (Vec3, Vec3)) -> f32 {
+    point.clamp(mins, maxs).distance(point)
+}
+
+/// Distance along the ray to where it enters the box, `None` if it misses.
+fn ray_to_box(origin: Vec3, direction: Vec3, (mins, maxs): (Vec3, Vec3)) -> Option<f32> {
+    let inverse = direction.recip();
+    let t0 = (mins - origin) * inverse;
+    let t1 = (maxs - origin) * inverse;
+
+    let near = t0.min(t1).max_element();
+    let far = t0.max(t1).min_element();
+
+    (near <= far && far >= 0.).then_some(near.max(0.))
+}
+
+/// Finds the run between the start and stop buttons or triggers of the map.
+///
+/// Pressing start again restarts the timer, so the run goes from the last start to the first stop after it.
+/// A button is pressed on the first frame of touching it or using it in reach, a trigger on the first frame of touching it.
+/// Without buttons in the ghost, looking at a button in reach counts as using it.
+///
+/// Same as [`crate::jumpstats`], only pass a ghost segment that is on this map.
+pub fn run_timer(ghost: &GhostInfo, bsp: &Bsp) -> Option<RunTimer> {
+    let starts = TimerEntities::new(bsp, START_NAMES);
+    let stops = TimerEntities::new(bsp, STOP_NAMES);
+
+    if starts.is_empty() || stops.is_empty() {
+        return None;
+    }
+
+    let analysis = ghost.analyze();
+
+    let presses = |entities: &TimerEntities, idx: usize| {
+        let frame = &ghost.frames()[idx];
+        let (player_min, player_max) = player_box(frame.origin, analysis.frames[idx].ducking);
+        let eye = frame.origin + Vec3::Z * frame.viewoffset_z;
+        let (forward, _, _) = angle_vectors(frame.viewangles);
+
+        let touching = |&(mins, maxs): &(Vec3, Vec3)| {
+            player_min.cmple(maxs).all() && player_max.cmpge(mins).all()
+        };
+
+        let using = |button: (Vec3, Vec3)| match frame.buttons {
+            Some(buttons) => buttons & IN_USE != 0 && distance_to_box(eye, button) <= USE_RANGE,
+            // being in reach is not enough, the player walks up to buttons that are pressed by touching
+            None => ray_to_box(eye, forward, button).is_some_and(|distance| distance <= USE_RANGE),
+        };
+
+        entities.triggers.iter().any(touching)
+            || entities
+                .buttons
+                .iter()
+                .any(|&button| touching(&button) || using(button))
+    };
+
+    let mut start_frame = None;
+    let mut was_pressing = (false, false);
+
+    for idx in 0..ghost.frames().len() {
+        let pressing = (presses(&starts, idx), presses(&stops, idx));
+
+        if pressing.0 && !was_pressing.0 {
+            start_frame = Some(idx);
+        }
+
+        if pressing.1
+            && !was_pressing.1
+            && let Some(start_frame) = start_frame
+        {
+            let time = ghost
+                .get_frame_time(idx, None)
+                .zip(ghost.get_frame_time(start_frame, None))
+                .map(|(end, start)| end - start);
+
+            return Some(RunTimer {
+                start_frame,
+                end_frame: idx,
+                time,
+            });
+        }
+
+        was_pressing = pressing;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use bsp::Model;
+
+    use super::*;
+    use crate::get_ghost::{sample_frame, sample_ghost};
+
+    const FRAMETIME: f32 = 0.01;
+
+    /// Adds a brush entity with the given bounds to the map.
+    fn add_timer_entity(bsp: &mut Bsp, classname: &str, target: &str, mins: Vec3, maxs: Vec3) {
+        bsp.models.push(Model {
+            mins: bsp::Vec3::from_array(mins.to_array()),
+            maxs: bsp::Vec3::from_array(maxs.to_array()),
+            origin: bsp::Vec3::ZERO,
+            head_nodes: [0; 4],
+            vis_leaves_count: 0,
+            first_face: 0,
+            face_count: 0,
+        });
+
+        let entity = [
+            ("classname", classname.to_string()),
+            ("target", target.to_string()),
+            ("model", format!("*{}", bsp.models.len() - 1)),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect();
+
+        bsp.entities.push(entity);
+    }
+
+    fn add_button(bsp: &mut Bsp, target: &str, mins: Vec3, maxs: Vec3) {
+        add_timer_entity(bsp, "func_button", target, mins, maxs);
+    }
+
+    fn timer_map() -> Bsp {
+        let mut bsp = Bsp::from_bytes(include_bytes!("../../bsp/src/tests/normal.bsp")).unwrap();
+
+        // to the side of the path, only reachable with +use
+        add_button(
+            &mut bsp,
+            "counter_start",
+            Vec3::new(50., 60., -10.),
+            Vec3::new(60., 70., 10.),
+        );
+        // on the path
+        add_button(
+            &mut bsp,
+            "counter_off",
+            Vec3::new(250., -10., -10.),
+            Vec3::new(260., 10., 10.),
+        );
+
+        bsp
+    }
+
+    /// Walks one unit every frame along x.
+    fn walk(buttons: impl Fn(usize) -> Option<u32>) -> GhostInfo {
+        walk_looking(|_| 0., buttons)
+    }
+
+    fn walk_looking(
+        yaw: impl Fn(usize) -> f32,
+        buttons: impl Fn(usize) -> Option<u32>,
+    ) -> GhostInfo {
+        let frames = (0..300)
+            .map(|idx| {
+                sample_frame(
+                    Vec3::new(idx as f32, 0., 0.),
+                    yaw(idx),
+                    Some(FRAMETIME),
+                    buttons(idx),
+                )
+            })
+            .collect();
+
+        sample_ghost(frames)
+    }
+
+    #[test]
+    fn in_reach_without_buttons() {
+        let bsp = timer_map();
+
+        // start button is in reach but never looked at
+        assert!(run_timer(&walk(|_| None), &bsp).is_none());
+    }
+
+    #[test]
+    fn looking_without_buttons() {
+        let bsp = timer_map();
+        // looks at the start button on the way, away from the path otherwise
+        let mut ghost = walk_looking(
+            |idx| if (52..=56).contains(&idx) { 90. } else { -90. },
+            |_| None,
+        );
+
+        let timer = run_timer(&ghost, &bsp).unwrap();
+
+        // view ray hits the start button 60 units away
+        assert_eq!(timer.start_frame, 52);
+        // stop button is touched, not reached from afar
+        assert_eq!(timer.end_frame, 234);
+        assert!((timer.time.unwrap() - 1.82).abs() < 0.001);
+
+        ghost.trim(timer.frame_range());
+
+        assert_eq!(ghost.frames().len(), 183);
+        assert_eq!(ghost.frames()[0].origin.x, 52.);
+        assert_eq!(ghost.segments[0].start_frame, 0);
+    }
+
+    #[test]
+    fn use_restarts_timer() {
+        let bsp = timer_map();
+        let ghost = walk(|idx| {
+            let using = (40..42).contains(&idx) || (55..57).contains(&idx);
+
+            Some(if using { IN_USE } else { 0 })
+        });
+
+        let timer = run_timer(&ghost, &bsp).unwrap();
+
+        assert_eq!(timer.start_frame, 55);
+        // player box touches the stop button
+        assert_eq!(timer.end_frame, 234);
+    }
+
+    #[test]
+    fn button_with_origin() {
+        let mut bsp = Bsp::from_bytes(include_bytes!("../../bsp/src/tests/normal.bsp")).unwrap();
+
+        add_button(
+            &mut bsp,
+            "counter_start",
+            Vec3::new(50., 60., -10.),
+            Vec3::new(60., 70., 10.),
+        );
+        // same place as the stop button in `timer_map` once moved by the origin
+        add_button(
+            &mut bsp,
+            "counter_off",
+            Vec3::new(50., -10., -10.),
+            Vec3::new(60., 10., 10.),
+        );
+
+        bsp.entities
+            .last_mut()
+            .unwrap()
+            .insert("origin".to_string(), "200 0 0".to_string());
+
+        let ghost = walk(|idx| Some(if idx == 28 { IN_USE } else { 0 }));
+        let timer = run_timer(&ghost, &bsp).unwrap();
+
+        // 22 units away along x and 60 along y is just in reach
+        assert_eq!(timer.start_frame, 28);
+        // touching where the origin moves the stop button to
+        assert_eq!(timer.end_frame, 234);
+    }
+
+    #[test]
+    fn triggers_only_by_touching() {
+        let mut bsp = Bsp::from_bytes(include_bytes!("../../bsp/src/tests/normal.bsp")).unwrap();
+
+        // same places as the buttons in `timer_map`
+        add_timer_entity(
+            &mut bsp,
+            "trigger_multiple",
+            "counter_start",
+            Vec3::new(50., 60., -10.),
+            Vec3::new(60., 70., 10.),
+        );
+        add_timer_entity(
+            &mut bsp,
+            "trigger_once",
+            "counter_off",
+            Vec3::new(250., -10., -10.),
+            Vec3::new(260., 10., 10.),
+        );
+
+        // the start is off the path
+        assert!(run_timer(&walk(|_| None), &bsp).is_none());
+
+        // now on the path
+        bsp.models[bsp.models.len() - 2].mins.y = -10.;
+
+        let timer = run_timer(&walk(|_| None), &bsp).unwrap();
+
+        // player box is 16 units wide, the stop trigger is touched and not reached from afar
+        assert_eq!(timer.start_frame, 34);
+        assert_eq!(timer.end_frame, 234);
+    }
+
+    #[test]
+    fn no_timer_buttons() {
+        let bsp = Bsp::from_bytes(include_bytes!("../../bsp/src/tests/normal.bsp")).unwrap();
+
+        assert!(run_timer(&walk(|_| None), &bsp).is_none());
+    }
+}
@@ -31,6 +31,8 @@ use wasm_bindgen::JsCast;
 use winit::platform::web::{EventLoopExtWebSys, WindowAttributesExtWebSys};
 
 pub mod constants;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod offline_render;
 mod state;
 mod user_event;
 
@@ -38,8 +40,8 @@ use crate::renderer::{
     EguiRenderer, RenderContext, skybox::SkyboxBuffer, world_buffer::WorldStaticBuffer,
 };
 use loader::{
-    MapIdentifier, MapList, ReplayList, Resource, ResourceMap, bsp_resource::BspResource,
-    error::ResourceProviderError,
+    MapIdentifier, MapList, ReplayList, ReplayMetadataList, Resource, ResourceMap,
+    bsp_resource::BspResource, error::ResourceProviderError,
 };
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -82,8 +84,14 @@ pub enum AppEvent {
     ReceiveReplayBlob {
         replay_name: PathBuf,
         replay_blob: GhostBlob,
+        /// Raced against the current replay instead of replacing it.
+        as_racer: bool,
     },
-    ReceiveReplay(MapIdentifier, GhostInfo),
+    /// Map, replay ghost and other players in the replay.
+    ReceiveReplay(MapIdentifier, GhostInfo, Vec<GhostInfo>),
+    /// Replay to race against the current replay.
+    RequestRacer(String),
+    ReceiveRacer(GhostInfo),
     ReceivePostProcessingUpdate(PostProcessingControlState),
     MaybeStartAudioBackEnd,
     RequestCommonResource,
@@ -93,6 +101,7 @@ pub enum AppEvent {
     ReceivedMapList(MapList),
     RequestReplayList,
     ReceiveReplayList(ReplayList),
+    ReceiveReplayMetadata(ReplayMetadataList),
     FinishCreateWorld(BspResource, WorldStaticBuffer, Option<SkyboxBuffer>),
     UpdateFetchProgress(f32),
     #[cfg(target_arch = "wasm32")]
@@ -153,7 +162,7 @@ impl App {
             });
 
         let event_loop_proxy = event_loop.create_proxy();
-        let state = AppState::new(event_loop_proxy.clone());
+        let state = AppState::new(Some(event_loop_proxy.clone()));
 
         Self {
             options: options.clone(),
@@ -495,3 +504,22 @@ pub fn run_kdr(options: RunKDROptions) {
         event_loop.run_app(&mut app).unwrap();
     }
 }
+
+/// Entry point for `kdr render`. `args` are the arguments after `render`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn run_kdr_offline(args: &[String]) {
+    tracing::ensure_logging_hooks();
+
+    let options = match offline_render::OfflineRenderOptions::from_args(args) {
+        Ok(options) => options,
+        Err(err) => {
+            warn!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(err) = offline_render::run_offline_render(options) {
+        warn!("Offline render failed: {}", err);
+        std::process::exit(1);
+    }
+}
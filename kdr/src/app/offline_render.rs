@@ -0,0 +1,387 @@
+//! Headless replay to video renderer.
+//!
+//! Runs the exact same [`AppState`] tick and render path as the interactive app, except that:
+//! - there is no window and no event loop,
+//! - time advances by a fixed step instead of the wall clock,
+//! - each frame is rendered into a [`FrameCapture`] and read back to the CPU.
+//!
+//! Frames are either written out as a PNG sequence or piped into `ffmpeg`.
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, Command, Stdio},
+};
+
+use futures::executor::block_on;
+use loader::{ResourceProvider, native::NativeResourceProvider};
+use tracing::{info, warn};
+
+use crate::renderer::{
+    RenderContext, frame_capture::FrameCapture, skybox::SkyboxLoader, world_buffer::WorldLoader,
+};
+
+use super::{
+    constants::DEFAULT_FRAMETIME,
+    state::{
+        AppState,
+        playback::replay::{RACER_COLORS, RaceTag, Replay, ReplayPlaybackMode, weapon_changes},
+    },
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum OfflineRenderError {
+    #[error("Bad arguments: {reason}")]
+    BadArguments { reason: String },
+
+    #[error("Cannot find a suitable graphics adapter")]
+    NoAdapter,
+
+    #[error("Problems with resource provider: {source}")]
+    ProviderError {
+        #[source]
+        source: loader::error::ResourceProviderError,
+    },
+
+    #[error("Cannot parse replay: {source}")]
+    GhostError {
+        #[source]
+        source: ghost::GhostError,
+    },
+
+    #[error("Cannot write frame `{path}`: {source}")]
+    WriteImage {
+        #[source]
+        source: image::ImageError,
+        path: PathBuf,
+    },
+
+    #[error("IO error: {source}")]
+    IOError {
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("ffmpeg exits with {status}")]
+    Ffmpeg { status: std::process::ExitStatus },
+}
+
+#[derive(Debug, Clone)]
+pub enum OfflineRenderOutput {
+    /// Folder to write `00000.png`, `00001.png`, ...
+    PngSequence(PathBuf),
+    /// Output file for ffmpeg. Raw frames are piped into ffmpeg stdin.
+    Ffmpeg(PathBuf),
+}
+
+#[derive(Debug, Clone)]
+pub struct OfflineRenderOptions {
+    pub game_dir: PathBuf,
+    /// Relative to `game_dir` or absolute.
+    pub replay: String,
+    pub output: OfflineRenderOutput,
+    pub fps: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Forces software adapter such as lavapipe.
+    pub software: bool,
+}
+
+const USAGE: &str = "\
+usage: kdr render --game-dir <path> --replay <path> (--output <folder> | --ffmpeg <file>) [options]
+
+options:
+    --fps <number>       output frame rate, default 60
+    --width <number>     default 1280
+    --height <number>    default 720
+    --software           use software rasterizer (lavapipe)";
+
+impl OfflineRenderOptions {
+    /// Parses arguments after `kdr render`.
+    pub fn from_args(args: &[String]) -> Result<Self, OfflineRenderError> {
+        let bad = |reason: String| OfflineRenderError::BadArguments {
+            reason: format!("{}\n\n{}", reason, USAGE),
+        };
+
+        let mut game_dir = None;
+        let mut replay = None;
+        let mut output = None;
+        let mut fps = 60;
+        let mut width = 1280;
+        let mut height = 720;
+        let mut software = false;
+
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| bad(format!("missing value for `{}`", arg)))
+            };
+
+            let number = |s: String| {
+                s.parse::<u32>()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| bad(format!("`{}` is not a positive number", s)))
+            };
+
+            match arg.as_str() {
+                "--game-dir" => game_dir = Some(PathBuf::from(value()?)),
+                "--replay" => replay = Some(value()?),
+                "--output" => output = Some(OfflineRenderOutput::PngSequence(value()?.into())),
+                "--ffmpeg" => output = Some(OfflineRenderOutput::Ffmpeg(value()?.into())),
+                "--fps" => fps = number(value()?)?,
+                "--width" => width = number(value()?)?,
+                "--height" => height = number(value()?)?,
+                "--software" => software = true,
+                _ => return Err(bad(format!("unknown argument `{}`", arg))),
+            }
+        }
+
+        Ok(Self {
+            game_dir: game_dir.ok_or_else(|| bad("missing `--game-dir`".to_string()))?,
+            replay: replay.ok_or_else(|| bad("missing `--replay`".to_string()))?,
+            output: output.ok_or_else(|| bad("missing `--output` or `--ffmpeg`".to_string()))?,
+            fps,
+            width,
+            height,
+            software,
+        })
+    }
+}
+
+enum FrameSink {
+    PngSequence(PathBuf),
+    Ffmpeg { child: Child, stdin: ChildStdin },
+}
+
+impl FrameSink {
+    fn new(options: &OfflineRenderOptions) -> Result<Self, OfflineRenderError> {
+        match &options.output {
+            OfflineRenderOutput::PngSequence(folder) => {
+                std::fs::create_dir_all(folder)
+                    .map_err(|op| OfflineRenderError::IOError { source: op })?;
+
+                Ok(Self::PngSequence(folder.to_owned()))
+            }
+            OfflineRenderOutput::Ffmpeg(out) => {
+                let mut child = Command::new("ffmpeg")
+                    .args(["-y", "-loglevel", "error"])
+                    .args(["-f", "rawvideo", "-pixel_format", "rgba"])
+                    .args([
+                        "-video_size",
+                        &format!("{}x{}", options.width, options.height),
+                    ])
+                    .args(["-framerate", &options.fps.to_string()])
+                    .args(["-i", "-"])
+                    .args(["-c:v", "libx264", "-pix_fmt", "yuv420p"])
+                    .arg(out)
+                    .stdin(Stdio::piped())
+                    .spawn()
+                    .map_err(|op| OfflineRenderError::IOError { source: op })?;
+
+                let stdin = child.stdin.take().expect("ffmpeg stdin is piped");
+
+                Ok(Self::Ffmpeg { child, stdin })
+            }
+        }
+    }
+
+    fn write_frame(
+        &mut self,
+        frame_index: usize,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Result<(), OfflineRenderError> {
+        match self {
+            Self::PngSequence(folder) => {
+                let path = folder.join(format!("{:05}.png", frame_index));
+
+                image::save_buffer(&path, pixels, width, height, image::ColorType::Rgba8)
+                    .map_err(|op| OfflineRenderError::WriteImage { source: op, path })
+            }
+            Self::Ffmpeg { stdin, .. } => stdin
+                .write_all(pixels)
+                .map_err(|op| OfflineRenderError::IOError { source: op }),
+        }
+    }
+
+    fn finish(self) -> Result<(), OfflineRenderError> {
+        match self {
+            Self::PngSequence(_) => Ok(()),
+            Self::Ffmpeg { mut child, stdin } => {
+                // closing stdin so ffmpeg knows there is no more frame
+                drop(stdin);
+
+                let status = child
+                    .wait()
+                    .map_err(|op| OfflineRenderError::IOError { source: op })?;
+
+                if !status.success() {
+                    return Err(OfflineRenderError::Ffmpeg { status });
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Renders a replay to images or video without opening a window.
+pub fn run_offline_render(options: OfflineRenderOptions) -> Result<(), OfflineRenderError> {
+    let provider = NativeResourceProvider::new(options.game_dir.as_path());
+
+    let render_context = block_on(RenderContext::new_headless(
+        options.width,
+        options.height,
+        options.software,
+    ))
+    .ok_or(OfflineRenderError::NoAdapter)?;
+
+    let frame_capture = FrameCapture::new(render_context.device(), options.width, options.height);
+
+    // resources
+    let ghost_blob = block_on(provider.get_replay(&options.replay))
+        .map_err(|op| OfflineRenderError::ProviderError { source: op })?;
+
+    let (map_identifier, ghost, players) =
+        block_on(provider.get_ghost_data(Path::new(&options.replay), ghost_blob))
+            .map_err(|op| OfflineRenderError::GhostError { source: op })?;
+
+    info!(
+        "Rendering `{}` on `{}`",
+        ghost.ghost_name, map_identifier.map_name
+    );
+
+    let common_resource = block_on(provider.request_common_resource())
+        .map_err(|op| OfflineRenderError::ProviderError { source: op })?;
+
+    let resource = block_on(provider.get_map(&map_identifier))
+        .map_err(|op| OfflineRenderError::ProviderError { source: op })?;
+
+    let bsp_resource = resource.to_bsp_resource();
+
+    let world_buffer = WorldLoader::load_static_world(
+        render_context.device(),
+        render_context.queue(),
+        &bsp_resource,
+    );
+
+    let skybox_buffer = SkyboxLoader::load_skybox(
+        render_context.device(),
+        render_context.queue(),
+        &bsp_resource.skybox,
+    );
+
+    // states
+    let mut state = AppState::new(None);
+
+    state.load_viewmodels(&render_context, &common_resource);
+    state.load_player_models(&render_context, &common_resource);
+    state.other_resources.common_resource = common_resource;
+
+    // there is no event loop to load another map, so it stops where the map changes
+    let ghost_length = match ghost.segments.get(1) {
+        Some(segment) => {
+            warn!(
+                "Replay changes map {} times, only `{}` is rendered",
+                ghost.segments.len() - 1,
+                map_identifier.map_name
+            );
+
+            ghost
+                .get_frame_time(segment.start_frame, None)
+                .unwrap_or(segment.start_frame as f32 * DEFAULT_FRAMETIME)
+        }
+        None => ghost.get_ghost_length()(DEFAULT_FRAMETIME),
+    };
+    let analysis = ghost.analyze();
+    let weapon_changes = weapon_changes(&ghost);
+    let findings = ghost.check(&analysis);
+    let tag = RaceTag::new(&ghost, RACER_COLORS[0]);
+
+    state.playback_state.set_replay(Replay {
+        ghost,
+        players,
+        playback_mode: ReplayPlaybackMode::Interpolated,
+        last_frame: 0,
+        segment: 0,
+        changing_map: false,
+        analysis,
+        weapon_changes,
+        findings,
+        run_timer: None,
+        tag,
+        racers: vec![],
+    });
+    state.input_state.free_cam = false;
+
+    // after the replay so there is a player model for every player
+    state.load_world(
+        Some(&render_context),
+        bsp_resource,
+        world_buffer,
+        skybox_buffer,
+    );
+
+    state.update_fov_with_dimensions(options.width, options.height);
+
+    debug_assert_eq!(
+        state
+            .entity_state
+            .as_ref()
+            .map(|entity_state| entity_state.playermodel_state.players.len()),
+        state
+            .playback_state
+            .get_replay()
+            .map(|replay| 1 + replay.players.len() + replay.racers.len()),
+        "player models are created after the replay is set"
+    );
+
+    // rendering
+    let frame_time = 1. / options.fps as f32;
+    let frame_count = (ghost_length * options.fps as f32).ceil() as usize;
+
+    let mut sink = FrameSink::new(&options)?;
+
+    info!("Rendering {} frames", frame_count);
+
+    for frame_index in 0..frame_count {
+        // first frame should start at time 0
+        if frame_index == 0 {
+            state.apply_replay_start();
+            state.fixed_tick(0.);
+        } else {
+            state.fixed_tick(frame_time);
+        }
+
+        let mut encoder =
+            render_context
+                .device()
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("offline render encoder"),
+                });
+
+        state.render(&render_context, &mut encoder, &frame_capture.view);
+
+        frame_capture.copy_to_buffer(&mut encoder);
+
+        render_context.queue().submit(Some(encoder.finish()));
+
+        let pixels = frame_capture.read_frame(render_context.device());
+
+        sink.write_frame(frame_index, options.width, options.height, &pixels)?;
+
+        if frame_index % options.fps as usize == 0 {
+            info!("Frame {}/{}", frame_index, frame_count);
+        }
+    }
+
+    sink.finish()?;
+
+    info!("Finished rendering");
+
+    Ok(())
+}
@@ -7,8 +7,8 @@ use mdl::Mdl;
 use tracing::warn;
 
 use crate::{
-    app::{App, constants::MAX_MVP, state::AppState},
-    renderer::{mvp_buffer::MvpBuffer, world_buffer::WorldLoader},
+    app::{constants::MAX_MVP, state::AppState},
+    renderer::{RenderContext, mvp_buffer::MvpBuffer, world_buffer::WorldLoader},
 };
 
 pub struct PlayerModel {
@@ -56,9 +56,11 @@ impl PlayerModel {
 }
 
 impl PlayerModelState {
-    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, player_count: usize) -> Self {
         Self {
-            players: vec![PlayerModel::new(device, queue)],
+            players: (0..player_count.max(1))
+                .map(|_| PlayerModel::new(device, queue))
+                .collect(),
         }
     }
 
@@ -99,8 +101,12 @@ impl AppState {
     }
 }
 
-impl App {
-    pub fn load_player_models(&mut self, resource_map: &ResourceMap) {
+impl AppState {
+    pub fn load_player_models(
+        &mut self,
+        render_context: &RenderContext,
+        resource_map: &ResourceMap,
+    ) {
         resource_map.iter().for_each(|(file_path, file_bytes)| {
             let is_player_model =
                 file_path.starts_with("models/player/") && file_path.ends_with(".mdl");
@@ -116,12 +122,6 @@ impl App {
 
             let actual_file_name = Path::new(file_path).file_stem().unwrap().to_str().unwrap();
 
-            // we should already have render context by this point
-            let Some(render_context) = self.render_context.as_ref() else {
-                warn!("Trying to create dynamic buffer without render context");
-                return;
-            };
-
             let dynamic_buffer = WorldLoader::load_dynamic_world(
                 render_context.device(),
                 render_context.queue(),
@@ -130,10 +130,7 @@ impl App {
                 0,
             );
 
-            self.state
-                .render_state
-                .playermodel_buffers
-                .push(dynamic_buffer);
+            self.render_state.playermodel_buffers.push(dynamic_buffer);
         });
     }
 }
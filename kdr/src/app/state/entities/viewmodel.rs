@@ -9,8 +9,8 @@ use mdl::Mdl;
 use tracing::warn;
 
 use crate::{
-    app::{App, state::AppState},
-    renderer::world_buffer::WorldLoader,
+    app::state::AppState,
+    renderer::{RenderContext, world_buffer::WorldLoader},
 };
 
 pub struct ViewModelState {
@@ -100,8 +100,8 @@ impl AppState {
     }
 }
 
-impl App {
-    pub fn load_viewmodels(&mut self, resource_map: &ResourceMap) {
+impl AppState {
+    pub fn load_viewmodels(&mut self, render_context: &RenderContext, resource_map: &ResourceMap) {
         resource_map.iter().for_each(|(file_path, file_bytes)| {
             let is_viewmodel = file_path.starts_with("models/v_") && file_path.ends_with(".mdl");
 
@@ -116,12 +116,6 @@ impl App {
 
             let actual_file_name = Path::new(file_path).file_stem().unwrap().to_str().unwrap();
 
-            // we should already have render context by this point
-            let Some(render_context) = self.render_context.as_ref() else {
-                warn!("Trying to create dynamic buffer without render context");
-                return;
-            };
-
             let dynamic_buffer = WorldLoader::load_dynamic_world(
                 render_context.device(),
                 render_context.queue(),
@@ -130,10 +124,7 @@ impl App {
                 0,
             );
 
-            self.state
-                .render_state
-                .viewmodel_buffers
-                .push(dynamic_buffer);
+            self.render_state.viewmodel_buffers.push(dynamic_buffer);
         });
     }
 }
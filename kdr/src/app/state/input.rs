@@ -10,7 +10,10 @@ use crate::app::{
     constants::{DEFAULT_NOCLIP_SPEED, DEFAULT_SENSITIVITY},
 };
 
-use super::AppState;
+use super::{
+    AppState,
+    movement::{MovementMode, WalkState},
+};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Key(u32);
@@ -28,6 +31,7 @@ bitflags! {
         const Shift     = (1 << 8);
         const Control   = (1 << 9);
         const Alt       = (1 << 10);
+        const Jump      = (1 << 11);
     }
 }
 
@@ -38,6 +42,8 @@ pub struct InputState {
     pub sensitivity: f32,
     pub noclip_speed: f32,
     pub free_cam: bool,
+    pub movement_mode: MovementMode,
+    pub walk_state: Option<WalkState>,
 }
 
 impl Default for InputState {
@@ -48,6 +54,8 @@ impl Default for InputState {
             sensitivity: DEFAULT_SENSITIVITY,
             noclip_speed: DEFAULT_NOCLIP_SPEED,
             free_cam: true,
+            movement_mode: MovementMode::default(),
+            walk_state: None,
         }
     }
 }
@@ -157,6 +165,18 @@ impl AppState {
                         self.ui_state.is_main_ui_enabled = !self.ui_state.is_main_ui_enabled;
                     }
                 }
+                // jumps instead of pausing while walking around
+                KeyCode::Space
+                    if self.input_state.free_cam
+                        && self.input_state.movement_mode == MovementMode::Walk =>
+                {
+                    if state.is_pressed() {
+                        self.input_state.keys = self.input_state.keys.union(Key::Jump);
+                    } else {
+                        self.input_state.keys =
+                            self.input_state.keys.intersection(Key::Jump.complement());
+                    }
+                }
                 KeyCode::Space | KeyCode::KeyK => {
                     if state.is_pressed() {
                         self.paused = !self.paused;
@@ -1,9 +1,14 @@
-use cgmath::InnerSpace;
-
 use crate::app::state::AppState;
 
+const TRACE_DISTANCE: f32 = 8192.0;
+
 impl AppState {
     pub(super) fn misc_tick(&mut self) {
+        // the trace is only shown in the debug panel
+        if !(self.ui_state.is_main_ui_enabled && self.ui_state.control_panel.enable_debug_panel) {
+            return;
+        }
+
         let Some(bsp) = &self.other_resources.bsp else {
             return;
         };
@@ -11,14 +16,27 @@ impl AppState {
         let start = self.render_state.camera.pos;
         // i should better rotate the vector myself but whatever
         let end = self.render_state.camera.target;
-        let trace_end = start + (end - start).normalize() * 8192.0;
+        let direction = end - start;
+
+        let start = bsp::Vec3::new(start.x, start.y, start.z);
+        let direction = bsp::Vec3::new(direction.x, direction.y, direction.z);
 
-        let tr = bsp.trace_line_hull(
-            self.ui_state.debug_panel.trace_hull_type,
-            [start.x, start.y, start.z].into(),
-            [trace_end.x, trace_end.y, trace_end.z].into(),
-        );
+        // only point traces can find the face under the crosshair
+        let tr = match self.ui_state.debug_panel.trace_hull_type {
+            bsp::HullType::Point => bsp.pick_models(
+                &self.other_resources.pickable_entities,
+                start,
+                direction,
+                TRACE_DISTANCE,
+            ),
+            hull_type => bsp.trace_world_and_models(
+                &self.other_resources.solid_entities,
+                hull_type,
+                start,
+                start + direction.normalize_or_zero() * TRACE_DISTANCE,
+            ),
+        };
 
-        self.ui_state.debug_panel.trace_result.end_pos = tr.end_pos;
+        self.ui_state.debug_panel.trace_result = tr;
     }
 }
@@ -1,20 +1,24 @@
-use crate::renderer::camera::Camera;
+use crate::renderer::{
+    RenderContext, camera::Camera, skybox::SkyboxBuffer, world_buffer::WorldStaticBuffer,
+};
 
 // need to do like this
 use super::{Duration, Instant};
 
 use audio::AudioState;
+use common::vec3;
 use egui::ahash::{HashMap, HashMapExt};
-use entities::EntityState;
-use file::FileState;
+use entities::{EntityState, playermodel::PlayerModelState, viewmodel::ViewModelState};
+use file::{FileState, SelectedFileType};
+use ghost::GhostMetadata;
 use input::InputState;
 use kira::sound::static_sound::StaticSoundData;
-use loader::{ReplayList, ResourceMap};
+use loader::{ReplayList, ResourceMap, bsp_resource::BspResource};
 use overlay::{UIState, text::TextState};
 use playback::PlaybackState;
-use render::RenderState;
+use render::{RenderOptions, RenderState};
 use window::WindowState;
-use winit::event_loop::EventLoopProxy;
+use winit::event_loop::{EventLoopClosed, EventLoopProxy};
 
 use super::AppEvent;
 
@@ -27,6 +31,7 @@ pub mod movement;
 pub mod overlay;
 pub mod playback;
 pub mod render;
+pub mod visibility;
 pub mod window;
 
 pub type SortedMapList = Vec<(String, Vec<String>)>;
@@ -34,10 +39,15 @@ pub type SortedMapList = Vec<(String, Vec<String>)>;
 #[derive(Debug, Default)]
 pub struct OtherResources {
     pub bsp: Option<bsp::Bsp>,
+    // (model index, origin) of brush entities in the bsp so the debug trace doesn't look them up every tick
+    pub pickable_entities: Vec<(usize, bsp::Vec3)>,
+    pub solid_entities: Vec<(usize, bsp::Vec3)>,
     // from MapList type, we sort it so it becomes a vector
     pub common_resource: ResourceMap,
     pub map_list: SortedMapList,
     pub replay_list: ReplayList,
+    // metadata from the server and of replays that have been loaded, key is the replay name
+    pub replay_metadata: HashMap<String, GhostMetadata>,
 }
 
 // Decouples states from App so that we can impl specific stuffs that affect states without affecting App.
@@ -70,11 +80,25 @@ pub struct AppState {
     pub entity_state: Option<EntityState>,
 
     // talk with other modules
-    event_loop_proxy: EventLoopProxy<AppEvent>,
+    event_loop_proxy: AppEventSender,
+}
+
+/// Wraps [`EventLoopProxy`] so that [`AppState`] can live without an event loop.
+///
+/// Offline rendering doesn't have an event loop, so events are just dropped.
+pub struct AppEventSender(Option<EventLoopProxy<AppEvent>>);
+
+impl AppEventSender {
+    pub fn send_event(&self, event: AppEvent) -> Result<(), EventLoopClosed<AppEvent>> {
+        match &self.0 {
+            Some(event_loop_proxy) => event_loop_proxy.send_event(event),
+            None => Ok(()),
+        }
+    }
 }
 
 impl AppState {
-    pub fn new(event_loop_proxy: EventLoopProxy<AppEvent>) -> Self {
+    pub fn new(event_loop_proxy: Option<EventLoopProxy<AppEvent>>) -> Self {
         Self {
             time: 0.,
             last_time: 0.,
@@ -96,7 +120,7 @@ impl AppState {
             playback_state: PlaybackState::default(),
             entity_state: None,
 
-            event_loop_proxy,
+            event_loop_proxy: AppEventSender(event_loop_proxy),
             window_state: None,
         }
     }
@@ -112,6 +136,25 @@ impl AppState {
         #[cfg(target_arch = "wasm32")]
         self.poll_puppeteer();
 
+        self.tick_systems();
+    }
+
+    /// Same as [`AppState::tick`] but time advances by a fixed `frame_time` instead of the wall clock.
+    ///
+    /// Offline rendering uses this so that the output frame rate does not depend on how fast we can render.
+    pub fn fixed_tick(&mut self, frame_time: f32) {
+        self.frame_time = frame_time;
+        self.last_instant = Instant::now();
+
+        if !self.paused {
+            self.last_time = self.time;
+            self.time += frame_time * self.playback_speed;
+        }
+
+        self.tick_systems();
+    }
+
+    fn tick_systems(&mut self) {
         // need interaction tick to process first so that the viewmodel is updated
         // and then replay tick to get the latest view
         // TODO maybe viewmodel a different state?
@@ -124,6 +167,9 @@ impl AppState {
 
         // whatever i want here
         self.misc_tick();
+
+        // after everything that moves the camera
+        self.visibility_tick();
     }
 
     fn delta_update(&mut self) {
@@ -176,10 +222,121 @@ impl AppState {
         let width = window_state.width;
         let height = window_state.height;
 
+        self.update_fov_with_dimensions(width, height);
+    }
+
+    /// Same as [`AppState::update_fov`] but without a window.
+    pub(super) fn update_fov_with_dimensions(&mut self, width: u32, height: u32) {
         self.render_state.camera.fovy =
             Camera::calculate_y_fov(self.render_state.camera.fovx, width as f32, height as f32);
 
         // only change the aspects
         self.render_state.camera.aspect = width as f32 / height as f32;
     }
+
+    /// Swaps in a newly loaded world and resets everything that depends on the previous map.
+    pub fn load_world(
+        &mut self,
+        render_context: Option<&RenderContext>,
+        bsp_resource: BspResource,
+        world_buffer: WorldStaticBuffer,
+        skybox_buffer: Option<SkyboxBuffer>,
+    ) {
+        self.render_state.world_buffer = world_buffer.into();
+        self.render_state.visibility.clear();
+
+        self.render_state.skybox = skybox_buffer;
+
+        // inserting audio from bsp resourec
+        // but first, need to clear audio that are not part of the common resource
+        self.audio_resource
+            .retain(|k, _| self.other_resources.common_resource.contains_key(k));
+
+        bsp_resource.sound_lookup.iter().for_each(|(k, v)| {
+            self.audio_resource.insert(k.to_string(), v.clone());
+        });
+
+        // restart the camera
+        self.render_state.camera = Camera::default();
+
+        // but then set our camera to be in one of the spawn location
+        bsp_resource
+            .bsp
+            .entities
+            .iter()
+            .find(|entity| {
+                entity
+                    .get("classname")
+                    .is_some_and(|classname| classname == "info_player_start")
+            })
+            .map(|entity| {
+                entity
+                    .get("origin")
+                    .and_then(|origin_text| vec3(&origin_text))
+                    .map(|origin| {
+                        self.render_state.camera.set_position(origin);
+                        self.render_state.camera.rebuild_orientation();
+                    });
+            });
+
+        // restart render options
+        self.render_state.render_options = RenderOptions::default();
+
+        // the walking player is from the old map
+        self.input_state.walk_state = None;
+
+        // if loading bsp, just force free cam every time
+        match self.file_state.selected_file_type {
+            SelectedFileType::Bsp => {
+                self.input_state.free_cam = true;
+            }
+            _ => (),
+        }
+        // reset file input tpye
+        self.file_state.selected_file_type = SelectedFileType::None;
+
+        // reset texts
+        self.text_state.clear_text();
+
+        // jumpstats are traced on the old map
+        self.ui_state.jumpstats.jumps = None;
+
+        // resetting time when we are ready
+        // unless replay is only going to its next map
+        match self.playback_state.get_replay_mut() {
+            Some(replay) if replay.changing_map => replay.changing_map = false,
+            _ => self.time = 0.,
+        }
+
+        // stop spinner
+        self.file_state.stop_spinner();
+
+        self.update_run_timer(&bsp_resource.bsp);
+
+        let Some(render_context) = render_context else {
+            return;
+        };
+
+        // entity dictionary
+        self.entity_state = Some(EntityState {
+            entity_dictionary: bsp_resource.entity_dictionary,
+            viewmodel_state: ViewModelState::default(),
+            playermodel_state: PlayerModelState::new(
+                render_context.device(),
+                render_context.queue(),
+                // one for the replay and the rest for other players in the replay and racers
+                1 + self
+                    .playback_state
+                    .get_replay()
+                    .map(|replay| replay.players.len() + replay.racers.len())
+                    .unwrap_or(0),
+            ),
+        });
+
+        self.other_resources.pickable_entities = bsp_resource.bsp.pickable_entities();
+        self.other_resources.solid_entities = bsp_resource.bsp.solid_brush_entities();
+
+        // store bsp to do things
+        self.other_resources.bsp = bsp_resource.bsp.into();
+    }
 }
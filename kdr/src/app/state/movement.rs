@@ -1,9 +1,46 @@
 use cgmath::Deg;
+use ghost::{
+    FORWARD_SPEED, IN_BACK, IN_DUCK, IN_FORWARD, IN_JUMP, IN_MOVELEFT, IN_MOVERIGHT, MoveVars,
+    PlayerMove, PlayerState, SIDE_SPEED, UserCmd, VEC_VIEW,
+};
 
 use crate::app::constants::CAM_TURN;
 
 use super::{input::Key, *};
 
+// cl_movespeedkey, walking with shift
+const WALK_SPEED_MULTIPLIER: f32 = 0.3;
+// a long frame would go through things in one move
+const MAX_WALK_FRAMETIME: f32 = 0.1;
+
+/// How free cam moves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MovementMode {
+    #[default]
+    Noclip,
+    /// GoldSrc movement on the loaded map.
+    Walk,
+}
+
+/// The player of [`MovementMode::Walk`].
+#[derive(Debug)]
+pub struct WalkState {
+    player_move: PlayerMove,
+    pub player: PlayerState,
+}
+
+impl WalkState {
+    /// Player with its eyes at `view_origin`.
+    fn new(bsp: &bsp::Bsp, view_origin: [f32; 3]) -> Self {
+        let [x, y, z] = view_origin;
+
+        Self {
+            player_move: PlayerMove::new(bsp, MoveVars::default()),
+            player: PlayerState::new([x, y, z - VEC_VIEW].into()),
+        }
+    }
+}
+
 impl AppState {
     fn forward(&mut self) {
         self.render_state
@@ -71,24 +108,107 @@ impl AppState {
         }
     }
 
+    /// Walking starts again from the camera every time it is turned on.
+    pub fn set_movement_mode(&mut self, movement_mode: MovementMode) {
+        self.input_state.movement_mode = movement_mode;
+        self.input_state.walk_state = None;
+    }
+
+    fn walk_cmd(&self) -> UserCmd {
+        let keys = self.input_state.keys;
+        let multiplier = if keys.contains(Key::Shift) {
+            WALK_SPEED_MULTIPLIER
+        } else {
+            1.
+        };
+
+        let axis = |positive: Key, negative: Key, speed: f32| {
+            let mut res = 0.;
+
+            if keys.contains(positive) {
+                res += speed * multiplier;
+            }
+
+            if keys.contains(negative) {
+                res -= speed * multiplier;
+            }
+
+            res
+        };
+
+        let buttons = [
+            (Key::Forward, IN_FORWARD),
+            (Key::Back, IN_BACK),
+            (Key::MoveLeft, IN_MOVELEFT),
+            (Key::MoveRight, IN_MOVERIGHT),
+            (Key::Jump, IN_JUMP),
+            (Key::Control, IN_DUCK),
+        ]
+        .into_iter()
+        .filter(|(key, _)| keys.contains(*key))
+        .fold(0, |buttons, (_, button)| buttons | button);
+
+        let camera = &self.render_state.camera;
+
+        UserCmd {
+            frametime: self.frame_time.min(MAX_WALK_FRAMETIME),
+            // camera pitch goes up
+            viewangles: [-camera.pitch().0, camera.yaw().0, 0.].into(),
+            forwardmove: axis(Key::Forward, Key::Back, FORWARD_SPEED),
+            sidemove: axis(Key::MoveRight, Key::MoveLeft, SIDE_SPEED),
+            upmove: 0.,
+            buttons,
+        }
+    }
+
+    /// Moves the walking player and puts the camera on its eyes.
+    fn walk(&mut self) {
+        let Some(bsp) = self.other_resources.bsp.as_ref() else {
+            return;
+        };
+
+        let cmd = self.walk_cmd();
+        let camera_pos = self.render_state.camera.pos;
+
+        let walk_state = self
+            .input_state
+            .walk_state
+            .get_or_insert_with(|| WalkState::new(bsp, camera_pos.into()));
+
+        walk_state
+            .player_move
+            .player_move(bsp, &mut walk_state.player, &cmd);
+
+        self.render_state
+            .camera
+            .set_position(walk_state.player.view_origin().to_array());
+        self.render_state.camera.rebuild_orientation();
+    }
+
     pub(super) fn interaction_tick(&mut self) {
         // shouldnt be able to move if we are not in free cam
         if !self.input_state.free_cam {
             return;
         }
 
-        if self.input_state.keys.contains(Key::Forward) {
-            self.forward();
-        }
-        if self.input_state.keys.contains(Key::Back) {
-            self.back();
-        }
-        if self.input_state.keys.contains(Key::MoveLeft) {
-            self.moveleft();
-        }
-        if self.input_state.keys.contains(Key::MoveRight) {
-            self.moveright();
+        match self.input_state.movement_mode {
+            MovementMode::Noclip => {
+                if self.input_state.keys.contains(Key::Forward) {
+                    self.forward();
+                }
+                if self.input_state.keys.contains(Key::Back) {
+                    self.back();
+                }
+                if self.input_state.keys.contains(Key::MoveLeft) {
+                    self.moveleft();
+                }
+                if self.input_state.keys.contains(Key::MoveRight) {
+                    self.moveright();
+                }
+            }
+            MovementMode::Walk => self.walk(),
         }
+
         if self.input_state.keys.contains(Key::Left) {
             self.left();
         }
@@ -9,10 +9,12 @@ use crate::{
         },
         state::{
             AppState,
+            movement::MovementMode,
             playback::{self, replay::ReplayPlaybackMode},
         },
     },
     renderer::camera::{FOV_DEFAULT, FOV_MAX, FOV_MIN},
+    utils::format_time,
 };
 
 // awkward......
@@ -31,6 +33,10 @@ pub struct PostProcessingControlState {
 pub struct ControlPanelUIState {
     pub crosshair: bool,
     pub enable_debug_panel: bool,
+    pub enable_run_stats: bool,
+    pub enable_jumpstats: bool,
+    pub enable_inspector: bool,
+    pub enable_race: bool,
 }
 
 impl Default for ControlPanelUIState {
@@ -38,6 +44,10 @@ impl Default for ControlPanelUIState {
         Self {
             crosshair: true,
             enable_debug_panel: false,
+            enable_run_stats: false,
+            enable_jumpstats: false,
+            enable_inspector: false,
+            enable_race: false,
         }
     }
 }
@@ -60,6 +70,8 @@ impl AppState {
             title_name
         };
 
+        let mut should_trim = false;
+
         egui::Window::new(title_name)
             .resizable(false)
             .vscroll(false)
@@ -90,26 +102,104 @@ impl AppState {
                 });
 
                 // playback info
-                match &self.playback_state.playback_mode {
+                match &mut self.playback_state.playback_mode {
                     playback::PlaybackMode::Replay(replay) => {
                         ui.separator();
 
                         ui.vertical(|ui| {
-                            let playback_mode = match replay.playback_mode {
+                            let playback_mode_name = |mode: &ReplayPlaybackMode| match mode {
                                 ReplayPlaybackMode::Immediate(_) => "Immediate",
                                 ReplayPlaybackMode::Interpolated => "Interpolated",
                                 ReplayPlaybackMode::FrameAccurate => "Frame Accurate",
                             };
 
+                            let metadata = &replay.ghost.metadata;
+
                             ui.label(format!("Ghost name: {}", replay.ghost.ghost_name));
-                            ui.label(format!("Map: {}", replay.ghost.map_name));
+                            if let Some(player_name) = &metadata.player_name {
+                                ui.label(format!("Player: {}", player_name));
+                            }
+                            if let Some(steam_id) = &metadata.steam_id {
+                                ui.label(format!("Steam ID: {}", steam_id));
+                            }
+                            if let Some(official_time) = metadata.official_time {
+                                ui.label(format!("Time: {}", format_time(official_time)));
+                            }
+                            if let Some(timer) = &replay.run_timer {
+                                ui.horizontal(|ui| {
+                                    ui.label(format!(
+                                        "Run: frame {} to {}",
+                                        timer.start_frame, timer.end_frame
+                                    ));
+
+                                    // other players are not cut along
+                                    let can_trim = replay.players.is_empty()
+                                        && (timer.start_frame != 0
+                                            || timer.end_frame + 1 != replay.ghost.frames().len());
+
+                                    if ui
+                                        .add_enabled(can_trim, egui::Button::new("Trim"))
+                                        .on_hover_text("Cut the replay down to the run")
+                                        .clicked()
+                                    {
+                                        should_trim = true;
+                                    }
+                                });
+                            }
+                            if let Some(record_date) = &metadata.record_date {
+                                ui.label(format!("Date: {}", record_date));
+                            }
+                            if let Some(source_format) = metadata.source_format.clone() {
+                                let source_format: &str = source_format.into();
+
+                                ui.label(format!("Format: {}", source_format));
+                            }
+                            if replay.ghost.segments.len() > 1 {
+                                let segment = &replay.ghost.segments[replay.segment];
+
+                                ui.label(format!(
+                                    "Map: {} ({}/{})",
+                                    segment.map_name,
+                                    replay.segment + 1,
+                                    replay.ghost.segments.len()
+                                ));
+                            } else {
+                                ui.label(format!("Map: {}", replay.ghost.map_name));
+                            }
                             ui.label(format!("Game mod: {}", replay.ghost.game_mod));
                             ui.label(format!(
                                 "Length: {:.2} seconds over {} frames",
                                 replay.ghost.get_ghost_length()(DEFAULT_FRAMETIME),
-                                replay.ghost.frames.len(),
+                                replay.ghost.frames().len(),
                             ));
-                            ui.label(format!("Playback mode: {}", playback_mode));
+                            ui.horizontal(|ui| {
+                                ui.label("Playback mode: ");
+
+                                // immediate mode starts from the current frame
+                                let current_frame = replay.ghost.get_frame_index(self.time, None);
+
+                                egui::ComboBox::from_id_salt("replay-playback-mode")
+                                    .selected_text(playback_mode_name(&replay.playback_mode))
+                                    .show_ui(ui, |ui| {
+                                        [
+                                            ReplayPlaybackMode::Interpolated,
+                                            ReplayPlaybackMode::FrameAccurate,
+                                            ReplayPlaybackMode::Immediate(current_frame),
+                                        ]
+                                        .into_iter()
+                                        .for_each(|mode| {
+                                            let is_selected = std::mem::discriminant(&mode)
+                                                == std::mem::discriminant(&replay.playback_mode);
+                                            let name = playback_mode_name(&mode);
+
+                                            if ui.selectable_label(is_selected, name).clicked()
+                                                && !is_selected
+                                            {
+                                                replay.playback_mode = mode;
+                                            }
+                                        });
+                                    });
+                            });
                         });
                     }
                     playback::PlaybackMode::Live(puppet) => {
@@ -164,9 +254,36 @@ impl AppState {
                 ui.horizontal(|ui| {
                     ui.checkbox(&mut self.input_state.free_cam, "Freecam");
 
+                    let mut walk = self.input_state.movement_mode == MovementMode::Walk;
+                    let response = ui.checkbox(&mut walk, "Walk").on_hover_text(
+                        "GoldSrc movement. SPACE to jump, CTRL to duck, SHIFT to walk, K to pause",
+                    );
+
+                    if response.changed() {
+                        self.set_movement_mode(if walk {
+                            MovementMode::Walk
+                        } else {
+                            MovementMode::Noclip
+                        });
+                    }
+
                     ui.checkbox(&mut self.ui_state.control_panel.crosshair, "Crosshair");
 
                     ui.checkbox(&mut self.ui_state.control_panel.enable_debug_panel, "Debug");
+
+                    ui.checkbox(&mut self.ui_state.control_panel.enable_run_stats, "Stats");
+
+                    ui.checkbox(
+                        &mut self.ui_state.control_panel.enable_jumpstats,
+                        "Jumpstats",
+                    );
+
+                    ui.checkbox(
+                        &mut self.ui_state.control_panel.enable_inspector,
+                        "Inspector",
+                    );
+
+                    ui.checkbox(&mut self.ui_state.control_panel.enable_race, "Race");
                 });
 
                 // resolutions
@@ -286,11 +403,25 @@ impl AppState {
                     );
                 });
 
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.render_state.render_options.culling, "Culling")
+                        .on_hover_text("Only draws world faces that can be seen");
+                    ui.checkbox(
+                        &mut self.render_state.render_options.freeze_visibility,
+                        "Freeze Visibility",
+                    )
+                    .on_hover_text("Keeps the current visible faces to check them from elsewhere");
+                });
+
                 // watermark
                 ui.separator();
                 ui.vertical_centered(|ui| {
                     ui.hyperlink_to("kdr on GitHub", "https://github.com/khanghugo/kdr");
                 });
             });
+
+        if should_trim {
+            self.trim_replay_to_run();
+        }
     }
 }
@@ -74,6 +74,56 @@ impl AppState {
                     self.ui_state.debug_panel.trace_result.end_pos
                 ));
 
+                let trace_result = &self.ui_state.debug_panel.trace_result;
+
+                ui.label(format!("Trace contents: {:?}", trace_result.contents));
+
+                ui.label(format!(
+                    "Trace face: {} {}",
+                    trace_result
+                        .face
+                        .map(|face| face.to_string())
+                        .unwrap_or("-".to_string()),
+                    trace_result.texture_name.as_deref().unwrap_or("")
+                ));
+
+                // classname and targetname of what is under the crosshair
+                let entity = trace_result.entity_index.and_then(|entity_index| {
+                    self.other_resources
+                        .bsp
+                        .as_ref()
+                        .and_then(|bsp| bsp.entities.get(entity_index))
+                        .map(|entity| (entity_index, entity))
+                });
+
+                ui.label(format!(
+                    "Trace entity: {}",
+                    entity
+                        .map(|(entity_index, entity)| format!(
+                            "#{} {} {}",
+                            entity_index,
+                            entity.get("classname").map(String::as_str).unwrap_or(""),
+                            entity.get("targetname").map(String::as_str).unwrap_or(""),
+                        ))
+                        .unwrap_or("-".to_string())
+                ));
+
+                let visibility = &self.render_state.visibility;
+
+                ui.label(format!(
+                    "Camera leaf: {} Visible faces: {}",
+                    visibility.camera_leaf,
+                    visibility
+                        .visible_faces
+                        .as_ref()
+                        .map(|visible_faces| format!(
+                            "{}/{}",
+                            visible_faces.count(),
+                            visible_faces.capacity()
+                        ))
+                        .unwrap_or("all".to_string())
+                ));
+
                 ui.separator()
             });
     }
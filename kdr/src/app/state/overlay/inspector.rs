@@ -0,0 +1,295 @@
+use ghost::{FrameStats, button_names};
+
+use crate::app::state::{AppState, playback::replay::Replay};
+
+// frames shown before and after the current frame
+const NEARBY_FRAMES: usize = 8;
+const PLOT_HEIGHT: f32 = 80.;
+
+fn frame_row(ui: &mut egui::Ui, replay: &Replay, idx: usize) -> bool {
+    let frame = &replay.ghost.frames()[idx];
+    let is_current = idx == replay.last_frame;
+
+    let cell = |ui: &mut egui::Ui, text: String| {
+        let text = egui::RichText::new(text).monospace();
+
+        if is_current {
+            ui.label(text.strong().color(egui::Color32::WHITE));
+        } else {
+            ui.label(text);
+        }
+    };
+
+    let clicked = ui
+        .selectable_label(is_current, format!("#{}", idx))
+        .clicked();
+
+    cell(
+        ui,
+        format!(
+            "{:.02} {:.02} {:.02}",
+            frame.origin.x, frame.origin.y, frame.origin.z
+        ),
+    );
+    cell(
+        ui,
+        format!(
+            "{:.02} {:.02} {:.02}",
+            frame.viewangles.x, frame.viewangles.y, frame.viewangles.z
+        ),
+    );
+    cell(
+        ui,
+        replay
+            .analysis
+            .frames
+            .get(idx)
+            .map(|frame_stat| format!("{:.02}", frame_stat.horizontal_speed))
+            .unwrap_or("-".to_string()),
+    );
+    cell(
+        ui,
+        frame
+            .buttons
+            .map(|buttons| button_names(buttons).join(" "))
+            .unwrap_or("-".to_string()),
+    );
+    cell(
+        ui,
+        frame
+            .frametime
+            .map(|frametime| format!("{:.01} ms", frametime * 1000.))
+            .unwrap_or("-".to_string()),
+    );
+    cell(
+        ui,
+        frame
+            .fov
+            .map(|fov| format!("{:.0}", fov))
+            .unwrap_or("-".to_string()),
+    );
+
+    let extras = frame.extras.as_ref();
+
+    // weapon sequence only shows when it is played
+    let weapon = replay.weapon_at(idx).unwrap_or("-");
+    let weapon = match extras.and_then(|extra| extra.weapon_sequence) {
+        Some(weapon_sequence) => format!("{} ({})", weapon, weapon_sequence),
+        None => weapon.to_string(),
+    };
+
+    cell(ui, weapon);
+    cell(
+        ui,
+        extras
+            .and_then(|extra| extra.anim.as_ref())
+            .and_then(|anim| anim.sequence)
+            .map(|sequence| sequence.to_string())
+            .unwrap_or("-".to_string()),
+    );
+
+    ui.end_row();
+
+    clicked
+}
+
+/// Draws `value` of every frame over the whole replay with the playhead at `time`.
+///
+/// Returns the time where the plot is clicked or dragged.
+fn time_plot(
+    ui: &mut egui::Ui,
+    frame_stats: &[FrameStats],
+    value: impl Fn(&FrameStats) -> f32,
+    time: f32,
+    duration: f32,
+) -> Option<f32> {
+    let (response, painter) = ui.allocate_painter(
+        egui::vec2(ui.available_width(), PLOT_HEIGHT),
+        egui::Sense::click_and_drag(),
+    );
+    let rect = response.rect;
+
+    painter.rect_filled(rect, 2., egui::Color32::from_gray(20));
+
+    if frame_stats.is_empty() || duration <= 0. {
+        return None;
+    }
+
+    // one point for every pixel, keeping the biggest value so spikes still show
+    let columns = (rect.width() as usize).max(1);
+    let mut buckets: Vec<Option<f32>> = vec![None; columns];
+
+    frame_stats.iter().for_each(|frame_stat| {
+        let column = ((frame_stat.time / duration * columns as f32) as usize).min(columns - 1);
+        let value = value(frame_stat);
+
+        if buckets[column].is_none_or(|bucket| value.abs() > bucket.abs()) {
+            buckets[column] = Some(value);
+        }
+    });
+
+    let (min, max) = buckets
+        .iter()
+        .flatten()
+        .fold((0f32, 0f32), |(min, max), &value| {
+            (min.min(value), max.max(value))
+        });
+
+    // flat line in the middle
+    let (min, max) = if max > min { (min, max) } else { (-1., 1.) };
+
+    let to_y = |value: f32| egui::remap(value, min..=max, rect.bottom()..=rect.top());
+
+    if min < 0. {
+        painter.hline(
+            rect.x_range(),
+            to_y(0.),
+            egui::Stroke::new(1., egui::Color32::from_gray(60)),
+        );
+    }
+
+    let points: Vec<egui::Pos2> = buckets
+        .iter()
+        .enumerate()
+        .filter_map(|(column, value)| {
+            value.map(|value| egui::pos2(rect.left() + column as f32 + 0.5, to_y(value)))
+        })
+        .collect();
+
+    painter.add(egui::Shape::line(
+        points,
+        egui::Stroke::new(1., egui::Color32::LIGHT_GREEN),
+    ));
+
+    // playhead
+    let playhead_x = egui::lerp(rect.left()..=rect.right(), (time / duration).clamp(0., 1.));
+
+    painter.vline(
+        playhead_x,
+        rect.y_range(),
+        egui::Stroke::new(1., egui::Color32::WHITE),
+    );
+
+    let font = egui::FontId::monospace(10.);
+
+    painter.text(
+        rect.left_top(),
+        egui::Align2::LEFT_TOP,
+        format!("{:.0}", max),
+        font.clone(),
+        egui::Color32::GRAY,
+    );
+    painter.text(
+        rect.left_bottom(),
+        egui::Align2::LEFT_BOTTOM,
+        format!("{:.0}", min),
+        font,
+        egui::Color32::GRAY,
+    );
+
+    if !response.clicked() && !response.dragged() {
+        return None;
+    }
+
+    response
+        .interact_pointer_pos()
+        .map(|pos| egui::remap_clamp(pos.x, rect.left()..=rect.right(), 0.0..=duration))
+}
+
+impl AppState {
+    pub(super) fn inspector(&mut self, ctx: &egui::Context) {
+        if !self.ui_state.control_panel.enable_inspector {
+            return;
+        }
+
+        let Some(replay) = self.playback_state.get_replay() else {
+            return;
+        };
+
+        if replay.ghost.frames().is_empty() {
+            return;
+        }
+
+        let current_frame = replay.last_frame.min(replay.ghost.frames().len() - 1);
+        let first_frame = current_frame.saturating_sub(NEARBY_FRAMES);
+        let last_frame = (current_frame + NEARBY_FRAMES).min(replay.ghost.frames().len() - 1);
+
+        let frame_stats = &replay.analysis.frames;
+        let duration = replay.analysis.stats.duration;
+
+        let mut seek_frame = None;
+        let mut seek_time = None;
+
+        egui::Window::new("Inspector")
+            .resizable(true)
+            .default_open(true)
+            .collapsible(true)
+            .default_width(720.)
+            .show(ctx, |ui| {
+                egui::ScrollArea::horizontal().show(ui, |ui| {
+                    egui::Grid::new("inspector-frames")
+                        .num_columns(9)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            [
+                                "Frame",
+                                "Origin",
+                                "Viewangles",
+                                "Speed",
+                                "Buttons",
+                                "Frametime",
+                                "FOV",
+                                "Weapon",
+                                "Sequence",
+                            ]
+                            .into_iter()
+                            .for_each(|header| {
+                                ui.strong(header);
+                            });
+                            ui.end_row();
+
+                            (first_frame..=last_frame).for_each(|idx| {
+                                if frame_row(ui, replay, idx) {
+                                    seek_frame = Some(idx);
+                                }
+                            });
+                        });
+                });
+
+                ui.separator();
+
+                ui.label("Speed");
+                if let Some(time) = time_plot(
+                    ui,
+                    frame_stats,
+                    |frame_stat| frame_stat.horizontal_speed,
+                    self.time,
+                    duration,
+                ) {
+                    seek_time = Some(time);
+                }
+
+                ui.label("Yaw delta");
+                if let Some(time) = time_plot(
+                    ui,
+                    frame_stats,
+                    |frame_stat| frame_stat.yaw_delta,
+                    self.time,
+                    duration,
+                ) {
+                    seek_time = Some(time);
+                }
+            });
+
+        // frame times of the analysis are the same as the replay
+        if let Some(time) = seek_time {
+            seek_frame = frame_stats
+                .partition_point(|frame_stat| frame_stat.time <= time)
+                .checked_sub(1);
+        }
+
+        if let Some(frame_idx) = seek_frame {
+            self.seek_replay_frame(frame_idx);
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use ghost::{JumpKind, JumpStat};
+
+use crate::app::state::AppState;
+
+#[derive(Default)]
+pub struct JumpstatsUIState {
+    /// Jumps of the current replay on the current map.
+    ///
+    /// Traced when the panel is opened because it goes through every frame.
+    pub jumps: Option<Vec<JumpStat>>,
+}
+
+impl AppState {
+    pub(super) fn jumpstats(&mut self, ctx: &egui::Context) {
+        if !self.ui_state.control_panel.enable_jumpstats {
+            return;
+        }
+
+        let Some(replay) = self.playback_state.get_replay() else {
+            return;
+        };
+
+        if self.ui_state.jumpstats.jumps.is_none() {
+            let Some(bsp) = self.other_resources.bsp.as_ref() else {
+                return;
+            };
+
+            self.ui_state.jumpstats.jumps = ghost::jumpstats(&replay.ghost, bsp).into();
+        }
+
+        let Some(jumps) = self.ui_state.jumpstats.jumps.as_ref() else {
+            return;
+        };
+
+        let mut seek_frame = None;
+
+        egui::Window::new("Jumpstats")
+            .resizable(false)
+            .default_open(true)
+            .collapsible(true)
+            .show(ctx, |ui| {
+                // other maps of the replay are traced against the wrong map
+                let jumps: Vec<&JumpStat> = jumps
+                    .iter()
+                    .filter(|jump| {
+                        replay.ghost.get_segment_index(jump.start_frame) == replay.segment
+                    })
+                    .collect();
+
+                if jumps.is_empty() {
+                    ui.label("No jumps");
+                    return;
+                }
+
+                egui::ScrollArea::vertical()
+                    .max_height(400.)
+                    .show(ui, |ui| {
+                        egui::Grid::new("jumpstats-grid")
+                            .num_columns(8)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                [
+                                    "Type", "Distance", "Block", "Pre", "Max", "Strafes", "Sync",
+                                    "",
+                                ]
+                                .into_iter()
+                                .for_each(|header| {
+                                    ui.strong(header);
+                                });
+                                ui.end_row();
+
+                                jumps.iter().for_each(|jump| {
+                                    let kind = match jump.kind {
+                                        JumpKind::LongJump => "LJ",
+                                        JumpKind::Bhop => "BJ",
+                                        JumpKind::CountJump => "CJ",
+                                        JumpKind::LadderJump => "LAJ",
+                                    };
+
+                                    ui.label(kind);
+                                    ui.label(format!("{:.03}", jump.distance));
+                                    ui.label(
+                                        jump.block
+                                            .map(|block| format!("{block:.0}"))
+                                            .unwrap_or("-".to_string()),
+                                    );
+                                    ui.label(format!("{:.02}", jump.prestrafe));
+                                    ui.label(format!("{:.02}", jump.max_speed));
+                                    ui.label(jump.strafes.to_string());
+                                    ui.label(
+                                        jump.sync
+                                            .map(|sync| format!("{:.01}%", sync * 100.))
+                                            .unwrap_or("-".to_string()),
+                                    );
+
+                                    if ui.button("Go").clicked() {
+                                        seek_frame = Some(jump.start_frame);
+                                    }
+
+                                    ui.end_row();
+                                });
+                            });
+                    });
+            });
+
+        if let Some(frame_idx) = seek_frame {
+            self.seek_replay_frame(frame_idx);
+        }
+    }
+}
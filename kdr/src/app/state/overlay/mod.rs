@@ -1,5 +1,7 @@
 use control_panel::{ControlPanelUIState, PostProcessingControlState};
+use jumpstats::JumpstatsUIState;
 use map_list::MapListUIState;
+use race::RaceUIState;
 use replay_list::ReplayListUIState;
 use unknown_format_modal::UnknownFormatModalUIState;
 
@@ -10,11 +12,15 @@ use super::AppState;
 pub mod control_panel;
 mod crosshair;
 mod debug_panel;
+mod inspector;
+mod jumpstats;
 mod loading_spinner;
 mod map_list;
 mod puppet_player_info;
 mod puppet_player_list;
+mod race;
 mod replay_list;
+mod run_stats;
 mod seekbar;
 pub mod text;
 mod unknown_format_modal;
@@ -27,6 +33,8 @@ pub struct UIState {
     pub map_list: MapListUIState,
     pub replay_list: ReplayListUIState,
     pub debug_panel: DebugPanelUIState,
+    pub jumpstats: JumpstatsUIState,
+    pub race: RaceUIState,
     pub unknown_format_modal: UnknownFormatModalUIState,
     pub toaster: egui_notify::Toasts,
 }
@@ -40,6 +48,8 @@ impl Default for UIState {
             map_list: MapListUIState::default(),
             replay_list: ReplayListUIState::default(),
             debug_panel: DebugPanelUIState::default(),
+            jumpstats: JumpstatsUIState::default(),
+            race: RaceUIState::default(),
             unknown_format_modal: UnknownFormatModalUIState::default(),
             toaster: egui_notify::Toasts::default(),
         }
@@ -69,8 +79,15 @@ impl AppState {
                 self.map_list(ctx);
                 self.puppet_player_list(ctx);
                 self.debug_panel(ctx);
+                self.run_stats(ctx);
+                self.jumpstats(ctx);
+                self.inspector(ctx);
+                self.race(ctx);
             }
 
+            self.race_route(ctx);
+            self.race_name_tags(ctx);
+            self.race_live_delta(ctx);
             self.draw_entity_text(ctx);
             self.draw_say_text(ctx);
             self.puppet_player_info(ctx);
@@ -0,0 +1,462 @@
+use cgmath::{Matrix4, Vector4};
+use tracing::warn;
+
+use crate::{
+    app::{
+        AppEvent,
+        state::{
+            AppState,
+            playback::replay::{RaceTag, race_time},
+        },
+    },
+    utils::format_time,
+};
+
+// above the head of the player model
+const NAME_TAG_HEIGHT: f32 = 44.;
+// route is drawn on the feet
+const ROUTE_HEIGHT: f32 = -32.;
+// route is fully red or green at this many seconds behind or ahead
+const ROUTE_COLOR_RANGE: f32 = 1.;
+
+pub struct RaceUIState {
+    pub show_name_tags: bool,
+    /// Draws the route of the first person replay, coloured by time against [`RaceUIState::route_racer`].
+    pub show_route: bool,
+    pub route_racer: usize,
+}
+
+impl Default for RaceUIState {
+    fn default() -> Self {
+        Self {
+            show_name_tags: true,
+            show_route: true,
+            route_racer: 0,
+        }
+    }
+}
+
+enum RaceAction {
+    FirstPerson(usize),
+    Remove(usize),
+}
+
+fn color32(color: [u8; 3]) -> egui::Color32 {
+    egui::Color32::from_rgb(color[0], color[1], color[2])
+}
+
+/// White when even, going red when behind and green when ahead.
+fn delta_color(delta: f32) -> egui::Color32 {
+    let t = (delta.abs() / ROUTE_COLOR_RANGE).min(1.);
+    let target = if delta > 0. {
+        egui::Color32::RED
+    } else {
+        egui::Color32::GREEN
+    };
+
+    let mix = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * t) as u8;
+
+    egui::Color32::from_rgb(
+        mix(255, target.r()),
+        mix(255, target.g()),
+        mix(255, target.b()),
+    )
+}
+
+fn delta_text(delta: Option<f32>) -> egui::RichText {
+    match delta {
+        Some(delta) => egui::RichText::new(format!("{:+.02}", delta)).color(delta_color(delta)),
+        None => egui::RichText::new("-"),
+    }
+}
+
+/// Screen position of a point in the world, `None` when it is behind the camera.
+fn to_screen(
+    view_proj: Matrix4<f32>,
+    point: [f32; 3],
+    width: f32,
+    height: f32,
+) -> Option<egui::Pos2> {
+    let clip = view_proj * Vector4::new(point[0], point[1], point[2], 1.);
+
+    // also too close to the camera to be useful
+    if clip.w < 1. {
+        return None;
+    }
+
+    let ndc = clip.truncate() / clip.w;
+
+    Some(egui::pos2(
+        (ndc.x + 1.) / 2. * width,
+        (1. - ndc.y) / 2. * height,
+    ))
+}
+
+fn model_combo_box(
+    ui: &mut egui::Ui,
+    id: impl std::hash::Hash,
+    tag: &mut RaceTag,
+    models: &[String],
+) {
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(&tag.model_name)
+        .width(80.)
+        .show_ui(ui, |ui| {
+            models.iter().for_each(|model_name| {
+                ui.selectable_value(&mut tag.model_name, model_name.to_owned(), model_name);
+            });
+        });
+}
+
+impl AppState {
+    pub(super) fn race(&mut self, ctx: &egui::Context) {
+        if !self.ui_state.control_panel.enable_race {
+            return;
+        }
+
+        let models: Vec<String> = self
+            .render_state
+            .playermodel_buffers
+            .iter()
+            .map(|buffer| buffer.name.to_owned())
+            .collect();
+
+        let time = self.time;
+        let replay_list = &self.other_resources.replay_list;
+        let current_replay = self.file_state.selected_file.as_ref();
+
+        let Some(replay) = self.playback_state.get_replay_mut() else {
+            return;
+        };
+
+        let mut action = None;
+        let mut requested_racer = None;
+
+        egui::Window::new("Race")
+            .resizable(false)
+            .default_open(true)
+            .collapsible(true)
+            .show(ctx, |ui| {
+                if replay.ghost.segments.len() > 1 {
+                    ui.label("Races need a replay on one map");
+                    return;
+                }
+
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_salt("race-add-replay")
+                        .selected_text("Add replay")
+                        .height(300.)
+                        .show_ui(ui, |ui| {
+                            replay_list
+                                .iter()
+                                .filter(|replay_name| Some(*replay_name) != current_replay)
+                                .for_each(|replay_name| {
+                                    if ui.selectable_label(false, replay_name).clicked() {
+                                        requested_racer = Some(replay_name.to_owned());
+                                    }
+                                });
+                        });
+
+                    ui.checkbox(&mut self.ui_state.race.show_name_tags, "Name tags");
+                    ui.checkbox(&mut self.ui_state.race.show_route, "Route");
+                });
+
+                if replay.racers.is_empty() {
+                    ui.label("No racers");
+                    return;
+                }
+
+                ui.separator();
+
+                // None is the first person replay
+                let main_time = race_time(&replay.ghost, None);
+                let mut order: Vec<(Option<usize>, f32)> = std::iter::once((None, main_time))
+                    .chain(
+                        replay
+                            .racers
+                            .iter()
+                            .enumerate()
+                            .map(|(idx, racer)| (Some(idx), racer.run_time)),
+                    )
+                    .collect();
+
+                order.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+                let leader_time = order[0].1;
+                let has_no_players = replay.players.is_empty();
+
+                egui::Grid::new("race-leaderboard")
+                    .num_columns(9)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        [
+                            "#", "", "Name", "Time", "Gap", "Live", "Offset", "Model", "",
+                        ]
+                        .into_iter()
+                        .for_each(|header| {
+                            ui.strong(header);
+                        });
+                        ui.end_row();
+
+                        order
+                            .iter()
+                            .enumerate()
+                            .for_each(|(position, (racer_idx, run_time))| {
+                                ui.label((position + 1).to_string());
+
+                                let tag = match racer_idx {
+                                    Some(idx) => &mut replay.racers[*idx].tag,
+                                    None => &mut replay.tag,
+                                };
+
+                                ui.color_edit_button_srgb(&mut tag.color);
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut tag.name)
+                                        .desired_width(120.)
+                                        .text_color(color32(tag.color)),
+                                );
+
+                                ui.label(format_time(*run_time));
+
+                                if position == 0 {
+                                    ui.label("-");
+                                } else {
+                                    ui.label(format!("+{:.03}", run_time - leader_time));
+                                }
+
+                                match racer_idx {
+                                    Some(idx) => {
+                                        ui.label(delta_text(replay.racers[*idx].delta_at(time)))
+                                            .on_hover_text(
+                                                "Your time minus theirs at the same spot",
+                                            );
+                                    }
+                                    None => {
+                                        ui.label("-");
+                                    }
+                                }
+
+                                match racer_idx {
+                                    Some(idx) => {
+                                        ui.add(
+                                            egui::DragValue::new(&mut replay.racers[*idx].offset)
+                                                .speed(0.01)
+                                                .fixed_decimals(3)
+                                                .suffix(" s"),
+                                        )
+                                        .on_hover_text("Seconds the racer starts after you");
+                                    }
+                                    None => {
+                                        ui.label("-");
+                                    }
+                                }
+
+                                let tag = match racer_idx {
+                                    Some(idx) => &mut replay.racers[*idx].tag,
+                                    None => &mut replay.tag,
+                                };
+
+                                model_combo_box(ui, ("race-model", racer_idx), tag, &models);
+
+                                match racer_idx {
+                                    Some(idx) => {
+                                        ui.horizontal(|ui| {
+                                            // other players in the demo follow the first person replay
+                                            if ui
+                                                .add_enabled(has_no_players, egui::Button::new("👁"))
+                                                .on_hover_text("First person")
+                                                .clicked()
+                                            {
+                                                action = Some(RaceAction::FirstPerson(*idx));
+                                            }
+
+                                            if ui.button("✖").on_hover_text("Remove").clicked() {
+                                                action = Some(RaceAction::Remove(*idx));
+                                            }
+                                        });
+                                    }
+                                    None => {
+                                        ui.label("POV");
+                                    }
+                                }
+
+                                ui.end_row();
+                            });
+                    });
+
+                ui.horizontal(|ui| {
+                    let route_racer = &mut self.ui_state.race.route_racer;
+
+                    ui.label("Route against:");
+                    egui::ComboBox::from_id_salt("race-route-racer")
+                        .selected_text(
+                            replay
+                                .racers
+                                .get(*route_racer)
+                                .map(|racer| racer.tag.name.as_str())
+                                .unwrap_or("-"),
+                        )
+                        .show_ui(ui, |ui| {
+                            replay.racers.iter().enumerate().for_each(|(idx, racer)| {
+                                ui.selectable_value(route_racer, idx, &racer.tag.name);
+                            });
+                        });
+                });
+            });
+
+        if let Some(replay_name) = requested_racer {
+            self.event_loop_proxy
+                .send_event(AppEvent::RequestRacer(replay_name))
+                .unwrap_or_else(|_| warn!("Failed to send RequestRacer"));
+        }
+
+        match action {
+            Some(RaceAction::FirstPerson(racer_idx)) => self.set_first_person_racer(racer_idx),
+            Some(RaceAction::Remove(racer_idx)) => self.remove_racer(racer_idx),
+            None => (),
+        }
+    }
+
+    /// Draws the names of racers above their player models.
+    pub(super) fn race_name_tags(&mut self, ctx: &egui::Context) {
+        if !self.ui_state.race.show_name_tags {
+            return;
+        }
+
+        let Some(replay) = self.playback_state.get_replay() else {
+            return;
+        };
+
+        if replay.racers.is_empty() {
+            return;
+        }
+
+        let Some(entity_state) = self.entity_state.as_ref() else {
+            return;
+        };
+
+        let Some((width, height)) = self.egui_window_dimensions(ctx) else {
+            return;
+        };
+
+        let camera = &self.render_state.camera;
+        let view_proj = camera.proj() * camera.view();
+        let painter = ctx.layer_painter(egui::LayerId::background());
+        let players = &entity_state.playermodel_state.players;
+
+        // first person player model only shows in free cam
+        let tags = std::iter::once((players.first(), &replay.tag)).chain(
+            replay
+                .racers
+                .iter()
+                .enumerate()
+                .map(|(idx, racer)| (players.get(1 + replay.players.len() + idx), &racer.tag)),
+        );
+
+        tags.for_each(|(player, tag)| {
+            let Some(player) = player.filter(|player| player.should_draw) else {
+                return;
+            };
+
+            let Some(screen_pos) = to_screen(
+                view_proj,
+                [
+                    player.origin.x,
+                    player.origin.y,
+                    player.origin.z + NAME_TAG_HEIGHT,
+                ],
+                width as f32,
+                height as f32,
+            ) else {
+                return;
+            };
+
+            painter.text(
+                screen_pos,
+                egui::Align2::CENTER_BOTTOM,
+                &tag.name,
+                egui::FontId::proportional(14.),
+                color32(tag.color),
+            );
+        });
+    }
+
+    /// Shows how far ahead or behind the first person replay is against every racer.
+    pub(super) fn race_live_delta(&mut self, ctx: &egui::Context) {
+        let Some(replay) = self.playback_state.get_replay() else {
+            return;
+        };
+
+        if replay.racers.is_empty() {
+            return;
+        }
+
+        egui::Area::new(egui::Id::new("race-live-delta"))
+            .anchor(egui::Align2::CENTER_TOP, [0., 0.])
+            .constrain(false)
+            .show(ctx, |ui| {
+                egui::Frame::new()
+                    .fill(egui::Color32::from_gray(30))
+                    .inner_margin(8.0)
+                    .outer_margin(8.0)
+                    .corner_radius(8.0)
+                    .stroke(egui::Stroke::new(0.5, egui::Color32::GRAY))
+                    .show(ui, |ui| {
+                        replay.racers.iter().for_each(|racer| {
+                            ui.horizontal(|ui| {
+                                ui.label(
+                                    egui::RichText::new(&racer.tag.name)
+                                        .color(color32(racer.tag.color))
+                                        .font(egui::FontId::new(16., egui::FontFamily::Monospace)),
+                                );
+                                ui.label(
+                                    delta_text(racer.delta_at(self.time))
+                                        .font(egui::FontId::new(16., egui::FontFamily::Monospace)),
+                                );
+                            });
+                        });
+                    });
+            });
+    }
+
+    /// Draws the route of the first person replay, coloured by the time against a racer.
+    pub(super) fn race_route(&mut self, ctx: &egui::Context) {
+        if !self.ui_state.race.show_route {
+            return;
+        }
+
+        let Some(replay) = self.playback_state.get_replay() else {
+            return;
+        };
+
+        let Some(racer) = replay.racers.get(self.ui_state.race.route_racer) else {
+            return;
+        };
+
+        let Some((width, height)) = self.egui_window_dimensions(ctx) else {
+            return;
+        };
+
+        let camera = &self.render_state.camera;
+        let view_proj = camera.proj() * camera.view();
+        let painter = ctx.layer_painter(egui::LayerId::background());
+
+        let mut last_pos: Option<egui::Pos2> = None;
+
+        racer.route_points.iter().for_each(|route_point| {
+            let point = route_point.origin + glam::Vec3::Z * ROUTE_HEIGHT;
+            let screen_pos = to_screen(view_proj, point.to_array(), width as f32, height as f32);
+
+            if let (Some(from), Some(to)) = (last_pos, screen_pos) {
+                let color = route_point
+                    .delta
+                    .map(|delta| delta_color(delta - racer.offset))
+                    .unwrap_or(egui::Color32::GRAY);
+
+                painter.line_segment([from, to], egui::Stroke::new(2., color));
+            }
+
+            last_pos = screen_pos;
+        });
+    }
+}
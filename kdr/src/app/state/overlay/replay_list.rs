@@ -1,8 +1,12 @@
+use ghost::GhostMetadata;
 use tracing::warn;
 
-use crate::app::{
-    AppEvent,
-    state::{AppState, file::SelectedFileType},
+use crate::{
+    app::{
+        AppEvent,
+        state::{AppState, file::SelectedFileType},
+    },
+    utils::format_time,
 };
 
 #[derive(Default)]
@@ -14,6 +18,18 @@ pub struct ReplayListUIState {
     should_focus: bool,
 }
 
+/// Player name and time, whichever is known.
+fn replay_metadata_text(metadata: &GhostMetadata) -> String {
+    let player_name = metadata.player_name.clone();
+    let time = metadata.official_time.map(format_time);
+
+    player_name
+        .into_iter()
+        .chain(time)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 impl AppState {
     // mimicking map_list.rs
     // make sure they both behave similarly
@@ -72,7 +88,18 @@ impl AppState {
                                     &self.other_resources.replay_list[row]
                                 };
 
-                            let selectable_label = egui::SelectableLabel::new(false, replay_name);
+                            let label = match self
+                                .other_resources
+                                .replay_metadata
+                                .get(replay_name)
+                                .map(replay_metadata_text)
+                                .filter(|text| !text.is_empty())
+                            {
+                                Some(text) => format!("{} ({})", replay_name, text),
+                                None => replay_name.to_owned(),
+                            };
+
+                            let selectable_label = egui::SelectableLabel::new(false, label);
 
                             if ui.add(selectable_label).clicked() {
                                 self.event_loop_proxy
@@ -123,9 +150,26 @@ impl AppState {
                                             .iter()
                                             .enumerate()
                                             .for_each(|(replay_idx, replay_name)| {
-                                                if replay_name.to_lowercase().contains(
-                                                    &self.ui_state.replay_list.search_text,
-                                                ) {
+                                                let search_text =
+                                                    &self.ui_state.replay_list.search_text;
+
+                                                // player names are from the server or after loading
+                                                let player_name_matched = self
+                                                    .other_resources
+                                                    .replay_metadata
+                                                    .get(replay_name)
+                                                    .and_then(|metadata| {
+                                                        metadata.player_name.as_ref()
+                                                    })
+                                                    .is_some_and(|player_name| {
+                                                        player_name
+                                                            .to_lowercase()
+                                                            .contains(search_text)
+                                                    });
+
+                                                if replay_name.to_lowercase().contains(search_text)
+                                                    || player_name_matched
+                                                {
                                                     filtered.push(replay_idx);
                                                 }
                                             });
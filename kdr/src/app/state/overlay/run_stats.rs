@@ -0,0 +1,127 @@
+use ghost::{MoveEvent, StrafeDirection};
+
+use crate::{app::state::AppState, utils::format_time};
+
+impl AppState {
+    pub(super) fn run_stats(&mut self, ctx: &egui::Context) {
+        if !self.ui_state.control_panel.enable_run_stats {
+            return;
+        }
+
+        let Some(replay) = self.playback_state.get_replay() else {
+            return;
+        };
+
+        let analysis = &replay.analysis;
+        let stats = &analysis.stats;
+
+        egui::Window::new("Run Stats")
+            .resizable(false)
+            .vscroll(false)
+            .default_open(true)
+            .collapsible(true)
+            .show(ctx, |ui| {
+                // current frame
+                if let Some(frame) = analysis.frames.get(replay.last_frame) {
+                    egui::Grid::new("run-stats-frame")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            ui.label("Frame");
+                            ui.label(replay.last_frame.to_string());
+                            ui.end_row();
+
+                            ui.label("Speed");
+                            ui.label(format!("{:.02}", frame.horizontal_speed));
+                            ui.end_row();
+
+                            ui.label("Vertical speed");
+                            ui.label(format!("{:.02}", frame.vertical_speed));
+                            ui.end_row();
+
+                            ui.label("Yaw delta");
+                            ui.label(format!("{:.03}", frame.yaw_delta));
+                            ui.end_row();
+
+                            let state = match (frame.on_ground, frame.ducking) {
+                                (true, true) => "Ground, ducking",
+                                (true, false) => "Ground",
+                                (false, true) => "Air, ducking",
+                                (false, false) => "Air",
+                            };
+
+                            ui.label("State");
+                            ui.label(state);
+                            ui.end_row();
+
+                            let strafe = match frame.strafe {
+                                Some(StrafeDirection::Left) => "Left",
+                                Some(StrafeDirection::Right) => "Right",
+                                None => "-",
+                            };
+
+                            ui.label("Strafe");
+                            ui.label(strafe);
+                            ui.end_row();
+
+                            let event = match frame.event {
+                                Some(MoveEvent::Jump) => "Jump",
+                                Some(MoveEvent::Land) => "Land",
+                                None => "-",
+                            };
+
+                            ui.label("Event");
+                            ui.label(event);
+                            ui.end_row();
+                        });
+                }
+
+                ui.separator();
+
+                // whole run
+                egui::Grid::new("run-stats-summary")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Time");
+                        ui.label(format_time(stats.duration));
+                        ui.end_row();
+
+                        ui.label("Max speed");
+                        ui.label(format!("{:.02}", stats.max_speed));
+                        ui.end_row();
+
+                        ui.label("Average speed");
+                        ui.label(format!("{:.02}", stats.average_speed));
+                        ui.end_row();
+
+                        ui.label("Distance");
+                        ui.label(format!("{:.02}", stats.distance));
+                        ui.end_row();
+
+                        ui.label("Jumps");
+                        ui.label(stats.jumps.to_string());
+                        ui.end_row();
+
+                        ui.label("Strafes");
+                        ui.label(stats.strafes.to_string());
+                        ui.end_row();
+
+                        ui.label("Sync");
+                        ui.label(
+                            stats
+                                .sync
+                                .map(|sync| format!("{:.01}%", sync * 100.))
+                                .unwrap_or("-".to_string()),
+                        );
+                        ui.end_row();
+
+                        ui.label("Air time");
+                        ui.label(format_time(stats.air_time));
+                        ui.end_row();
+
+                        ui.label("Duck time");
+                        ui.label(format_time(stats.duck_time));
+                        ui.end_row();
+                    });
+            });
+    }
+}
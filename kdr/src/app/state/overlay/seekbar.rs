@@ -1,8 +1,21 @@
+use ghost::Severity;
+
 use crate::{
     app::{constants::DEFAULT_FRAMETIME, state::AppState},
     utils::format_time,
 };
 
+// how close the cursor has to be to show a finding
+const FINDING_HOVER_DISTANCE: f32 = 4.;
+
+fn severity_color(severity: Severity) -> egui::Color32 {
+    match severity {
+        Severity::Info => egui::Color32::LIGHT_BLUE,
+        Severity::Warning => egui::Color32::YELLOW,
+        Severity::Suspicious => egui::Color32::RED,
+    }
+}
+
 impl AppState {
     pub(super) fn seek_bar(&mut self, ctx: &egui::Context) {
         const SLIDER_WIDTH_PERC: f32 = 0.65;
@@ -64,6 +77,7 @@ impl AppState {
                                     .show_value(false);
 
                             let response = ui.add(timeline_slider);
+                            let response = self.seek_bar_findings(ui, response, slider_max);
 
                             if response.changed() {
                                 // if seekbar is used, reset text states and alike
@@ -96,6 +110,59 @@ impl AppState {
                                 self.paused = !self.paused;
                             }
 
+                            // frame step, only replay has discrete frames
+                            if let Some(replay) = self.playback_state.get_replay() {
+                                let frame_index = replay.shown_frame();
+
+                                let step_back_button =
+                                    egui::Button::new(egui::RichText::new("⏪").size(24.0))
+                                        .min_size([pause_button_size, pause_button_size].into());
+                                let step_forward_button =
+                                    egui::Button::new(egui::RichText::new("⏩").size(24.0))
+                                        .min_size([pause_button_size, pause_button_size].into());
+
+                                if ui
+                                    .add(step_back_button)
+                                    .on_hover_text("Previous frame")
+                                    .clicked()
+                                {
+                                    self.step_replay_frame(-1);
+                                }
+
+                                if ui
+                                    .add(step_forward_button)
+                                    .on_hover_text("Next frame")
+                                    .clicked()
+                                {
+                                    self.step_replay_frame(1);
+                                }
+
+                                let run_start_frame =
+                                    replay.run_timer.as_ref().map(|timer| timer.start_frame);
+
+                                if let Some(run_start_frame) = run_start_frame {
+                                    let run_start_button =
+                                        egui::Button::new(egui::RichText::new("⏮").size(24.0))
+                                            .min_size(
+                                                [pause_button_size, pause_button_size].into(),
+                                            );
+
+                                    if ui
+                                        .add(run_start_button)
+                                        .on_hover_text("Run start")
+                                        .clicked()
+                                    {
+                                        self.seek_replay_frame(run_start_frame);
+                                    }
+                                }
+
+                                let frame_text = egui::RichText::new(format!("#{}", frame_index))
+                                    .size(14.0)
+                                    .monospace();
+
+                                ui.add(egui::Label::new(frame_text).selectable(false));
+                            }
+
                             // playback speed slider/drag value
                             let drag_size = height as f32 * 0.03;
                             let speed_slider = egui::DragValue::new(&mut self.playback_speed)
@@ -114,4 +181,58 @@ impl AppState {
                     });
             });
     }
+
+    /// Marks checker findings on the seek bar and shows the ones under the cursor.
+    fn seek_bar_findings(
+        &self,
+        ui: &egui::Ui,
+        response: egui::Response,
+        slider_max: f32,
+    ) -> egui::Response {
+        let Some(replay) = self.playback_state.get_replay() else {
+            return response;
+        };
+
+        if replay.findings.is_empty() || slider_max <= 0. {
+            return response;
+        }
+
+        // same as the slider rail
+        let rail = response.rect.x_range().shrink(response.rect.height() / 2.5);
+        let hover_x = response.hover_pos().map(|pos| pos.x);
+        let painter = ui.painter();
+
+        let hovered: Vec<_> = replay
+            .findings
+            .iter()
+            .filter(|finding| {
+                let time = replay
+                    .ghost
+                    .get_frame_time(finding.frame, None)
+                    .unwrap_or(finding.frame as f32 * DEFAULT_FRAMETIME);
+                let x = egui::lerp(rail, (time / slider_max).clamp(0., 1.));
+
+                painter.vline(
+                    x,
+                    response.rect.y_range(),
+                    egui::Stroke::new(2., severity_color(finding.severity)),
+                );
+
+                hover_x.is_some_and(|hover_x| (hover_x - x).abs() <= FINDING_HOVER_DISTANCE)
+            })
+            .collect();
+
+        if hovered.is_empty() {
+            return response;
+        }
+
+        response.on_hover_ui_at_pointer(|ui| {
+            hovered.iter().for_each(|finding| {
+                ui.colored_label(
+                    severity_color(finding.severity),
+                    format!("#{}: {}", finding.frame, finding.message),
+                );
+            });
+        })
+    }
 }
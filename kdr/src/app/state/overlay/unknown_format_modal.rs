@@ -39,6 +39,7 @@ impl AppState {
                     let sg_code: &str = GhostBlobType::SurfGateway.into();
                     let rj_code: &str = GhostBlobType::RomanianJumpers.into();
                     let hlkz_code: &str = GhostBlobType::SRHLKZ.into();
+                    let kdr_code: &str = GhostBlobType::Kdr.into();
 
                     ui.selectable_value(
                         &mut self.ui_state.unknown_format_modal.selected,
@@ -69,6 +70,12 @@ impl AppState {
                         hlkz_code.to_string(),
                         "SourceRuns HLKZ",
                     );
+
+                    ui.selectable_value(
+                        &mut self.ui_state.unknown_format_modal.selected,
+                        kdr_code.to_string(),
+                        "kdr",
+                    );
                 });
 
             if ui.button("Go").clicked() {
@@ -97,6 +104,7 @@ impl AppState {
                     .send_event(AppEvent::ReceiveReplayBlob {
                         replay_name: file_name.into(),
                         replay_blob: ghost_blob,
+                        as_racer: false,
                     })
                     .unwrap_or_else(|_| warn!("Cannot send ReceiveReplayBlob"));
 
@@ -39,6 +39,14 @@ impl PlaybackState {
         }
     }
 
+    pub fn get_replay_mut(&mut self) -> Option<&mut Replay> {
+        if let PlaybackMode::Replay(x) = &mut self.playback_mode {
+            Some(x)
+        } else {
+            None
+        }
+    }
+
     pub fn set_puppet(&mut self, puppet: Puppet) {
         self.playback_mode = PlaybackMode::Live(puppet);
     }
@@ -1,18 +1,41 @@
 use cgmath::Deg;
-use ghost::{GhostFrameEntityText, GhostInfo};
+use ghost::{
+    Finding, GhostFrame, GhostFrameEntityText, GhostInfo, RouteComparison, RunAnalysis, RunTimer,
+};
+use loader::MapIdentifier;
+use tracing::warn;
 
-use crate::app::state::{
-    AppState,
-    overlay::text::{MAX_SAY_TEXT, SAY_TEXT_LIFE},
+use crate::app::{
+    AppEvent,
+    constants::DEFAULT_FRAMETIME,
+    state::{
+        AppState,
+        entities::playermodel::PlayerModel,
+        overlay::text::{MAX_SAY_TEXT, SAY_TEXT_LIFE},
+        playback::PlaybackMode,
+    },
 };
 
+// points of the drawn route closer than this are skipped
+const ROUTE_MIN_DISTANCE: f32 = 16.;
+
+/// Colours given to racers in the order they are added.
+pub const RACER_COLORS: [[u8; 3]; 6] = [
+    [255, 200, 0],
+    [0, 180, 255],
+    [255, 80, 80],
+    [120, 255, 120],
+    [220, 120, 255],
+    [255, 255, 255],
+];
+
 /// How a replay is played.
 pub enum ReplayPlaybackMode {
     /// One discrete replay frame for every tick.
     ///
     /// (current frame)
     ///
-    /// Not really working right now but it is there.
+    /// App time is snapped to the start of the current frame so the seek bar still works.
     Immediate(usize),
     /// Interpolated replay frame for the current time in the app.
     ///
@@ -20,24 +43,251 @@ pub enum ReplayPlaybackMode {
     Interpolated,
     /// No interpolation.
     ///
-    // TODO: make ghost playback do this
+    /// Shows the exact recorded frame for the current time in the app.
     FrameAccurate,
 }
 
 pub struct Replay {
     pub ghost: GhostInfo,
+    /// Other players in the replay, drawn as player models.
+    ///
+    /// Player model `i + 1` is for player `i` because the first one is the replay ghost.
+    pub players: Vec<GhostInfo>,
     pub playback_mode: ReplayPlaybackMode,
     // recorded last frame in the ghost
     // with this, we can know if we missed anything
     // and then fire all the events between current frame and last frame + 1
     pub last_frame: usize,
+    /// Ghost segment of the loaded map.
+    pub segment: usize,
+    /// The next map is being loaded, so the replay time should be kept.
+    pub changing_map: bool,
+    /// Movement data of [`Replay::ghost`] for the stats panel.
+    pub analysis: RunAnalysis,
+    /// Frames of [`Replay::ghost`] that change weapon, for the inspector.
+    pub weapon_changes: Vec<usize>,
+    /// Suspicious input and timing of [`Replay::ghost`], shown on the seek bar.
+    pub findings: Vec<Finding>,
+    /// Run between the timer buttons of the loaded map.
+    pub run_timer: Option<RunTimer>,
+    /// How [`Replay::ghost`] shows up in a race.
+    pub tag: RaceTag,
+    /// Other replays of the same map, drawn as player models after [`Replay::players`].
+    pub racers: Vec<Racer>,
+}
+
+impl Replay {
+    /// Recorded frame that is on screen, same as the one that events are fired up to.
+    pub fn shown_frame(&self) -> usize {
+        match self.playback_mode {
+            ReplayPlaybackMode::Immediate(current_frame) => current_frame,
+            ReplayPlaybackMode::Interpolated | ReplayPlaybackMode::FrameAccurate => self.last_frame,
+        }
+    }
+
+    /// Weapon held at frame `idx`, from the last weapon change.
+    pub fn weapon_at(&self, idx: usize) -> Option<&str> {
+        let changes = self
+            .weapon_changes
+            .partition_point(|&frame_idx| frame_idx <= idx);
+
+        changes
+            .checked_sub(1)
+            .and_then(|change| {
+                self.ghost.frames()[self.weapon_changes[change]]
+                    .extras
+                    .as_ref()
+            })
+            .and_then(|extra| extra.weapon_change.as_deref())
+    }
+}
+
+/// Frames of `ghost` that change weapon.
+pub fn weapon_changes(ghost: &GhostInfo) -> Vec<usize> {
+    ghost
+        .frames()
+        .iter()
+        .enumerate()
+        .filter(|(_, frame)| {
+            frame
+                .extras
+                .as_ref()
+                .is_some_and(|extra| extra.weapon_change.is_some())
+        })
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+/// Name tag, colour and player model of a replay in a race.
+pub struct RaceTag {
+    pub name: String,
+    pub color: [u8; 3],
+    pub model_name: String,
+}
+
+impl RaceTag {
+    pub fn new(ghost: &GhostInfo, color: [u8; 3]) -> Self {
+        Self {
+            name: ghost.display_name().to_owned(),
+            color,
+            model_name: "leet".into(),
+        }
+    }
+}
+
+/// Another replay raced against [`Replay::ghost`].
+pub struct Racer {
+    pub ghost: GhostInfo,
+    pub tag: RaceTag,
+    /// Seconds that the racer starts after [`Replay::ghost`].
+    ///
+    /// Negative starts before.
+    pub offset: f32,
+    /// Time of the run for the leaderboard.
+    pub run_time: f32,
+    /// [`Replay::ghost`] compared against this racer at the same spot, without the offset.
+    pub route: Option<RouteComparison>,
+    /// Route of [`Replay::ghost`] to draw, without points close together.
+    pub route_points: Vec<RoutePoint>,
+}
+
+/// Point of the drawn route of [`Replay::ghost`] against a racer.
+pub struct RoutePoint {
+    pub origin: glam::Vec3,
+    /// Delta of [`Racer::route`] at this point, without the offset.
+    pub delta: Option<f32>,
+}
+
+impl Racer {
+    /// Time of [`Replay::ghost`] minus the time of this racer at the same spot.
+    ///
+    /// `time` is the time of [`Replay::ghost`]. Positive is behind.
+    pub fn delta_at(&self, time: f32) -> Option<f32> {
+        self.route
+            .as_ref()
+            .and_then(|route| route.delta_at(time))
+            .map(|delta| delta - self.offset)
+    }
+
+    /// Compares `ghost` against this racer and makes the route to draw.
+    pub fn update_route(&mut self, ghost: &GhostInfo) {
+        self.route = ghost.compare_route(&self.ghost).ok();
+        self.route_points = route_points(ghost, self.route.as_ref());
+    }
+}
+
+/// Frames of `ghost` at least [`ROUTE_MIN_DISTANCE`] apart, with the deltas of `route`.
+fn route_points(ghost: &GhostInfo, route: Option<&RouteComparison>) -> Vec<RoutePoint> {
+    let mut last_origin: Option<glam::Vec3> = None;
+
+    ghost
+        .frames()
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, frame)| {
+            // ghost has its own glam
+            let origin = glam::Vec3::from_array(frame.origin.to_array());
+
+            if last_origin
+                .is_some_and(|last_origin| last_origin.distance(origin) < ROUTE_MIN_DISTANCE)
+            {
+                return None;
+            }
+
+            last_origin = Some(origin);
+
+            Some(RoutePoint {
+                origin,
+                delta: route.and_then(|route| route.deltas.get(idx).copied().flatten()),
+            })
+        })
+        .collect()
+}
+
+/// Run time from the timer buttons on `bsp` if the ghost doesn't have one.
+///
+/// Falls back to the length of the ghost.
+pub fn race_time(ghost: &GhostInfo, bsp: Option<&bsp::Bsp>) -> f32 {
+    ghost
+        .metadata
+        .official_time
+        .or_else(|| bsp.and_then(|bsp| ghost::run_timer(ghost, bsp)?.time))
+        .unwrap_or_else(|| ghost.get_ghost_length()(DEFAULT_FRAMETIME))
+}
+
+/// Moves and animates a player model to `frame`.
+fn pose_player(player: &mut PlayerModel, frame: &GhostFrame) {
+    player.origin = frame.origin.to_array().into();
+    player.yaw = frame.viewangles[1];
+
+    let Some(anim) = frame.extras.as_ref().and_then(|extra| extra.anim.as_ref()) else {
+        return;
+    };
+
+    if let Some(sequence) = anim.sequence {
+        player.sequence = sequence as usize;
+    }
+
+    if let Some(gaitsequence) = anim.gaitsequence {
+        player.gaitsequence = gaitsequence as usize;
+    }
+
+    player.blending = anim.blending;
 }
 
 impl AppState {
     pub(super) fn process_replay_tick(&mut self, replay: &mut Replay) {
-        match replay.playback_mode {
-            ReplayPlaybackMode::Immediate(_) => todo!("not planned for now until the recorder"),
-            ReplayPlaybackMode::FrameAccurate => todo!("will be eventually, an easy task"),
+        let (frame_idx, frame) = match replay.playback_mode {
+            ReplayPlaybackMode::Immediate(current_frame) => {
+                let Some(current_time) = replay.ghost.get_frame_time(current_frame, None) else {
+                    return;
+                };
+
+                // nothing moves, paused or no playback speed
+                if self.time == current_time {
+                    return;
+                }
+
+                let next_frame =
+                    if !self.paused && self.last_time == current_time && self.time > self.last_time
+                    {
+                        // one frame every tick regardless of how much time has passed
+                        current_frame + 1
+                    } else {
+                        // seek bar or frame step changes the time so we follow it
+                        replay.ghost.get_frame_index(self.time, None)
+                    };
+
+                // hold the last frame when the replay ends
+                let next_frame = next_frame.min(replay.ghost.frames().len() - 1);
+
+                if next_frame == current_frame {
+                    self.time = current_time;
+                    return;
+                }
+
+                let Some(next_time) = replay.ghost.get_frame_time(next_frame, None) else {
+                    return;
+                };
+
+                // time follows the frame instead of the other way around
+                self.time = next_time;
+                replay.playback_mode = ReplayPlaybackMode::Immediate(next_frame);
+
+                (next_frame, replay.ghost.frames()[next_frame].clone())
+            }
+            ReplayPlaybackMode::FrameAccurate => {
+                // same reason as interpolated mode
+                if self.last_time == self.time {
+                    return;
+                }
+
+                let Some(res) = replay.ghost.get_frame_exact(self.time, None) else {
+                    return;
+                };
+
+                res
+            }
             ReplayPlaybackMode::Interpolated => {
                 // dont update anyting ghost related if "paused"
                 // texts and such will be wastefully added
@@ -48,155 +298,502 @@ impl AppState {
                     return;
                 }
 
-                let Some((frame_idx, frame)) = replay.ghost.get_frame(self.time, None) else {
+                let Some(res) = replay.ghost.get_frame(self.time, None) else {
                     return;
                 };
 
-                let missing_frame_count = (frame_idx - replay.last_frame).saturating_sub(1);
-
-                // discrete data
-                replay.ghost.frames[(replay.last_frame + 1).min(frame_idx)..frame_idx]
-                    .iter()
-                    // chain the current frame last
-                    .chain(std::iter::once(&frame))
-                    .enumerate()
-                    .for_each(|(chain_idx, frame)| {
-                        if let Some(extra) = &frame.extras {
-                            // discrete data, we don't need to add them again
-                            if replay.last_frame == frame_idx {
-                                return;
-                            }
+                res
+            }
+        };
+
+        self.update_replay_segment(replay, frame_idx);
+
+        self.apply_replay_frame(replay, frame_idx, &frame);
+
+        self.apply_replay_players(replay);
+
+        self.apply_racers(replay);
+
+        replay.last_frame = frame_idx;
+    }
+
+    /// Moves player models of other players in the replay.
+    fn apply_replay_players(&mut self, replay: &Replay) {
+        let Some(entity_state) = self.entity_state.as_mut() else {
+            return;
+        };
+
+        replay
+            .players
+            .iter()
+            .zip(entity_state.playermodel_state.players.iter_mut().skip(1))
+            .for_each(|(ghost, player)| {
+                // first frametime is the time before the player shows up
+                let show_up_time = ghost
+                    .frames()
+                    .first()
+                    .and_then(|frame| frame.frametime)
+                    .unwrap_or(0.);
+
+                let frame = if self.time < show_up_time {
+                    None
+                } else {
+                    ghost.get_frame(self.time, None)
+                };
+
+                let Some((_, frame)) = frame else {
+                    player.should_draw = false;
+                    return;
+                };
+
+                player.should_draw = true;
+                player.player_name = ghost.display_name().to_owned();
+
+                pose_player(player, &frame);
+            });
+    }
+
+    /// Moves player models of racers to their own time.
+    fn apply_racers(&mut self, replay: &Replay) {
+        let Some(entity_state) = self.entity_state.as_mut() else {
+            return;
+        };
+
+        // racers are only on one map
+        let map_name = replay
+            .ghost
+            .segments
+            .get(replay.segment)
+            .map(|segment| segment.map_name.as_str())
+            .unwrap_or(replay.ghost.map_name.as_str());
+
+        replay
+            .racers
+            .iter()
+            .zip(
+                entity_state
+                    .playermodel_state
+                    .players
+                    .iter_mut()
+                    .skip(1 + replay.players.len()),
+            )
+            .for_each(|(racer, player)| {
+                // wait at the start and stay at the end
+                let racer_time = (self.time - racer.offset).max(0.);
+                let frame = racer.ghost.get_frame(racer_time, None).or_else(|| {
+                    let last_frame = racer.ghost.frames().len().checked_sub(1)?;
+
+                    Some((last_frame, racer.ghost.frames()[last_frame].clone()))
+                });
+
+                let Some((_, frame)) = frame.filter(|_| racer.ghost.map_name == map_name) else {
+                    player.should_draw = false;
+                    return;
+                };
+
+                player.should_draw = true;
+                player.player_name = racer.tag.name.to_owned();
+                player.model_name = racer.tag.model_name.to_owned();
 
-                            extra.entity_text.iter().for_each(|text| {
-                                // something we do so that the final text of a channel is extended a bit longer
-                                let channel = text.channel;
-                                const EXTRA_TIME: f32 = 1.5;
-
-                                // decrease life of all previous texts in the channel
-                                self.text_state
-                                    .entity_text
-                                    .iter_mut()
-                                    .filter(|t| t.1.channel == channel)
-                                    .for_each(|t| t.1.life -= EXTRA_TIME);
-
-                                if let Some((_, prev_text)) = self
-                                    .text_state
-                                    .entity_text
-                                    .iter_mut()
-                                    .find(|(_, prev_text)| {
-                                        prev_text.location == text.location
-                                            && prev_text.text == text.text
-                                    })
-                                {
-                                    // if text is the same, extend life instead of pushing new text
-                                    // need to add extra time from the time we deducted
-                                    prev_text.life += text.life + EXTRA_TIME;
-                                } else {
-                                    // if there is no previous similar text, add new text
-                                    self.text_state.entity_text.push((
-                                        // need to id it correctly
-                                        frame_idx - missing_frame_count + chain_idx,
-                                        GhostFrameEntityText {
-                                            // here we do something a bit hacky by just adding new timer to the text we want
-                                            life: text.life + self.time + EXTRA_TIME,
-                                            ..text.clone()
-                                        },
-                                    ));
-                                }
-                            });
-
-                            extra.sound.iter().for_each(|sound| {
-                                let sound_path = format!("sound/{}", &sound.file_name);
-
-                                if let Some(sound_data) = self.audio_resource.get(&sound_path) {
-                                    if let Some(backend) = &mut self.audio_state.backend {
-                                        backend.play_audio_on_track(
-                                            sound_data.clone(),
-                                            0,
-                                            None,
-                                            false,
-                                            sound.volume * self.audio_state.volume,
-                                        );
-                                    }
-                                }
-                            });
-
-                            // say text
-                            extra.say_text.iter().for_each(|saytext| {
-                                self.text_state
-                                    .say_text
-                                    .push((self.time + SAY_TEXT_LIFE, saytext.clone()));
-
-                                while self.text_state.say_text.len() > MAX_SAY_TEXT {
-                                    self.text_state.say_text.remove(0);
-                                }
-                            });
-
-                            // player model and viewmodel
-                            if let Some(entity_state) = self.entity_state.as_mut() {
-                                if let Some(weapon_change) = &extra.weapon_change {
-                                    entity_state.viewmodel_state.active_viewmodel =
-                                        weapon_change.to_string();
-
-                                    entity_state.viewmodel_state.current_sequence = 0;
-                                    entity_state.viewmodel_state.time = 0.;
-                                }
-
-                                if let Some(weapon_sequence) = &extra.weapon_sequence {
-                                    // reset time for new sequence
-                                    entity_state.viewmodel_state.time = 0.;
-                                    entity_state.viewmodel_state.current_sequence =
-                                        *weapon_sequence as usize;
-                                }
-
-                                if let Some(anim) = &extra.anim {
-                                    if let Some(sequence) = anim.sequence {
-                                        entity_state.playermodel_state.players[0].sequence =
-                                            sequence as usize;
-                                    }
-
-                                    if let Some(gaitsequence) = anim.gaitsequence {
-                                        entity_state.playermodel_state.players[0].gaitsequence =
-                                            gaitsequence as usize;
-                                    }
-
-                                    entity_state.playermodel_state.players[0].blending =
-                                        anim.blending;
-                                }
+                pose_player(player, &frame);
+            });
+    }
+
+    /// Requests the next map when the replay moves to a frame on another map.
+    fn update_replay_segment(&mut self, replay: &mut Replay, frame_idx: usize) {
+        let segment_idx = replay.ghost.get_segment_index(frame_idx);
+
+        if segment_idx == replay.segment {
+            return;
+        }
+
+        let (Some(from), Some(to)) = (
+            replay.ghost.segments.get(replay.segment),
+            replay.ghost.segments.get(segment_idx),
+        ) else {
+            return;
+        };
+
+        let need_new_map = from.map_name != to.map_name;
+        let map_name = to.map_name.to_owned();
+
+        replay.segment = segment_idx;
+
+        // level can be restarted on the same map
+        if !need_new_map {
+            return;
+        }
+
+        replay.changing_map = true;
+
+        self.event_loop_proxy
+            .send_event(AppEvent::RequestMap(MapIdentifier {
+                map_name,
+                game_mod: replay.ghost.game_mod.to_owned(),
+            }))
+            .unwrap_or_else(|_| warn!("Failed to send RequestMap"));
+    }
+
+    /// Fires all discrete events since the last frame and then moves the camera and player model.
+    fn apply_replay_frame(&mut self, replay: &Replay, frame_idx: usize, frame: &GhostFrame) {
+        // scrubbing backward makes frame index smaller than last frame
+        let missing_frame_count = frame_idx
+            .saturating_sub(replay.last_frame)
+            .saturating_sub(1);
+
+        // discrete data
+        replay.ghost.frames()[(replay.last_frame + 1).min(frame_idx)..frame_idx]
+            .iter()
+            // chain the current frame last
+            .chain(std::iter::once(frame))
+            .enumerate()
+            .for_each(|(chain_idx, frame)| {
+                if let Some(extra) = &frame.extras {
+                    // discrete data, we don't need to add them again
+                    if replay.last_frame == frame_idx {
+                        return;
+                    }
+
+                    extra.entity_text.iter().for_each(|text| {
+                        // something we do so that the final text of a channel is extended a bit longer
+                        let channel = text.channel;
+                        const EXTRA_TIME: f32 = 1.5;
+
+                        // decrease life of all previous texts in the channel
+                        self.text_state
+                            .entity_text
+                            .iter_mut()
+                            .filter(|t| t.1.channel == channel)
+                            .for_each(|t| t.1.life -= EXTRA_TIME);
+
+                        if let Some((_, prev_text)) =
+                            self.text_state
+                                .entity_text
+                                .iter_mut()
+                                .find(|(_, prev_text)| {
+                                    prev_text.location == text.location
+                                        && prev_text.text == text.text
+                                })
+                        {
+                            // if text is the same, extend life instead of pushing new text
+                            // need to add extra time from the time we deducted
+                            prev_text.life += text.life + EXTRA_TIME;
+                        } else {
+                            // if there is no previous similar text, add new text
+                            self.text_state.entity_text.push((
+                                // need to id it correctly
+                                frame_idx - missing_frame_count + chain_idx,
+                                GhostFrameEntityText {
+                                    // here we do something a bit hacky by just adding new timer to the text we want
+                                    life: text.life + self.time + EXTRA_TIME,
+                                    ..text.clone()
+                                },
+                            ));
+                        }
+                    });
+
+                    extra.sound.iter().for_each(|sound| {
+                        let sound_path = format!("sound/{}", &sound.file_name);
+
+                        if let Some(sound_data) = self.audio_resource.get(&sound_path) {
+                            if let Some(backend) = &mut self.audio_state.backend {
+                                backend.play_audio_on_track(
+                                    sound_data.clone(),
+                                    0,
+                                    None,
+                                    false,
+                                    sound.volume * self.audio_state.volume,
+                                );
                             }
                         }
                     });
 
-                // if free cam, we update the entity instead
-                // replay mode should guarantee to have 1 player model
-                self.entity_state.as_mut().map(|x| {
-                    x.playermodel_state.players.get_mut(0).map(|player| {
-                        player.should_draw = self.input_state.free_cam;
-                        player.origin = frame.origin.to_array().into();
-                        player.yaw = frame.viewangles[1];
-                    })
-                });
+                    // say text
+                    extra.say_text.iter().for_each(|saytext| {
+                        self.text_state
+                            .say_text
+                            .push((self.time + SAY_TEXT_LIFE, saytext.clone()));
+
+                        while self.text_state.say_text.len() > MAX_SAY_TEXT {
+                            self.text_state.say_text.remove(0);
+                        }
+                    });
 
-                // only update the actual view when we're not in free cam
-                if !self.input_state.free_cam {
-                    // negative pitch
-                    self.render_state
-                        .camera
-                        .set_pitch(-Deg(frame.viewangles[0]));
-                    self.render_state.camera.set_yaw(Deg(frame.viewangles[1]));
+                    // player model and viewmodel
+                    if let Some(entity_state) = self.entity_state.as_mut() {
+                        if let Some(weapon_change) = &extra.weapon_change {
+                            entity_state.viewmodel_state.active_viewmodel =
+                                weapon_change.to_string();
 
-                    // TODO add our my viewoffset calculation
-                    let mut vieworigin = frame.origin;
-                    vieworigin.z += frame.viewoffset_z;
+                            entity_state.viewmodel_state.current_sequence = 0;
+                            entity_state.viewmodel_state.time = 0.;
+                        }
+
+                        if let Some(weapon_sequence) = &extra.weapon_sequence {
+                            // reset time for new sequence
+                            entity_state.viewmodel_state.time = 0.;
+                            entity_state.viewmodel_state.current_sequence =
+                                *weapon_sequence as usize;
+                        }
 
-                    self.render_state.camera.set_position(vieworigin.to_array());
+                        if let Some(anim) = &extra.anim {
+                            if let Some(sequence) = anim.sequence {
+                                entity_state.playermodel_state.players[0].sequence =
+                                    sequence as usize;
+                            }
 
-                    // important
-                    self.render_state.camera.rebuild_orientation();
+                            if let Some(gaitsequence) = anim.gaitsequence {
+                                entity_state.playermodel_state.players[0].gaitsequence =
+                                    gaitsequence as usize;
+                            }
+
+                            entity_state.playermodel_state.players[0].blending = anim.blending;
+                        }
+                    }
                 }
+            });
 
-                replay.last_frame = frame_idx;
-            }
+        // if free cam, we update the entity instead
+        // replay mode should guarantee to have 1 player model
+        self.entity_state.as_mut().map(|x| {
+            x.playermodel_state.players.get_mut(0).map(|player| {
+                player.should_draw = self.input_state.free_cam;
+                player.model_name = replay.tag.model_name.to_owned();
+                player.origin = frame.origin.to_array().into();
+                player.yaw = frame.viewangles[1];
+            })
+        });
+
+        // only update the actual view when we're not in free cam
+        if !self.input_state.free_cam {
+            // negative pitch
+            self.render_state
+                .camera
+                .set_pitch(-Deg(frame.viewangles[0]));
+            self.render_state.camera.set_yaw(Deg(frame.viewangles[1]));
+
+            // TODO add our my viewoffset calculation
+            let mut vieworigin = frame.origin;
+            vieworigin.z += frame.viewoffset_z;
+
+            self.render_state.camera.set_position(vieworigin.to_array());
+
+            // important
+            self.render_state.camera.rebuild_orientation();
         }
     }
+
+    /// Puts the camera and players on the first frame of the replay.
+    ///
+    /// Playback doesn't do anything until time moves, so a tick at time 0 would show the spawn point instead.
+    pub fn apply_replay_start(&mut self) {
+        // pain pattern
+        let playback_mode =
+            std::mem::replace(&mut self.playback_state.playback_mode, PlaybackMode::None);
+
+        if let PlaybackMode::Replay(replay) = &playback_mode
+            && let Some(frame) = replay.ghost.frames().first()
+        {
+            self.apply_replay_frame(replay, 0, frame);
+            self.apply_replay_players(replay);
+        }
+
+        self.playback_state.playback_mode = playback_mode;
+    }
+
+    /// Moves the replay to the start of recorded frame `frame_idx`.
+    pub(in crate::app::state) fn seek_replay_frame(&mut self, frame_idx: usize) {
+        let Some(target_time) = self
+            .playback_state
+            .get_replay()
+            .and_then(|replay| replay.ghost.get_frame_time(frame_idx, None))
+        else {
+            return;
+        };
+
+        self.time = target_time;
+
+        // same as scrubbing the seek bar
+        self.text_state.clear_text();
+    }
+
+    /// Finds the run on the newly loaded map and fills in the time if the replay doesn't have one.
+    pub(in crate::app::state) fn update_run_timer(&mut self, bsp: &bsp::Bsp) {
+        let Some(replay) = self.playback_state.get_replay_mut() else {
+            return;
+        };
+
+        // the whole run has to be on this map
+        replay.run_timer = ghost::run_timer(&replay.ghost, bsp).filter(|timer| {
+            replay.ghost.get_segment_index(timer.start_frame) == replay.segment
+                && replay.ghost.get_segment_index(timer.end_frame) == replay.segment
+        });
+
+        let Some(time) = replay.run_timer.as_ref().and_then(|timer| timer.time) else {
+            return;
+        };
+
+        if replay.ghost.metadata.official_time.is_some() {
+            return;
+        }
+
+        replay.ghost.metadata.official_time = Some(time);
+
+        // so the replay list shows it
+        if let Some(replay_name) = &self.file_state.selected_file {
+            self.other_resources
+                .replay_metadata
+                .insert(replay_name.to_owned(), replay.ghost.metadata.clone());
+        }
+    }
+
+    /// Cuts the replay down to the run between the timer buttons.
+    pub(in crate::app::state) fn trim_replay_to_run(&mut self) {
+        let Some(replay) = self.playback_state.get_replay_mut() else {
+            return;
+        };
+
+        let Some(timer) = replay.run_timer.take() else {
+            return;
+        };
+
+        let start_time = replay
+            .ghost
+            .get_frame_time(timer.start_frame, None)
+            .unwrap_or(0.);
+
+        replay.ghost.trim(timer.frame_range());
+
+        // racers stay where they are while the replay now starts later
+        replay.racers.iter_mut().for_each(|racer| {
+            racer.offset -= start_time;
+            racer.update_route(&replay.ghost);
+        });
+
+        replay.analysis = replay.ghost.analyze();
+        replay.weapon_changes = weapon_changes(&replay.ghost);
+        replay.findings = replay.ghost.check(&replay.analysis);
+        replay.last_frame = 0;
+
+        if let ReplayPlaybackMode::Immediate(current_frame) = &mut replay.playback_mode {
+            *current_frame = 0;
+        }
+
+        replay.segment = 0;
+        replay.run_timer = Some(RunTimer {
+            start_frame: 0,
+            end_frame: timer.end_frame - timer.start_frame,
+            ..timer
+        });
+
+        self.ui_state.jumpstats.jumps = None;
+        self.time = 0.;
+        self.text_state.clear_text();
+    }
+
+    /// Makes racer `racer_idx` the first person replay and races the current one instead.
+    ///
+    /// Offsets are moved so everyone stays where they are.
+    pub(in crate::app::state) fn set_first_person_racer(&mut self, racer_idx: usize) {
+        let bsp = self.other_resources.bsp.as_ref();
+
+        let Some(replay) = self.playback_state.get_replay_mut() else {
+            return;
+        };
+
+        let Some(racer) = replay.racers.get_mut(racer_idx) else {
+            return;
+        };
+
+        let offset = racer.offset;
+
+        std::mem::swap(&mut replay.ghost, &mut racer.ghost);
+        std::mem::swap(&mut replay.tag, &mut racer.tag);
+
+        racer.offset = -offset;
+        racer.run_time = race_time(&racer.ghost, bsp);
+
+        replay
+            .racers
+            .iter_mut()
+            .enumerate()
+            .filter(|(idx, _)| *idx != racer_idx)
+            .for_each(|(_, racer)| racer.offset -= offset);
+
+        self.time = (self.time - offset).max(0.);
+
+        replay
+            .racers
+            .iter_mut()
+            .for_each(|racer| racer.update_route(&replay.ghost));
+
+        // racers are on one map so there is only one segment
+        replay.analysis = replay.ghost.analyze();
+        replay.weapon_changes = weapon_changes(&replay.ghost);
+        replay.findings = replay.ghost.check(&replay.analysis);
+        replay.run_timer = bsp.and_then(|bsp| ghost::run_timer(&replay.ghost, bsp));
+        replay.segment = 0;
+
+        // don't fire every event up to the new time
+        replay.last_frame = replay.ghost.get_frame_index(self.time, None);
+
+        if let ReplayPlaybackMode::Immediate(current_frame) = &mut replay.playback_mode {
+            *current_frame = replay.last_frame;
+        }
+
+        self.ui_state.jumpstats.jumps = None;
+        self.text_state.clear_text();
+    }
+
+    /// Removes racer `racer_idx` and its player model.
+    pub(in crate::app::state) fn remove_racer(&mut self, racer_idx: usize) {
+        let Some(replay) = self.playback_state.get_replay_mut() else {
+            return;
+        };
+
+        if racer_idx >= replay.racers.len() {
+            return;
+        }
+
+        replay.racers.remove(racer_idx);
+
+        let player_idx = 1 + replay.players.len() + racer_idx;
+
+        if let Some(entity_state) = self.entity_state.as_mut()
+            && player_idx < entity_state.playermodel_state.players.len()
+        {
+            entity_state.playermodel_state.players.remove(player_idx);
+        }
+    }
+
+    /// Pauses the replay and moves it by `step` recorded frames.
+    pub(in crate::app::state) fn step_replay_frame(&mut self, step: isize) {
+        let Some(replay) = self.playback_state.get_replay() else {
+            return;
+        };
+
+        let current_frame = replay.shown_frame();
+
+        let target_frame = current_frame
+            .saturating_add_signed(step)
+            .min(replay.ghost.frames().len().saturating_sub(1));
+
+        // interpolated playback shows frame `i` from the start of frame `i + 1`
+        let target_time = match replay.playback_mode {
+            ReplayPlaybackMode::Interpolated => replay
+                .ghost
+                .get_frame_time(target_frame + 1, None)
+                .or_else(|| replay.ghost.get_frame_time(target_frame, None)),
+            ReplayPlaybackMode::Immediate(_) | ReplayPlaybackMode::FrameAccurate => {
+                replay.ghost.get_frame_time(target_frame, None)
+            }
+        };
+
+        let Some(target_time) = target_time else {
+            return;
+        };
+
+        self.paused = true;
+        self.time = target_time;
+    }
 }
@@ -7,7 +7,7 @@ use crate::renderer::{
     },
 };
 
-use super::AppState;
+use super::{AppState, visibility::VisibilityState};
 
 pub struct RenderState {
     pub world_buffer: Option<WorldStaticBuffer>,
@@ -18,6 +18,7 @@ pub struct RenderState {
 
     pub camera: Camera,
     pub render_options: RenderOptions,
+    pub visibility: VisibilityState,
 
     // debug
     pub draw_call: usize,
@@ -31,6 +32,7 @@ impl Default for RenderState {
             draw_call: 0,
             world_buffer: None,
             render_options: RenderOptions::default(),
+            visibility: VisibilityState::default(),
             viewmodel_buffers: vec![],
             playermodel_buffers: vec![],
         }
@@ -45,6 +47,10 @@ pub struct RenderOptions {
     pub render_skybox: bool,
     pub render_transparent: bool,
     pub full_bright: bool,
+    /// Only draws world faces that can be seen
+    pub culling: bool,
+    /// Keeps the current visible faces no matter where the camera goes
+    pub freeze_visibility: bool,
 }
 
 impl Default for RenderOptions {
@@ -55,6 +61,8 @@ impl Default for RenderOptions {
             render_skybox: true,
             render_transparent: true,
             full_bright: false,
+            culling: true,
+            freeze_visibility: false,
         }
     }
 }
@@ -214,24 +222,42 @@ impl AppState {
                     opaque_pass.set_bind_group(3, &world_buffer.bsp_lightmap.bind_group, &[]);
 
                     // static world
-                    world_buffer.opaque.iter().for_each(|batch| {
-                        self.render_state.draw_call += 1;
+                    world_buffer
+                        .opaque
+                        .iter()
+                        .enumerate()
+                        .for_each(|(batch_idx, batch)| {
+                            let draw_ranges = self.render_state.visibility.batch_ranges(batch_idx);
 
-                        // texture array
-                        opaque_pass.set_bind_group(
-                            2,
-                            &world_buffer.textures[batch.texture_array_index].bind_group,
-                            &[],
-                        );
+                            // nothing visible in this batch
+                            if draw_ranges.is_some_and(|ranges| ranges.is_empty()) {
+                                return;
+                            }
 
-                        opaque_pass.set_vertex_buffer(0, batch.vertex_buffer.slice(..));
-                        opaque_pass.set_index_buffer(
-                            batch.index_buffer.slice(..),
-                            wgpu::IndexFormat::Uint32,
-                        );
+                            // texture array
+                            opaque_pass.set_bind_group(
+                                2,
+                                &world_buffer.textures[batch.texture_array_index].bind_group,
+                                &[],
+                            );
 
-                        opaque_pass.draw_indexed(0..batch.index_count as u32, 0, 0..1);
-                    });
+                            opaque_pass.set_vertex_buffer(0, batch.vertex_buffer.slice(..));
+                            opaque_pass.set_index_buffer(
+                                batch.index_buffer.slice(..),
+                                wgpu::IndexFormat::Uint32,
+                            );
+
+                            match draw_ranges {
+                                Some(ranges) => ranges.iter().for_each(|range| {
+                                    self.render_state.draw_call += 1;
+                                    opaque_pass.draw_indexed(range.clone(), 0, 0..1);
+                                }),
+                                None => {
+                                    self.render_state.draw_call += 1;
+                                    opaque_pass.draw_indexed(0..batch.index_count as u32, 0, 0..1);
+                                }
+                            }
+                        });
 
                     // viewmodels
                     self.render_state
@@ -385,7 +411,14 @@ impl AppState {
 
                     rpass.set_vertex_buffer(0, batch.vertex_buffer.slice(..));
                     rpass.set_index_buffer(batch.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-                    rpass.draw_indexed(0..batch.index_count as u32, 0, 0..1);
+
+                    // sky faces that can't be seen don't mask the skybox either
+                    match self.render_state.visibility.batch_ranges(batch_idx) {
+                        Some(ranges) => ranges
+                            .iter()
+                            .for_each(|range| rpass.draw_indexed(range.clone(), 0, 0..1)),
+                        None => rpass.draw_indexed(0..batch.index_count as u32, 0, 0..1),
+                    }
                 });
         }
 
@@ -0,0 +1,300 @@
+//! Picks which world faces to draw every frame from the PVS, like the game does.
+//!
+//! Leaves in the PVS of the camera leaf are kept when their bounding box is inside the view frustum.
+//! Nodes outside of the frustum are skipped entirely. Brush entities and models are always drawn.
+use std::ops::Range;
+
+use bsp::{BitSet, Bsp};
+use cgmath::{Matrix4, Point3, Vector4};
+
+use super::AppState;
+
+/// Left, right, bottom and top planes of the view frustum.
+///
+/// Near and far planes don't cull anything useful for a map.
+struct Frustum {
+    planes: [Vector4<f32>; 4],
+}
+
+impl Frustum {
+    fn from_view_proj(view_proj: Matrix4<f32>) -> Self {
+        // cgmath is column major
+        let row = |idx: usize| {
+            Vector4::new(
+                view_proj.x[idx],
+                view_proj.y[idx],
+                view_proj.z[idx],
+                view_proj.w[idx],
+            )
+        };
+
+        let (x, y, w) = (row(0), row(1), row(3));
+
+        Self {
+            planes: [w + x, w - x, w + y, w - y],
+        }
+    }
+
+    /// False when the box is completely behind one of the planes.
+    fn intersects_box(&self, mins: [i16; 3], maxs: [i16; 3]) -> bool {
+        self.planes.iter().all(|plane| {
+            // corner furthest along the plane normal
+            let corner = |axis: usize| {
+                if plane[axis] >= 0. {
+                    maxs[axis] as f32
+                } else {
+                    mins[axis] as f32
+                }
+            };
+
+            plane.x * corner(0) + plane.y * corner(1) + plane.z * corner(2) + plane.w >= 0.
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct VisibilityState {
+    /// Camera leaf and its PVS, so it is only decompressed when the camera changes leaf.
+    pvs: Option<(usize, BitSet)>,
+    /// Visible world faces, indexed by BSP face.
+    ///
+    /// `None` means everything is drawn.
+    pub visible_faces: Option<BitSet>,
+    /// Index ranges to draw for every opaque batch of the world buffer.
+    pub draw_ranges: Vec<Vec<Range<u32>>>,
+    pub camera_leaf: usize,
+}
+
+impl VisibilityState {
+    fn mark_node(
+        bsp: &Bsp,
+        node_idx: i32,
+        pvs: &BitSet,
+        frustum: &Frustum,
+        visible_faces: &mut BitSet,
+    ) {
+        if node_idx < 0 {
+            let leaf_idx = !node_idx as usize;
+
+            let Some(leaf) = bsp.leaves.get(leaf_idx) else {
+                return;
+            };
+
+            if !pvs.contains(leaf_idx) || !frustum.intersects_box(leaf.mins, leaf.maxs) {
+                return;
+            }
+
+            bsp.leaf_faces(leaf_idx)
+                .iter()
+                .for_each(|&face| visible_faces.insert(face as usize));
+
+            return;
+        }
+
+        let Some(node) = bsp.nodes.get(node_idx as usize) else {
+            return;
+        };
+
+        if !frustum.intersects_box(node.mins, node.maxs) {
+            return;
+        }
+
+        node.children.iter().for_each(|&child| {
+            Self::mark_node(bsp, child as i32, pvs, frustum, visible_faces);
+        });
+    }
+
+    /// Finds visible world faces from `camera_pos` looking through `view_proj`.
+    fn update(&mut self, bsp: &Bsp, camera_pos: Point3<f32>, view_proj: Matrix4<f32>) {
+        let leaf = bsp.leaf_at(bsp::Vec3::new(camera_pos.x, camera_pos.y, camera_pos.z));
+
+        if self
+            .pvs
+            .as_ref()
+            .is_none_or(|(pvs_leaf, _)| *pvs_leaf != leaf)
+        {
+            self.pvs = Some((leaf, bsp.visible_leaves(leaf)));
+        }
+
+        let Some((_, pvs)) = self.pvs.as_ref() else {
+            return;
+        };
+
+        let mut visible_faces = BitSet::new(bsp.faces.len());
+        let head_node = bsp
+            .models
+            .first()
+            .map(|model| model.head_nodes[0])
+            .unwrap_or(0);
+
+        Self::mark_node(
+            bsp,
+            head_node,
+            pvs,
+            &Frustum::from_view_proj(view_proj),
+            &mut visible_faces,
+        );
+
+        self.camera_leaf = leaf;
+        self.visible_faces = Some(visible_faces);
+    }
+
+    /// Builds the index ranges of every batch from the visible faces.
+    ///
+    /// Anything that isn't a worldspawn face is always in the ranges. Neighbouring ranges are merged to keep draw calls down.
+    fn build_draw_ranges(&mut self, batches: &[(u32, &[(usize, Range<u32>)])]) {
+        self.draw_ranges = batches
+            .iter()
+            .map(|(index_count, world_faces)| {
+                let Some(visible_faces) = self.visible_faces.as_ref() else {
+                    return vec![0..*index_count];
+                };
+
+                batch_draw_ranges(*index_count, world_faces, visible_faces)
+            })
+            .collect();
+    }
+
+    /// Index ranges of opaque batch `batch_idx`, `None` draws the whole batch.
+    pub fn batch_ranges(&self, batch_idx: usize) -> Option<&[Range<u32>]> {
+        self.draw_ranges.get(batch_idx).map(Vec::as_slice)
+    }
+
+    /// Forgets everything from the previous map.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+fn batch_draw_ranges(
+    index_count: u32,
+    world_faces: &[(usize, Range<u32>)],
+    visible_faces: &BitSet,
+) -> Vec<Range<u32>> {
+    let mut res: Vec<Range<u32>> = vec![];
+    let mut push = |range: Range<u32>| {
+        if range.is_empty() {
+            return;
+        }
+
+        if let Some(last) = res.last_mut()
+            && last.end == range.start
+        {
+            last.end = range.end;
+        } else {
+            res.push(range);
+        }
+    };
+
+    // whatever is between world faces belongs to entities
+    let mut cursor = 0;
+
+    world_faces.iter().for_each(|(face, range)| {
+        push(cursor..range.start);
+
+        if visible_faces.contains(*face) {
+            push(range.clone());
+        }
+
+        cursor = range.end;
+    });
+
+    push(cursor..index_count);
+
+    res
+}
+
+impl AppState {
+    pub(super) fn visibility_tick(&mut self) {
+        let options = self.render_state.render_options;
+
+        if options.freeze_visibility {
+            return;
+        }
+
+        let Some(world_buffer) = self.render_state.world_buffer.as_ref() else {
+            return;
+        };
+
+        let visibility = &mut self.render_state.visibility;
+
+        match self.other_resources.bsp.as_ref() {
+            Some(bsp) if options.culling => {
+                let camera = &self.render_state.camera;
+
+                visibility.update(bsp, camera.pos, camera.proj() * camera.view());
+            }
+            _ => visibility.visible_faces = None,
+        }
+
+        let batches: Vec<(u32, &[(usize, Range<u32>)])> = world_buffer
+            .opaque
+            .iter()
+            .zip(world_buffer.opaque_world_faces.iter())
+            .map(|(batch, world_faces)| (batch.index_count as u32, world_faces.as_slice()))
+            .collect();
+
+        visibility.build_draw_ranges(&batches);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Faces 0 to 3 with 6 indices each, starting after 6 entity indices.
+    fn world_faces() -> Vec<(usize, Range<u32>)> {
+        (0..4)
+            .map(|face| (face, 6 + face as u32 * 6..12 + face as u32 * 6))
+            .collect()
+    }
+
+    fn visible(faces: &[usize]) -> BitSet {
+        let mut res = BitSet::new(4);
+
+        faces.iter().for_each(|&face| res.insert(face));
+
+        res
+    }
+
+    #[test]
+    fn adjacent_faces() {
+        // entity indices before and after are merged too
+        assert_eq!(
+            batch_draw_ranges(36, &world_faces(), &visible(&[0, 1, 2, 3])),
+            [0..36]
+        );
+
+        assert_eq!(
+            batch_draw_ranges(36, &world_faces(), &visible(&[1, 2])),
+            [0..6, 12..24, 30..36]
+        );
+    }
+
+    #[test]
+    fn disjoint_faces() {
+        assert_eq!(
+            batch_draw_ranges(36, &world_faces(), &visible(&[0, 2])),
+            [0..12, 18..24, 30..36]
+        );
+
+        // entity indices between world faces
+        let world_faces = [(0, 0..6), (1, 12..18)];
+
+        assert_eq!(batch_draw_ranges(18, &world_faces, &visible(&[1])), [6..18]);
+    }
+
+    #[test]
+    fn empty_faces() {
+        assert_eq!(
+            batch_draw_ranges(36, &world_faces(), &visible(&[])),
+            [0..6, 30..36]
+        );
+
+        // only world faces and none of them visible
+        assert!(batch_draw_ranges(12, &[(0, 0..6), (1, 6..12)], &visible(&[])).is_empty());
+
+        assert_eq!(batch_draw_ranges(12, &[], &visible(&[])), [0..12]);
+        assert!(batch_draw_ranges(0, &[], &visible(&[])).is_empty());
+    }
+}
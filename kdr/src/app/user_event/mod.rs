@@ -117,21 +117,29 @@ impl App {
                         .send_event(AppEvent::ReceiveReplayBlob {
                             replay_name: file_path.to_path_buf(),
                             replay_blob: ghost_blob,
+                            as_racer: false,
                         })
                         .unwrap_or_else(|_| warn!("Cannot send ReceiveReplayBlob"));
                 }
             }
             AppEvent::RequestReplay(replay_name) => {
-                self.request_replay(replay_name);
+                self.request_replay(replay_name, false);
             }
             AppEvent::ReceiveReplayBlob {
                 replay_name,
                 replay_blob,
+                as_racer,
             } => {
-                self.receive_replay_blob(replay_name, replay_blob);
+                self.receive_replay_blob(replay_name, replay_blob, as_racer);
             }
-            AppEvent::ReceiveReplay(identifier, ghost) => {
-                self.receive_replay(identifier, ghost);
+            AppEvent::ReceiveReplay(identifier, ghost, players) => {
+                self.receive_replay(identifier, ghost, players);
+            }
+            AppEvent::RequestRacer(replay_name) => {
+                self.request_replay(replay_name, true);
+            }
+            AppEvent::ReceiveRacer(ghost) => {
+                self.receive_racer(ghost);
             }
             AppEvent::ReceivePostProcessingUpdate(state) => {
                 let Some(render_context) = &self.render_context else {
@@ -196,6 +204,9 @@ impl App {
             AppEvent::ReceiveReplayList(replay_list) => {
                 self.receive_replay_list(replay_list);
             }
+            AppEvent::ReceiveReplayMetadata(replay_metadata) => {
+                self.receive_replay_metadata(replay_metadata);
+            }
             #[cfg(target_arch = "wasm32")]
             AppEvent::CheckHostConfiguration => {
                 self.check_host_configuration();
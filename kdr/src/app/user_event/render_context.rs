@@ -1,22 +1,13 @@
 use std::sync::Arc;
 
-use common::vec3;
 use loader::bsp_resource::BspResource;
 use tracing::{info, warn};
 use winit::window::Window;
 
 use crate::{
-    app::{
-        App, AppEvent,
-        state::{
-            entities::{EntityState, playermodel::PlayerModelState, viewmodel::ViewModelState},
-            file::SelectedFileType,
-            render::RenderOptions,
-        },
-    },
+    app::{App, AppEvent},
     renderer::{
-        EguiRenderer, RenderContext, camera::Camera, skybox::SkyboxBuffer,
-        world_buffer::WorldStaticBuffer,
+        EguiRenderer, RenderContext, skybox::SkyboxBuffer, world_buffer::WorldStaticBuffer,
     },
     utils::spawn_async,
 };
@@ -110,80 +101,11 @@ impl App {
         world_buffer: WorldStaticBuffer,
         skybox_buffer: Option<SkyboxBuffer>,
     ) {
-        self.state.render_state.world_buffer = world_buffer.into();
-
-        self.state.render_state.skybox = skybox_buffer;
-
-        // inserting audio from bsp resourec
-        // but first, need to clear audio that are not part of the common resource
-        self.state
-            .audio_resource
-            .retain(|k, _| self.state.other_resources.common_resource.contains_key(k));
-
-        bsp_resource.sound_lookup.iter().for_each(|(k, v)| {
-            self.state.audio_resource.insert(k.to_string(), v.clone());
-        });
-
-        // restart the camera
-        self.state.render_state.camera = Camera::default();
-
-        // but then set our camera to be in one of the spawn location
-        bsp_resource
-            .bsp
-            .entities
-            .iter()
-            .find(|entity| {
-                entity
-                    .get("classname")
-                    .is_some_and(|classname| classname == "info_player_start")
-            })
-            .map(|entity| {
-                entity
-                    .get("origin")
-                    .and_then(|origin_text| vec3(&origin_text))
-                    .map(|origin| {
-                        self.state.render_state.camera.set_position(origin);
-                        self.state.render_state.camera.rebuild_orientation();
-                    });
-            });
-
-        // restart render options
-        self.state.render_state.render_options = RenderOptions::default();
-
-        // if loading bsp, just force free cam every time
-        match self.state.file_state.selected_file_type {
-            SelectedFileType::Bsp => {
-                self.state.input_state.free_cam = true;
-            }
-            _ => (),
-        }
-        // reset file input tpye
-        self.state.file_state.selected_file_type = SelectedFileType::None;
-
-        // reset texts
-        self.state.text_state.clear_text();
-
-        // resetting time when we are ready
-        self.state.time = 0.;
-
-        // stop spinner
-        self.state.file_state.stop_spinner();
-
-        let Some(render_context) = self.render_context.as_ref() else {
-            return;
-        };
-
-        // entity dictionary
-        self.state.entity_state = Some(EntityState {
-            entity_dictionary: bsp_resource.entity_dictionary,
-            viewmodel_state: ViewModelState::default(),
-            playermodel_state: PlayerModelState::new(
-                render_context.device(),
-                render_context.queue(),
-            ),
-        });
-
-        // store bsp to do things
-        self.state.other_resources.bsp = bsp_resource.bsp.into();
+        self.state.load_world(
+            self.render_context.as_ref(),
+            bsp_resource,
+            world_buffer,
+            skybox_buffer,
+        );
     }
 }
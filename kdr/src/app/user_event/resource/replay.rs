@@ -1,22 +1,31 @@
 use std::path::PathBuf;
 
 use ghost::{GhostBlob, GhostInfo};
-use loader::{MapIdentifier, ReplayList, ResourceProvider, error::ResourceProviderError};
+use loader::{
+    MapIdentifier, ReplayList, ReplayMetadataList, ResourceProvider, error::ResourceProviderError,
+};
 use tracing::{info, warn};
 
 use crate::{
     app::{
         App, AppError, AppEvent,
         state::{
+            entities::playermodel::PlayerModel,
             file::SelectedFileType,
-            playback::replay::{Replay, ReplayPlaybackMode},
+            playback::replay::{
+                RACER_COLORS, RaceTag, Racer, Replay, ReplayPlaybackMode, race_time, weapon_changes,
+            },
         },
     },
     utils::spawn_async,
 };
 
 impl App {
-    pub(in crate::app::user_event) fn request_replay(&mut self, replay_name: String) {
+    pub(in crate::app::user_event) fn request_replay(
+        &mut self,
+        replay_name: String,
+        as_racer: bool,
+    ) {
         info!("Requesting replay `{}`", replay_name);
 
         #[cfg(not(target_arch = "wasm32"))]
@@ -49,6 +58,7 @@ impl App {
                             .send_event(AppEvent::ReceiveReplayBlob {
                                 replay_name: replay_name.clone().into(),
                                 replay_blob: ghost_blob,
+                                as_racer,
                             })
                             .unwrap_or_else(|_| warn!("Failed to send ReceivedGhostRequest"));
                     }
@@ -69,13 +79,21 @@ impl App {
         &mut self,
         replay_name: PathBuf,
         replay_blob: GhostBlob,
+        as_racer: bool,
     ) {
         info!("Received replay blob");
 
         let event_loop_proxy = self.event_loop_proxy.clone();
-        let send_message = move |identifier, ghost| {
+        let send_message = move |identifier, ghost, players| {
+            let event = if as_racer {
+                // other players of the racer are not raced
+                AppEvent::ReceiveRacer(ghost)
+            } else {
+                AppEvent::ReceiveReplay(identifier, ghost, players)
+            };
+
             event_loop_proxy
-                .send_event(AppEvent::ReceiveReplay(identifier, ghost))
+                .send_event(event)
                 .unwrap_or_else(|_| warn!("Failed to send ReceivedGhostRequest"));
         };
 
@@ -100,33 +118,64 @@ impl App {
         let provider = provider.clone();
 
         spawn_async(async move {
-            let Ok((identifier, ghost)) = provider.get_ghost_data(replay_name, replay_blob).await
+            let Ok((identifier, ghost, players)) =
+                provider.get_ghost_data(replay_name, replay_blob).await
             else {
                 warn!("Cannot load ghost data");
                 // TODO send error here
                 return;
             };
 
-            send_message(identifier, ghost);
+            send_message(identifier, ghost, players);
         });
 
-        self.state.file_state.selected_file_type = SelectedFileType::Replay;
+        if !as_racer {
+            self.state.file_state.selected_file_type = SelectedFileType::Replay;
+        }
     }
 
     pub(in crate::app::user_event) fn receive_replay(
         &mut self,
         identifier: MapIdentifier,
         ghost: GhostInfo,
+        players: Vec<GhostInfo>,
     ) {
         info!("Finished processing .dem. Loading replay");
 
+        if !players.is_empty() {
+            info!("Replay has {} other players", players.len());
+        }
+
+        // so the replay list can show who is in it
+        if let Some(replay_name) = &self.state.file_state.selected_file {
+            self.state
+                .other_resources
+                .replay_metadata
+                .insert(replay_name.to_owned(), ghost.metadata.clone());
+        }
+
+        let analysis = ghost.analyze();
+        let weapon_changes = weapon_changes(&ghost);
+        let findings = ghost.check(&analysis);
+        let tag = RaceTag::new(&ghost, RACER_COLORS[0]);
+
         let replay = Replay {
             ghost,
+            players,
             playback_mode: ReplayPlaybackMode::Interpolated,
             last_frame: 0,
+            segment: 0,
+            changing_map: false,
+            analysis,
+            weapon_changes,
+            findings,
+            run_timer: None,
+            tag,
+            racers: vec![],
         };
 
         self.state.playback_state.set_replay(replay);
+        self.state.ui_state.jumpstats.jumps = None;
 
         // make sure the user cannot move camera because it is true by default
         self.state.input_state.free_cam = false;
@@ -136,6 +185,73 @@ impl App {
             .unwrap_or_else(|_| warn!("Failed to send RequestResource"));
     }
 
+    pub(in crate::app::user_event) fn receive_racer(&mut self, ghost: GhostInfo) {
+        let Some(replay) = self.state.playback_state.get_replay_mut() else {
+            warn!("Received a racer without a replay");
+            return;
+        };
+
+        // racing only makes sense on one map
+        if replay.ghost.segments.len() > 1
+            || ghost.segments.len() > 1
+            || ghost.map_name != replay.ghost.map_name
+        {
+            self.state.ui_state.toaster.warning(format!(
+                "Cannot race `{}` on `{}` against a replay on `{}`",
+                ghost.display_name(),
+                ghost.map_name,
+                replay.ghost.map_name
+            ));
+
+            return;
+        }
+
+        info!("Racing against `{}`", ghost.display_name());
+
+        let bsp = self.state.other_resources.bsp.as_ref();
+
+        // both runs start at the same time if the map has a timer
+        let offset = bsp
+            .and_then(|bsp| {
+                let start_time = |ghost: &GhostInfo| {
+                    let timer = ghost::run_timer(ghost, bsp)?;
+
+                    ghost.get_frame_time(timer.start_frame, None)
+                };
+
+                Some(start_time(&replay.ghost)? - start_time(&ghost)?)
+            })
+            .unwrap_or(0.);
+
+        let color = RACER_COLORS[(replay.racers.len() + 1) % RACER_COLORS.len()];
+
+        let mut racer = Racer {
+            tag: RaceTag::new(&ghost, color),
+            offset,
+            run_time: race_time(&ghost, bsp),
+            route: None,
+            route_points: vec![],
+            ghost,
+        };
+
+        racer.update_route(&replay.ghost);
+        replay.racers.push(racer);
+
+        // player models are made for the racers when the map is loaded
+        // so only the new one is needed
+        if let (Some(render_context), Some(entity_state)) =
+            (&self.render_context, self.state.entity_state.as_mut())
+        {
+            entity_state
+                .playermodel_state
+                .players
+                .push(PlayerModel::new(
+                    render_context.device(),
+                    render_context.queue(),
+                ));
+        }
+    }
+
     pub(in crate::app::user_event) fn request_replay_list(&mut self) {
         info!("Requesting replay list");
 
@@ -167,9 +283,24 @@ impl App {
                 .unwrap_or_else(|_| warn!("cannot send AppError::ProviderError")),
         };
 
+        let metadata_event_loop_proxy = self.event_loop_proxy.clone();
+
         spawn_async(async move {
             let replay_list = resource_provider.get_replay_list().await;
+            let has_replay_list = replay_list.is_ok();
             send_receive_message(replay_list);
+
+            if !has_replay_list {
+                return;
+            }
+
+            // the list still works without names and times
+            match resource_provider.get_replay_metadata().await {
+                Ok(replay_metadata) => metadata_event_loop_proxy
+                    .send_event(AppEvent::ReceiveReplayMetadata(replay_metadata))
+                    .unwrap_or_else(|_| warn!("cannot send ReceiveReplayMetadata")),
+                Err(err) => warn!("Cannot get replay metadata: {}", err),
+            }
         });
     }
 
@@ -180,4 +311,16 @@ impl App {
 
         self.state.other_resources.replay_list = replay_list;
     }
+
+    pub(in crate::app::user_event) fn receive_replay_metadata(
+        &mut self,
+        replay_metadata: ReplayMetadataList,
+    ) {
+        info!("Received metadata of {} replays", replay_metadata.len());
+
+        self.state
+            .other_resources
+            .replay_metadata
+            .extend(replay_metadata);
+    }
 }
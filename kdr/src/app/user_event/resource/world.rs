@@ -79,11 +79,17 @@ impl App {
             self.state.audio_resource.insert(k.to_string(), sound_data);
         });
 
-        // create view model buffer
-        self.load_viewmodels(&common_resource);
-
-        // create player model buffer
-        self.load_player_models(&common_resource);
+        // we should already have render context by this point
+        if let Some(render_context) = self.render_context.as_ref() {
+            // create view model buffer
+            self.state.load_viewmodels(render_context, &common_resource);
+
+            // create player model buffer
+            self.state
+                .load_player_models(render_context, &common_resource);
+        } else {
+            warn!("Trying to create dynamic buffer without render context");
+        }
 
         self.state.other_resources.common_resource = common_resource;
     }
@@ -9,6 +9,14 @@ mod utils;
 pub fn main() {
     use app::RunKDROptions;
 
+    let args: Vec<String> = std::env::args().collect();
+
+    // headless mode that renders a replay to video without opening a window
+    if args.get(1).is_some_and(|arg| arg == "render") {
+        app::run_kdr_offline(&args[2..]);
+        return;
+    }
+
     let options = RunKDROptions {
         resource_provider_base: Some("/WD1/half-life".to_string()),
         websocket_url: None,
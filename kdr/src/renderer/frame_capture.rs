@@ -0,0 +1,119 @@
+//! Render target that can be read back to the CPU.
+//!
+//! This replaces the swapchain when rendering offline.
+
+pub struct FrameCapture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub buffer: wgpu::Buffer,
+    pub width: u32,
+    pub height: u32,
+    // copy_texture_to_buffer requires rows to be aligned
+    padded_bytes_per_row: u32,
+}
+
+impl FrameCapture {
+    const BYTES_PER_PIXEL: u32 = 4;
+
+    pub fn texture_format() -> wgpu::TextureFormat {
+        wgpu::TextureFormat::Rgba8UnormSrgb
+    }
+
+    pub fn new(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("frame capture texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::texture_format(),
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let unpadded_bytes_per_row = width * Self::BYTES_PER_PIXEL;
+        let padded_bytes_per_row = unpadded_bytes_per_row
+            .div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("frame capture buffer"),
+            size: (padded_bytes_per_row * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            texture,
+            view,
+            buffer,
+            width,
+            height,
+            padded_bytes_per_row,
+        }
+    }
+
+    /// Records the copy from the capture texture to the readback buffer.
+    ///
+    /// Must be called after the frame is rendered to [`Self::view`] and before submitting the encoder.
+    pub fn copy_to_buffer(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &self.buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_bytes_per_row),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Blocks until the submitted frame is available and returns tightly packed RGBA8 pixels.
+    pub fn read_frame(&self, device: &wgpu::Device) -> Vec<u8> {
+        let slice = self.buffer.slice(..);
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |res| {
+            let _ = sender.send(res);
+        });
+
+        device.poll(wgpu::Maintain::Wait);
+
+        receiver
+            .recv()
+            .expect("map_async callback is not called")
+            .expect("cannot map frame capture buffer");
+
+        let unpadded_bytes_per_row = (self.width * Self::BYTES_PER_PIXEL) as usize;
+        let mut res = Vec::with_capacity(unpadded_bytes_per_row * self.height as usize);
+
+        {
+            let data = slice.get_mapped_range();
+
+            data.chunks(self.padded_bytes_per_row as usize)
+                .for_each(|row| res.extend_from_slice(&row[..unpadded_bytes_per_row]));
+        }
+
+        self.buffer.unmap();
+
+        res
+    }
+}
@@ -2,10 +2,14 @@ use std::sync::{Arc, RwLock};
 
 use camera::CameraBuffer;
 use finalize::FinalizeRenderPipeline;
+#[cfg(not(target_arch = "wasm32"))]
+use frame_capture::FrameCapture;
 use oit::{OITRenderTarget, OITResolver};
 use post_process::PostProcessing;
 use render_targets::RenderTargets;
 use skybox::SkyboxLoader;
+#[cfg(not(target_arch = "wasm32"))]
+use tracing::info;
 use utils::FullScrenTriVertexShader;
 use winit::window::Window;
 use world_buffer::WorldLoader;
@@ -21,6 +25,8 @@ mod bsp_lightmap;
 pub mod camera;
 mod egui_renderer;
 pub mod finalize;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod frame_capture;
 pub mod mvp_buffer;
 pub mod oit;
 pub mod post_process;
@@ -40,7 +46,8 @@ pub struct RenderContext {
     pub world_skybox_mask_render_pipeline: wgpu::RenderPipeline,
     pub world_transparent_render_pipeline: wgpu::RenderPipeline,
     pub swapchain_format: wgpu::TextureFormat,
-    pub surface: wgpu::Surface<'static>,
+    /// Headless render context doesn't have a surface.
+    pub surface: Option<wgpu::Surface<'static>>,
     pub surface_config: wgpu::SurfaceConfiguration,
     pub oit_resolver: OITResolver,
     pub camera_buffer: CameraBuffer,
@@ -68,18 +75,7 @@ impl RenderContext {
             winit::dpi::LogicalSize::new(DEFAULT_WIDTH, DEFAULT_HEIGHT)
         };
 
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::VULKAN // native windows/linux
-            | wgpu::Backends::GL, // webgpu doesnt work well on modern browsers, yet TODO: come back in 2 years
-            flags: wgpu::InstanceFlags::default(),
-            backend_options: wgpu::BackendOptions {
-                // need to be explicit here just to be safe
-                gl: wgpu::GlBackendOptions {
-                    gles_minor_version: wgpu::Gles3MinorVersion::Automatic,
-                },
-                dx12: wgpu::Dx12BackendOptions::default(),
-            },
-        });
+        let instance = Self::create_instance();
 
         let surface = instance.create_surface(window).unwrap();
 
@@ -97,6 +93,82 @@ impl RenderContext {
         //     .block_on()
         //     .unwrap();
 
+        let (device, queue) = Self::request_device(&adapter).await;
+
+        // swap chain stuffs
+        let swapchain_capabilities = surface.get_capabilities(&adapter);
+        let swapchain_format = swapchain_capabilities.formats[0];
+
+        let config = surface
+            .get_default_config(&adapter, size.width, size.height)
+            .unwrap();
+
+        let config = wgpu::SurfaceConfiguration {
+            present_mode: wgpu::PresentMode::Fifo,
+            ..config
+        };
+
+        surface.configure(&device, &config);
+
+        Self::create_pipelines(device, queue, Some(surface), config)
+    }
+
+    /// Creates a render context without window and surface.
+    ///
+    /// The final image must be written to a texture that we own, see [`frame_capture::FrameCapture`].
+    ///
+    /// `force_software` asks for a CPU adapter such as lavapipe so it can run on machines without a GPU.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn new_headless(width: u32, height: u32, force_software: bool) -> Option<Self> {
+        let instance = Self::create_instance();
+
+        let adapter = if force_software {
+            instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    force_fallback_adapter: true,
+                    compatible_surface: None,
+                })
+                .await?
+        } else {
+            wgpu::util::initialize_adapter_from_env_or_default(&instance, None).await?
+        };
+
+        info!("Headless adapter: {:?}", adapter.get_info());
+
+        let (device, queue) = Self::request_device(&adapter).await;
+
+        // there is no swapchain but the finalize step still needs to know what to write to
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: FrameCapture::texture_format(),
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            desired_maximum_frame_latency: 2,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+
+        Some(Self::create_pipelines(device, queue, None, config))
+    }
+
+    fn create_instance() -> wgpu::Instance {
+        wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::VULKAN // native windows/linux
+            | wgpu::Backends::GL, // webgpu doesnt work well on modern browsers, yet TODO: come back in 2 years
+            flags: wgpu::InstanceFlags::default(),
+            backend_options: wgpu::BackendOptions {
+                // need to be explicit here just to be safe
+                gl: wgpu::GlBackendOptions {
+                    gles_minor_version: wgpu::Gles3MinorVersion::Automatic,
+                },
+                dx12: wgpu::Dx12BackendOptions::default(),
+            },
+        })
+    }
+
+    async fn request_device(adapter: &wgpu::Adapter) -> (wgpu::Device, wgpu::Queue) {
         // edit limits
         let mut limits =
             wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits());
@@ -107,7 +179,7 @@ impl RenderContext {
         limits.max_push_constant_size = 128; // TODO may not be working
         // end limits
 
-        let (device, queue) = adapter
+        adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
@@ -119,14 +191,25 @@ impl RenderContext {
                 None,
             )
             .await
-            .unwrap();
+            .unwrap()
+    }
+
+    fn create_pipelines(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        surface: Option<wgpu::Surface<'static>>,
+        config: wgpu::SurfaceConfiguration,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        };
+        let swapchain_format = config.format;
 
         // camera buffer
         let camera_buffer = CameraBuffer::create(&device);
 
-        // swap chain stuffs
-        let swapchain_capabilities = surface.get_capabilities(&adapter);
-        let swapchain_format = swapchain_capabilities.formats[0];
         let render_targets = RenderTargets::new(&device, size.width, size.height);
         let render_target_format = RenderTargets::main_texture_format();
 
@@ -171,15 +254,6 @@ impl RenderContext {
             depth_texture_format,
         );
 
-        let config = surface
-            .get_default_config(&adapter, size.width, size.height)
-            .unwrap();
-
-        let config = wgpu::SurfaceConfiguration {
-            present_mode: wgpu::PresentMode::Fifo,
-            ..config
-        };
-
         let oit_resolver = OITResolver::new(
             &device,
             size.width,
@@ -188,8 +262,6 @@ impl RenderContext {
             &fullscreen_tri_vertex_shader,
         );
 
-        surface.configure(&device, &config);
-
         let finalize_render_pipeline = FinalizeRenderPipeline::create_pipeline(
             &device,
             // take in composite view and then render it out to the target swapchain
@@ -249,6 +321,8 @@ impl RenderContext {
 
     pub fn surface_texture(&self) -> wgpu::SurfaceTexture {
         self.surface
+            .as_ref()
+            .expect("headless render context does not have a surface")
             .get_current_texture()
             .expect("cannot get surface texture")
     }
@@ -260,7 +334,10 @@ impl RenderContext {
     pub fn resize(&mut self, width: u32, height: u32) {
         self.surface_config.width = width;
         self.surface_config.height = height;
-        self.surface.configure(&self.device, &self.surface_config);
+
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.surface_config);
+        }
 
         let new_render_targets = RenderTargets::new(&self.device, width, height);
 
@@ -3,7 +3,7 @@
 //! The BSP it self and long with models inside the map are considered static. Even when the models and entities do move around,
 //! the actual data buffer don't swap out.
 
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::Range};
 
 use common::BuildMvpResult;
 use image::RgbaImage;
@@ -34,6 +34,11 @@ pub(super) type WorldTextureLookupTable = HashMap<(usize, usize), (usize, usize)
 
 pub(super) type MvpLookup = HashMap<usize, usize>;
 
+/// Key: Texture Array Index
+///
+/// Value: (BSP Face Index, Index Range) of worldspawn faces in that batch
+pub(super) type WorldFaceLookup = HashMap<usize, Vec<(usize, Range<u32>)>>;
+
 pub(super) struct ProcessBspFaceData<'a> {
     pub bsp_face_index: usize,
     pub world_entity_index: usize,
@@ -63,6 +68,10 @@ pub struct WorldStaticBuffer {
     // a map might not have sky texture so this is optional
     // the index is for opaque buffer vector
     pub skybrush_batch_index: Option<usize>,
+    /// (BSP Face Index, Index Range) of worldspawn faces for every opaque batch, in order.
+    ///
+    /// Same order as `opaque` so visibility can draw only parts of a batch.
+    pub opaque_world_faces: Vec<Vec<(usize, Range<u32>)>>,
 }
 
 impl WorldLoader {
@@ -83,12 +92,22 @@ impl WorldLoader {
 
         let (lookup_table, texture_arrays) =
             Self::load_static_world_textures(device, queue, resource);
-        let (opaque_batch, transparent_batch, mvp_lookup) =
+        let (opaque_batch, transparent_batch, mvp_lookup, mut world_face_lookup) =
             create_batch_lookups(resource, &entity_infos, &lookup_table, &lightmap);
 
         let opaque_vertex_buffer = create_world_vertex_buffer(device, opaque_batch);
         let transparent_vertex_buffer = create_world_vertex_buffer(device, transparent_batch);
 
+        // vertex buffers come out of a hash map so they need to be matched again
+        let opaque_world_faces = opaque_vertex_buffer
+            .iter()
+            .map(|batch| {
+                world_face_lookup
+                    .remove(&batch.texture_array_index)
+                    .unwrap_or_default()
+            })
+            .collect();
+
         // creating transformations
         // we have an array of 1024 mat4s
         // the index i is the transformation of entity index i
@@ -136,6 +155,7 @@ impl WorldLoader {
             mvp_buffer,
             skybrush_batch_index,
             mvp_lookup,
+            opaque_world_faces,
         }
     }
 
@@ -305,17 +325,18 @@ impl WorldLoader {
     }
 }
 
-// Returns (opaque batch lookup, transparent batch lookup)
+// Returns (opaque batch lookup, transparent batch lookup, mvp lookup, worldspawn face lookup)
 fn create_batch_lookups(
     resource: &BspResource,
     // make sure entity info is sorted by world index
     sorted_entity_infos: &[&WorldEntity],
     world_texture_lookup: &WorldTextureLookupTable,
     lightmap: &LightMapAtlasBuffer,
-) -> (BatchLookup, BatchLookup, MvpLookup) {
+) -> (BatchLookup, BatchLookup, MvpLookup, WorldFaceLookup) {
     let mut opaque_lookup = BatchLookup::new();
     let mut transparent_lookup = BatchLookup::new();
     let mut mvp_lookup: HashMap<usize, usize> = HashMap::new();
+    let mut world_face_lookup = WorldFaceLookup::new();
 
     let bsp = &resource.bsp;
 
@@ -411,11 +432,20 @@ fn create_batch_lookups(
                         // newer vertices will have their index start at 0 but we don't want that
                         // need to divide by <x> because each "vertices" has <x> floats
                         let new_vertices_offset = batch.0.len();
+                        let first_index = batch.1.len() as u32;
 
                         batch.0.extend(vertices);
                         batch
                             .1
                             .extend(indices.into_iter().map(|i| i + new_vertices_offset as u32));
+
+                        // only worldspawn faces are in leaves
+                        if bsp_model_index == 0 {
+                            world_face_lookup
+                                .entry(*array_idx)
+                                .or_default()
+                                .push((bsp_face_index, first_index..batch.1.len() as u32));
+                        }
                     });
 
                 // create_bsp_batch_lookup(bsp)
@@ -537,5 +567,10 @@ fn create_batch_lookups(
         };
     });
 
-    (opaque_lookup, transparent_lookup, mvp_lookup)
+    (
+        opaque_lookup,
+        transparent_lookup,
+        mvp_lookup,
+        world_face_lookup,
+    )
 }
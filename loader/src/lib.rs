@@ -25,8 +25,12 @@ use std::{
 
 use bsp_resource::BspResource;
 use error::ResourceProviderError;
-use ghost::{GhostBlob, GhostError, GhostInfo, get_ghost_from_blob};
+use ghost::{
+    GhostBlob, GhostError, GhostInfo, GhostMetadata, GhostParseMode,
+    get_ghost_with_players_from_blob,
+};
 use serde::Deserialize;
+use tracing::warn;
 
 pub mod bsp_resource;
 pub mod error;
@@ -69,6 +73,10 @@ pub type ResourceMap = HashMap<String, Vec<u8>>;
 /// Value: Set of names of maps in that game mod excluding ".bsp" at the end
 pub type MapList = HashMap<String, HashSet<String>>;
 pub type ReplayList = Vec<String>;
+/// Key: Replay name from [`ReplayList`]
+///
+/// Value: Metadata of the replay. Replays that cannot be parsed are not in here.
+pub type ReplayMetadataList = HashMap<String, GhostMetadata>;
 
 /// .bsp resources is sent from server to client.
 pub struct Resource {
@@ -106,24 +114,34 @@ pub trait ResourceProvider {
     ///
     /// Nicely enough, with browser sandboxed file system, we do know the file name to nicely categorise it.
     ///
-    /// The output should be a map identifier and then ghost data.
+    /// The output should be a map identifier, ghost data and then other players in the replay.
     ///
     /// The client should handle the error properly.
     async fn get_ghost_data(
         &self,
         path: impl AsRef<Path> + AsRef<OsStr>,
         ghost_blob: GhostBlob,
-    ) -> Result<(MapIdentifier, GhostInfo), GhostError> {
+    ) -> Result<(MapIdentifier, GhostInfo, Vec<GhostInfo>), GhostError> {
         let path: &Path = path.as_ref();
 
-        let ghost = get_ghost_from_blob(&path.display().to_string(), ghost_blob)?;
+        // one bad message shouldn't stop the whole replay from playing
+        let (ghost, players) = get_ghost_with_players_from_blob(
+            &path.display().to_string(),
+            ghost_blob,
+            GhostParseMode::Lenient,
+        )?;
+
+        ghost
+            .warnings
+            .iter()
+            .for_each(|warning| warn!("Skipped part of replay `{}`: {}", path.display(), warning));
 
         let map_identifier = MapIdentifier {
             map_name: ghost.map_name.to_owned(),
             game_mod: ghost.game_mod.to_owned(),
         };
 
-        Ok((map_identifier, ghost))
+        Ok((map_identifier, ghost, players))
     }
 
     /// Gets map list from the game directory
@@ -138,6 +156,14 @@ pub trait ResourceProvider {
     /// The server implementation is to scan specified folders for ghosts.
     async fn get_replay_list(&self) -> Result<ReplayList, ResourceProviderError>;
 
+    /// Gets metadata of replays in the replay list so the list can show player names and times.
+    ///
+    /// On native, this is empty because parsing every demo in the game directory takes too long.
+    /// Metadata is then only known after a replay is loaded.
+    ///
+    /// On web, the server parses its replays once when it builds the replay list.
+    async fn get_replay_metadata(&self) -> Result<ReplayMetadataList, ResourceProviderError>;
+
     /// Gets resources not related to the map.
     ///
     /// This includes player models, weapon sounds, view models, blah blah blah.
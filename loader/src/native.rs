@@ -14,8 +14,8 @@ use bsp::Bsp;
 use wad::types::Wad;
 
 use crate::{
-    MODEL_ENTITIES, MapIdentifier, MapList, ProgressResourceProvider, ReplayList, ResourceMap,
-    SOUND_ENTITIES,
+    MODEL_ENTITIES, MapIdentifier, MapList, ProgressResourceProvider, ReplayList,
+    ReplayMetadataList, ResourceMap, SOUND_ENTITIES,
 };
 
 use super::{ResourceProvider, SKYBOX_SUFFIXES, error::ResourceProviderError, fix_bsp_file_name};
@@ -185,6 +185,10 @@ impl ResourceProvider for NativeResourceProvider {
         }
     }
 
+    async fn get_replay_metadata(&self) -> Result<ReplayMetadataList, ResourceProviderError> {
+        Ok(ReplayMetadataList::new())
+    }
+
     async fn get_replay(
         &self,
         replay_name: &str,
@@ -5,7 +5,8 @@ use std::{
 
 use bsp::Bsp;
 use common::{
-    API_SCOPE_VERSION, GET_MAPS_ENDPOINT, GET_REPLAYS_ENDPOINT, REQUEST_COMMON_RESOURCE_ENDPOINT,
+    API_SCOPE_VERSION, GET_MAPS_ENDPOINT, GET_REPLAY_METADATA_ENDPOINT, GET_REPLAYS_ENDPOINT,
+    REQUEST_COMMON_RESOURCE_ENDPOINT,
 };
 use futures_util::StreamExt;
 use ghost::GhostBlob;
@@ -203,6 +204,25 @@ impl ResourceProvider for WebResourceProvider {
             .map_err(|op| ResourceProviderError::ResponsePayloadError { source: op })
     }
 
+    async fn get_replay_metadata(
+        &self,
+    ) -> Result<crate::ReplayMetadataList, ResourceProviderError> {
+        let url = format!(
+            "{}/{API_SCOPE_VERSION}/{GET_REPLAY_METADATA_ENDPOINT}",
+            self.base_url
+        );
+
+        let response = reqwest::get(url)
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|op| ResourceProviderError::RequestError { source: op })?;
+
+        response
+            .json()
+            .await
+            .map_err(|op| ResourceProviderError::ResponsePayloadError { source: op })
+    }
+
     async fn get_replay(
         &self,
         replay_name: &str,
@@ -0,0 +1,289 @@
+//! Converts replays between ghost formats so that records from different communities can be served as one format.
+//!
+//! rest-api-server convert <input file or folder> <output file or folder> [--from <parser>] [--to <type>] [--trim <start>:<end>] [--resample <rate>]
+//!
+//! Types are `demo`, `simen`, `surf_gateway`, `romanian_jumpers`, `hlkz` and `kdr`. Without `--to`, the type is from the output file name.
+//! `--from` takes a ghost parser name, which is the same as the type for built-in formats. `schema` ghost can only be read.
+//! `--trim` keeps the part between two times in seconds and `--resample` changes the frame rate, which makes smaller replays.
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use ghost::{
+    GhostBlobType, GhostError, GhostInfo, GhostParseMode, GhostParserRegistry, write_ghost_to_blob,
+};
+use tracing::{info, warn};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConvertError {
+    #[error("Bad arguments: {reason}")]
+    BadArguments { reason: String },
+
+    #[error("Failed to convert `{path}`: {source}")]
+    Ghost {
+        #[source]
+        source: GhostError,
+        path: PathBuf,
+    },
+
+    #[error("Cannot write `{path}`: {source}")]
+    IOError {
+        #[source]
+        source: std::io::Error,
+        path: PathBuf,
+    },
+}
+
+const USAGE: &str = "usage: rest-api-server convert <input> <output> [--from <parser>] [--to <type>] [--trim <start>:<end>] [--resample <rate>]";
+
+struct ConvertArgs {
+    input: PathBuf,
+    output: PathBuf,
+    from: Option<String>,
+    to: Option<GhostBlobType>,
+    trim: Option<Range<f32>>,
+    resample: Option<f32>,
+}
+
+/// Parses `<start>:<end>` in seconds.
+fn parse_trim(value: &str) -> Option<Range<f32>> {
+    let (start, end) = value.split_once(':')?;
+
+    Some(start.parse().ok()?..end.parse().ok()?)
+}
+
+fn parse_args(args: &[String]) -> Result<ConvertArgs, ConvertError> {
+    let bad = |reason: String| ConvertError::BadArguments {
+        reason: format!("{}\n{}", reason, USAGE),
+    };
+
+    let mut paths = vec![];
+    let mut from = None;
+    let mut to = None;
+    let mut trim = None;
+    let mut resample = None;
+
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from" | "--to" | "--trim" | "--resample" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| bad(format!("missing value for `{}`", arg)))?;
+
+                match arg.as_str() {
+                    "--from" => from = Some(value.to_owned()),
+                    "--to" => {
+                        to = Some(
+                            GhostBlobType::try_from(value.as_str())
+                                .map_err(|op| bad(op.to_string()))?,
+                        )
+                    }
+                    "--trim" => {
+                        trim = Some(parse_trim(value).ok_or_else(|| {
+                            bad(format!("`{}` is not `<start>:<end>` in seconds", value))
+                        })?)
+                    }
+                    _ => {
+                        resample = Some(
+                            value
+                                .parse::<f32>()
+                                .ok()
+                                .filter(|rate| *rate > 0.)
+                                .ok_or_else(|| bad(format!("`{}` is not a frame rate", value)))?,
+                        )
+                    }
+                }
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    let [input, output] = <[PathBuf; 2]>::try_from(paths)
+        .map_err(|_| bad("expecting one input and one output".to_string()))?;
+
+    Ok(ConvertArgs {
+        input,
+        output,
+        from,
+        to,
+        trim,
+        resample,
+    })
+}
+
+/// Applies `--trim` and then `--resample`.
+fn edit_ghost(
+    ghost: &mut GhostInfo,
+    input: &Path,
+    trim: Option<&Range<f32>>,
+    resample: Option<f32>,
+) -> Result<(), ConvertError> {
+    let ghost_err = |op| ConvertError::Ghost {
+        source: op,
+        path: input.to_path_buf(),
+    };
+
+    if let Some(trim) = trim {
+        ghost.trim_time(trim.clone()).map_err(ghost_err)?;
+    }
+
+    if let Some(rate) = resample {
+        ghost.resample(rate).map_err(ghost_err)?;
+    }
+
+    Ok(())
+}
+
+fn read_ghost(
+    parsers: &GhostParserRegistry,
+    input: &Path,
+    from: Option<&str>,
+) -> Result<GhostInfo, ConvertError> {
+    let ghost_err = |op| ConvertError::Ghost {
+        source: op,
+        path: input.to_path_buf(),
+    };
+
+    let bytes = std::fs::read(input).map_err(|op| ConvertError::IOError {
+        source: op,
+        path: input.to_path_buf(),
+    })?;
+
+    let ghost = parsers
+        .parse(
+            input.display().to_string().as_str(),
+            &bytes,
+            from,
+            GhostParseMode::Lenient,
+        )
+        .map_err(ghost_err)?;
+
+    ghost
+        .warnings
+        .iter()
+        .for_each(|warning| warn!("Skipped part of `{}`: {}", input.display(), warning));
+
+    Ok(ghost)
+}
+
+fn write_ghost(ghost: &GhostInfo, output: &Path, to: GhostBlobType) -> Result<(), ConvertError> {
+    let bytes = write_ghost_to_blob(ghost, to)
+        .map_err(|op| ConvertError::Ghost {
+            source: op,
+            path: output.to_path_buf(),
+        })?
+        .into_bytes()
+        .unwrap_or_default();
+
+    std::fs::write(output, bytes).map_err(|op| ConvertError::IOError {
+        source: op,
+        path: output.to_path_buf(),
+    })?;
+
+    info!("Converted `{}` to `{}`", ghost.ghost_name, output.display());
+
+    Ok(())
+}
+
+/// Output file name for a ghost in folder conversion.
+fn output_file_name(input_file_name: &str, map_name: &str, to: &GhostBlobType) -> String {
+    let stem = GhostBlobType::try_from_file_name(input_file_name)
+        .and_then(|blob_type| input_file_name.strip_suffix(blob_type.file_suffix()))
+        .unwrap_or(input_file_name);
+
+    // hlkz needs map name in the file name
+    let stem = match to {
+        GhostBlobType::SRHLKZ if !stem.contains("_0_0_") && !stem.contains("_0_1_") => {
+            format!("{}_0_0_{}", map_name, stem)
+        }
+        _ => stem.to_string(),
+    };
+
+    format!("{}{}", stem, to.file_suffix())
+}
+
+pub fn run_convert(args: &[String]) -> Result<(), ConvertError> {
+    let ConvertArgs {
+        input,
+        output,
+        from,
+        to,
+        trim,
+        resample,
+    } = parse_args(args)?;
+
+    let parsers = GhostParserRegistry::default();
+
+    if let Some(name) = from.as_ref().filter(|name| parsers.get(name).is_none()) {
+        return Err(ConvertError::Ghost {
+            source: GhostError::UnknownParser { name: name.clone() },
+            path: input,
+        });
+    }
+
+    if !input.is_dir() {
+        let to = to
+            .or_else(|| GhostBlobType::try_from_file_name(output.display().to_string().as_str()))
+            .ok_or_else(|| ConvertError::BadArguments {
+                reason: format!(
+                    "cannot guess output type for `{}`, use `--to`",
+                    output.display()
+                ),
+            })?;
+
+        let mut ghost = read_ghost(&parsers, &input, from.as_deref())?;
+
+        edit_ghost(&mut ghost, &input, trim.as_ref(), resample)?;
+
+        return write_ghost(&ghost, &output, to);
+    }
+
+    // folder conversion
+    let to = to.ok_or_else(|| ConvertError::BadArguments {
+        reason: "`--to` is required when converting a folder".to_string(),
+    })?;
+
+    std::fs::create_dir_all(&output).map_err(|op| ConvertError::IOError {
+        source: op,
+        path: output.to_path_buf(),
+    })?;
+
+    let entries = std::fs::read_dir(&input).map_err(|op| ConvertError::IOError {
+        source: op,
+        path: input.to_path_buf(),
+    })?;
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .for_each(|input_file| {
+            let Some(input_file_name) = input_file.file_name().and_then(|name| name.to_str())
+            else {
+                return;
+            };
+
+            // only convert what we know, unless forced
+            if from.is_none() && parsers.find_by_file_name(input_file_name).is_none() {
+                return;
+            }
+
+            let res = read_ghost(&parsers, &input_file, from.as_deref()).and_then(|mut ghost| {
+                edit_ghost(&mut ghost, &input_file, trim.as_ref(), resample)?;
+
+                let output_file =
+                    output.join(output_file_name(input_file_name, &ghost.map_name, &to));
+
+                write_ghost(&ghost, &output_file, to.clone())
+            });
+
+            if let Err(err) = res {
+                warn!("{}", err);
+            }
+        });
+
+    Ok(())
+}
@@ -6,8 +6,10 @@ use std::path::PathBuf;
 use common::CONFIG_FILE_NAME;
 use config::KDRApiServerConfig;
 use server::start_server;
+use tracing::warn;
 use utils::start_tracing;
 
+mod convert;
 mod send_res;
 mod server;
 pub(crate) mod utils;
@@ -21,6 +23,18 @@ pub struct ServerArgs {
 fn main() -> std::io::Result<()> {
     start_tracing();
 
+    let args: Vec<String> = std::env::args().collect();
+
+    // converting replays instead of starting the server
+    if args.get(1).is_some_and(|arg| arg == "convert") {
+        if let Err(err) = convert::run_convert(&args[2..]) {
+            warn!("{}", err);
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+
     let config_from_env = std::env::var(KDR_API_CONFIG_PATH_ENV).map(|what| PathBuf::from(what));
     let config_from_local =
         std::env::current_exe().map(|path| path.with_file_name(CONFIG_FILE_NAME));
@@ -3,7 +3,10 @@ use std::sync::{Arc, RwLock};
 use actix_web::{App, HttpResponse, HttpServer, Responder, get, middleware::Compress, post, web};
 use common::{CANNOT_FIND_REQUESTED_MAP_ERROR, CANNOT_FIND_REQUESTED_REPLAY_ERR};
 use config::KDRApiServerConfig;
-use loader::{MapIdentifier, MapList, ReplayList, native::NativeResourceProvider};
+use ghost::GhostParserRegistry;
+use loader::{
+    MapIdentifier, MapList, ReplayList, ReplayMetadataList, native::NativeResourceProvider,
+};
 use serde::Deserialize;
 use tracing::{info, info_span, warn};
 use uuid::Uuid;
@@ -12,8 +15,8 @@ use crate::{
     ServerArgs,
     send_res::{gchimp_resmake_way, native_way},
     utils::{
-        create_common_resource, fetch_map_list, fetch_replay, fetch_replay_list,
-        sanitize_identifier,
+        ConvertedReplayCache, create_common_resource, fetch_map_list, fetch_replay,
+        fetch_replay_list, fetch_replay_metadata, sanitize_identifier,
     },
 };
 
@@ -25,6 +28,9 @@ struct AppData {
     common_resource: Option<Vec<u8>>,
     map_list: Arc<RwLock<MapList>>,
     replay_list: Arc<RwLock<ReplayList>>,
+    replay_metadata: Arc<RwLock<ReplayMetadataList>>,
+    ghost_parsers: GhostParserRegistry,
+    converted_replays: Arc<RwLock<ConvertedReplayCache>>,
 
     // the rest of the config
     config: KDRApiServerConfig,
@@ -114,7 +120,27 @@ async fn get_replay(path: web::Path<String>, data: web::Data<AppData>) -> impl R
         return HttpResponse::BadRequest().body("No replay provided.");
     }
 
-    let Some(replay_blob) = fetch_replay(&data.config, replay_name.as_str()) else {
+    // parsing and converting a big demo takes a while, so it doesn't hold up the worker
+    let span = tracing::Span::current();
+    let blocking_data = data.clone();
+    let blocking_replay_name = replay_name.clone();
+
+    let replay_blob = web::block(move || {
+        span.in_scope(|| {
+            fetch_replay(
+                &blocking_data.config,
+                &blocking_data.ghost_parsers,
+                &blocking_data.converted_replays,
+                blocking_replay_name.as_str(),
+            )
+        })
+    })
+    .await
+    .inspect_err(|op| warn!("Cannot fetch replay `{}`: {}", replay_name, op))
+    .ok()
+    .flatten();
+
+    let Some(replay_blob) = replay_blob else {
         warn!("Cannot get replay: `{}`", replay_name);
 
         return HttpResponse::NotFound().body(CANNOT_FIND_REQUESTED_REPLAY_ERR);
@@ -144,6 +170,13 @@ async fn get_replay_list(data: web::Data<AppData>) -> impl Responder {
     HttpResponse::Ok().json(&*data.replay_list.read().unwrap())
 }
 
+#[get("/replay-metadata")]
+async fn get_replay_metadata(data: web::Data<AppData>) -> impl Responder {
+    info!("Request replay metadata");
+
+    HttpResponse::Ok().json(&*data.replay_metadata.read().unwrap())
+}
+
 #[post("/update-map-list")]
 async fn update_map_list(
     req: web::Json<UpdateRequest>,
@@ -176,12 +209,28 @@ async fn update_replay_list(
     if input_secret == &data.config.secret {
         let new_replay_list = fetch_replay_list(&data.config).await;
 
-        match data.replay_list.write() {
-            Ok(mut lock) => {
-                *lock = new_replay_list;
+        // parsing every replay takes a while, so it doesn't hold up the worker
+        let blocking_data = data.clone();
+        let blocking_replay_list = new_replay_list.clone();
+
+        let new_replay_metadata = web::block(move || {
+            fetch_replay_metadata(
+                &blocking_data.config,
+                &blocking_data.ghost_parsers,
+                &blocking_replay_list,
+            )
+        })
+        .await
+        .inspect_err(|op| warn!("Cannot fetch replay metadata: {}", op))
+        .unwrap_or_default();
+
+        match (data.replay_list.write(), data.replay_metadata.write()) {
+            (Ok(mut replay_list), Ok(mut replay_metadata)) => {
+                *replay_list = new_replay_list;
+                *replay_metadata = new_replay_metadata;
                 HttpResponse::Ok().finish()
             }
-            Err(_) => HttpResponse::InternalServerError().finish(),
+            _ => HttpResponse::InternalServerError().finish(),
         }
     } else {
         HttpResponse::Forbidden().finish()
@@ -217,11 +266,34 @@ pub async fn start_server(args: ServerArgs) -> std::io::Result<()> {
 
     let use_resmake_zip = config.use_resmake_zip;
 
+    // in-house ghost formats are registered here
+    let ghost_parsers = GhostParserRegistry::default();
+
+    // a typo in the config shouldn't stop the server
+    let ghost_parsers = if config.replay_parsers.is_empty() {
+        ghost_parsers
+    } else {
+        ghost_parsers
+            .select(&config.replay_parsers)
+            .unwrap_or_else(|err| {
+                warn!("Cannot select replay parsers, using every parser: {}", err);
+
+                ghost_parsers
+            })
+    };
+
+    info!("Ghost parsers: {:?}", ghost_parsers);
+
+    let replay_metadata = fetch_replay_metadata(&config, &ghost_parsers, &replay_list);
+
     let data = AppData {
         resource_provider,
         common_resource,
         map_list: Arc::new(RwLock::new(map_list)),
         replay_list: Arc::new(RwLock::new(replay_list)),
+        replay_metadata: Arc::new(RwLock::new(replay_metadata)),
+        ghost_parsers,
+        converted_replays: Arc::new(RwLock::new(ConvertedReplayCache::default())),
         config,
     };
 
@@ -255,6 +327,7 @@ pub async fn start_server(args: ServerArgs) -> std::io::Result<()> {
                     .service(get_common_resource)
                     .service(get_map_list)
                     .service(get_replay_list)
+                    .service(get_replay_metadata)
                     .service(update_map_list)
                     .service(update_replay_list)
                     .service(health_check),
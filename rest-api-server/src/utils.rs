@@ -1,12 +1,15 @@
 use std::{
+    collections::VecDeque,
     io::Write,
     path::{Path, PathBuf},
+    sync::RwLock,
+    time::SystemTime,
 };
 
 use config::KDRApiServerConfig;
-use ghost::{GhostBlob, get_ghost_blob_from_path};
+use ghost::{GhostBlob, GhostBlobType, GhostParseMode, GhostParserRegistry, write_ghost_to_blob};
 use loader::{
-    MapIdentifier, MapList, ReplayList, ResourceProvider,
+    MapIdentifier, MapList, ReplayList, ReplayMetadataList, ResourceProvider,
     native::{NativeResourceProvider, scan_folder_for_files, search_game_resource},
 };
 use tracing::{Level, info, warn};
@@ -123,20 +126,153 @@ pub async fn fetch_replay_list(config: &KDRApiServerConfig) -> ReplayList {
     replay_list
 }
 
-pub fn fetch_replay(config: &KDRApiServerConfig, replay_name: &str) -> Option<GhostBlob> {
+/// Parses every replay in the list for its metadata.
+///
+/// This is done once with the replay list so the client can show player names and times before loading a replay.
+pub fn fetch_replay_metadata(
+    config: &KDRApiServerConfig,
+    parsers: &GhostParserRegistry,
+    replay_list: &ReplayList,
+) -> ReplayMetadataList {
+    let replay_metadata: ReplayMetadataList = replay_list
+        .iter()
+        .filter_map(|replay_name| {
+            let metadata = config.replay_folders.iter().find_map(|folder| {
+                let path = replay_path(folder, replay_name)?;
+                let bytes = std::fs::read(&path).ok()?;
+
+                let parser = parsers.find(
+                    replay_name,
+                    &bytes,
+                    config.replay_unknown_format_override.as_deref(),
+                )?;
+
+                parser
+                    .parse(replay_name, &bytes, GhostParseMode::Lenient)
+                    .inspect_err(|op| warn!("Cannot parse replay `{}`: {}", replay_name, op))
+                    .ok()
+                    .map(|ghost| ghost.metadata)
+            })?;
+
+            Some((replay_name.to_owned(), metadata))
+        })
+        .collect();
+
+    info!("Found metadata of ({}) replays", replay_metadata.len());
+
+    replay_metadata
+}
+
+/// Returns the replay path if it is inside the replay folder.
+fn replay_path(folder: &Path, replay_name: &str) -> Option<PathBuf> {
+    let path = folder.join(replay_name);
+    let canonicalized = path.canonicalize().ok()?;
+
+    // sanitizing the path
+    canonicalized.starts_with(folder).then_some(canonicalized)
+}
+
+// number of converted replays kept in memory, the oldest one goes first
+const CONVERTED_REPLAY_CACHE_SIZE: usize = 32;
+
+/// Replays converted to kdr with [`KDRApiServerConfig::replay_send_as_kdr`] so they are only parsed once.
+#[derive(Debug, Default)]
+pub struct ConvertedReplayCache {
+    /// (Path, Modified Time, kdr Bytes)
+    replays: VecDeque<(PathBuf, SystemTime, Vec<u8>)>,
+}
+
+impl ConvertedReplayCache {
+    fn get(&self, path: &Path, modified: SystemTime) -> Option<Vec<u8>> {
+        self.replays
+            .iter()
+            .find(|(replay_path, replay_modified, _)| {
+                replay_path == path && *replay_modified == modified
+            })
+            .map(|(_, _, bytes)| bytes.clone())
+    }
+
+    fn insert(&mut self, path: PathBuf, modified: SystemTime, bytes: Vec<u8>) {
+        // older version of the same file
+        self.replays
+            .retain(|(replay_path, _, _)| *replay_path != path);
+
+        if self.replays.len() >= CONVERTED_REPLAY_CACHE_SIZE {
+            self.replays.pop_front();
+        }
+
+        self.replays.push_back((path, modified, bytes));
+    }
+}
+
+pub fn fetch_replay(
+    config: &KDRApiServerConfig,
+    parsers: &GhostParserRegistry,
+    converted_replays: &RwLock<ConvertedReplayCache>,
+    replay_name: &str,
+) -> Option<GhostBlob> {
     config.replay_folders.iter().find_map(|folder| {
-        let path = folder.join(replay_name);
-        let canonicalized = path.canonicalize().ok()?;
+        let canonicalized = replay_path(folder, replay_name)?;
+
+        // a changed file has a different modified time so it is converted again
+        let modified = std::fs::metadata(&canonicalized)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+
+        if config.replay_send_as_kdr
+            && let Some(modified) = modified
+            && let Some(bytes) = converted_replays
+                .read()
+                .ok()
+                .and_then(|cache| cache.get(&canonicalized, modified))
+        {
+            return Some(GhostBlob::Kdr(bytes));
+        }
 
-        // sanitizing the path
-        if !canonicalized.starts_with(folder) {
+        let bytes = std::fs::read(&canonicalized).ok()?;
+
+        let Some(parser) = parsers.find(
+            replay_name,
+            &bytes,
+            config.replay_unknown_format_override.as_deref(),
+        ) else {
+            warn!("No ghost parser for replay `{}`", replay_name);
             return None;
+        };
+
+        // the client can only parse built-in formats
+        match parser.blob_type() {
+            Some(blob_type)
+                if !config.replay_send_as_kdr || matches!(blob_type, GhostBlobType::Kdr) =>
+            {
+                return GhostBlob::from_bytes(blob_type, replay_name, bytes)
+                    .inspect_err(|op| warn!("Cannot read replay `{}`: {}", replay_name, op))
+                    .ok();
+            }
+            _ => (),
+        }
+
+        // parses the replay here so the client doesn't have to
+        let ghost = parser
+            .parse(replay_name, &bytes, GhostParseMode::Lenient)
+            .inspect_err(|op| warn!("Cannot parse replay `{}`: {}", replay_name, op))
+            .ok()?;
+
+        ghost
+            .warnings
+            .iter()
+            .for_each(|warning| warn!("Skipped part of replay `{}`: {}", replay_name, warning));
+
+        let blob = write_ghost_to_blob(&ghost, GhostBlobType::Kdr)
+            .inspect_err(|op| warn!("Cannot convert replay `{}`: {}", replay_name, op))
+            .ok()?;
+
+        if let (Some(modified), GhostBlob::Kdr(bytes)) = (modified, &blob)
+            && let Ok(mut cache) = converted_replays.write()
+        {
+            cache.insert(canonicalized, modified, bytes.clone());
         }
 
-        get_ghost_blob_from_path(
-            &canonicalized,
-            config.replay_unknown_format_override.clone(),
-        )
-        .ok()
+        Some(blob)
     })
 }